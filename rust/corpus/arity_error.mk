@@ -0,0 +1,2 @@
+let apply = fn(f) { f(1, 2) };
+apply(fn(a) { a })
@@ -0,0 +1,17 @@
+let counter = fn() {
+  let n = 0;
+  fn() { n += 1 }
+};
+let a = counter();
+let b = counter();
+a(); a(); b();
+
+let compose = fn(f, g) { fn(x) { f(g(x)) } };
+let inc = fn(x) { x + 1 };
+let double = fn(x) { x * 2 };
+let both = compose(inc, double);
+
+let later = fn() { defined_later * 2 };
+let defined_later = 21;
+
+[a(), b(), both(5), compose(double, inc)(5), later()]
@@ -0,0 +1,10 @@
+let a = [1, 2, [3, 4]];
+a[0] = 10;
+a[2][1] += 40;
+let h = {"one": 1, "nested": {"list": [1]}};
+h.two = 2;
+h["one"] -= 1;
+h.nested.list[0] *= 7;
+let copy = a;
+copy[1] = 0;
+[a, copy, h, h.keys(), a.first(), a.last(), [1, 2, 3].contains(2), a[1..3], null?.x, h?.missing ?? "default"]
@@ -0,0 +1,33 @@
+let total = 0;
+for (x in 1..=6) {
+  let y = if (x == 5) { break; } else { x * 2 };
+  total += y + if (x % 2 == 0) { continue; } else { 100 }
+}
+
+let cells = [];
+let i = 0;
+while (i < 5) {
+  i += 1;
+  cells = cells.push([i, if (i == 2) { continue; } else { i * i }, if (i == 4) { break; } else { 0 }])
+}
+
+let find = fn(xs, target) {
+  for (x in xs) {
+    let hit = if (x == target) { return x * 10; } else { null };
+  }
+  -1
+};
+
+let clamp = fn(n) {
+  len([n, if (n > 3) { return 3; } else { n }]) + n
+};
+
+let first_even = fn(xs) {
+  xs.map(fn(x) { if (x % 2 == 0) { return x; } else { 0 } });
+  for (x in xs) {
+    [x, 1 + if (x % 2 == 0) { return x; } else { 0 }]
+  }
+  null
+};
+
+[total, i, cells, find([1, 2, 3], 2), find([], 1), clamp(1), clamp(7), first_even([3, 5, 8, 9])]
@@ -0,0 +1,6 @@
+let average = fn(xs) {
+  let sum = 0;
+  for (x in xs) { sum += x }
+  sum / len(xs)
+};
+[average([1, 2, 3]), average([])]
@@ -0,0 +1,7 @@
+let xs = [1, 2, 3];
+let i = 0;
+while (i < 5) {
+  xs[i] = i * 2;
+  i += 1
+}
+xs
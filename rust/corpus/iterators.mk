@@ -0,0 +1,6 @@
+let squares = map(1..=5, fn(x) { x * x });
+let odd = filter(squares, fn(x) { x % 2 == 1 });
+let it = iter([1, 2, 3]);
+next(it);
+let evens = (1..10).filter(fn(x) { x % 2 == 0 }).map(fn(x) { x / 2 }).collect();
+[collect(odd), it.next(), evens, (1..4).contains(3), len(1..100)]
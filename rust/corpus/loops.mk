@@ -0,0 +1,24 @@
+let total = 0;
+let i = 0;
+while (true) {
+  i += 1;
+  if (i > 20) { break }
+  if (i % 3 == 0) { continue }
+  total += i
+}
+
+let pairs = [];
+for (x in 1..=3) {
+  for (y in [x, x * 10]) {
+    pairs = pairs.push(x + y)
+  }
+}
+
+let first_big = fn(xs) {
+  for (x in xs) {
+    if (x > 10) { return x }
+  }
+  null
+};
+
+[total, i, pairs, first_big([1, 5, 12, 30]), first_big([])]
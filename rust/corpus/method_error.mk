@@ -0,0 +1 @@
+[1, 2].map(fn(x) { x.upper() })
@@ -0,0 +1,6 @@
+let bits = [5 & 3, 5 | 3, 5 ^ 3, ~5, 1 << 4, 256 >> 2];
+let arithmetic = [7 / 2, -7 % 3, 2 ** 10, -(3 - 5)];
+let logic = [true && false, null || "fallback", !0, 1 < 2 == true, "a" != "b"];
+let ranges = [(1..3).collect(), (1..=3).collect()];
+let ternary = if (len(bits) > 3) { "many" } else { "few" };
+[bits, arithmetic, logic, ranges, ternary]
@@ -0,0 +1,2 @@
+let grow = fn(n) { grow(n * 1000) };
+grow(1)
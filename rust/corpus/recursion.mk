@@ -0,0 +1,13 @@
+let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } };
+
+let sum = fn(n, acc) { if (n == 0) { acc } else { sum(n - 1, acc + n) } };
+
+let even = fn(n) { if (n == 0) { true } else { odd(n - 1) } };
+let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } };
+
+let gcd = fn(a, b) {
+  if (b == 0) { return a; }
+  gcd(b, a % b)
+};
+
+[fib(20), sum(50000, 0), even(1001), gcd(1071, 462)]
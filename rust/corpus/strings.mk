@@ -0,0 +1,9 @@
+let name = "monkey";
+let greeting = "hello, ${name.upper()}!";
+let words = "the quick brown fox".split(" ");
+let lengths = words.map(fn(w) { len(w) });
+let s = "";
+for (c in "abc") {
+  s = c + s
+}
+[greeting, lengths, words.join("-"), s, " padded ".trim(), "${1 + 2} is ${"three"}", name.contains("key")]
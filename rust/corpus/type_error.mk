@@ -0,0 +1,5 @@
+let describe = fn(x) {
+  "value: " + x
+};
+describe("ok");
+describe(5)
@@ -0,0 +1,37 @@
+enum Shape { Circle(r), Rect(w, h), Empty }
+
+struct Point { x, y }
+
+let area = fn(shape) {
+  match (shape) {
+    Shape.Circle(r) => 3 * r * r,
+    Shape.Rect(w, h) => w * h,
+    Shape.Empty => 0,
+  }
+};
+
+let safe_div = fn(a, b) {
+  try { a / b } catch (e) { e.kind }
+};
+
+let [first, ..rest] = [Shape.Circle(2), Shape.Rect(2, 3), Shape.Empty];
+let {x, y} = {"x": 1, "y": 2};
+let p = Point { x: x, y: y };
+
+let squares = fn*(n) {
+  for (i in 0..n) { yield i * i; }
+};
+
+let total = fn(base = 10, ...more) { base + more.len() };
+
+[
+  area(first),
+  rest.map(area),
+  safe_div(7, 2),
+  safe_div(1, 0),
+  p.x + p.y,
+  collect(squares(4)),
+  total(),
+  total(base: 1),
+  total(...[1, 2, 3])
+]
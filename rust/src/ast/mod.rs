@@ -1,6 +1,13 @@
-use std::fmt::Display;
+use std::{cell::Cell, fmt::Display, rc::Rc};
 
-use crate::{Token, TokenType};
+use crate::{Span, Symbol, Token, TokenType};
+
+mod optimize;
+mod resolve;
+mod tests;
+
+pub use optimize::optimize;
+pub use resolve::Resolver;
 
 pub trait Node: std::fmt::Debug + std::fmt::Display {
     fn node_type(&self) -> NodeType;
@@ -9,13 +16,21 @@ pub trait Node: std::fmt::Debug + std::fmt::Display {
 
     fn token_type(&self) -> TokenType;
 
+    /// Where the node starts in the source.
+    fn span(&self) -> Span;
+
     fn as_any(&self) -> &dyn std::any::Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
 }
 
 pub trait Statement: Node {}
 
 pub trait Expression: Node {}
 
+/// Something a value can be matched against, binding names on success.
+pub trait Pattern: Node {}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum NodeType {
     Program,
@@ -31,18 +46,43 @@ pub enum NodeType {
     BlockStatement,
     FunctionLiteral,
     CallExpression,
+    WhileStatement,
+    ForStatement,
+    BreakStatement,
+    ContinueStatement,
+    ArrayLiteral,
+    IndexExpression,
+    StringLiteral,
+    HashLiteral,
+    AssignExpression,
+    NullLiteral,
+    MatchExpression,
+    WildcardPattern,
+    RestPattern,
+    ArrayPattern,
+    HashPattern,
+    DestructuringLetStatement,
+    SpreadExpression,
+    NamedArgument,
+    InterpolatedString,
+    MemberExpression,
+    StructStatement,
+    StructLiteral,
+    EnumStatement,
+    VariantPattern,
+    ThrowStatement,
+    TryExpression,
+    YieldStatement,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Program {
     statements: Vec<Box<dyn Statement>>,
 }
 
 impl Program {
     pub fn new() -> Self {
-        Program {
-            statements: Vec::new(),
-        }
+        Self::default()
     }
 
     pub fn statements(&self) -> &Vec<Box<dyn Statement>> {
@@ -54,7 +94,7 @@ impl Program {
     }
 
     pub fn token_literal(&self) -> &str {
-        if self.statements.len() > 0 {
+        if !self.statements.is_empty() {
             self.statements[0].token_literal()
         } else {
             ""
@@ -75,9 +115,19 @@ impl Node for Program {
         ""
     }
 
+    fn span(&self) -> Span {
+        self.statements
+            .first()
+            .map_or_else(Span::default, |stmt| stmt.span())
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Display for Program {
@@ -94,17 +144,17 @@ impl Display for Program {
 
 #[derive(Debug)]
 pub struct LetStatement {
-    token: Token,
+    token: Token<'static>,
     name: Identifier,
     value: Box<dyn Expression>,
 }
 
 impl LetStatement {
-    pub fn new(token: Token, name: Identifier, value: Box<dyn Expression>) -> Self {
+    pub fn new(token: Token<'static>, name: Identifier, value: Box<dyn Expression>) -> Self {
         LetStatement { token, name, value }
     }
 
-    pub fn token(&self) -> &Token {
+    pub fn token(&self) -> &Token<'static> {
         &self.token
     }
 
@@ -112,8 +162,8 @@ impl LetStatement {
         &self.name
     }
 
-    pub fn value(&self) -> &Box<dyn Expression> {
-        &self.value
+    pub fn value(&self) -> &dyn Expression {
+        self.value.as_ref()
     }
 }
 
@@ -128,40 +178,136 @@ impl Node for LetStatement {
         NodeType::LetStatement
     }
     fn token_literal(&self) -> &str {
-        &self.token.literal()
+        self.token.literal()
     }
 
     fn token_type(&self) -> TokenType {
         self.token.token_type()
     }
 
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Statement for LetStatement {}
 
+#[derive(Debug)]
+pub struct DestructuringLetStatement {
+    token: Token<'static>,
+    pattern: Box<dyn Pattern>,
+    value: Box<dyn Expression>,
+}
+
+impl DestructuringLetStatement {
+    pub fn new(
+        token: Token<'static>,
+        pattern: Box<dyn Pattern>,
+        value: Box<dyn Expression>,
+    ) -> Self {
+        DestructuringLetStatement {
+            token,
+            pattern,
+            value,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn pattern(&self) -> &dyn Pattern {
+        self.pattern.as_ref()
+    }
+
+    pub fn value(&self) -> &dyn Expression {
+        self.value.as_ref()
+    }
+}
+
+impl Display for DestructuringLetStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "let {} = {};", self.pattern, self.value)
+    }
+}
+
+impl Node for DestructuringLetStatement {
+    fn node_type(&self) -> NodeType {
+        NodeType::DestructuringLetStatement
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Statement for DestructuringLetStatement {}
+
+/// Where the value a name refers to is kept, as worked out by the [`Resolver`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// Slot `slot` of the environment `depth` environments out from the one the name is
+    /// evaluated in.
+    Variable { depth: usize, slot: usize },
+    /// The built-in function at `index` in the table of them.
+    Builtin(usize),
+}
+
 #[derive(Debug)]
 pub struct Identifier {
-    token: Token,
-    value: String,
+    token: Token<'static>,
+    value: Symbol,
+    binding: Cell<Option<Binding>>,
 }
 
 impl Identifier {
-    pub fn new(token: Token, value: impl ToString) -> Self {
+    pub fn new(token: Token<'static>, value: Symbol) -> Self {
         Identifier {
             token,
-            value: value.to_string(),
+            value,
+            binding: Cell::new(None),
         }
     }
 
-    pub fn token(&self) -> &Token {
+    pub fn token(&self) -> &Token<'static> {
         &self.token
     }
 
-    pub fn value(&self) -> &str {
-        &self.value
+    pub fn value(&self) -> &'static str {
+        self.value.as_str()
+    }
+
+    pub fn symbol(&self) -> Symbol {
+        self.value
+    }
+
+    /// Where the value the name refers to is kept, or `None` until the program it's in has
+    /// been resolved.
+    pub fn binding(&self) -> Option<Binding> {
+        self.binding.get()
     }
 }
 
@@ -176,33 +322,51 @@ impl Node for Identifier {
         NodeType::Identifier
     }
     fn token_literal(&self) -> &str {
-        &self.token.literal()
+        self.token.literal()
     }
 
     fn token_type(&self) -> TokenType {
         self.token.token_type()
     }
 
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Expression for Identifier {}
 
+impl Pattern for Identifier {}
+
 #[derive(Debug)]
 pub struct ReturnStatement {
-    pub token: Token,
+    pub token: Token<'static>,
     pub return_value: Box<dyn Expression>,
 }
 
 impl ReturnStatement {
-    pub fn new(token: Token, return_value: Box<dyn Expression>) -> Self {
+    pub fn new(token: Token<'static>, return_value: Box<dyn Expression>) -> Self {
         ReturnStatement {
             token,
             return_value,
         }
     }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn return_value(&self) -> &dyn Expression {
+        self.return_value.as_ref()
+    }
 }
 
 impl Display for ReturnStatement {
@@ -216,37 +380,45 @@ impl Node for ReturnStatement {
         NodeType::ReturnStatement
     }
     fn token_literal(&self) -> &str {
-        &self.token.literal()
+        self.token.literal()
     }
 
     fn token_type(&self) -> TokenType {
         self.token.token_type()
     }
 
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Statement for ReturnStatement {}
 
 #[derive(Debug)]
 pub struct ExpressionStatement {
-    pub token: Token,
+    pub token: Token<'static>,
     pub expression: Box<dyn Expression>,
 }
 
 impl ExpressionStatement {
-    pub fn new(token: Token, expression: Box<dyn Expression>) -> Self {
+    pub fn new(token: Token<'static>, expression: Box<dyn Expression>) -> Self {
         ExpressionStatement { token, expression }
     }
 
-    pub fn token(&self) -> &Token {
+    pub fn token(&self) -> &Token<'static> {
         &self.token
     }
 
-    pub fn expression(&self) -> &Box<dyn Expression> {
-        &self.expression
+    pub fn expression(&self) -> &dyn Expression {
+        self.expression.as_ref()
     }
 }
 
@@ -261,32 +433,40 @@ impl Node for ExpressionStatement {
         NodeType::ExpressionStatement
     }
     fn token_literal(&self) -> &str {
-        &self.token.literal()
+        self.token.literal()
     }
 
     fn token_type(&self) -> TokenType {
         self.token.token_type()
     }
 
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Statement for ExpressionStatement {}
 
 #[derive(Debug)]
 pub struct IntegerLiteral {
-    pub token: Token,
+    pub token: Token<'static>,
     pub value: i64,
 }
 
 impl IntegerLiteral {
-    pub fn new(token: Token, value: i64) -> Self {
+    pub fn new(token: Token<'static>, value: i64) -> Self {
         IntegerLiteral { token, value }
     }
 
-    pub fn token(&self) -> &Token {
+    pub fn token(&self) -> &Token<'static> {
         &self.token
     }
 
@@ -313,22 +493,32 @@ impl Node for IntegerLiteral {
         self.token.token_type()
     }
 
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Expression for IntegerLiteral {}
 
+impl Pattern for IntegerLiteral {}
+
 #[derive(Debug)]
 pub struct PrefixExpression {
-    pub token: Token,
+    pub token: Token<'static>,
     pub operator: String,
     pub right: Box<dyn Expression>,
 }
 
 impl PrefixExpression {
-    pub fn new(token: Token, operator: impl ToString, right: Box<dyn Expression>) -> Self {
+    pub fn new(token: Token<'static>, operator: impl ToString, right: Box<dyn Expression>) -> Self {
         PrefixExpression {
             token,
             operator: operator.to_string(),
@@ -336,7 +526,7 @@ impl PrefixExpression {
         }
     }
 
-    pub fn token(&self) -> &Token {
+    pub fn token(&self) -> &Token<'static> {
         &self.token
     }
 
@@ -344,8 +534,8 @@ impl PrefixExpression {
         &self.operator
     }
 
-    pub fn right(&self) -> &Box<dyn Expression> {
-        &self.right
+    pub fn right(&self) -> &dyn Expression {
+        self.right.as_ref()
     }
 }
 
@@ -367,16 +557,24 @@ impl Node for PrefixExpression {
         self.token.token_type()
     }
 
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Expression for PrefixExpression {}
 
 #[derive(Debug)]
 pub struct InfixExpression {
-    pub token: Token,
+    pub token: Token<'static>,
     pub left: Box<dyn Expression>,
     pub operator: String,
     pub right: Box<dyn Expression>,
@@ -384,7 +582,7 @@ pub struct InfixExpression {
 
 impl InfixExpression {
     pub fn new(
-        token: Token,
+        token: Token<'static>,
         left: Box<dyn Expression>,
         operator: impl ToString,
         right: Box<dyn Expression>,
@@ -397,20 +595,20 @@ impl InfixExpression {
         }
     }
 
-    pub fn token(&self) -> &Token {
+    pub fn token(&self) -> &Token<'static> {
         &self.token
     }
 
-    pub fn left(&self) -> &Box<dyn Expression> {
-        &self.left
+    pub fn left(&self) -> &dyn Expression {
+        self.left.as_ref()
     }
 
     pub fn operator(&self) -> &str {
         &self.operator
     }
 
-    pub fn right(&self) -> &Box<dyn Expression> {
-        &self.right
+    pub fn right(&self) -> &dyn Expression {
+        self.right.as_ref()
     }
 }
 
@@ -432,25 +630,33 @@ impl Node for InfixExpression {
         self.token.token_type()
     }
 
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Expression for InfixExpression {}
 
 #[derive(Debug)]
 pub struct Boolean {
-    token: Token,
+    token: Token<'static>,
     value: bool,
 }
 
 impl Boolean {
-    pub fn new(token: Token, value: bool) -> Self {
+    pub fn new(token: Token<'static>, value: bool) -> Self {
         Boolean { token, value }
     }
 
-    pub fn token(&self) -> &Token {
+    pub fn token(&self) -> &Token<'static> {
         &self.token
     }
 
@@ -477,16 +683,26 @@ impl Node for Boolean {
         self.token.token_type()
     }
 
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Expression for Boolean {}
 
+impl Pattern for Boolean {}
+
 #[derive(Debug)]
 pub struct IfExpression {
-    token: Token,
+    token: Token<'static>,
     condition: Box<dyn Expression>,
     consequence: BlockStatement,
     alternative: Option<BlockStatement>,
@@ -494,7 +710,7 @@ pub struct IfExpression {
 
 impl IfExpression {
     pub fn new(
-        token: Token,
+        token: Token<'static>,
         condition: Box<dyn Expression>,
         consequence: BlockStatement,
         alternative: Option<BlockStatement>,
@@ -507,12 +723,12 @@ impl IfExpression {
         }
     }
 
-    pub fn token(&self) -> &Token {
+    pub fn token(&self) -> &Token<'static> {
         &self.token
     }
 
-    pub fn condition(&self) -> &Box<dyn Expression> {
-        &self.condition
+    pub fn condition(&self) -> &dyn Expression {
+        self.condition.as_ref()
     }
 
     pub fn consequence(&self) -> &BlockStatement {
@@ -546,28 +762,36 @@ impl Node for IfExpression {
         self.token.token_type()
     }
 
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Expression for IfExpression {}
 
 #[derive(Debug)]
 pub struct BlockStatement {
-    token: Token,
+    token: Token<'static>,
     statements: Vec<Box<dyn Statement>>,
 }
 
 impl BlockStatement {
-    pub fn new(token: Token) -> Self {
+    pub fn new(token: Token<'static>) -> Self {
         BlockStatement {
             token,
             statements: Vec::new(),
         }
     }
 
-    pub fn token(&self) -> &Token {
+    pub fn token(&self) -> &Token<'static> {
         &self.token
     }
 
@@ -603,54 +827,137 @@ impl Node for BlockStatement {
         self.token.token_type()
     }
 
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Statement for BlockStatement {}
 
+// match arms can have a block for their body
+impl Expression for BlockStatement {}
+
+/// A function's parameter list: positional names, each with an optional default value that is
+/// evaluated when the function is called, optionally followed by a `...rest` parameter.
+#[derive(Debug, Default)]
+pub struct Parameters {
+    names: Vec<Identifier>,
+    defaults: Vec<Option<Box<dyn Expression>>>,
+    rest: Option<Identifier>,
+}
+
+impl Parameters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_parameter(&mut self, name: Identifier, default: Option<Box<dyn Expression>>) {
+        self.names.push(name);
+        self.defaults.push(default);
+    }
+
+    pub fn set_rest(&mut self, rest: Identifier) {
+        self.rest = Some(rest);
+    }
+
+    pub fn names(&self) -> &[Identifier] {
+        &self.names
+    }
+
+    pub fn default_value(&self, index: usize) -> Option<&dyn Expression> {
+        self.defaults[index].as_deref()
+    }
+
+    pub fn rest(&self) -> Option<&Identifier> {
+        self.rest.as_ref()
+    }
+
+    /// The number of parameters without a default value.
+    pub fn required(&self) -> usize {
+        self.defaults.iter().filter(|d| d.is_none()).count()
+    }
+}
+
+impl Display for Parameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut params = self
+            .names
+            .iter()
+            .zip(self.defaults.iter())
+            .map(|(name, default)| match default {
+                Some(default) => format!("{} = {}", name, default),
+                None => name.to_string(),
+            })
+            .collect::<Vec<_>>();
+        if let Some(rest) = &self.rest {
+            params.push(format!("...{}", rest));
+        }
+        write!(f, "{}", params.join(", "))
+    }
+}
+
 #[derive(Debug)]
 pub struct FunctionLiteral {
-    token: Token,
-    parameters: Vec<Identifier>,
-    body: BlockStatement,
+    token: Token<'static>,
+    parameters: Rc<Parameters>,
+    body: Rc<BlockStatement>,
+    generator: bool,
 }
 
 impl FunctionLiteral {
-    pub fn new(token: Token, parameters: Vec<Identifier>, body: BlockStatement) -> Self {
+    pub fn new(
+        token: Token<'static>,
+        parameters: Parameters,
+        body: BlockStatement,
+        generator: bool,
+    ) -> Self {
         FunctionLiteral {
             token,
-            parameters,
-            body,
+            parameters: Rc::new(parameters),
+            body: Rc::new(body),
+            generator,
         }
     }
 
-    pub fn token(&self) -> &Token {
+    pub fn token(&self) -> &Token<'static> {
         &self.token
     }
 
-    pub fn parameters(&self) -> &Vec<Identifier> {
+    /// The names of the positional parameters, see [`FunctionLiteral::signature`] for the rest.
+    pub fn parameters(&self) -> &[Identifier] {
+        self.parameters.names()
+    }
+
+    pub fn signature(&self) -> &Rc<Parameters> {
         &self.parameters
     }
 
-    pub fn body(&self) -> &BlockStatement {
+    pub fn body(&self) -> &Rc<BlockStatement> {
         &self.body
     }
+
+    /// Whether this is a `fn*` generator function.
+    pub fn generator(&self) -> bool {
+        self.generator
+    }
 }
 
 impl Display for FunctionLiteral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let params = self
-            .parameters
-            .iter()
-            .map(|p| p.to_string())
-            .collect::<Vec<_>>();
         write!(
             f,
-            "{}({}) {}",
+            "{}{}({}) {}",
             self.token.literal(),
-            params.join(", "),
+            if self.generator { "*" } else { "" },
+            self.parameters,
             self.body
         )
     }
@@ -668,23 +975,32 @@ impl Node for FunctionLiteral {
         self.token.token_type()
     }
 
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Expression for FunctionLiteral {}
 
 #[derive(Debug)]
 pub struct CallExpression {
-    token: Token,
+    token: Token<'static>,
     function: Box<dyn Expression>,
     arguments: Vec<Box<dyn Expression>>,
+    grouped: bool,
 }
 
 impl CallExpression {
     pub fn new(
-        token: Token,
+        token: Token<'static>,
         function: Box<dyn Expression>,
         arguments: Vec<Box<dyn Expression>>,
     ) -> Self {
@@ -692,20 +1008,30 @@ impl CallExpression {
             token,
             function,
             arguments,
+            grouped: false,
         }
     }
 
-    pub fn token(&self) -> &Token {
+    pub fn token(&self) -> &Token<'static> {
         &self.token
     }
 
-    pub fn function(&self) -> &Box<dyn Expression> {
-        &self.function
+    pub fn function(&self) -> &dyn Expression {
+        self.function.as_ref()
     }
 
     pub fn arguments(&self) -> &Vec<Box<dyn Expression>> {
         &self.arguments
     }
+
+    /// Whether the call is in parentheses, which end the chain of calls and accesses it's in.
+    pub fn grouped(&self) -> bool {
+        self.grouped
+    }
+
+    pub fn set_grouped(&mut self) {
+        self.grouped = true;
+    }
 }
 
 impl Display for CallExpression {
@@ -731,9 +1057,1770 @@ impl Node for CallExpression {
         self.token.token_type()
     }
 
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl Expression for CallExpression {}
+
+#[derive(Debug)]
+pub struct WhileStatement {
+    token: Token<'static>,
+    condition: Box<dyn Expression>,
+    body: BlockStatement,
+}
+
+impl WhileStatement {
+    pub fn new(
+        token: Token<'static>,
+        condition: Box<dyn Expression>,
+        body: BlockStatement,
+    ) -> Self {
+        WhileStatement {
+            token,
+            condition,
+            body,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn condition(&self) -> &dyn Expression {
+        self.condition.as_ref()
+    }
+
+    pub fn body(&self) -> &BlockStatement {
+        &self.body
+    }
+}
+
+impl Display for WhileStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "while {} {}", self.condition, self.body)
+    }
+}
+
+impl Node for WhileStatement {
+    fn node_type(&self) -> NodeType {
+        NodeType::WhileStatement
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Statement for WhileStatement {}
+
+#[derive(Debug)]
+pub struct ForStatement {
+    token: Token<'static>,
+    variable: Identifier,
+    iterable: Box<dyn Expression>,
+    body: BlockStatement,
+}
+
+impl ForStatement {
+    pub fn new(
+        token: Token<'static>,
+        variable: Identifier,
+        iterable: Box<dyn Expression>,
+        body: BlockStatement,
+    ) -> Self {
+        ForStatement {
+            token,
+            variable,
+            iterable,
+            body,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn variable(&self) -> &Identifier {
+        &self.variable
+    }
+
+    pub fn iterable(&self) -> &dyn Expression {
+        self.iterable.as_ref()
+    }
+
+    pub fn body(&self) -> &BlockStatement {
+        &self.body
+    }
+}
+
+impl Display for ForStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "for ({} in {}) {}",
+            self.variable, self.iterable, self.body
+        )
+    }
+}
+
+impl Node for ForStatement {
+    fn node_type(&self) -> NodeType {
+        NodeType::ForStatement
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Statement for ForStatement {}
+
+#[derive(Debug)]
+pub struct BreakStatement {
+    token: Token<'static>,
+}
+
+impl BreakStatement {
+    pub fn new(token: Token<'static>) -> Self {
+        BreakStatement { token }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+}
+
+impl Display for BreakStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "break;")
+    }
+}
+
+impl Node for BreakStatement {
+    fn node_type(&self) -> NodeType {
+        NodeType::BreakStatement
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Statement for BreakStatement {}
+
+#[derive(Debug)]
+pub struct ContinueStatement {
+    token: Token<'static>,
+}
+
+impl ContinueStatement {
+    pub fn new(token: Token<'static>) -> Self {
+        ContinueStatement { token }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+}
+
+impl Display for ContinueStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "continue;")
+    }
+}
+
+impl Node for ContinueStatement {
+    fn node_type(&self) -> NodeType {
+        NodeType::ContinueStatement
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Statement for ContinueStatement {}
+
+#[derive(Debug)]
+pub struct ArrayLiteral {
+    token: Token<'static>,
+    elements: Vec<Box<dyn Expression>>,
+}
+
+impl ArrayLiteral {
+    pub fn new(token: Token<'static>, elements: Vec<Box<dyn Expression>>) -> Self {
+        ArrayLiteral { token, elements }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn elements(&self) -> &Vec<Box<dyn Expression>> {
+        &self.elements
+    }
+}
+
+impl Display for ArrayLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let elements = self
+            .elements
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "[{}]", elements.join(", "))
+    }
+}
+
+impl Node for ArrayLiteral {
+    fn node_type(&self) -> NodeType {
+        NodeType::ArrayLiteral
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for ArrayLiteral {}
+
+#[derive(Debug)]
+pub struct IndexExpression {
+    token: Token<'static>,
+    left: Box<dyn Expression>,
+    index: Box<dyn Expression>,
+    optional: bool,
+    grouped: bool,
+}
+
+impl IndexExpression {
+    pub fn new(
+        token: Token<'static>,
+        left: Box<dyn Expression>,
+        index: Box<dyn Expression>,
+        optional: bool,
+    ) -> Self {
+        IndexExpression {
+            token,
+            left,
+            index,
+            optional,
+            grouped: false,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn left(&self) -> &dyn Expression {
+        self.left.as_ref()
+    }
+
+    pub fn index(&self) -> &dyn Expression {
+        self.index.as_ref()
+    }
+
+    /// Whether this is a `?[` access, which evaluates to null instead of failing when the left
+    /// side is null.
+    pub fn optional(&self) -> bool {
+        self.optional
+    }
+
+    /// Whether the access is in parentheses, which end the chain of calls and accesses it's in.
+    pub fn grouped(&self) -> bool {
+        self.grouped
+    }
+
+    pub fn set_grouped(&mut self) {
+        self.grouped = true;
+    }
+}
+
+impl Display for IndexExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.optional {
+            write!(f, "({}?[{}])", self.left, self.index)
+        } else {
+            write!(f, "({}[{}])", self.left, self.index)
+        }
+    }
+}
+
+impl Node for IndexExpression {
+    fn node_type(&self) -> NodeType {
+        NodeType::IndexExpression
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for IndexExpression {}
+
+/// A property access, `object.property`, or `object?.property` when it's optional. Calling a
+/// member expression calls a method on the object.
+#[derive(Debug)]
+pub struct MemberExpression {
+    token: Token<'static>,
+    object: Box<dyn Expression>,
+    property: Identifier,
+    optional: bool,
+    grouped: bool,
+}
+
+impl MemberExpression {
+    pub fn new(
+        token: Token<'static>,
+        object: Box<dyn Expression>,
+        property: Identifier,
+        optional: bool,
+    ) -> Self {
+        MemberExpression {
+            token,
+            object,
+            property,
+            optional,
+            grouped: false,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn object(&self) -> &dyn Expression {
+        self.object.as_ref()
+    }
+
+    pub fn property(&self) -> &Identifier {
+        &self.property
+    }
+
+    /// Whether this is a `?.` access, which evaluates to null instead of failing when the object
+    /// is null.
+    pub fn optional(&self) -> bool {
+        self.optional
+    }
+
+    /// Whether the access is in parentheses, which end the chain of calls and accesses it's in.
+    pub fn grouped(&self) -> bool {
+        self.grouped
+    }
+
+    pub fn set_grouped(&mut self) {
+        self.grouped = true;
+    }
+}
+
+impl Display for MemberExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operator = if self.optional { "?." } else { "." };
+        write!(f, "({}{}{})", self.object, operator, self.property)
+    }
+}
+
+impl Node for MemberExpression {
+    fn node_type(&self) -> NodeType {
+        NodeType::MemberExpression
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for MemberExpression {}
+
+#[derive(Debug)]
+pub struct StringLiteral {
+    token: Token<'static>,
+    value: String,
+}
+
+impl StringLiteral {
+    pub fn new(token: Token<'static>, value: impl ToString) -> Self {
+        StringLiteral {
+            token,
+            value: value.to_string(),
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl Display for StringLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\"", self.value)
+    }
+}
+
+impl Node for StringLiteral {
+    fn node_type(&self) -> NodeType {
+        NodeType::StringLiteral
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for StringLiteral {}
+
+impl Pattern for StringLiteral {}
+
+pub type HashPair = (Box<dyn Expression>, Box<dyn Expression>);
+
+#[derive(Debug)]
+pub struct HashLiteral {
+    token: Token<'static>,
+    pairs: Vec<HashPair>,
+}
+
+impl HashLiteral {
+    pub fn new(token: Token<'static>, pairs: Vec<HashPair>) -> Self {
+        HashLiteral { token, pairs }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn pairs(&self) -> &Vec<HashPair> {
+        &self.pairs
+    }
+}
+
+impl Display for HashLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pairs = self
+            .pairs
+            .iter()
+            .map(|(k, v)| format!("{}: {}", k, v))
+            .collect::<Vec<_>>();
+        write!(f, "{{{}}}", pairs.join(", "))
+    }
+}
+
+impl Node for HashLiteral {
+    fn node_type(&self) -> NodeType {
+        NodeType::HashLiteral
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for HashLiteral {}
+
+#[derive(Debug)]
+pub struct AssignExpression {
+    token: Token<'static>,
+    target: Box<dyn Expression>,
+    operator: String,
+    value: Box<dyn Expression>,
+}
+
+impl AssignExpression {
+    pub fn new(
+        token: Token<'static>,
+        target: Box<dyn Expression>,
+        operator: impl ToString,
+        value: Box<dyn Expression>,
+    ) -> Self {
+        AssignExpression {
+            token,
+            target,
+            operator: operator.to_string(),
+            value,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn target(&self) -> &dyn Expression {
+        self.target.as_ref()
+    }
+
+    pub fn operator(&self) -> &str {
+        &self.operator
+    }
+
+    pub fn value(&self) -> &dyn Expression {
+        self.value.as_ref()
+    }
+}
+
+impl Display for AssignExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({} {} {})", self.target, self.operator, self.value)
+    }
+}
+
+impl Node for AssignExpression {
+    fn node_type(&self) -> NodeType {
+        NodeType::AssignExpression
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for AssignExpression {}
+
+#[derive(Debug)]
+pub struct NullLiteral {
+    token: Token<'static>,
+}
+
+impl NullLiteral {
+    pub fn new(token: Token<'static>) -> Self {
+        NullLiteral { token }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+}
+
+impl Display for NullLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "null")
+    }
+}
+
+impl Node for NullLiteral {
+    fn node_type(&self) -> NodeType {
+        NodeType::NullLiteral
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for NullLiteral {}
+
+impl Pattern for NullLiteral {}
+
+#[derive(Debug)]
+pub struct MatchArm {
+    pattern: Box<dyn Pattern>,
+    guard: Option<Box<dyn Expression>>,
+    body: Box<dyn Expression>,
+}
+
+impl MatchArm {
+    pub fn new(
+        pattern: Box<dyn Pattern>,
+        guard: Option<Box<dyn Expression>>,
+        body: Box<dyn Expression>,
+    ) -> Self {
+        MatchArm {
+            pattern,
+            guard,
+            body,
+        }
+    }
+
+    pub fn pattern(&self) -> &dyn Pattern {
+        self.pattern.as_ref()
+    }
+
+    pub fn guard(&self) -> Option<&dyn Expression> {
+        self.guard.as_deref()
+    }
+
+    pub fn body(&self) -> &dyn Expression {
+        self.body.as_ref()
+    }
+}
+
+impl Display for MatchArm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pattern)?;
+        if let Some(guard) = &self.guard {
+            write!(f, " if {}", guard)?;
+        }
+        write!(f, " => {}", self.body)
+    }
+}
+
+#[derive(Debug)]
+pub struct MatchExpression {
+    token: Token<'static>,
+    subject: Box<dyn Expression>,
+    arms: Vec<MatchArm>,
+}
+
+impl MatchExpression {
+    pub fn new(token: Token<'static>, subject: Box<dyn Expression>, arms: Vec<MatchArm>) -> Self {
+        MatchExpression {
+            token,
+            subject,
+            arms,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn subject(&self) -> &dyn Expression {
+        self.subject.as_ref()
+    }
+
+    pub fn arms(&self) -> &Vec<MatchArm> {
+        &self.arms
+    }
+}
+
+impl Display for MatchExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let arms = self
+            .arms
+            .iter()
+            .map(|arm| arm.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "match ({}) {{{}}}", self.subject, arms.join(", "))
+    }
+}
+
+impl Node for MatchExpression {
+    fn node_type(&self) -> NodeType {
+        NodeType::MatchExpression
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for MatchExpression {}
+
+#[derive(Debug)]
+pub struct WildcardPattern {
+    token: Token<'static>,
+}
+
+impl WildcardPattern {
+    pub fn new(token: Token<'static>) -> Self {
+        WildcardPattern { token }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+}
+
+impl Display for WildcardPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "_")
+    }
+}
+
+impl Node for WildcardPattern {
+    fn node_type(&self) -> NodeType {
+        NodeType::WildcardPattern
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Pattern for WildcardPattern {}
+
+#[derive(Debug)]
+pub struct RestPattern {
+    token: Token<'static>,
+    name: Option<Identifier>,
+}
+
+impl RestPattern {
+    pub fn new(token: Token<'static>, name: Option<Identifier>) -> Self {
+        RestPattern { token, name }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn name(&self) -> Option<&Identifier> {
+        self.name.as_ref()
+    }
+}
+
+impl Display for RestPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "..{}", name),
+            None => write!(f, ".."),
+        }
+    }
+}
+
+impl Node for RestPattern {
+    fn node_type(&self) -> NodeType {
+        NodeType::RestPattern
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Pattern for RestPattern {}
+
+#[derive(Debug)]
+pub struct ArrayPattern {
+    token: Token<'static>,
+    elements: Vec<Box<dyn Pattern>>,
+}
+
+impl ArrayPattern {
+    pub fn new(token: Token<'static>, elements: Vec<Box<dyn Pattern>>) -> Self {
+        ArrayPattern { token, elements }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    /// The element patterns, at most one of which is a [`RestPattern`].
+    pub fn elements(&self) -> &Vec<Box<dyn Pattern>> {
+        &self.elements
+    }
+}
+
+impl Display for ArrayPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let elements = self
+            .elements
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "[{}]", elements.join(", "))
+    }
+}
+
+impl Node for ArrayPattern {
+    fn node_type(&self) -> NodeType {
+        NodeType::ArrayPattern
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Pattern for ArrayPattern {}
+
+pub type HashPatternPair = (Box<dyn Expression>, Box<dyn Pattern>);
+
+#[derive(Debug)]
+pub struct HashPattern {
+    token: Token<'static>,
+    pairs: Vec<HashPatternPair>,
+}
+
+impl HashPattern {
+    pub fn new(token: Token<'static>, pairs: Vec<HashPatternPair>) -> Self {
+        HashPattern { token, pairs }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn pairs(&self) -> &Vec<HashPatternPair> {
+        &self.pairs
+    }
+}
+
+impl Display for HashPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pairs = self
+            .pairs
+            .iter()
+            .map(|(k, v)| format!("{}: {}", k, v))
+            .collect::<Vec<_>>();
+        write!(f, "{{{}}}", pairs.join(", "))
+    }
+}
+
+impl Node for HashPattern {
+    fn node_type(&self) -> NodeType {
+        NodeType::HashPattern
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Pattern for HashPattern {}
+
+/// `...value` in a call's argument list, passing each element of an array as its own argument.
+#[derive(Debug)]
+pub struct SpreadExpression {
+    token: Token<'static>,
+    value: Box<dyn Expression>,
+}
+
+impl SpreadExpression {
+    pub fn new(token: Token<'static>, value: Box<dyn Expression>) -> Self {
+        SpreadExpression { token, value }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn value(&self) -> &dyn Expression {
+        self.value.as_ref()
+    }
+}
+
+impl Display for SpreadExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "...{}", self.value)
+    }
+}
+
+impl Node for SpreadExpression {
+    fn node_type(&self) -> NodeType {
+        NodeType::SpreadExpression
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for SpreadExpression {}
+
+/// `name: value` in a call's argument list, passing `value` to the parameter called `name`.
+#[derive(Debug)]
+pub struct NamedArgument {
+    token: Token<'static>,
+    name: Identifier,
+    value: Box<dyn Expression>,
+}
+
+impl NamedArgument {
+    pub fn new(token: Token<'static>, name: Identifier, value: Box<dyn Expression>) -> Self {
+        NamedArgument { token, name, value }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn name(&self) -> &Identifier {
+        &self.name
+    }
+
+    pub fn value(&self) -> &dyn Expression {
+        self.value.as_ref()
+    }
+}
+
+impl Display for NamedArgument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.value)
+    }
+}
+
+impl Node for NamedArgument {
+    fn node_type(&self) -> NodeType {
+        NodeType::NamedArgument
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for NamedArgument {}
+
+#[derive(Debug)]
+pub enum StringPart {
+    Literal(String),
+    Expression(Box<dyn Expression>),
+}
+
+/// A string literal containing `${expr}` interpolations.
+#[derive(Debug)]
+pub struct InterpolatedString {
+    token: Token<'static>,
+    parts: Vec<StringPart>,
+}
+
+impl InterpolatedString {
+    pub fn new(token: Token<'static>, parts: Vec<StringPart>) -> Self {
+        InterpolatedString { token, parts }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn parts(&self) -> &Vec<StringPart> {
+        &self.parts
+    }
+}
+
+impl Display for InterpolatedString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"")?;
+        for part in &self.parts {
+            match part {
+                StringPart::Literal(s) => write!(f, "{}", s)?,
+                StringPart::Expression(expr) => write!(f, "${{{}}}", expr)?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+impl Node for InterpolatedString {
+    fn node_type(&self) -> NodeType {
+        NodeType::InterpolatedString
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for InterpolatedString {}
+
+/// A struct declaration, `struct Point { x, y }`.
+#[derive(Debug)]
+pub struct StructStatement {
+    token: Token<'static>,
+    name: Identifier,
+    fields: Vec<Identifier>,
+}
+
+impl StructStatement {
+    pub fn new(token: Token<'static>, name: Identifier, fields: Vec<Identifier>) -> Self {
+        StructStatement {
+            token,
+            name,
+            fields,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn name(&self) -> &Identifier {
+        &self.name
+    }
+
+    pub fn fields(&self) -> &[Identifier] {
+        &self.fields
+    }
+}
+
+impl Display for StructStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = self
+            .fields
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>();
+        if fields.is_empty() {
+            return write!(f, "struct {} {{}}", self.name);
+        }
+        write!(f, "struct {} {{ {} }}", self.name, fields.join(", "))
+    }
+}
+
+impl Node for StructStatement {
+    fn node_type(&self) -> NodeType {
+        NodeType::StructStatement
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Statement for StructStatement {}
+
+pub type StructField = (Identifier, Box<dyn Expression>);
+
+/// A struct constructor, `Point { x: 1, y: 2 }`. A field without a value, like `Point { x }`,
+/// takes the value of the variable with the same name.
+#[derive(Debug)]
+pub struct StructLiteral {
+    token: Token<'static>,
+    name: Identifier,
+    fields: Vec<StructField>,
+}
+
+impl StructLiteral {
+    pub fn new(token: Token<'static>, name: Identifier, fields: Vec<StructField>) -> Self {
+        StructLiteral {
+            token,
+            name,
+            fields,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn name(&self) -> &Identifier {
+        &self.name
+    }
+
+    pub fn fields(&self) -> &[StructField] {
+        &self.fields
+    }
+}
+
+impl Display for StructLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect::<Vec<_>>();
+        if fields.is_empty() {
+            return write!(f, "{} {{}}", self.name);
+        }
+        write!(f, "{} {{ {} }}", self.name, fields.join(", "))
+    }
+}
+
+impl Node for StructLiteral {
+    fn node_type(&self) -> NodeType {
+        NodeType::StructLiteral
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for StructLiteral {}
+
+/// One variant of an enum declaration, `Circle(r)`, or just `Empty` when it has no fields.
+#[derive(Debug)]
+pub struct EnumVariant {
+    name: Identifier,
+    fields: Vec<Identifier>,
+}
+
+impl EnumVariant {
+    pub fn new(name: Identifier, fields: Vec<Identifier>) -> Self {
+        EnumVariant { name, fields }
+    }
+
+    pub fn name(&self) -> &Identifier {
+        &self.name
+    }
+
+    pub fn fields(&self) -> &[Identifier] {
+        &self.fields
+    }
+}
+
+impl Display for EnumVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.fields.is_empty() {
+            return write!(f, "{}", self.name);
+        }
+        let fields = self
+            .fields
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}({})", self.name, fields.join(", "))
+    }
+}
+
+/// An enum declaration, `enum Shape { Circle(r), Rect(w, h), Empty }`.
+#[derive(Debug)]
+pub struct EnumStatement {
+    token: Token<'static>,
+    name: Identifier,
+    variants: Vec<EnumVariant>,
+}
+
+impl EnumStatement {
+    pub fn new(token: Token<'static>, name: Identifier, variants: Vec<EnumVariant>) -> Self {
+        EnumStatement {
+            token,
+            name,
+            variants,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn name(&self) -> &Identifier {
+        &self.name
+    }
+
+    pub fn variants(&self) -> &[EnumVariant] {
+        &self.variants
+    }
+}
+
+impl Display for EnumStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.variants.is_empty() {
+            return write!(f, "enum {} {{}}", self.name);
+        }
+        let variants = self
+            .variants
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "enum {} {{ {} }}", self.name, variants.join(", "))
+    }
+}
+
+impl Node for EnumStatement {
+    fn node_type(&self) -> NodeType {
+        NodeType::EnumStatement
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Statement for EnumStatement {}
+
+/// Matches a value of one enum variant, `Shape.Circle(r)`. Without a field list, like
+/// `Shape.Circle`, any value of the variant matches.
+#[derive(Debug)]
+pub struct VariantPattern {
+    token: Token<'static>,
+    enum_name: Identifier,
+    variant: Identifier,
+    fields: Option<Vec<Box<dyn Pattern>>>,
+}
+
+impl VariantPattern {
+    pub fn new(
+        token: Token<'static>,
+        enum_name: Identifier,
+        variant: Identifier,
+        fields: Option<Vec<Box<dyn Pattern>>>,
+    ) -> Self {
+        VariantPattern {
+            token,
+            enum_name,
+            variant,
+            fields,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn enum_name(&self) -> &Identifier {
+        &self.enum_name
+    }
+
+    pub fn variant(&self) -> &Identifier {
+        &self.variant
+    }
+
+    pub fn fields(&self) -> Option<&[Box<dyn Pattern>]> {
+        self.fields.as_deref()
+    }
+}
+
+impl Display for VariantPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.enum_name, self.variant)?;
+        if let Some(fields) = &self.fields {
+            let fields = fields.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+            write!(f, "({})", fields.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Node for VariantPattern {
+    fn node_type(&self) -> NodeType {
+        NodeType::VariantPattern
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Pattern for VariantPattern {}
+
+#[derive(Debug)]
+pub struct ThrowStatement {
+    token: Token<'static>,
+    value: Box<dyn Expression>,
+}
+
+impl ThrowStatement {
+    pub fn new(token: Token<'static>, value: Box<dyn Expression>) -> Self {
+        ThrowStatement { token, value }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn value(&self) -> &dyn Expression {
+        self.value.as_ref()
+    }
+}
+
+impl Display for ThrowStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "throw {};", self.value)
+    }
+}
+
+impl Node for ThrowStatement {
+    fn node_type(&self) -> NodeType {
+        NodeType::ThrowStatement
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Statement for ThrowStatement {}
+
+/// A `catch (e) { ... }` clause, binding the caught value to a name.
+pub type CatchClause = (Identifier, BlockStatement);
+
+/// `try { ... } catch (e) { ... } finally { ... }`, where at least one of `catch` and `finally`
+/// is present. Only errors raised while the body runs are caught: a name that nothing in the
+/// program defines is reported before any of it runs, even inside a `try`.
+#[derive(Debug)]
+pub struct TryExpression {
+    token: Token<'static>,
+    body: BlockStatement,
+    catch: Option<CatchClause>,
+    finally: Option<BlockStatement>,
+}
+
+impl TryExpression {
+    pub fn new(
+        token: Token<'static>,
+        body: BlockStatement,
+        catch: Option<CatchClause>,
+        finally: Option<BlockStatement>,
+    ) -> Self {
+        TryExpression {
+            token,
+            body,
+            catch,
+            finally,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn body(&self) -> &BlockStatement {
+        &self.body
+    }
+
+    pub fn catch(&self) -> Option<&CatchClause> {
+        self.catch.as_ref()
+    }
+
+    pub fn finally(&self) -> Option<&BlockStatement> {
+        self.finally.as_ref()
+    }
+}
+
+impl Display for TryExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "try {}", self.body)?;
+        if let Some((name, body)) = &self.catch {
+            write!(f, " catch ({}) {}", name, body)?;
+        }
+        if let Some(finally) = &self.finally {
+            write!(f, " finally {}", finally)?;
+        }
+        Ok(())
+    }
+}
+
+impl Node for TryExpression {
+    fn node_type(&self) -> NodeType {
+        NodeType::TryExpression
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Expression for TryExpression {}
+
+#[derive(Debug)]
+pub struct YieldStatement {
+    token: Token<'static>,
+    value: Box<dyn Expression>,
+}
+
+impl YieldStatement {
+    pub fn new(token: Token<'static>, value: Box<dyn Expression>) -> Self {
+        YieldStatement { token, value }
+    }
+
+    pub fn token(&self) -> &Token<'static> {
+        &self.token
+    }
+
+    pub fn value(&self) -> &dyn Expression {
+        self.value.as_ref()
+    }
+}
+
+impl Display for YieldStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "yield {};", self.value)
+    }
+}
+
+impl Node for YieldStatement {
+    fn node_type(&self) -> NodeType {
+        NodeType::YieldStatement
+    }
+    fn token_literal(&self) -> &str {
+        self.token.literal()
+    }
+
+    fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Statement for YieldStatement {}
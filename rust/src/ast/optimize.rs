@@ -0,0 +1,354 @@
+use std::rc::Rc;
+
+use crate::{
+    ast::*,
+    eval::{eval_infix_expression, eval_prefix_expression},
+    object::Object,
+    Span, Token, TokenType,
+};
+
+/// Folds operators applied to literals into the literals they evaluate to, takes the branch
+/// an `if` with a literal condition would take, and removes the statements after a `return`,
+/// `break`, `continue` or `throw`, which can never run.
+///
+/// Programs behave the same once they're optimized, besides doing less work: operations that
+/// would fail, like dividing by zero, are left for evaluation to fail on and report. Functions
+/// are shown as their optimized bodies, though.
+pub fn optimize(program: &mut Program) {
+    fold_statements(&mut program.statements);
+}
+
+fn downcast<T: 'static>(node: &dyn Node) -> &T {
+    node.as_any().downcast_ref::<T>().unwrap()
+}
+
+fn downcast_mut<T: 'static>(node: &mut dyn Node) -> &mut T {
+    node.as_any_mut().downcast_mut::<T>().unwrap()
+}
+
+fn fold_statements(statements: &mut Vec<Box<dyn Statement>>) {
+    let count = statements.len();
+    let mut folded = Vec::with_capacity(count);
+    for (i, mut stmt) in std::mem::take(statements).into_iter().enumerate() {
+        fold_statement(stmt.as_mut());
+        // blocks don't have scopes of their own, so the branch taken can replace the `if`,
+        // unless it's empty and the `if` gives the value of the statements it's in
+        let replacement = match taken_branch(stmt.as_mut()) {
+            Some(branch) if branch.is_empty() && i == count - 1 => {
+                let token = Token::new(TokenType::Null, "null").with_span(stmt.span());
+                let null = Box::new(NullLiteral::new(token.clone()));
+                vec![Box::new(ExpressionStatement::new(token, null)) as Box<dyn Statement>]
+            },
+            Some(branch) => branch,
+            // a literal's value is only used if it's the last statement's
+            None if i < count - 1 && is_literal_statement(stmt.as_ref()) => vec![],
+            None => vec![stmt],
+        };
+        for stmt in replacement {
+            let ends = matches!(
+                stmt.node_type(),
+                NodeType::ReturnStatement
+                    | NodeType::BreakStatement
+                    | NodeType::ContinueStatement
+                    | NodeType::ThrowStatement
+            );
+            folded.push(stmt);
+            if ends {
+                *statements = folded;
+                return;
+            }
+        }
+    }
+    *statements = folded;
+}
+
+fn is_literal_statement(stmt: &dyn Statement) -> bool {
+    stmt.node_type() == NodeType::ExpressionStatement
+        && constant(downcast::<ExpressionStatement>(stmt).expression.as_ref()).is_some()
+}
+
+/// The statements of the branch an `if` statement with a literal condition takes.
+fn taken_branch(stmt: &mut dyn Statement) -> Option<Vec<Box<dyn Statement>>> {
+    if stmt.node_type() != NodeType::ExpressionStatement {
+        return None;
+    }
+    let expr = &mut downcast_mut::<ExpressionStatement>(stmt).expression;
+    if expr.node_type() != NodeType::IfExpression {
+        return None;
+    }
+    let expr = downcast_mut::<IfExpression>(expr.as_mut());
+    let condition = constant(expr.condition.as_ref())?;
+    if condition.is_truthy() {
+        Some(std::mem::take(&mut expr.consequence.statements))
+    } else {
+        Some(
+            expr.alternative
+                .take()
+                .map_or_else(Vec::new, |alt| alt.statements),
+        )
+    }
+}
+
+fn fold_block(block: &mut BlockStatement) {
+    fold_statements(&mut block.statements);
+}
+
+fn fold_statement(stmt: &mut dyn Statement) {
+    match stmt.node_type() {
+        NodeType::LetStatement => fold_expression(&mut downcast_mut::<LetStatement>(stmt).value),
+        NodeType::DestructuringLetStatement => {
+            fold_expression(&mut downcast_mut::<DestructuringLetStatement>(stmt).value)
+        },
+        NodeType::ReturnStatement => {
+            fold_expression(&mut downcast_mut::<ReturnStatement>(stmt).return_value)
+        },
+        NodeType::ExpressionStatement => {
+            fold_expression(&mut downcast_mut::<ExpressionStatement>(stmt).expression)
+        },
+        NodeType::BlockStatement => fold_block(downcast_mut::<BlockStatement>(stmt)),
+        NodeType::WhileStatement => {
+            let stmt = downcast_mut::<WhileStatement>(stmt);
+            fold_expression(&mut stmt.condition);
+            fold_block(&mut stmt.body);
+        },
+        NodeType::ForStatement => {
+            let stmt = downcast_mut::<ForStatement>(stmt);
+            fold_expression(&mut stmt.iterable);
+            fold_block(&mut stmt.body);
+        },
+        NodeType::ThrowStatement => {
+            fold_expression(&mut downcast_mut::<ThrowStatement>(stmt).value)
+        },
+        NodeType::YieldStatement => {
+            fold_expression(&mut downcast_mut::<YieldStatement>(stmt).value)
+        },
+        _ => {},
+    }
+}
+
+fn fold_expression(expr: &mut Box<dyn Expression>) {
+    match expr.node_type() {
+        NodeType::BlockStatement => fold_block(downcast_mut::<BlockStatement>(expr.as_mut())),
+        NodeType::PrefixExpression => {
+            let prefix = downcast_mut::<PrefixExpression>(expr.as_mut());
+            fold_expression(&mut prefix.right);
+            let folded = constant(prefix.right.as_ref())
+                .and_then(|right| eval_prefix_expression(&prefix.operator, right).ok());
+            replace(expr, folded);
+        },
+        NodeType::InfixExpression => {
+            let infix = downcast_mut::<InfixExpression>(expr.as_mut());
+            fold_expression(&mut infix.left);
+            fold_expression(&mut infix.right);
+            if let Some(folded) = fold_infix_expression(infix) {
+                *expr = folded;
+            }
+        },
+        NodeType::IfExpression => {
+            let if_expr = downcast_mut::<IfExpression>(expr.as_mut());
+            fold_expression(&mut if_expr.condition);
+            fold_block(&mut if_expr.consequence);
+            if let Some(alternative) = &mut if_expr.alternative {
+                fold_block(alternative);
+            }
+            if let Some(folded) = fold_if_expression(if_expr) {
+                *expr = folded;
+            }
+        },
+        NodeType::FunctionLiteral => {
+            let func = downcast_mut::<FunctionLiteral>(expr.as_mut());
+            // the function can only be changed while nothing else shares it
+            if let Some(parameters) = Rc::get_mut(&mut func.parameters) {
+                parameters
+                    .defaults
+                    .iter_mut()
+                    .flatten()
+                    .for_each(fold_expression);
+            }
+            if let Some(body) = Rc::get_mut(&mut func.body) {
+                fold_block(body);
+            }
+        },
+        NodeType::CallExpression => {
+            let call = downcast_mut::<CallExpression>(expr.as_mut());
+            fold_expression(&mut call.function);
+            call.arguments.iter_mut().for_each(fold_expression);
+        },
+        NodeType::ArrayLiteral => {
+            let array = downcast_mut::<ArrayLiteral>(expr.as_mut());
+            array.elements.iter_mut().for_each(fold_expression);
+        },
+        NodeType::IndexExpression => {
+            let index = downcast_mut::<IndexExpression>(expr.as_mut());
+            fold_expression(&mut index.left);
+            fold_expression(&mut index.index);
+        },
+        NodeType::MemberExpression => {
+            fold_expression(&mut downcast_mut::<MemberExpression>(expr.as_mut()).object)
+        },
+        NodeType::HashLiteral => {
+            for (key, value) in &mut downcast_mut::<HashLiteral>(expr.as_mut()).pairs {
+                fold_expression(key);
+                fold_expression(value);
+            }
+        },
+        NodeType::AssignExpression => {
+            let assign = downcast_mut::<AssignExpression>(expr.as_mut());
+            fold_target(&mut assign.target);
+            fold_expression(&mut assign.value);
+        },
+        NodeType::MatchExpression => {
+            let match_expr = downcast_mut::<MatchExpression>(expr.as_mut());
+            fold_expression(&mut match_expr.subject);
+            for arm in &mut match_expr.arms {
+                arm.guard.iter_mut().for_each(fold_expression);
+                fold_expression(&mut arm.body);
+            }
+        },
+        NodeType::SpreadExpression => {
+            fold_expression(&mut downcast_mut::<SpreadExpression>(expr.as_mut()).value)
+        },
+        NodeType::NamedArgument => {
+            fold_expression(&mut downcast_mut::<NamedArgument>(expr.as_mut()).value)
+        },
+        NodeType::InterpolatedString => {
+            for part in &mut downcast_mut::<InterpolatedString>(expr.as_mut()).parts {
+                if let StringPart::Expression(expr) = part {
+                    fold_expression(expr);
+                }
+            }
+        },
+        NodeType::StructLiteral => {
+            for (_, value) in &mut downcast_mut::<StructLiteral>(expr.as_mut()).fields {
+                fold_expression(value);
+            }
+        },
+        NodeType::TryExpression => {
+            let try_expr = downcast_mut::<TryExpression>(expr.as_mut());
+            fold_block(&mut try_expr.body);
+            if let Some((_, catch)) = &mut try_expr.catch {
+                fold_block(catch);
+            }
+            if let Some(finally) = &mut try_expr.finally {
+                fold_block(finally);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Folds the indexes in an assignment's target, but not what's assigned to, which has to stay
+/// something that can be assigned to, or not, as it was written.
+fn fold_target(target: &mut Box<dyn Expression>) {
+    match target.node_type() {
+        NodeType::IndexExpression => {
+            let index = downcast_mut::<IndexExpression>(target.as_mut());
+            fold_target(&mut index.left);
+            fold_expression(&mut index.index);
+        },
+        NodeType::MemberExpression => {
+            fold_target(&mut downcast_mut::<MemberExpression>(target.as_mut()).object)
+        },
+        _ => {},
+    }
+}
+
+fn fold_infix_expression(infix: &mut InfixExpression) -> Option<Box<dyn Expression>> {
+    let left = constant(infix.left.as_ref())?;
+    let span = infix.token.span();
+    match infix.operator.as_str() {
+        // the right side is only evaluated when it can change the result, so it doesn't need
+        // to be a literal when it isn't
+        "&&" if !left.is_truthy() => literal(Object::Boolean(false), span),
+        "||" if left.is_truthy() => literal(Object::Boolean(true), span),
+        "&&" | "||" => literal(
+            Object::Boolean(constant(infix.right.as_ref())?.is_truthy()),
+            span,
+        ),
+        "??" if left != Object::Null => Some(std::mem::replace(&mut infix.left, null(span))),
+        "??" => Some(std::mem::replace(&mut infix.right, null(span))),
+        operator => {
+            let right = constant(infix.right.as_ref())?;
+            literal(eval_infix_expression(operator, left, right).ok()?, span)
+        },
+    }
+}
+
+/// An `if` expression with a literal condition becomes the value of the branch it takes, as
+/// long as that branch is just the one expression.
+fn fold_if_expression(if_expr: &mut IfExpression) -> Option<Box<dyn Expression>> {
+    let condition = constant(if_expr.condition.as_ref())?;
+    let branch = if condition.is_truthy() {
+        &mut if_expr.consequence
+    } else {
+        match &mut if_expr.alternative {
+            Some(alternative) => alternative,
+            None => return Some(null(if_expr.token.span())),
+        }
+    };
+    match branch.statements.as_mut_slice() {
+        [] => Some(null(if_expr.token.span())),
+        [stmt] if stmt.node_type() == NodeType::ExpressionStatement => {
+            let stmt = downcast_mut::<ExpressionStatement>(stmt.as_mut());
+            Some(std::mem::replace(
+                &mut stmt.expression,
+                null(if_expr.token.span()),
+            ))
+        },
+        _ => None,
+    }
+}
+
+/// Replaces an expression with the literal for its value, if it has one.
+fn replace(expr: &mut Box<dyn Expression>, value: Option<Object>) {
+    if let Some(literal) = value.and_then(|value| literal(value, expr.span())) {
+        *expr = literal;
+    }
+}
+
+/// The value of a literal.
+fn constant(expr: &dyn Expression) -> Option<Object> {
+    let node = expr.as_any();
+    if let Some(integer) = node.downcast_ref::<IntegerLiteral>() {
+        Some(Object::Integer(integer.value))
+    } else if let Some(boolean) = node.downcast_ref::<Boolean>() {
+        Some(Object::Boolean(boolean.value))
+    } else if let Some(string) = node.downcast_ref::<StringLiteral>() {
+        Some(Object::String(string.value.as_str().into()))
+    } else if node.is::<NullLiteral>() {
+        Some(Object::Null)
+    } else {
+        None
+    }
+}
+
+/// The literal for a value, located at `span`, if it's a value literals can be written for.
+fn literal(value: Object, span: Span) -> Option<Box<dyn Expression>> {
+    Some(match value {
+        Object::Integer(i) => {
+            let token = Token::new(TokenType::Int, i.to_string());
+            let token = token.with_span(span);
+            Box::new(IntegerLiteral::new(token, i))
+        },
+        Object::Boolean(b) => {
+            let (token_type, literal) = match b {
+                true => (TokenType::True, Symbol::TRUE),
+                false => (TokenType::False, Symbol::FALSE),
+            };
+            let token = Token::from_symbol(token_type, literal);
+            Box::new(Boolean::new(token.with_span(span), b))
+        },
+        Object::String(s) => {
+            let token = Token::new(TokenType::Str, s.to_string()).with_span(span);
+            Box::new(StringLiteral::new(token, &s))
+        },
+        Object::Null => null(span),
+        _ => return None,
+    })
+}
+
+fn null(span: Span) -> Box<dyn Expression> {
+    Box::new(NullLiteral::new(
+        Token::new(TokenType::Null, "null").with_span(span),
+    ))
+}
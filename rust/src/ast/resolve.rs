@@ -0,0 +1,623 @@
+use std::collections::HashMap;
+
+use crate::{ast::*, eval::builtins, object::Exception};
+
+fn downcast<T: 'static>(node: &dyn Node) -> &T {
+    node.as_any().downcast_ref::<T>().unwrap()
+}
+
+/// Works out where the value each name in a program is kept before it's evaluated, so that
+/// evaluation can find it by index instead of looking it up by name, and reports the names
+/// that can't refer to anything.
+///
+/// There's a scope for each environment evaluation makes: one for the program, one for each
+/// call to a function, and one for each match arm and catch block, for the names they bind.
+/// Blocks share the scope they're in. A name defined anywhere in a scope refers to the same
+/// slot throughout it, so functions can refer to names defined after them, but the scope's
+/// own code can't use a name before it's defined.
+///
+/// A name a function uses that isn't defined anywhere in the program is reported once the
+/// whole program is resolved. When programs are resolved incrementally, like the lines of the
+/// REPL, it's taken to be a global that a later program defines before the function is called
+/// instead, and using it before then fails when it's evaluated.
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    scopes: Vec<Scope>,
+    incremental: bool,
+}
+
+/// The names of one environment, which are given slots in the order they're declared.
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    variables: HashMap<Symbol, Variable>,
+    /// Whether the scope is a function's, whose body can run after the names outside it
+    /// are defined.
+    function: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Variable {
+    slot: usize,
+    state: State,
+    /// Where the name was first used, if that was before it was declared.
+    used_at: Option<Span>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Only used by functions, which are expecting it to be defined globally later.
+    Used,
+    /// Defined somewhere in the scope, but not before the code being resolved.
+    Declared,
+    Defined,
+}
+
+impl Scope {
+    fn function() -> Self {
+        Scope {
+            function: true,
+            ..Scope::default()
+        }
+    }
+
+    /// Gives a name a slot if it doesn't have one yet, keeping the one it has otherwise, since
+    /// defining a name again replaces its value.
+    fn declare(&mut self, name: Symbol, state: State) -> &mut Variable {
+        let slot = self.variables.len();
+        let variable = self.variables.entry(name).or_insert(Variable {
+            slot,
+            state: State::Used,
+            used_at: None,
+        });
+        if variable.state == State::Used {
+            variable.state = state;
+        }
+        variable
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Resolver {
+            scopes: vec![Scope::default()],
+            incremental: false,
+        }
+    }
+
+    /// A resolver for programs that are parts of one, like the lines of the REPL, whose
+    /// functions can use globals the programs after them define.
+    pub fn incremental() -> Self {
+        Resolver {
+            incremental: true,
+            ..Resolver::new()
+        }
+    }
+
+    /// Resolves the names in a program, which sees the globals of the programs resolved before
+    /// it, like its evaluation will. A program that fails to resolve defines nothing.
+    pub fn resolve(&mut self, program: &Program) -> Result<(), Exception> {
+        let globals = self.scopes[0].clone();
+        self.declare_statements(&program.statements);
+        let result = self
+            .resolve_statements(&program.statements)
+            .and_then(|()| self.check_defined());
+        if result.is_err() {
+            self.scopes.truncate(1);
+            self.scopes[0] = globals;
+        }
+        result
+    }
+
+    /// The names of the globals of the programs resolved so far, and the slots they're kept in.
+    pub fn globals(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        let variables = self.scopes[0].variables.iter();
+        variables.map(|(name, variable)| (name.as_str(), variable.slot))
+    }
+
+    /// Fails on the first use of a global that functions use but nothing defines, unless a
+    /// later program can still define it.
+    fn check_defined(&self) -> Result<(), Exception> {
+        if self.incremental {
+            return Ok(());
+        }
+        let undefined = self.scopes[0]
+            .variables
+            .iter()
+            .filter(|(_, variable)| variable.state == State::Used)
+            .filter_map(|(name, variable)| Some((name, variable.used_at?)))
+            .min_by_key(|(_, span)| (span.line(), span.column()));
+        match undefined {
+            Some((name, span)) => {
+                Err(Exception::from(format!("identifier not found: {}", name)).with_span(span))
+            },
+            None => Ok(()),
+        }
+    }
+
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    /// Resolves code in a scope of its own.
+    fn enclosed(
+        &mut self,
+        scope: Scope,
+        f: impl FnOnce(&mut Self) -> Result<(), Exception>,
+    ) -> Result<(), Exception> {
+        self.scopes.push(scope);
+        f(self)?;
+        self.scopes.pop();
+        Ok(())
+    }
+
+    /// Defines a name in the innermost scope, where evaluation will set it.
+    fn define(&mut self, name: &Identifier) {
+        let variable = self.scope().declare(name.symbol(), State::Defined);
+        // a name declared before its definition is defined from here on
+        variable.state = State::Defined;
+        let slot = variable.slot;
+        name.binding.set(Some(Binding::Variable { depth: 0, slot }));
+    }
+
+    /// Finds what a name refers to. Assignments can't refer to builtins.
+    fn lookup(&mut self, name: Symbol, span: Span, assigned: bool) -> Result<Binding, String> {
+        let mut in_function = false;
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(variable) = scope.variables.get(&name) {
+                match variable.state {
+                    State::Used if !in_function => break,
+                    State::Declared if !in_function => {
+                        return Err(format!("identifier used before it's defined: {}", name))
+                    },
+                    _ => {},
+                }
+                let slot = variable.slot;
+                return Ok(Binding::Variable { depth, slot });
+            }
+            in_function |= scope.function;
+        }
+        if let Some(index) = builtins::index_of(name.as_str()).filter(|_| !assigned) {
+            return Ok(Binding::Builtin(index));
+        }
+        if !in_function {
+            return Err(format!("identifier not found: {}", name));
+        }
+        let depth = self.scopes.len() - 1;
+        let variable = self.scopes[0].declare(name, State::Used);
+        variable.used_at.get_or_insert(span);
+        let slot = variable.slot;
+        Ok(Binding::Variable { depth, slot })
+    }
+
+    fn resolve_identifier(&mut self, ident: &Identifier) -> Result<(), Exception> {
+        let binding = self
+            .lookup(ident.symbol(), ident.span(), false)
+            .map_err(|err| Exception::from(err).with_span(ident.span()))?;
+        ident.binding.set(Some(binding));
+        Ok(())
+    }
+
+    fn resolve_statements(&mut self, statements: &[Box<dyn Statement>]) -> Result<(), Exception> {
+        statements
+            .iter()
+            .try_for_each(|stmt| self.resolve_statement(stmt.as_ref()))
+    }
+
+    fn resolve_block(&mut self, block: &BlockStatement) -> Result<(), Exception> {
+        self.resolve_statements(&block.statements)
+    }
+
+    fn resolve_statement(&mut self, stmt: &dyn Statement) -> Result<(), Exception> {
+        match stmt.node_type() {
+            NodeType::LetStatement => {
+                let stmt = downcast::<LetStatement>(stmt);
+                self.resolve_expression(stmt.value.as_ref())?;
+                self.define(&stmt.name);
+            },
+            NodeType::DestructuringLetStatement => {
+                let stmt = downcast::<DestructuringLetStatement>(stmt);
+                self.resolve_expression(stmt.value.as_ref())?;
+                self.resolve_pattern(stmt.pattern.as_ref())?;
+            },
+            NodeType::ReturnStatement => {
+                self.resolve_expression(downcast::<ReturnStatement>(stmt).return_value.as_ref())?
+            },
+            NodeType::ExpressionStatement => {
+                self.resolve_expression(downcast::<ExpressionStatement>(stmt).expression.as_ref())?
+            },
+            NodeType::BlockStatement => self.resolve_block(downcast::<BlockStatement>(stmt))?,
+            NodeType::WhileStatement => {
+                let stmt = downcast::<WhileStatement>(stmt);
+                self.resolve_expression(stmt.condition.as_ref())?;
+                self.resolve_block(&stmt.body)?;
+            },
+            NodeType::ForStatement => {
+                let stmt = downcast::<ForStatement>(stmt);
+                self.resolve_expression(stmt.iterable.as_ref())?;
+                self.define(&stmt.variable);
+                self.resolve_block(&stmt.body)?;
+            },
+            NodeType::StructStatement => self.define(&downcast::<StructStatement>(stmt).name),
+            NodeType::EnumStatement => self.define(&downcast::<EnumStatement>(stmt).name),
+            NodeType::ThrowStatement => {
+                self.resolve_expression(downcast::<ThrowStatement>(stmt).value.as_ref())?
+            },
+            NodeType::YieldStatement => {
+                self.resolve_expression(downcast::<YieldStatement>(stmt).value.as_ref())?
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+
+    fn resolve_expression(&mut self, expr: &dyn Node) -> Result<(), Exception> {
+        match expr.node_type() {
+            NodeType::BlockStatement => self.resolve_block(downcast::<BlockStatement>(expr))?,
+            NodeType::Identifier => self.resolve_identifier(downcast::<Identifier>(expr))?,
+            NodeType::PrefixExpression => {
+                self.resolve_expression(downcast::<PrefixExpression>(expr).right.as_ref())?
+            },
+            NodeType::InfixExpression => {
+                let infix = downcast::<InfixExpression>(expr);
+                self.resolve_expression(infix.left.as_ref())?;
+                self.resolve_expression(infix.right.as_ref())?;
+            },
+            NodeType::IfExpression => {
+                let if_expr = downcast::<IfExpression>(expr);
+                self.resolve_expression(if_expr.condition.as_ref())?;
+                self.resolve_block(&if_expr.consequence)?;
+                if let Some(alternative) = &if_expr.alternative {
+                    self.resolve_block(alternative)?;
+                }
+            },
+            NodeType::FunctionLiteral => {
+                self.resolve_function_literal(downcast::<FunctionLiteral>(expr))?
+            },
+            NodeType::CallExpression => {
+                let call = downcast::<CallExpression>(expr);
+                self.resolve_expression(call.function.as_ref())?;
+                for arg in &call.arguments {
+                    self.resolve_expression(arg.as_ref())?;
+                }
+            },
+            NodeType::ArrayLiteral => {
+                for element in &downcast::<ArrayLiteral>(expr).elements {
+                    self.resolve_expression(element.as_ref())?;
+                }
+            },
+            NodeType::IndexExpression => {
+                let index = downcast::<IndexExpression>(expr);
+                self.resolve_expression(index.left.as_ref())?;
+                self.resolve_expression(index.index.as_ref())?;
+            },
+            NodeType::MemberExpression => {
+                self.resolve_expression(downcast::<MemberExpression>(expr).object.as_ref())?
+            },
+            NodeType::HashLiteral => {
+                for (key, value) in &downcast::<HashLiteral>(expr).pairs {
+                    self.resolve_expression(key.as_ref())?;
+                    self.resolve_expression(value.as_ref())?;
+                }
+            },
+            NodeType::AssignExpression => {
+                self.resolve_assign_expression(downcast::<AssignExpression>(expr))?
+            },
+            NodeType::MatchExpression => {
+                let match_expr = downcast::<MatchExpression>(expr);
+                self.resolve_expression(match_expr.subject.as_ref())?;
+                for arm in &match_expr.arms {
+                    self.resolve_match_arm(arm)?;
+                }
+            },
+            NodeType::SpreadExpression => {
+                self.resolve_expression(downcast::<SpreadExpression>(expr).value.as_ref())?
+            },
+            NodeType::NamedArgument => {
+                self.resolve_expression(downcast::<NamedArgument>(expr).value.as_ref())?
+            },
+            NodeType::InterpolatedString => {
+                for part in &downcast::<InterpolatedString>(expr).parts {
+                    if let StringPart::Expression(expr) = part {
+                        self.resolve_expression(expr.as_ref())?;
+                    }
+                }
+            },
+            NodeType::StructLiteral => {
+                let literal = downcast::<StructLiteral>(expr);
+                self.resolve_identifier(&literal.name)?;
+                for (_, value) in &literal.fields {
+                    self.resolve_expression(value.as_ref())?;
+                }
+            },
+            NodeType::TryExpression => {
+                let try_expr = downcast::<TryExpression>(expr);
+                self.resolve_block(&try_expr.body)?;
+                if let Some((name, catch)) = &try_expr.catch {
+                    // the caught value is only visible to the catch block
+                    self.enclosed(Scope::default(), |resolver| {
+                        resolver.define(name);
+                        resolver.declare_block(catch);
+                        resolver.resolve_block(catch)
+                    })?;
+                }
+                if let Some(finally) = &try_expr.finally {
+                    self.resolve_block(finally)?;
+                }
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+
+    fn resolve_function_literal(&mut self, func: &FunctionLiteral) -> Result<(), Exception> {
+        let params = &func.parameters;
+        self.enclosed(Scope::function(), |resolver| {
+            for name in params.names.iter().chain(&params.rest) {
+                resolver.scope().declare(name.symbol(), State::Declared);
+            }
+            resolver.declare_block(&func.body);
+            // defaults are evaluated in the call's environment as the parameters are bound, so
+            // they can refer to the parameters before them
+            for (name, default) in params.names.iter().zip(&params.defaults) {
+                if let Some(default) = default {
+                    resolver.resolve_expression(default.as_ref())?;
+                }
+                resolver.define(name);
+            }
+            if let Some(rest) = &params.rest {
+                resolver.define(rest);
+            }
+            resolver.resolve_block(&func.body)
+        })
+    }
+
+    fn resolve_assign_expression(&mut self, expr: &AssignExpression) -> Result<(), Exception> {
+        // the parser only accepts a name followed by any number of indexes and members as a
+        // target
+        let mut target = expr.target.as_ref();
+        loop {
+            match target.node_type() {
+                NodeType::IndexExpression => {
+                    let index = downcast::<IndexExpression>(target);
+                    self.resolve_expression(index.index.as_ref())?;
+                    target = index.left.as_ref();
+                },
+                NodeType::MemberExpression => {
+                    target = downcast::<MemberExpression>(target).object.as_ref();
+                },
+                _ => break,
+            }
+        }
+        self.resolve_expression(expr.value.as_ref())?;
+        let name = downcast::<Identifier>(target);
+        let binding = self
+            .lookup(name.symbol(), expr.span(), true)
+            .map_err(|err| Exception::from(err).with_span(expr.span()))?;
+        name.binding.set(Some(binding));
+        Ok(())
+    }
+
+    fn resolve_match_arm(&mut self, arm: &MatchArm) -> Result<(), Exception> {
+        // bindings made by the pattern are only visible to this arm's guard and body
+        self.enclosed(Scope::default(), |resolver| {
+            resolver.declare_pattern(arm.pattern.as_ref());
+            if let Some(guard) = &arm.guard {
+                resolver.declare_expression(guard.as_ref());
+            }
+            resolver.declare_expression(arm.body.as_ref());
+            resolver.resolve_pattern(arm.pattern.as_ref())?;
+            if let Some(guard) = &arm.guard {
+                resolver.resolve_expression(guard.as_ref())?;
+            }
+            resolver.resolve_expression(arm.body.as_ref())
+        })
+    }
+
+    /// Resolves a pattern, defining the names it binds.
+    fn resolve_pattern(&mut self, pattern: &dyn Pattern) -> Result<(), Exception> {
+        match pattern.node_type() {
+            NodeType::Identifier => self.define(downcast::<Identifier>(pattern)),
+            NodeType::RestPattern => {
+                if let Some(name) = &downcast::<RestPattern>(pattern).name {
+                    self.define(name);
+                }
+            },
+            NodeType::ArrayPattern => {
+                for element in &downcast::<ArrayPattern>(pattern).elements {
+                    self.resolve_pattern(element.as_ref())?;
+                }
+            },
+            NodeType::HashPattern => {
+                for (key, value) in &downcast::<HashPattern>(pattern).pairs {
+                    self.resolve_expression(key.as_ref())?;
+                    self.resolve_pattern(value.as_ref())?;
+                }
+            },
+            NodeType::VariantPattern => {
+                let pattern = downcast::<VariantPattern>(pattern);
+                self.resolve_identifier(&pattern.enum_name)?;
+                for field in pattern.fields.iter().flatten() {
+                    self.resolve_pattern(field.as_ref())?;
+                }
+            },
+            NodeType::WildcardPattern => {},
+            _ => self.resolve_expression(pattern)?,
+        }
+        Ok(())
+    }
+
+    /// Declares the names the statements define in the innermost scope, leaving out those
+    /// defined in the scopes of functions, match arms and catch blocks in them.
+    fn declare_statements(&mut self, statements: &[Box<dyn Statement>]) {
+        for stmt in statements {
+            self.declare_statement(stmt.as_ref());
+        }
+    }
+
+    fn declare_block(&mut self, block: &BlockStatement) {
+        self.declare_statements(&block.statements);
+    }
+
+    fn declare(&mut self, name: &Identifier) {
+        self.scope().declare(name.symbol(), State::Declared);
+    }
+
+    fn declare_statement(&mut self, stmt: &dyn Statement) {
+        match stmt.node_type() {
+            NodeType::LetStatement => {
+                let stmt = downcast::<LetStatement>(stmt);
+                self.declare_expression(stmt.value.as_ref());
+                self.declare(&stmt.name);
+            },
+            NodeType::DestructuringLetStatement => {
+                let stmt = downcast::<DestructuringLetStatement>(stmt);
+                self.declare_expression(stmt.value.as_ref());
+                self.declare_pattern(stmt.pattern.as_ref());
+            },
+            NodeType::ReturnStatement => {
+                self.declare_expression(downcast::<ReturnStatement>(stmt).return_value.as_ref())
+            },
+            NodeType::ExpressionStatement => {
+                self.declare_expression(downcast::<ExpressionStatement>(stmt).expression.as_ref())
+            },
+            NodeType::BlockStatement => self.declare_block(downcast::<BlockStatement>(stmt)),
+            NodeType::WhileStatement => {
+                let stmt = downcast::<WhileStatement>(stmt);
+                self.declare_expression(stmt.condition.as_ref());
+                self.declare_block(&stmt.body);
+            },
+            NodeType::ForStatement => {
+                let stmt = downcast::<ForStatement>(stmt);
+                self.declare_expression(stmt.iterable.as_ref());
+                self.declare(&stmt.variable);
+                self.declare_block(&stmt.body);
+            },
+            NodeType::StructStatement => self.declare(&downcast::<StructStatement>(stmt).name),
+            NodeType::EnumStatement => self.declare(&downcast::<EnumStatement>(stmt).name),
+            NodeType::ThrowStatement => {
+                self.declare_expression(downcast::<ThrowStatement>(stmt).value.as_ref())
+            },
+            NodeType::YieldStatement => {
+                self.declare_expression(downcast::<YieldStatement>(stmt).value.as_ref())
+            },
+            _ => {},
+        }
+    }
+
+    /// Declares the names defined by the statements in blocks in an expression, which can
+    /// be nested in any part of it.
+    fn declare_expression(&mut self, expr: &dyn Node) {
+        match expr.node_type() {
+            NodeType::BlockStatement => self.declare_block(downcast::<BlockStatement>(expr)),
+            NodeType::PrefixExpression => {
+                self.declare_expression(downcast::<PrefixExpression>(expr).right.as_ref())
+            },
+            NodeType::InfixExpression => {
+                let infix = downcast::<InfixExpression>(expr);
+                self.declare_expression(infix.left.as_ref());
+                self.declare_expression(infix.right.as_ref());
+            },
+            NodeType::IfExpression => {
+                let if_expr = downcast::<IfExpression>(expr);
+                self.declare_expression(if_expr.condition.as_ref());
+                self.declare_block(&if_expr.consequence);
+                if let Some(alternative) = &if_expr.alternative {
+                    self.declare_block(alternative);
+                }
+            },
+            NodeType::CallExpression => {
+                let call = downcast::<CallExpression>(expr);
+                self.declare_expression(call.function.as_ref());
+                for arg in &call.arguments {
+                    self.declare_expression(arg.as_ref());
+                }
+            },
+            NodeType::ArrayLiteral => {
+                for element in &downcast::<ArrayLiteral>(expr).elements {
+                    self.declare_expression(element.as_ref());
+                }
+            },
+            NodeType::IndexExpression => {
+                let index = downcast::<IndexExpression>(expr);
+                self.declare_expression(index.left.as_ref());
+                self.declare_expression(index.index.as_ref());
+            },
+            NodeType::MemberExpression => {
+                self.declare_expression(downcast::<MemberExpression>(expr).object.as_ref())
+            },
+            NodeType::HashLiteral => {
+                for (key, value) in &downcast::<HashLiteral>(expr).pairs {
+                    self.declare_expression(key.as_ref());
+                    self.declare_expression(value.as_ref());
+                }
+            },
+            NodeType::AssignExpression => {
+                let assign = downcast::<AssignExpression>(expr);
+                self.declare_expression(assign.target.as_ref());
+                self.declare_expression(assign.value.as_ref());
+            },
+            NodeType::MatchExpression => {
+                self.declare_expression(downcast::<MatchExpression>(expr).subject.as_ref())
+            },
+            NodeType::SpreadExpression => {
+                self.declare_expression(downcast::<SpreadExpression>(expr).value.as_ref())
+            },
+            NodeType::NamedArgument => {
+                self.declare_expression(downcast::<NamedArgument>(expr).value.as_ref())
+            },
+            NodeType::InterpolatedString => {
+                for part in &downcast::<InterpolatedString>(expr).parts {
+                    if let StringPart::Expression(expr) = part {
+                        self.declare_expression(expr.as_ref());
+                    }
+                }
+            },
+            NodeType::StructLiteral => {
+                for (_, value) in &downcast::<StructLiteral>(expr).fields {
+                    self.declare_expression(value.as_ref());
+                }
+            },
+            NodeType::TryExpression => {
+                let try_expr = downcast::<TryExpression>(expr);
+                self.declare_block(&try_expr.body);
+                if let Some(finally) = &try_expr.finally {
+                    self.declare_block(finally);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn declare_pattern(&mut self, pattern: &dyn Pattern) {
+        match pattern.node_type() {
+            NodeType::Identifier => self.declare(downcast::<Identifier>(pattern)),
+            NodeType::RestPattern => {
+                if let Some(name) = &downcast::<RestPattern>(pattern).name {
+                    self.declare(name);
+                }
+            },
+            NodeType::ArrayPattern => {
+                for element in &downcast::<ArrayPattern>(pattern).elements {
+                    self.declare_pattern(element.as_ref());
+                }
+            },
+            NodeType::HashPattern => {
+                for (_, value) in &downcast::<HashPattern>(pattern).pairs {
+                    self.declare_pattern(value.as_ref());
+                }
+            },
+            NodeType::VariantPattern => {
+                for field in downcast::<VariantPattern>(pattern).fields.iter().flatten() {
+                    self.declare_pattern(field.as_ref());
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Resolver::new()
+    }
+}
@@ -0,0 +1,295 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::optimize,
+        object::{Exception, ExecutionLimits},
+        Binding, Engine, ExpressionStatement, FunctionLiteral, Identifier, InfixExpression,
+        Interpreter, LetStatement, Lexer, Node, Parser, Program, Resolver,
+    };
+
+    fn parse(input: &str) -> Program {
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        p.parse_program()
+            .unwrap_or_else(|err| panic!("{}: {}", input, err))
+    }
+
+    fn optimized(input: &str) -> Program {
+        let mut program = parse(input);
+        optimize(&mut program);
+        program
+    }
+
+    fn run(input: &str, optimized: bool) -> String {
+        let mut interpreter =
+            Interpreter::new(Engine::Tree, ExecutionLimits::new()).with_optimization(optimized);
+        match interpreter.run(parse(input)) {
+            Ok(obj) => obj.to_string(),
+            Err(err @ Exception::Thrown(..)) => match err.span() {
+                Some(span) => format!("{} at {}", err, span),
+                None => err.to_string(),
+            },
+            Err(err) => err.to_string(),
+        }
+    }
+
+    fn run_optimizer_tests(tests: Vec<(&str, &str)>) {
+        for (input, expected) in tests {
+            let program = optimized(input);
+            assert_eq!(program.to_string(), expected, "{}", input);
+            assert_eq!(run(input, true), run(input, false), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let tests = vec![
+            ("1 + 2 * 3", "7"),
+            ("-(2 - 5) ** 2", "-9"),
+            ("~0 & 6 | 1 << 4", "22"),
+            ("1 < 2 == true", "true"),
+            ("!(1 > 2)", "true"),
+            (r#""mon" + "key""#, r#""monkey""#),
+            (r#""a" == "b""#, "false"),
+            ("null == null", "true"),
+            ("x + (2 + 3)", "(x + 5)"),
+            ("let x = 4 / 2; x", "let x = 2;x"),
+            ("[1 + 1, {3 - 1: 2 * 2}]", "[2, {2: 4}]"),
+            (
+                "let f = fn(a = 1 + 1) { a * (3 - 1) }; f()",
+                "let f = fn(a = 2) {(a * 2)};f()",
+            ),
+            ("false && f()", "false"),
+            ("1 || f()", "true"),
+            ("true && 0", "true"),
+            ("true && null", "false"),
+            ("true && f()", "(true && f())"),
+            ("2 ?? f()", "2"),
+            ("null ?? f()", "f()"),
+            (
+                "let a = [1, 2, 3]; a[1 + 1] += 2 * 2; a",
+                "let a = [1, 2, 3];((a[2]) += 4)a",
+            ),
+            ("\"${1 + 1}\"", "\"${2}\""),
+        ];
+        run_optimizer_tests(tests);
+    }
+
+    #[test]
+    fn test_failing_operations_are_not_folded() {
+        let tests = vec![
+            ("1 / 0", "(1 / 0)"),
+            ("(2 + 3) % (1 - 1)", "(5 % 0)"),
+            ("9223372036854775807 + 1", "(9223372036854775807 + 1)"),
+            ("-(-9223372036854775807 - 1)", "(--9223372036854775808)"),
+            ("2 ** -1", "(2 ** -1)"),
+            ("1 + true", "(1 + true)"),
+            ("-\"a\"", "(-\"a\")"),
+            ("1..3", "(1 .. 3)"),
+        ];
+        for (input, expected) in &tests {
+            assert_eq!(optimized(input).to_string(), *expected, "{}", input);
+        }
+        // they fail where they would have, with the same errors
+        for (input, _) in tests {
+            let input = format!("let x = 1;\nlet y = {};", input);
+            assert_eq!(run(&input, true), run(&input, false), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_dead_branches() {
+        let tests = vec![
+            ("if (true) { 1 } else { 2 }", "1"),
+            ("if (1 > 2) { 1 } else { 2 }", "2"),
+            ("if (false) { 1 }", "null"),
+            ("if (0) { 1 } else { 2 }", "1"),
+            ("if (null) { 1 } else { 2 }", "2"),
+            ("let x = if (2 > 1) { 10 } else { 20 }; x", "let x = 10;x"),
+            (
+                "if (true) { let a = 1; a + 1 } else { 0 }",
+                "let a = 1;(a + 1)",
+            ),
+            ("if (false) { 1 }; 2", "2"),
+            ("1; \"a\"; x", "x"),
+            ("if (x) { 1 + 1 } else { 2 }", "if x {2} else {2}"),
+            (
+                "let f = fn() { if (true) { let a = 1; a } else { 2 } }; f()",
+                "let f = fn() {let a = 1;a};f()",
+            ),
+        ];
+        run_optimizer_tests(tests);
+    }
+
+    #[test]
+    fn test_unreachable_statements() {
+        let tests = vec![
+            (
+                "let f = fn() { return 1; 2 }; f()",
+                "let f = fn() {return 1;};f()",
+            ),
+            (
+                "let f = fn(x) { if (x) { return 1; x = 2 } 3 }; f(true)",
+                "let f = fn(x) {if x {return 1;}3};f(true)",
+            ),
+            (
+                "let f = fn() { if (true) { return 1 } 2 }; f()",
+                "let f = fn() {return 1;};f()",
+            ),
+            (
+                "let i = 0; while (i < 3) { i += 1; continue; i = 10 } i",
+                "let i = 0;while (i < 3) {(i += 1)continue;}i",
+            ),
+            ("return 1; 2", "return 1;"),
+        ];
+        run_optimizer_tests(tests);
+    }
+
+    #[test]
+    fn test_resolved_bindings() {
+        let program = parse("let a = 1; let f = fn(x) { a + x }");
+        Resolver::new().resolve(&program).unwrap();
+        let f = program.statements()[1].as_any();
+        let f = f.downcast_ref::<LetStatement>().unwrap();
+        assert_eq!(
+            f.name().binding(),
+            Some(Binding::Variable { depth: 0, slot: 1 })
+        );
+        let body = f.value().as_any().downcast_ref::<FunctionLiteral>();
+        let stmt = body.unwrap().body().statements()[0].as_any();
+        let expr = stmt.downcast_ref::<ExpressionStatement>().unwrap();
+        let sum = expr.expression().as_any();
+        let sum = sum.downcast_ref::<InfixExpression>().unwrap();
+        let binding = |expr: &dyn Node| {
+            let ident = expr.as_any().downcast_ref::<Identifier>().unwrap();
+            ident.binding()
+        };
+        assert_eq!(
+            binding(sum.left()),
+            Some(Binding::Variable { depth: 1, slot: 0 })
+        );
+        assert_eq!(
+            binding(sum.right()),
+            Some(Binding::Variable { depth: 0, slot: 0 })
+        );
+
+        let program = parse("len");
+        Resolver::new().resolve(&program).unwrap();
+        let stmt = program.statements()[0].as_any();
+        let expr = stmt.downcast_ref::<ExpressionStatement>().unwrap();
+        assert_eq!(binding(expr.expression()), Some(Binding::Builtin(0)));
+    }
+
+    #[test]
+    fn test_resolution() {
+        let tests =
+            vec![
+            ("let f = fn() { g() }; let g = fn() { 1 }; f()", "1"),
+            (
+                "let even = fn(n) { if (n == 0) { true } else { odd(n - 1) } };
+                 let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } };
+                 even(10)",
+                "true",
+            ),
+            ("let x = 1; let f = fn() { let x = 2; x }; [f(), x]", "[2, 1]"),
+            ("let x = 1; let f = fn(x) { x * 10 }; [f(2), x]", "[20, 1]"),
+            ("let x = 1; [match (5) { x => x }, x]", "[5, 1]"),
+            ("let x = 1; let x = x + 1; x", "2"),
+            ("let f = fn(a, b = a * 2, ...c) { [a, b, c] }; f(1)", "[1, 2, []]"),
+            ("let len = fn(a) { 0 }; len([1])", "0"),
+            (
+                "let counter = fn() { let n = 0; fn() { n += 1 } }; let c = counter(); c(); c()",
+                "2",
+            ),
+            ("let e = 1; [try { throw 2; } catch (e) { e }, e]", "[2, 1]"),
+            ("let [a, [b, ..c]] = [1, [2, 3]]; [a, b, c]", "[1, 2, [3]]"),
+            ("if (true) { let a = 1; } a", "1"),
+            ("let a = 0; for (i in 1..4) { let b = i; a += b } [a, i, b]", "[6, 3, 3]"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(run(input, false), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_resolution_errors() {
+        let tests = vec![
+            ("x", "identifier not found: x at 1:1"),
+            (
+                "let a = 1;\nif (false) { b }",
+                "identifier not found: b at 2:14",
+            ),
+            ("len = 1", "identifier not found: len at 1:5"),
+            ("let f = fn() { 1 }; g()", "identifier not found: g at 1:21"),
+            ("match (1) { n => n }; n", "identifier not found: n at 1:23"),
+            (
+                "try { 1 } catch (e) { e }; e",
+                "identifier not found: e at 1:28",
+            ),
+            (
+                "x; let x = 1",
+                "identifier used before it's defined: x at 1:1",
+            ),
+            (
+                "let x = x + 1",
+                "identifier used before it's defined: x at 1:9",
+            ),
+            (
+                "x += 1; let x = 1",
+                "identifier used before it's defined: x at 1:3",
+            ),
+            (
+                "let x = 1; let f = fn() { let y = x; let x = 2; y }",
+                "identifier used before it's defined: x at 1:35",
+            ),
+            (
+                "let f = fn(a = b, b = 1) { a }",
+                "identifier used before it's defined: b at 1:16",
+            ),
+            (
+                "let i = 0; while (i < 2) { if (i > 0) { j } let j = i; i += 1 }",
+                "identifier used before it's defined: j at 1:41",
+            ),
+            // names functions use are only found once the whole program has been resolved
+            (
+                "let f = fn() { g() }; f()",
+                "identifier not found: g at 1:16",
+            ),
+            (
+                "let f = fn() { missing }; 1",
+                "identifier not found: missing at 1:16",
+            ),
+            (
+                "let f = fn() { y += 1 };\nlet g = fn() { fn() { x; y } }",
+                "identifier not found: y at 1:18",
+            ),
+            (
+                "if (false) { 1 } else { 2 }; a; let a = 1",
+                "identifier used before it's defined: a at 1:30",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(run(input, false), expected, "{}", input);
+            // names are resolved before dead code is removed
+            assert_eq!(run(input, true), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_failed_resolution_defines_nothing() {
+        let mut interpreter = Interpreter::new(Engine::Tree, ExecutionLimits::new()).incremental();
+        let mut run = |input: &str| match interpreter.run(parse(input)) {
+            Ok(obj) => obj.to_string(),
+            Err(err) => err.to_string(),
+        };
+        assert_eq!(run("let f = fn() { g() }"), "null");
+        assert_eq!(run("f()"), "identifier not found: g");
+        assert_eq!(
+            run("let x = 1; let g = fn() { 2 }; y"),
+            "identifier not found: y"
+        );
+        assert_eq!(run("x"), "identifier not found: x");
+        assert_eq!(run("let g = fn() { 3 }"), "null");
+        assert_eq!(run("f()"), "3");
+    }
+}
@@ -0,0 +1,394 @@
+use std::fmt::Display;
+
+use crate::Span;
+
+/// An instruction's operation, which is its first byte. The operands that follow it are
+/// big-endian, with the widths given by its [`Definition`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Opcode {
+    /// Pushes the constant at the operand's index in the constant pool.
+    Constant,
+    Pop,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Equal,
+    NotEqual,
+    LessThan,
+    GreaterThan,
+    LessEqual,
+    GreaterEqual,
+    Range,
+    RangeInclusive,
+    Minus,
+    Bang,
+    BitNot,
+    True,
+    False,
+    Null,
+    Jump,
+    /// Pops the top of the stack and jumps if it isn't truthy.
+    JumpNotTruthy,
+    /// Jumps if the top of the stack is null, leaving it there.
+    JumpNull,
+    /// Jumps if the top of the stack isn't null, leaving it there, and pops it otherwise.
+    JumpNotNull,
+    GetGlobal,
+    SetGlobal,
+    GetLocal,
+    SetLocal,
+    /// Gets a local of an enclosing function, given how many functions out it is and its
+    /// index there.
+    GetFree,
+    SetFree,
+    GetBuiltin,
+    Array,
+    Hash,
+    Index,
+    /// Gets the field named by the constant at the operand's index.
+    GetField,
+    Call,
+    /// Calls a function whose result is returned right away, reusing the caller's frame.
+    TailCall,
+    /// Calls the method named by the constant at the first operand's index on the receiver
+    /// below the arguments.
+    CallMethod,
+    ReturnValue,
+    Closure,
+    /// Replaces the top of the stack with an iterator over it.
+    Iter,
+    /// Pops an iterator and pushes its next element, or jumps once it has run out.
+    IterNext,
+    /// Joins the given number of values into a string.
+    Interpolate,
+    /// Assigns to a place in a variable's value, given the constant describing the path to
+    /// the place and how many index values it uses. Pops the index values, the value being
+    /// assigned and the variable's value, and pushes the assignment's result and the
+    /// variable's new value.
+    Assign,
+}
+
+/// The name of an opcode and the widths of its operands in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Definition {
+    pub name: &'static str,
+    pub operand_widths: &'static [usize],
+}
+
+const OPCODES: &[Opcode] = &[
+    Opcode::Constant,
+    Opcode::Pop,
+    Opcode::Add,
+    Opcode::Sub,
+    Opcode::Mul,
+    Opcode::Div,
+    Opcode::Mod,
+    Opcode::Pow,
+    Opcode::BitAnd,
+    Opcode::BitOr,
+    Opcode::BitXor,
+    Opcode::Shl,
+    Opcode::Shr,
+    Opcode::Equal,
+    Opcode::NotEqual,
+    Opcode::LessThan,
+    Opcode::GreaterThan,
+    Opcode::LessEqual,
+    Opcode::GreaterEqual,
+    Opcode::Range,
+    Opcode::RangeInclusive,
+    Opcode::Minus,
+    Opcode::Bang,
+    Opcode::BitNot,
+    Opcode::True,
+    Opcode::False,
+    Opcode::Null,
+    Opcode::Jump,
+    Opcode::JumpNotTruthy,
+    Opcode::JumpNull,
+    Opcode::JumpNotNull,
+    Opcode::GetGlobal,
+    Opcode::SetGlobal,
+    Opcode::GetLocal,
+    Opcode::SetLocal,
+    Opcode::GetFree,
+    Opcode::SetFree,
+    Opcode::GetBuiltin,
+    Opcode::Array,
+    Opcode::Hash,
+    Opcode::Index,
+    Opcode::GetField,
+    Opcode::Call,
+    Opcode::TailCall,
+    Opcode::CallMethod,
+    Opcode::ReturnValue,
+    Opcode::Closure,
+    Opcode::Iter,
+    Opcode::IterNext,
+    Opcode::Interpolate,
+    Opcode::Assign,
+];
+
+/// Infix operators and the opcodes that apply them.
+const INFIX_OPERATORS: &[(&str, Opcode)] = &[
+    ("+", Opcode::Add),
+    ("-", Opcode::Sub),
+    ("*", Opcode::Mul),
+    ("/", Opcode::Div),
+    ("%", Opcode::Mod),
+    ("**", Opcode::Pow),
+    ("&", Opcode::BitAnd),
+    ("|", Opcode::BitOr),
+    ("^", Opcode::BitXor),
+    ("<<", Opcode::Shl),
+    (">>", Opcode::Shr),
+    ("==", Opcode::Equal),
+    ("!=", Opcode::NotEqual),
+    ("<", Opcode::LessThan),
+    (">", Opcode::GreaterThan),
+    ("<=", Opcode::LessEqual),
+    (">=", Opcode::GreaterEqual),
+    ("..", Opcode::Range),
+    ("..=", Opcode::RangeInclusive),
+];
+
+/// Prefix operators and the opcodes that apply them.
+const PREFIX_OPERATORS: &[(&str, Opcode)] = &[
+    ("-", Opcode::Minus),
+    ("!", Opcode::Bang),
+    ("~", Opcode::BitNot),
+];
+
+impl Opcode {
+    pub fn definition(self) -> Definition {
+        use Opcode::*;
+
+        let (name, operand_widths): (_, &[usize]) = match self {
+            Constant => ("OpConstant", &[2]),
+            Pop => ("OpPop", &[]),
+            Add => ("OpAdd", &[]),
+            Sub => ("OpSub", &[]),
+            Mul => ("OpMul", &[]),
+            Div => ("OpDiv", &[]),
+            Mod => ("OpMod", &[]),
+            Pow => ("OpPow", &[]),
+            BitAnd => ("OpBitAnd", &[]),
+            BitOr => ("OpBitOr", &[]),
+            BitXor => ("OpBitXor", &[]),
+            Shl => ("OpShl", &[]),
+            Shr => ("OpShr", &[]),
+            Equal => ("OpEqual", &[]),
+            NotEqual => ("OpNotEqual", &[]),
+            LessThan => ("OpLessThan", &[]),
+            GreaterThan => ("OpGreaterThan", &[]),
+            LessEqual => ("OpLessEqual", &[]),
+            GreaterEqual => ("OpGreaterEqual", &[]),
+            Range => ("OpRange", &[]),
+            RangeInclusive => ("OpRangeInclusive", &[]),
+            Minus => ("OpMinus", &[]),
+            Bang => ("OpBang", &[]),
+            BitNot => ("OpBitNot", &[]),
+            True => ("OpTrue", &[]),
+            False => ("OpFalse", &[]),
+            Null => ("OpNull", &[]),
+            Jump => ("OpJump", &[2]),
+            JumpNotTruthy => ("OpJumpNotTruthy", &[2]),
+            JumpNull => ("OpJumpNull", &[2]),
+            JumpNotNull => ("OpJumpNotNull", &[2]),
+            GetGlobal => ("OpGetGlobal", &[2]),
+            SetGlobal => ("OpSetGlobal", &[2]),
+            GetLocal => ("OpGetLocal", &[1]),
+            SetLocal => ("OpSetLocal", &[1]),
+            GetFree => ("OpGetFree", &[1, 1]),
+            SetFree => ("OpSetFree", &[1, 1]),
+            GetBuiltin => ("OpGetBuiltin", &[1]),
+            Array => ("OpArray", &[2]),
+            Hash => ("OpHash", &[2]),
+            Index => ("OpIndex", &[]),
+            GetField => ("OpGetField", &[2]),
+            Call => ("OpCall", &[1]),
+            TailCall => ("OpTailCall", &[1]),
+            CallMethod => ("OpCallMethod", &[2, 1]),
+            ReturnValue => ("OpReturnValue", &[]),
+            Closure => ("OpClosure", &[2]),
+            Iter => ("OpIter", &[]),
+            IterNext => ("OpIterNext", &[2]),
+            Interpolate => ("OpInterpolate", &[2]),
+            Assign => ("OpAssign", &[2, 1]),
+        };
+        Definition {
+            name,
+            operand_widths,
+        }
+    }
+
+    /// The opcode applying an infix operator, unless it's one that needs jumps.
+    pub fn infix(operator: &str) -> Option<Opcode> {
+        INFIX_OPERATORS
+            .iter()
+            .find(|(o, _)| *o == operator)
+            .map(|(_, op)| *op)
+    }
+
+    pub fn prefix(operator: &str) -> Option<Opcode> {
+        PREFIX_OPERATORS
+            .iter()
+            .find(|(o, _)| *o == operator)
+            .map(|(_, op)| *op)
+    }
+
+    /// The operator this opcode applies, if it's an infix or prefix operator.
+    pub fn operator(self) -> Option<&'static str> {
+        INFIX_OPERATORS
+            .iter()
+            .chain(PREFIX_OPERATORS)
+            .find(|(_, op)| *op == self)
+            .map(|(o, _)| *o)
+    }
+}
+
+impl TryFrom<u8> for Opcode {
+    type Error = String;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        OPCODES
+            .get(byte as usize)
+            .copied()
+            .ok_or_else(|| format!("opcode {} undefined", byte))
+    }
+}
+
+/// Encodes an instruction.
+pub fn make(op: Opcode, operands: &[usize]) -> Vec<u8> {
+    let widths = op.definition().operand_widths;
+    let mut instruction = vec![op as u8];
+    for (operand, width) in operands.iter().zip(widths) {
+        match width {
+            2 => instruction.extend((*operand as u16).to_be_bytes()),
+            1 => instruction.push(*operand as u8),
+            _ => unreachable!("operands are 1 or 2 bytes wide"),
+        }
+    }
+    instruction
+}
+
+/// Decodes the operands at the start of `ins`, returning them and how many bytes they took up.
+pub fn read_operands(def: &Definition, ins: &[u8]) -> (Vec<usize>, usize) {
+    let mut operands = Vec::with_capacity(def.operand_widths.len());
+    let mut offset = 0;
+    for width in def.operand_widths {
+        operands.push(match width {
+            2 => read_u16(ins, offset) as usize,
+            _ => ins[offset] as usize,
+        });
+        offset += width;
+    }
+    (operands, offset)
+}
+
+pub fn read_u16(ins: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([ins[offset], ins[offset + 1]])
+}
+
+/// A sequence of encoded instructions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Instructions(pub Vec<u8>);
+
+impl Instructions {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Decodes the instructions, giving the offset of each along with its opcode and operands,
+    /// up to and including the first that can't be decoded.
+    pub fn decode(
+        &self,
+    ) -> impl Iterator<Item = (usize, Result<(Opcode, Vec<usize>), String>)> + '_ {
+        let mut offset = 0;
+        std::iter::from_fn(move || {
+            let start = offset;
+            let op = match Opcode::try_from(*self.0.get(start)?) {
+                Ok(op) => op,
+                Err(err) => {
+                    offset = self.0.len();
+                    return Some((start, Err(err)));
+                },
+            };
+            let def = op.definition();
+            if self.0.len() - start - 1 < def.operand_widths.iter().sum() {
+                offset = self.0.len();
+                return Some((start, Err(format!("{} is cut off", def.name))));
+            }
+            let (operands, read) = read_operands(&def, &self.0[start + 1..]);
+            offset = start + 1 + read;
+            Some((start, Ok((op, operands))))
+        })
+    }
+}
+
+/// Lists the instructions one per line, like `0000 OpConstant 0`.
+impl Display for Instructions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (offset, instruction) in self.decode() {
+            match instruction {
+                Ok((op, operands)) => {
+                    writeln!(f, "{:04} {}", offset, format_instruction(op, &operands))?
+                },
+                Err(err) => writeln!(f, "{:04} ERROR: {}", offset, err)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats an instruction like `OpGetFree 1 0`.
+pub fn format_instruction(op: Opcode, operands: &[usize]) -> String {
+    let mut instruction = op.definition().name.to_string();
+    for operand in operands {
+        instruction.push_str(&format!(" {}", operand));
+    }
+    instruction
+}
+
+impl From<Vec<Vec<u8>>> for Instructions {
+    fn from(instructions: Vec<Vec<u8>>) -> Self {
+        Instructions(instructions.concat())
+    }
+}
+
+/// Where the code for the instructions starting at each offset came from, so runtime errors
+/// can be located like the tree-walking evaluator locates them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap(Vec<(usize, Span)>);
+
+impl SourceMap {
+    /// Records that the instructions from `offset` on come from `span`.
+    pub fn add(&mut self, offset: usize, span: Span) {
+        if self.0.last().is_none_or(|(_, last)| *last != span) {
+            self.0.push((offset, span));
+        }
+    }
+
+    /// Where the instruction at `offset` came from.
+    pub fn span_at(&self, offset: usize) -> Option<Span> {
+        let index = self.0.partition_point(|(start, _)| *start <= offset);
+        index.checked_sub(1).map(|i| self.0[i].1)
+    }
+
+    pub fn entries(&self) -> &[(usize, Span)] {
+        &self.0
+    }
+}
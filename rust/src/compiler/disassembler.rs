@@ -0,0 +1,103 @@
+use std::fmt::{self, Display};
+
+use crate::{
+    compiler::{format_instruction, Bytecode, Instructions, Opcode},
+    eval::builtins,
+    object::Object,
+};
+
+/// Lists the constant pool, with the code of each compiled function in it, the globals and
+/// then the program's own code. Instructions are annotated with the constants, globals,
+/// locals and builtins their operands refer to.
+impl Display for Bytecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "constants:")?;
+        for (index, constant) in self.constants.iter().enumerate() {
+            writeln!(f, "{:04} {}", index, describe(constant))?;
+            if let Object::CompiledFunction(function) = constant {
+                let locals = function.locals().iter().map(|name| shown(name));
+                let locals = locals.collect::<Vec<_>>();
+                writeln!(
+                    f,
+                    "     parameters: {}, locals: {}",
+                    function.num_parameters(),
+                    locals.join(", ")
+                )?;
+                self.list(f, function.instructions(), function.locals(), "     ")?;
+            }
+        }
+        writeln!(f, "globals:")?;
+        for (index, name) in self.globals.iter().enumerate() {
+            writeln!(f, "{:04} {}", index, shown(name))?;
+        }
+        writeln!(f, "instructions:")?;
+        self.list(f, &self.instructions, &[], "")
+    }
+}
+
+impl Bytecode {
+    /// Lists code run with `locals`, indenting each line by `indent`.
+    fn list(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        instructions: &Instructions,
+        locals: &[String],
+        indent: &str,
+    ) -> fmt::Result {
+        for (offset, instruction) in instructions.decode() {
+            let (op, operands) = match instruction {
+                Ok(instruction) => instruction,
+                Err(err) => return writeln!(f, "{}{:04} ERROR: {}", indent, offset, err),
+            };
+            write!(
+                f,
+                "{}{:04} {}",
+                indent,
+                offset,
+                format_instruction(op, &operands)
+            )?;
+            match self.note(op, &operands, locals) {
+                Some(note) => writeln!(f, " ; {}", note)?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// What an instruction's first operand refers to, if it refers to something by index.
+    fn note(&self, op: Opcode, operands: &[usize], locals: &[String]) -> Option<String> {
+        use Opcode::*;
+
+        let index = *operands.first()?;
+        match op {
+            Constant | GetField | CallMethod | Closure | Assign => {
+                self.constants.get(index).map(describe)
+            },
+            GetGlobal | SetGlobal => self.globals.get(index).map(|name| shown(name).to_string()),
+            GetLocal | SetLocal => locals.get(index).map(|name| shown(name).to_string()),
+            GetBuiltin => builtins::at(index).map(|builtin| builtin.name().to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Shows a variable's name, or `_` for the variables the compiler makes for itself.
+fn shown(name: &str) -> &str {
+    if name.is_empty() {
+        "_"
+    } else {
+        name
+    }
+}
+
+/// Shows a constant, quoting strings so they can be told apart from names.
+fn describe(constant: &Object) -> String {
+    match constant {
+        Object::String(s) => format!("{:?}", s.as_ref()),
+        Object::Array(elements) => {
+            let elements = elements.iter().map(describe).collect::<Vec<_>>();
+            format!("[{}]", elements.join(", "))
+        },
+        _ => constant.to_string(),
+    }
+}
@@ -0,0 +1,393 @@
+//! The format compiled programs are written to `.mkc` files in, so they can be run later
+//! without being parsed and compiled again.
+//!
+//! A file starts with [`MAGIC`], the format's [`VERSION`] and a CRC-32 checksum of the rest of
+//! the file, which holds the program's code, its constant pool and the names of its globals.
+//! Numbers are big-endian, like operands, and strings and lists are preceded by their length.
+
+use std::{collections::HashSet, rc::Rc};
+
+use crate::{
+    compiler::{Bytecode, Instructions, Opcode, SourceMap},
+    eval::builtins,
+    object::{CompiledFunction, Object},
+    Span,
+};
+
+/// What every compiled file starts with.
+pub const MAGIC: &[u8; 4] = b"MKC\0";
+
+/// The version of the format, which changes whenever the format or the meaning of any opcode
+/// does, since files are only run by the version of the VM that compiled them.
+pub const VERSION: u16 = 1;
+
+const INTEGER: u8 = 0;
+const STRING: u8 = 1;
+const NULL: u8 = 2;
+const ARRAY: u8 = 3;
+const FUNCTION: u8 = 4;
+
+/// How deeply array constants can be nested in a file. The compiler only makes arrays of the
+/// indexes assignments use, so this is plenty, and reading a corrupt file that nests them
+/// much more deeply could overflow the stack.
+const MAX_CONSTANT_DEPTH: usize = 32;
+
+impl Bytecode {
+    /// Encodes the program in the compiled file format.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut body = Writer(Vec::new());
+        body.code(&self.instructions, &self.source_map)?;
+        body.len(self.constants.len())?;
+        for constant in &self.constants {
+            body.constant(constant)?;
+        }
+        body.strings(&self.globals)?;
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_be_bytes());
+        bytes.extend(checksum(&body.0).to_be_bytes());
+        bytes.extend(body.0);
+        Ok(bytes)
+    }
+
+    /// Decodes a program in the compiled file format, checking that it was written by this
+    /// version, that it hasn't been corrupted and that every instruction in it refers to
+    /// things that exist.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Bytecode, String> {
+        if !bytes.starts_with(MAGIC) {
+            return Err("not a compiled program".to_string());
+        }
+        let mut header = Reader {
+            bytes,
+            offset: MAGIC.len(),
+        };
+        let version = header.u16()?;
+        if version != VERSION {
+            return Err(format!(
+                "compiled with format version {}, but only version {} can be run",
+                version, VERSION
+            ));
+        }
+        let expected = header.u32()?;
+        let body = &bytes[header.offset..];
+        if checksum(body) != expected {
+            return Err("checksum mismatch: the file is corrupt".to_string());
+        }
+
+        let mut body = Reader {
+            bytes: body,
+            offset: 0,
+        };
+        let (instructions, source_map) = body.code()?;
+        let constants = (0..body.len()?)
+            .map(|_| body.constant(0))
+            .collect::<Result<Vec<_>, _>>()?;
+        let globals = body.strings()?;
+        if body.offset != body.bytes.len() {
+            return Err("unexpected data after the program".to_string());
+        }
+
+        let bytecode = Bytecode {
+            instructions,
+            source_map,
+            constants,
+            globals,
+        };
+        Validator::new(&bytecode).code(&bytecode.instructions, None, &[])?;
+        Ok(bytecode)
+    }
+}
+
+/// The CRC-32 checksum of `bytes`, as used by zip and PNG.
+fn checksum(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u32(&mut self, value: usize) -> Result<(), String> {
+        let value = u32::try_from(value).map_err(|_| format!("{} is too large to write", value))?;
+        self.0.extend(value.to_be_bytes());
+        Ok(())
+    }
+
+    fn len(&mut self, len: usize) -> Result<(), String> {
+        self.u32(len)
+    }
+
+    fn string(&mut self, s: &str) -> Result<(), String> {
+        self.len(s.len())?;
+        self.0.extend(s.as_bytes());
+        Ok(())
+    }
+
+    fn strings(&mut self, strings: &[String]) -> Result<(), String> {
+        self.len(strings.len())?;
+        strings.iter().try_for_each(|s| self.string(s))
+    }
+
+    fn code(&mut self, instructions: &Instructions, source_map: &SourceMap) -> Result<(), String> {
+        self.len(instructions.len())?;
+        self.0.extend(&instructions.0);
+        self.len(source_map.entries().len())?;
+        for (offset, span) in source_map.entries() {
+            self.u32(*offset)?;
+            self.u32(span.line())?;
+            self.u32(span.column())?;
+        }
+        Ok(())
+    }
+
+    fn constant(&mut self, constant: &Object) -> Result<(), String> {
+        match constant {
+            Object::Integer(value) => {
+                self.0.push(INTEGER);
+                self.0.extend(value.to_be_bytes());
+            },
+            Object::String(s) => {
+                self.0.push(STRING);
+                self.string(s)?;
+            },
+            Object::Null => self.0.push(NULL),
+            Object::Array(elements) => {
+                self.0.push(ARRAY);
+                self.len(elements.len())?;
+                for element in elements.iter() {
+                    self.constant(element)?;
+                }
+            },
+            Object::CompiledFunction(function) => {
+                self.0.push(FUNCTION);
+                self.code(function.instructions(), function.source_map())?;
+                self.strings(function.locals())?;
+                self.u32(function.num_parameters())?;
+                self.string(&function.to_string())?;
+            },
+            _ => {
+                return Err(format!(
+                    "{} constants can't be written",
+                    constant.type_name()
+                ))
+            },
+        }
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .bytes
+            .get(self.offset..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or("the file is cut off")?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    /// Reads a length, which can't be longer than what's left of the file since everything
+    /// counted takes up at least a byte, so a corrupt one can't make too much be allocated.
+    fn len(&mut self) -> Result<usize, String> {
+        let len = self.u32()? as usize;
+        if len > self.bytes.len() - self.offset {
+            return Err("the file is cut off".to_string());
+        }
+        Ok(len)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.len()?;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "invalid UTF-8 in a string".to_string())
+    }
+
+    fn strings(&mut self) -> Result<Vec<String>, String> {
+        (0..self.len()?).map(|_| self.string()).collect()
+    }
+
+    fn code(&mut self) -> Result<(Instructions, SourceMap), String> {
+        let len = self.len()?;
+        let instructions = Instructions(self.bytes(len)?.to_vec());
+        let mut source_map = SourceMap::default();
+        for _ in 0..self.len()? {
+            let offset = self.u32()? as usize;
+            let line = self.u32()? as usize;
+            let column = self.u32()? as usize;
+            source_map.add(offset, Span::new(line, column));
+        }
+        Ok((instructions, source_map))
+    }
+
+    /// Reads a constant that's nested in `depth` arrays.
+    fn constant(&mut self, depth: usize) -> Result<Object, String> {
+        Ok(match self.u8()? {
+            INTEGER => Object::Integer(i64::from_be_bytes(self.bytes(8)?.try_into().unwrap())),
+            STRING => Object::String(self.string()?.into()),
+            NULL => Object::Null,
+            ARRAY if depth == MAX_CONSTANT_DEPTH => {
+                return Err("constants are nested too deeply".to_string())
+            },
+            ARRAY => {
+                let elements = (0..self.len()?)
+                    .map(|_| self.constant(depth + 1))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Object::Array(Rc::new(elements))
+            },
+            FUNCTION => {
+                let (instructions, source_map) = self.code()?;
+                let locals = self.strings()?;
+                let num_parameters = self.u32()? as usize;
+                if num_parameters > locals.len() {
+                    return Err("a function has more parameters than locals".to_string());
+                }
+                let display = self.string()?;
+                Object::CompiledFunction(Rc::new(CompiledFunction::new(
+                    instructions,
+                    source_map,
+                    locals,
+                    num_parameters,
+                    display,
+                )))
+            },
+            tag => return Err(format!("unknown constant tag {}", tag)),
+        })
+    }
+}
+
+/// Checks that instructions decode and only refer to constants, globals, locals and builtins
+/// that exist. What they do with the stack and the types of the constants they use aren't
+/// checked, which the VM does as it runs them instead.
+struct Validator<'a> {
+    bytecode: &'a Bytecode,
+    /// The functions checked so far and how many locals each of the functions they were
+    /// created in had, since a function's code is valid or not depending on those.
+    checked: HashSet<(usize, Vec<usize>)>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(bytecode: &'a Bytecode) -> Self {
+        Validator {
+            bytecode,
+            checked: HashSet::new(),
+        }
+    }
+
+    /// Checks code run with `locals` locals, or outside any function if that's `None`, where
+    /// `enclosing` is how many locals the functions it was created in have, innermost last.
+    fn code(
+        &mut self,
+        instructions: &Instructions,
+        locals: Option<usize>,
+        enclosing: &[usize],
+    ) -> Result<(), String> {
+        let decoded = instructions.decode().collect::<Vec<_>>();
+        let offsets = decoded
+            .iter()
+            .map(|(offset, _)| *offset)
+            .collect::<HashSet<_>>();
+        for (offset, instruction) in decoded {
+            self.instruction(instruction, &offsets, instructions.len(), locals, enclosing)
+                .map_err(|err| format!("invalid instruction at {:04}: {}", offset, err))?;
+        }
+        Ok(())
+    }
+
+    fn instruction(
+        &mut self,
+        instruction: Result<(Opcode, Vec<usize>), String>,
+        offsets: &HashSet<usize>,
+        len: usize,
+        locals: Option<usize>,
+        enclosing: &[usize],
+    ) -> Result<(), String> {
+        use Opcode::*;
+
+        let (op, operands) = instruction?;
+        let bytecode = self.bytecode;
+        let constants = &bytecode.constants;
+        let in_range = |index: usize, len: usize, what: &str| {
+            if index < len {
+                Ok(())
+            } else {
+                Err(format!("{} {} doesn't exist", what, index))
+            }
+        };
+        match op {
+            Constant | GetField | CallMethod | Assign => {
+                in_range(operands[0], constants.len(), "constant")
+            },
+            Jump | JumpNotTruthy | JumpNull | JumpNotNull | IterNext => {
+                if operands[0] == len || offsets.contains(&operands[0]) {
+                    Ok(())
+                } else {
+                    Err(format!("jump to {} isn't to an instruction", operands[0]))
+                }
+            },
+            GetGlobal | SetGlobal => in_range(operands[0], self.bytecode.globals.len(), "global"),
+            GetLocal | SetLocal => in_range(operands[0], locals.unwrap_or(0), "local"),
+            GetFree | SetFree => {
+                let depth = operands[0];
+                if depth == 0 || depth > enclosing.len() {
+                    return Err(format!("no function encloses this one {} deep", depth));
+                }
+                in_range(
+                    operands[1],
+                    enclosing[enclosing.len() - depth],
+                    "free variable",
+                )
+            },
+            GetBuiltin => match builtins::at(operands[0]) {
+                Some(_) => Ok(()),
+                None => Err(format!("builtin {} doesn't exist", operands[0])),
+            },
+            Closure => {
+                let Some(Object::CompiledFunction(function)) = constants.get(operands[0]) else {
+                    return Err(format!("constant {} isn't a function", operands[0]));
+                };
+                // functions made outside any function don't enclose anything
+                let mut inner = enclosing.to_vec();
+                inner.extend(locals);
+                // a function can't be nested deeper than there are functions, unless a
+                // function's code creates itself
+                if inner.len() > constants.len() {
+                    return Err("functions are nested too deeply".to_string());
+                }
+                if !self.checked.insert((operands[0], inner.clone())) {
+                    return Ok(());
+                }
+                self.code(
+                    function.instructions(),
+                    Some(function.locals().len()),
+                    &inner,
+                )
+            },
+            _ => Ok(()),
+        }
+    }
+}
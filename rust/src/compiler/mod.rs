@@ -0,0 +1,778 @@
+use std::rc::Rc;
+
+use crate::{
+    eval::builtins,
+    object::{CompiledFunction, Object},
+    ArrayLiteral, AssignExpression, BlockStatement, Boolean, CallExpression, ExpressionStatement,
+    ForStatement, FunctionLiteral, HashLiteral, Identifier, IfExpression, IndexExpression,
+    InfixExpression, IntegerLiteral, InterpolatedString, LetStatement, MemberExpression, Node,
+    NodeType, PrefixExpression, Program, ReturnStatement, Span, Statement, StringLiteral,
+    StringPart, WhileStatement,
+};
+
+mod code;
+mod disassembler;
+mod file;
+mod symbol_table;
+mod tests;
+
+pub use code::*;
+pub use file::{MAGIC, VERSION};
+use symbol_table::Symbol;
+pub use symbol_table::SymbolTable;
+
+/// The operand jumps are emitted with until where they go is known.
+const PLACEHOLDER: usize = 9999;
+
+fn downcast<T: 'static>(node: &dyn Node) -> &T {
+    node.as_any().downcast_ref::<T>().unwrap()
+}
+
+/// A compiled program, ready to be run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bytecode {
+    instructions: Instructions,
+    source_map: SourceMap,
+    constants: Vec<Object>,
+    globals: Vec<String>,
+}
+
+impl Bytecode {
+    pub fn instructions(&self) -> &Instructions {
+        &self.instructions
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    pub fn constants(&self) -> &[Object] {
+        &self.constants
+    }
+
+    /// The names of the globals by index, so that reading one before it's been set can be
+    /// reported by name.
+    pub fn globals(&self) -> &[String] {
+        &self.globals
+    }
+}
+
+/// What becomes of the value of the code being compiled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Usage {
+    Discard,
+    Keep,
+    /// The value is returned from the function being compiled, so calls making it can be
+    /// tail calls.
+    Return,
+}
+
+struct Loop {
+    /// How many values were on the stack when the loop started, which `break` and `continue`
+    /// pop back down to.
+    depth: usize,
+    continue_target: usize,
+    breaks: Vec<usize>,
+}
+
+/// The code of the function being compiled, or of the program outside any function.
+#[derive(Default)]
+struct CompilationScope {
+    instructions: Vec<u8>,
+    source_map: SourceMap,
+    /// How many values the code so far leaves on the stack.
+    depth: usize,
+    loops: Vec<Loop>,
+}
+
+/// Lowers programs into bytecode. Code is compiled so that it behaves like it does when it's
+/// evaluated, including what's left for names that are used before they're defined: those
+/// are resolved to the functions they're defined in and fail at run time if they're still
+/// undefined when they're read.
+///
+/// Only part of the language is compiled: literals, operators, `let` with a name, `if`,
+/// functions with plain parameters and calls to them with positional arguments, indexing,
+/// member access, assignment, `while` and `for` loops, `break`, `continue` and `return`.
+/// Programs that use `match`, destructuring `let`, structs, enums, `try`, `throw`, generators,
+/// default or rest parameters, or spread or named arguments fail to compile, with an error
+/// naming what isn't supported.
+pub struct Compiler {
+    constants: Vec<Object>,
+    symbol_table: SymbolTable,
+    scopes: Vec<CompilationScope>,
+    /// Where the node being compiled is, which the instructions for it are mapped to.
+    span: Span,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self::with_state(SymbolTable::new(), Vec::new())
+    }
+
+    /// A compiler that carries on from the globals and constants of the code compiled before,
+    /// like the REPL needs.
+    pub fn with_state(symbol_table: SymbolTable, constants: Vec<Object>) -> Self {
+        Compiler {
+            constants,
+            symbol_table,
+            scopes: vec![CompilationScope::default()],
+            span: Span::default(),
+        }
+    }
+
+    pub fn into_state(self) -> (SymbolTable, Vec<Object>) {
+        (self.symbol_table, self.constants)
+    }
+
+    /// Compiles a program, whose value is left on the stack, failing if it uses anything
+    /// outside the part of the language that's compiled.
+    pub fn compile(&mut self, program: &Program) -> Result<(), String> {
+        self.compile_statements(program.statements(), Usage::Keep)
+    }
+
+    pub fn bytecode(&self) -> Bytecode {
+        let scope = &self.scopes[0];
+        Bytecode {
+            instructions: Instructions(scope.instructions.clone()),
+            source_map: scope.source_map.clone(),
+            constants: self.constants.clone(),
+            globals: self.symbol_table.names().to_vec(),
+        }
+    }
+
+    fn scope(&mut self) -> &mut CompilationScope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn position(&self) -> usize {
+        self.scopes.last().unwrap().instructions.len()
+    }
+
+    fn emit(&mut self, op: Opcode, operands: &[usize]) -> Result<usize, String> {
+        check_operands(op, operands)?;
+        let span = self.span;
+        let scope = self.scope();
+        let position = scope.instructions.len();
+        scope.source_map.add(position, span);
+        scope.instructions.extend(make(op, operands));
+        scope.depth = scope
+            .depth
+            .saturating_add_signed(stack_effect(op, operands));
+        Ok(position)
+    }
+
+    /// Points the jump at `position` to where the next instruction will go.
+    fn patch_jump(&mut self, position: usize) -> Result<(), String> {
+        let target = self.position();
+        let scope = self.scope();
+        let op = Opcode::try_from(scope.instructions[position])?;
+        check_operands(op, &[target])?;
+        scope.instructions[position + 1..position + 3]
+            .copy_from_slice(&(target as u16).to_be_bytes());
+        Ok(())
+    }
+
+    fn add_constant(&mut self, obj: Object) -> usize {
+        self.constants.push(obj);
+        self.constants.len() - 1
+    }
+
+    fn emit_constant(&mut self, obj: Object) -> Result<usize, String> {
+        let index = self.add_constant(obj);
+        self.emit(Opcode::Constant, &[index])
+    }
+
+    fn load_symbol(&mut self, symbol: Symbol) -> Result<usize, String> {
+        match symbol {
+            Symbol::Global(index) => self.emit(Opcode::GetGlobal, &[index]),
+            Symbol::Local(index) => self.emit(Opcode::GetLocal, &[index]),
+            Symbol::Free(depth, index) => self.emit(Opcode::GetFree, &[depth, index]),
+        }
+    }
+
+    fn store_symbol(&mut self, symbol: Symbol) -> Result<usize, String> {
+        match symbol {
+            Symbol::Global(index) => self.emit(Opcode::SetGlobal, &[index]),
+            Symbol::Local(index) => self.emit(Opcode::SetLocal, &[index]),
+            Symbol::Free(depth, index) => self.emit(Opcode::SetFree, &[depth, index]),
+        }
+    }
+
+    fn compile_statements(
+        &mut self,
+        statements: &[Box<dyn Statement>],
+        usage: Usage,
+    ) -> Result<(), String> {
+        let Some((last, rest)) = statements.split_last() else {
+            if usage != Usage::Discard {
+                self.emit(Opcode::Null, &[])?;
+            }
+            return Ok(());
+        };
+        for stmt in rest {
+            self.compile_statement(stmt.as_ref(), Usage::Discard)?;
+        }
+        self.compile_statement(last.as_ref(), usage)
+    }
+
+    fn compile_block(&mut self, block: &BlockStatement, usage: Usage) -> Result<(), String> {
+        self.compile_statements(block.statements(), usage)
+    }
+
+    fn compile_statement(&mut self, stmt: &dyn Node, usage: Usage) -> Result<(), String> {
+        let span = std::mem::replace(&mut self.span, stmt.span());
+        let result = self.compile_statement_node(stmt, usage);
+        self.span = span;
+        result
+    }
+
+    fn compile_statement_node(&mut self, stmt: &dyn Node, usage: Usage) -> Result<(), String> {
+        match stmt.node_type() {
+            NodeType::ExpressionStatement => {
+                let expr = downcast::<ExpressionStatement>(stmt).expression();
+                self.compile_expression(expr, usage == Usage::Return)?;
+                if usage == Usage::Discard {
+                    self.emit(Opcode::Pop, &[])?;
+                }
+                return Ok(());
+            },
+            NodeType::LetStatement => {
+                let stmt = downcast::<LetStatement>(stmt);
+                self.compile_expression(stmt.value(), false)?;
+                let symbol = self.symbol_table.define(stmt.name().value());
+                self.store_symbol(symbol)?;
+            },
+            NodeType::ReturnStatement => {
+                let value = downcast::<ReturnStatement>(stmt).return_value();
+                if usage == Usage::Return {
+                    // the value is returned by the code it's left for
+                    return self.compile_expression(value, true);
+                }
+                self.compile_expression(value, false)?;
+                self.emit(Opcode::ReturnValue, &[])?;
+                // nothing after a return runs, but the code after it is compiled as if the
+                // statement had a value like other statements
+            },
+            NodeType::WhileStatement => self.compile_while_statement(downcast(stmt))?,
+            NodeType::ForStatement => self.compile_for_statement(downcast(stmt))?,
+            NodeType::BreakStatement => self.compile_loop_jump(true)?,
+            NodeType::ContinueStatement => self.compile_loop_jump(false)?,
+            node_type => return Err(unsupported(node_type)),
+        }
+        if usage != Usage::Discard {
+            self.emit(Opcode::Null, &[])?;
+        }
+        Ok(())
+    }
+
+    fn compile_while_statement(&mut self, stmt: &WhileStatement) -> Result<(), String> {
+        let start = self.position();
+        self.compile_expression(stmt.condition(), false)?;
+        let to_end = self.emit(Opcode::JumpNotTruthy, &[PLACEHOLDER])?;
+        let breaks = self.compile_loop_body(stmt.body(), start)?;
+        for jump in std::iter::once(to_end).chain(breaks) {
+            self.patch_jump(jump)?;
+        }
+        Ok(())
+    }
+
+    fn compile_for_statement(&mut self, stmt: &ForStatement) -> Result<(), String> {
+        self.compile_expression(stmt.iterable(), false)?;
+        self.emit(Opcode::Iter, &[])?;
+        let iterator = self.symbol_table.define_temporary();
+        self.store_symbol(iterator)?;
+        let start = self.position();
+        self.load_symbol(iterator)?;
+        let to_end = self.emit(Opcode::IterNext, &[PLACEHOLDER])?;
+        let variable = self.symbol_table.define(stmt.variable().value());
+        self.store_symbol(variable)?;
+        let breaks = self.compile_loop_body(stmt.body(), start)?;
+        for jump in std::iter::once(to_end).chain(breaks) {
+            self.patch_jump(jump)?;
+        }
+        Ok(())
+    }
+
+    /// Compiles a loop's body followed by a jump back to `start`, returning the jumps its
+    /// `break`s make, which go to wherever the loop ends.
+    fn compile_loop_body(
+        &mut self,
+        body: &BlockStatement,
+        start: usize,
+    ) -> Result<Vec<usize>, String> {
+        let depth = self.scope().depth;
+        self.scope().loops.push(Loop {
+            depth,
+            continue_target: start,
+            breaks: Vec::new(),
+        });
+        let result = self
+            .compile_block(body, Usage::Discard)
+            .and_then(|()| self.emit(Opcode::Jump, &[start]));
+        let lp = self.scope().loops.pop().unwrap();
+        result?;
+        Ok(lp.breaks)
+    }
+
+    /// Compiles a `break`, or a `continue` if `brk` is false.
+    fn compile_loop_jump(&mut self, brk: bool) -> Result<(), String> {
+        let depth = self.scope().depth;
+        // the parser only accepts them in loops
+        let (loop_depth, target) = self
+            .scope()
+            .loops
+            .last()
+            .map(|lp| (lp.depth, lp.continue_target))
+            .unwrap();
+        // values the loop is in the middle of computing are dropped
+        for _ in loop_depth..depth {
+            self.emit(Opcode::Pop, &[])?;
+        }
+        if brk {
+            let jump = self.emit(Opcode::Jump, &[PLACEHOLDER])?;
+            self.scope().loops.last_mut().unwrap().breaks.push(jump);
+        } else {
+            self.emit(Opcode::Jump, &[target])?;
+        }
+        // the code after it is unreachable, but it's compiled as if the values were still there
+        self.scope().depth = depth;
+        Ok(())
+    }
+
+    /// Compiles an expression, which leaves its value on the stack. Calls making the value of a
+    /// `tail` expression are tail calls.
+    fn compile_expression(&mut self, expr: &dyn Node, tail: bool) -> Result<(), String> {
+        let span = std::mem::replace(&mut self.span, expr.span());
+        let result = self.compile_expression_node(expr, tail);
+        self.span = span;
+        result
+    }
+
+    fn compile_expression_node(&mut self, expr: &dyn Node, tail: bool) -> Result<(), String> {
+        match expr.node_type() {
+            NodeType::IntegerLiteral => {
+                let value = downcast::<IntegerLiteral>(expr).value();
+                self.emit_constant(Object::Integer(value))?;
+            },
+            NodeType::StringLiteral => {
+                let value = downcast::<StringLiteral>(expr).value();
+                self.emit_constant(Object::String(value.into()))?;
+            },
+            NodeType::Boolean => {
+                let op = match downcast::<Boolean>(expr).value() {
+                    true => Opcode::True,
+                    false => Opcode::False,
+                };
+                self.emit(op, &[])?;
+            },
+            NodeType::NullLiteral => {
+                self.emit(Opcode::Null, &[])?;
+            },
+            NodeType::PrefixExpression => {
+                let expr = downcast::<PrefixExpression>(expr);
+                self.compile_expression(expr.right(), false)?;
+                let op = Opcode::prefix(expr.operator())
+                    .ok_or_else(|| format!("unknown operator: {}", expr.operator()))?;
+                self.emit(op, &[])?;
+            },
+            NodeType::InfixExpression => self.compile_infix_expression(downcast(expr))?,
+            NodeType::IfExpression => self.compile_if_expression(downcast(expr), tail)?,
+            NodeType::Identifier => {
+                self.compile_identifier(downcast::<Identifier>(expr).value())?
+            },
+            NodeType::FunctionLiteral => self.compile_function_literal(downcast(expr))?,
+            NodeType::CallExpression | NodeType::IndexExpression | NodeType::MemberExpression => {
+                let mut to_end = Vec::new();
+                self.compile_chain_link(expr, tail, &mut to_end)?;
+                for jump in to_end {
+                    self.patch_jump(jump)?;
+                }
+            },
+            NodeType::ArrayLiteral => {
+                let elements = downcast::<ArrayLiteral>(expr).elements();
+                for element in elements {
+                    self.compile_expression(element.as_ref(), false)?;
+                }
+                self.emit(Opcode::Array, &[elements.len()])?;
+            },
+            NodeType::HashLiteral => {
+                let pairs = downcast::<HashLiteral>(expr).pairs();
+                for (key, value) in pairs {
+                    self.compile_expression(key.as_ref(), false)?;
+                    self.compile_expression(value.as_ref(), false)?;
+                }
+                self.emit(Opcode::Hash, &[pairs.len()])?;
+            },
+            NodeType::InterpolatedString => {
+                let parts = downcast::<InterpolatedString>(expr).parts();
+                for part in parts {
+                    match part {
+                        StringPart::Literal(s) => {
+                            self.emit_constant(Object::String(s.as_str().into()))?
+                        },
+                        StringPart::Expression(expr) => {
+                            self.compile_expression(expr.as_ref(), false)?;
+                            continue;
+                        },
+                    };
+                }
+                self.emit(Opcode::Interpolate, &[parts.len()])?;
+            },
+            NodeType::AssignExpression => self.compile_assign_expression(downcast(expr))?,
+            node_type => return Err(unsupported(node_type)),
+        }
+        Ok(())
+    }
+
+    /// Compiles a call or access that's part of a chain of them, adding the jumps its optional
+    /// accesses make when what they access is null to `to_end`, which go past the whole chain
+    /// so that null is its value.
+    fn compile_chain_link(
+        &mut self,
+        expr: &dyn Node,
+        tail: bool,
+        to_end: &mut Vec<usize>,
+    ) -> Result<(), String> {
+        match expr.node_type() {
+            NodeType::CallExpression => self.compile_call_expression(downcast(expr), tail, to_end),
+            NodeType::IndexExpression => {
+                let expr = downcast::<IndexExpression>(expr);
+                self.compile_chain_object(expr.left(), to_end)?;
+                self.emit_optional_jump(expr.optional(), to_end)?;
+                self.compile_expression(expr.index(), false)?;
+                self.emit(Opcode::Index, &[])?;
+                Ok(())
+            },
+            NodeType::MemberExpression => {
+                let expr = downcast::<MemberExpression>(expr);
+                self.compile_chain_object(expr.object(), to_end)?;
+                self.emit_optional_jump(expr.optional(), to_end)?;
+                let name = self.add_constant(Object::String(expr.property().value().into()));
+                self.emit(Opcode::GetField, &[name])?;
+                Ok(())
+            },
+            _ => self.compile_expression(expr, false),
+        }
+    }
+
+    /// Compiles what a call or access is made on, which carries on the chain it's in unless
+    /// it's in parentheses, whose optional accesses only jump past what's in them.
+    fn compile_chain_object(
+        &mut self,
+        expr: &dyn Node,
+        to_end: &mut Vec<usize>,
+    ) -> Result<(), String> {
+        if grouped(expr) {
+            return self.compile_expression(expr, false);
+        }
+        let span = std::mem::replace(&mut self.span, expr.span());
+        let result = self.compile_chain_link(expr, false, to_end);
+        self.span = span;
+        result
+    }
+
+    /// Emits the jump past the chain an optional access is in for when what's accessed is null.
+    fn emit_optional_jump(
+        &mut self,
+        optional: bool,
+        to_end: &mut Vec<usize>,
+    ) -> Result<(), String> {
+        if optional {
+            to_end.push(self.emit(Opcode::JumpNull, &[PLACEHOLDER])?);
+        }
+        Ok(())
+    }
+
+    fn compile_infix_expression(&mut self, expr: &InfixExpression) -> Result<(), String> {
+        self.compile_expression(expr.left(), false)?;
+        match expr.operator() {
+            "&&" | "||" => {
+                let to_other = self.emit(Opcode::JumpNotTruthy, &[PLACEHOLDER])?;
+                let depth = self.scope().depth;
+                if expr.operator() == "&&" {
+                    self.compile_boolean(expr.right())?;
+                } else {
+                    self.emit(Opcode::True, &[])?;
+                }
+                let to_end = self.emit(Opcode::Jump, &[PLACEHOLDER])?;
+                self.patch_jump(to_other)?;
+                self.scope().depth = depth;
+                if expr.operator() == "&&" {
+                    self.emit(Opcode::False, &[])?;
+                } else {
+                    self.compile_boolean(expr.right())?;
+                }
+                self.patch_jump(to_end)?;
+            },
+            "??" => {
+                let to_end = self.emit(Opcode::JumpNotNull, &[PLACEHOLDER])?;
+                self.compile_expression(expr.right(), false)?;
+                self.patch_jump(to_end)?;
+            },
+            operator => {
+                self.compile_expression(expr.right(), false)?;
+                let op = Opcode::infix(operator)
+                    .ok_or_else(|| format!("unknown operator: {}", operator))?;
+                self.emit(op, &[])?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Compiles an expression whose value is turned into whether it's truthy.
+    fn compile_boolean(&mut self, expr: &dyn Node) -> Result<(), String> {
+        self.compile_expression(expr, false)?;
+        self.emit(Opcode::Bang, &[])?;
+        self.emit(Opcode::Bang, &[])?;
+        Ok(())
+    }
+
+    fn compile_if_expression(&mut self, expr: &IfExpression, tail: bool) -> Result<(), String> {
+        let usage = if tail { Usage::Return } else { Usage::Keep };
+        self.compile_expression(expr.condition(), false)?;
+        let to_alternative = self.emit(Opcode::JumpNotTruthy, &[PLACEHOLDER])?;
+        let depth = self.scope().depth;
+        self.compile_block(expr.consequence(), usage)?;
+        let to_end = self.emit(Opcode::Jump, &[PLACEHOLDER])?;
+        self.patch_jump(to_alternative)?;
+        self.scope().depth = depth;
+        match expr.alternative() {
+            Some(alternative) => self.compile_block(alternative, usage)?,
+            None => {
+                self.emit(Opcode::Null, &[])?;
+            },
+        }
+        self.patch_jump(to_end)
+    }
+
+    fn compile_identifier(&mut self, name: &str) -> Result<(), String> {
+        if let Some(symbol) = self.symbol_table.resolve(name) {
+            self.load_symbol(symbol)?;
+        } else if let Some(index) = builtins::index_of(name) {
+            self.emit(Opcode::GetBuiltin, &[index])?;
+        } else {
+            // it may be a global that's defined later, before this runs
+            let symbol = self.symbol_table.define_global(name);
+            self.load_symbol(symbol)?;
+        }
+        Ok(())
+    }
+
+    fn compile_function_literal(&mut self, func: &FunctionLiteral) -> Result<(), String> {
+        let params = func.signature();
+        if func.generator() {
+            return Err("generators aren't supported by the compiler yet".to_string());
+        }
+        if params.rest().is_some()
+            || (0..params.names().len()).any(|i| params.default_value(i).is_some())
+        {
+            return Err(
+                "default and rest parameters aren't supported by the compiler yet".to_string(),
+            );
+        }
+        self.scopes.push(CompilationScope::default());
+        let outer = std::mem::take(&mut self.symbol_table);
+        self.symbol_table = SymbolTable::new_enclosed(outer);
+        for name in params.names() {
+            self.symbol_table.define(name.value());
+        }
+        // every name the body defines is a local from the start, so functions in the body can
+        // refer to the ones it defines after them
+        let mut declared = Vec::new();
+        declarations(func.body().statements(), &mut declared);
+        for name in declared {
+            self.symbol_table.define(name);
+        }
+        let result = self
+            .compile_block(func.body(), Usage::Return)
+            .and_then(|()| self.emit(Opcode::ReturnValue, &[]));
+        let scope = self.scopes.pop().unwrap();
+        let locals = self.symbol_table.names().to_vec();
+        let table = std::mem::take(&mut self.symbol_table);
+        self.symbol_table = table.into_outer().unwrap();
+        result?;
+
+        let function = CompiledFunction::new(
+            Instructions(scope.instructions),
+            scope.source_map,
+            locals,
+            params.names().len(),
+            func,
+        );
+        let index = self.add_constant(Object::CompiledFunction(Rc::new(function)));
+        self.emit(Opcode::Closure, &[index])?;
+        Ok(())
+    }
+
+    fn compile_call_expression(
+        &mut self,
+        call: &CallExpression,
+        tail: bool,
+        to_end: &mut Vec<usize>,
+    ) -> Result<(), String> {
+        let args = call.arguments();
+        if args.iter().any(|arg| {
+            matches!(
+                arg.node_type(),
+                NodeType::SpreadExpression | NodeType::NamedArgument
+            )
+        }) {
+            return Err(
+                "spread and named arguments aren't supported by the compiler yet".to_string(),
+            );
+        }
+        if call.function().node_type() == NodeType::MemberExpression {
+            let member = downcast::<MemberExpression>(call.function());
+            self.compile_chain_object(member.object(), to_end)?;
+            self.emit_optional_jump(member.optional(), to_end)?;
+            for arg in args {
+                self.compile_expression(arg.as_ref(), false)?;
+            }
+            let name = self.add_constant(Object::String(member.property().value().into()));
+            self.emit(Opcode::CallMethod, &[name, args.len()])?;
+            return Ok(());
+        }
+        self.compile_chain_object(call.function(), to_end)?;
+        for arg in args {
+            self.compile_expression(arg.as_ref(), false)?;
+        }
+        let op = if tail { Opcode::TailCall } else { Opcode::Call };
+        self.emit(op, &[args.len()])?;
+        Ok(())
+    }
+
+    /// Compiles an assignment into the index values the target uses, the value being
+    /// assigned and the variable's value, followed by an [`Opcode::Assign`] whose constant is
+    /// an array of the operator without its `=`, then the name of each member the target
+    /// accesses, or null for each index.
+    fn compile_assign_expression(&mut self, expr: &AssignExpression) -> Result<(), String> {
+        let mut target = expr.target();
+        let mut accesses = Vec::new();
+        loop {
+            match target.node_type() {
+                NodeType::IndexExpression => {
+                    accesses.push(target);
+                    target = downcast::<IndexExpression>(target).left();
+                },
+                NodeType::MemberExpression => {
+                    accesses.push(target);
+                    target = downcast::<MemberExpression>(target).object();
+                },
+                _ => break,
+            }
+        }
+        let name = downcast::<Identifier>(target).value();
+        let operator = expr.operator().strip_suffix('=').unwrap();
+        let mut path = vec![Object::String(operator.into())];
+        let mut indexes = 0;
+        for access in accesses.into_iter().rev() {
+            match access.node_type() {
+                NodeType::IndexExpression => {
+                    self.compile_expression(downcast::<IndexExpression>(access).index(), false)?;
+                    path.push(Object::Null);
+                    indexes += 1;
+                },
+                _ => {
+                    let member = downcast::<MemberExpression>(access).property().value();
+                    path.push(Object::String(member.into()));
+                },
+            }
+        }
+        self.compile_expression(expr.value(), false)?;
+        // built-in functions can't be assigned to, so unlike reading a name, assigning to it
+        // doesn't fall back to them
+        let symbol = match self.symbol_table.resolve(name) {
+            Some(symbol) => symbol,
+            None => self.symbol_table.define_global(name),
+        };
+        self.load_symbol(symbol)?;
+        let path = self.add_constant(Object::Array(Rc::new(path)));
+        self.emit(Opcode::Assign, &[path, indexes])?;
+        self.store_symbol(symbol)?;
+        Ok(())
+    }
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Compiler::new()
+    }
+}
+
+/// Whether a node is a call or access in parentheses.
+fn grouped(node: &dyn Node) -> bool {
+    match node.node_type() {
+        NodeType::CallExpression => downcast::<CallExpression>(node).grouped(),
+        NodeType::IndexExpression => downcast::<IndexExpression>(node).grouped(),
+        NodeType::MemberExpression => downcast::<MemberExpression>(node).grouped(),
+        _ => false,
+    }
+}
+
+fn unsupported(node_type: NodeType) -> String {
+    format!("{:?} isn't supported by the compiler yet", node_type)
+}
+
+/// Fails if an operand doesn't fit in the width the instruction has for it.
+fn check_operands(op: Opcode, operands: &[usize]) -> Result<(), String> {
+    let def = op.definition();
+    for (operand, width) in operands.iter().zip(def.operand_widths) {
+        if *operand >= 1 << (8 * width) {
+            return Err(format!("operand {} too large for {}", operand, def.name));
+        }
+    }
+    Ok(())
+}
+
+/// How many values an instruction leaves on the stack, less how many it takes off.
+fn stack_effect(op: Opcode, operands: &[usize]) -> isize {
+    use Opcode::*;
+
+    let count = operands.last().copied().unwrap_or(0) as isize;
+    match op {
+        Constant | True | False | Null | GetGlobal | GetLocal | GetFree | GetBuiltin | Closure => 1,
+        Minus | Bang | BitNot | Jump | JumpNull | GetField | Iter | IterNext => 0,
+        Pop | JumpNotTruthy | JumpNotNull | SetGlobal | SetLocal | SetFree | Index
+        | ReturnValue => -1,
+        Add | Sub | Mul | Div | Mod | Pow | BitAnd | BitOr | BitXor | Shl | Shr | Equal
+        | NotEqual | LessThan | GreaterThan | LessEqual | GreaterEqual | Range | RangeInclusive => {
+            -1
+        },
+        Array | Interpolate => 1 - count,
+        Hash => 1 - 2 * count,
+        Call | TailCall | CallMethod | Assign => -count,
+    }
+}
+
+/// Collects the names the statements define, leaving out those defined in functions in them.
+fn declarations<'a>(statements: &'a [Box<dyn Statement>], names: &mut Vec<&'a str>) {
+    for stmt in statements {
+        match stmt.node_type() {
+            NodeType::LetStatement => {
+                names.push(downcast::<LetStatement>(stmt.as_ref()).name().value())
+            },
+            NodeType::WhileStatement => declarations(
+                downcast::<WhileStatement>(stmt.as_ref())
+                    .body()
+                    .statements(),
+                names,
+            ),
+            NodeType::ForStatement => {
+                let stmt = downcast::<ForStatement>(stmt.as_ref());
+                names.push(stmt.variable().value());
+                declarations(stmt.body().statements(), names);
+            },
+            NodeType::ExpressionStatement => {
+                let expr = downcast::<ExpressionStatement>(stmt.as_ref()).expression();
+                if expr.node_type() == NodeType::IfExpression {
+                    let expr = downcast::<IfExpression>(expr);
+                    declarations(expr.consequence().statements(), names);
+                    if let Some(alternative) = expr.alternative() {
+                        declarations(alternative.statements(), names);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+}
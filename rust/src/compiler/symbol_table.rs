@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+/// Where the value a name refers to is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symbol {
+    Global(usize),
+    /// A local of the function being compiled.
+    Local(usize),
+    /// A local of the function `depth` functions out from the one being compiled, and its index
+    /// there.
+    Free(usize, usize),
+}
+
+/// The names defined in a function, or globally for the outermost table. Like environments,
+/// there's one scope per function rather than per block.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    outer: Option<Box<SymbolTable>>,
+    store: HashMap<String, usize>,
+    names: Vec<String>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn new_enclosed(outer: SymbolTable) -> Self {
+        SymbolTable {
+            outer: Some(Box::new(outer)),
+            ..Self::default()
+        }
+    }
+
+    /// The table this one is enclosed in, once the function it's for has been compiled.
+    pub fn into_outer(self) -> Option<SymbolTable> {
+        self.outer.map(|outer| *outer)
+    }
+
+    /// Defines a name in this table, keeping the index it already has if it's been defined
+    /// here before, since defining a name again replaces its value.
+    pub fn define(&mut self, name: &str) -> Symbol {
+        let index = match self.store.get(name) {
+            Some(index) => *index,
+            None => {
+                let index = self.define_hidden();
+                self.names[index] = name.to_string();
+                self.store.insert(name.to_string(), index);
+                index
+            },
+        };
+        self.symbol(index)
+    }
+
+    /// Defines a name in the outermost table.
+    pub fn define_global(&mut self, name: &str) -> Symbol {
+        match &mut self.outer {
+            Some(outer) => outer.define_global(name),
+            None => self.define(name),
+        }
+    }
+
+    /// The index of the global `name`, defining it if it isn't defined yet.
+    pub fn global_index(&mut self, name: &str) -> usize {
+        match self.define_global(name) {
+            Symbol::Global(index) => index,
+            _ => unreachable!("names in the outermost table are globals"),
+        }
+    }
+
+    /// Makes room for a value that no name refers to.
+    pub fn define_temporary(&mut self) -> Symbol {
+        let index = self.define_hidden();
+        self.symbol(index)
+    }
+
+    fn define_hidden(&mut self) -> usize {
+        self.names.push(String::new());
+        self.names.len() - 1
+    }
+
+    fn symbol(&self, index: usize) -> Symbol {
+        match self.outer {
+            Some(_) => Symbol::Local(index),
+            None => Symbol::Global(index),
+        }
+    }
+
+    pub fn resolve(&self, name: &str) -> Option<Symbol> {
+        if let Some(index) = self.store.get(name) {
+            return Some(self.symbol(*index));
+        }
+        match self.outer.as_ref()?.resolve(name)? {
+            Symbol::Global(index) => Some(Symbol::Global(index)),
+            Symbol::Local(index) => Some(Symbol::Free(1, index)),
+            Symbol::Free(depth, index) => Some(Symbol::Free(depth + 1, index)),
+        }
+    }
+
+    /// The names of the values defined in this table by index, which are empty for values no
+    /// name refers to.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+}
@@ -0,0 +1,944 @@
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        compiler::{
+            make, read_operands, Bytecode, Compiler, Instructions, Opcode, SourceMap, MAGIC,
+            VERSION,
+        },
+        object::{CompiledFunction, ExecutionLimits, Object},
+        vm::Vm,
+        Lexer, Parser, Span,
+    };
+    use Opcode::*;
+
+    enum Constant {
+        Int(i64),
+        Str(&'static str),
+        Function(Vec<Vec<u8>>),
+        Other(Object),
+    }
+
+    /// An input and the constants and instructions it compiles to.
+    type CompilerTest = (&'static str, Vec<Constant>, Vec<Vec<u8>>);
+
+    fn compile_bytecode(input: &str) -> Result<Bytecode, String> {
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program()?;
+        let mut compiler = Compiler::new();
+        compiler.compile(&program)?;
+        Ok(compiler.bytecode())
+    }
+
+    fn compile(input: &str) -> Result<(Instructions, Vec<Object>), String> {
+        let bytecode = compile_bytecode(input)?;
+        Ok((
+            bytecode.instructions().clone(),
+            bytecode.constants().to_vec(),
+        ))
+    }
+
+    fn run_compiler_tests(tests: Vec<CompilerTest>) {
+        for (input, expected_constants, expected_instructions) in tests {
+            let (instructions, constants) = compile(input).unwrap();
+            assert_eq!(
+                instructions,
+                Instructions::from(expected_instructions),
+                "{}",
+                input
+            );
+            assert_eq!(constants.len(), expected_constants.len(), "{}", input);
+            for (constant, expected) in constants.iter().zip(expected_constants) {
+                match (constant, expected) {
+                    (Object::Integer(value), Constant::Int(expected)) => {
+                        assert_eq!(*value, expected, "{}", input)
+                    },
+                    (Object::String(value), Constant::Str(expected)) => {
+                        assert_eq!(value.as_ref(), expected, "{}", input)
+                    },
+                    (Object::CompiledFunction(func), Constant::Function(expected)) => {
+                        assert_eq!(
+                            *func.instructions(),
+                            Instructions::from(expected),
+                            "{}",
+                            input
+                        )
+                    },
+                    (constant, Constant::Other(expected)) => {
+                        assert_eq!(*constant, expected, "{}", input)
+                    },
+                    (constant, _) => panic!("unexpected constant {} in {}", constant, input),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_make() {
+        let tests = vec![
+            (Constant, vec![65534], vec![Constant as u8, 255, 254]),
+            (Add, vec![], vec![Add as u8]),
+            (GetLocal, vec![255], vec![GetLocal as u8, 255]),
+            (
+                CallMethod,
+                vec![65534, 3],
+                vec![CallMethod as u8, 255, 254, 3],
+            ),
+        ];
+        for (op, operands, expected) in tests {
+            assert_eq!(make(op, &operands), expected);
+        }
+    }
+
+    #[test]
+    fn test_read_operands() {
+        let tests = vec![
+            (Constant, vec![65535], 2),
+            (GetFree, vec![3, 255], 2),
+            (Assign, vec![1000, 2], 3),
+        ];
+        for (op, operands, bytes_read) in tests {
+            let instruction = make(op, &operands);
+            let def = Opcode::try_from(instruction[0]).unwrap().definition();
+            let (read, n) = read_operands(&def, &instruction[1..]);
+            assert_eq!(n, bytes_read);
+            assert_eq!(read, operands);
+        }
+        assert_eq!(
+            Opcode::try_from(255).unwrap_err(),
+            "opcode 255 undefined".to_string()
+        );
+    }
+
+    #[test]
+    fn test_instructions_display() {
+        let instructions = Instructions::from(vec![
+            make(Add, &[]),
+            make(GetLocal, &[1]),
+            make(Constant, &[2]),
+            make(Constant, &[65535]),
+            make(GetFree, &[1, 0]),
+            make(CallMethod, &[3, 2]),
+        ]);
+        let expected = "\
+0000 OpAdd
+0001 OpGetLocal 1
+0003 OpConstant 2
+0006 OpConstant 65535
+0009 OpGetFree 1 0
+0012 OpCallMethod 3 2
+";
+        assert_eq!(instructions.to_string(), expected);
+
+        let instructions = Instructions(vec![Pop as u8, Constant as u8, 0]);
+        assert_eq!(
+            instructions.to_string(),
+            "0000 OpPop\n0001 ERROR: OpConstant is cut off\n"
+        );
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let bytecode = compile_bytecode("let f = fn(a) { let b = a; len(b) }; f(\"x\")").unwrap();
+        let expected = "\
+constants:
+0000 fn(a) {let b = a;len(b)}
+     parameters: 1, locals: a, b
+     0000 OpGetLocal 0 ; a
+     0002 OpSetLocal 1 ; b
+     0004 OpGetBuiltin 0 ; len
+     0006 OpGetLocal 1 ; b
+     0008 OpTailCall 1
+     0010 OpReturnValue
+0001 \"x\"
+globals:
+0000 f
+instructions:
+0000 OpClosure 0 ; fn(a) {let b = a;len(b)}
+0003 OpSetGlobal 0 ; f
+0006 OpGetGlobal 0 ; f
+0009 OpConstant 1 ; \"x\"
+0012 OpCall 1
+";
+        assert_eq!(bytecode.to_string(), expected);
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let inputs = [
+            "",
+            "let a = [1, \"two\", null]; a[0] += -3; a",
+            "let f = fn(a) { fn(b) { a + b } }; f(1)(2)",
+            "let s = 0; for (x in 1..5) { if (x == 3) { continue } s += x } \"${s}\"",
+        ];
+        for input in inputs {
+            let bytecode = compile_bytecode(input).unwrap();
+            let bytes = bytecode.to_bytes().unwrap();
+            assert!(bytes.starts_with(MAGIC), "{}", input);
+            assert_eq!(Bytecode::from_bytes(&bytes).unwrap(), bytecode, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_file_errors() {
+        let bytes = compile_bytecode("let f = fn(a) { a * 2 }; f(1)")
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        let with = |offset: usize, byte: u8| {
+            let mut bytes = bytes.clone();
+            bytes[offset] = byte;
+            bytes
+        };
+        let tests = vec![
+            (b"#!monkey".to_vec(), "not a compiled program".to_string()),
+            (
+                with(5, VERSION as u8 + 1),
+                format!(
+                    "compiled with format version {}, but only version {} can be run",
+                    VERSION + 1,
+                    VERSION
+                ),
+            ),
+            (
+                with(bytes.len() - 1, b'g'),
+                "checksum mismatch: the file is corrupt".to_string(),
+            ),
+            (
+                bytes[..bytes.len() - 1].to_vec(),
+                "checksum mismatch: the file is corrupt".to_string(),
+            ),
+            (bytes[..8].to_vec(), "the file is cut off".to_string()),
+        ];
+        for (bytes, expected) in tests {
+            assert_eq!(Bytecode::from_bytes(&bytes).unwrap_err(), expected);
+        }
+
+        // constants nested too deeply to read are rejected rather than overflowing the stack
+        let nested = |depth: usize| {
+            let constant =
+                (0..depth).fold(Object::Null, |obj, _| Object::Array(Rc::new(vec![obj])));
+            let bytecode = Bytecode {
+                instructions: Instructions::default(),
+                source_map: SourceMap::default(),
+                constants: vec![constant],
+                globals: vec![],
+            };
+            Bytecode::from_bytes(&bytecode.to_bytes().unwrap())
+        };
+        assert!(nested(32).is_ok());
+        assert_eq!(nested(33).unwrap_err(), "constants are nested too deeply");
+
+        // files with valid checksums are still checked for code that can't be run
+        let function = |instructions: Vec<Vec<u8>>, locals: &[&str]| {
+            Object::CompiledFunction(Rc::new(CompiledFunction::new(
+                Instructions::from(instructions),
+                SourceMap::default(),
+                locals.iter().map(|s| s.to_string()).collect(),
+                0,
+                "fn() {}",
+            )))
+        };
+        let tests = vec![
+            (
+                vec![make(GetGlobal, &[1])],
+                vec![],
+                "invalid instruction at 0000: global 1 doesn't exist",
+            ),
+            (
+                vec![make(Pop, &[]), make(Jump, &[2])],
+                vec![],
+                "invalid instruction at 0001: jump to 2 isn't to an instruction",
+            ),
+            (
+                vec![make(GetLocal, &[0])],
+                vec![],
+                "invalid instruction at 0000: local 0 doesn't exist",
+            ),
+            (
+                vec![make(GetBuiltin, &[200])],
+                vec![],
+                "invalid instruction at 0000: builtin 200 doesn't exist",
+            ),
+            (
+                vec![vec![255]],
+                vec![],
+                "invalid instruction at 0000: opcode 255 undefined",
+            ),
+            (
+                vec![make(Closure, &[0])],
+                vec![Object::Integer(1)],
+                "invalid instruction at 0000: constant 0 isn't a function",
+            ),
+            (
+                vec![make(Closure, &[0])],
+                vec![function(vec![make(GetFree, &[1, 0])], &[])],
+                "invalid instruction at 0000: no function encloses this one 1 deep",
+            ),
+            (
+                vec![make(Closure, &[0])],
+                vec![function(vec![make(Closure, &[0])], &[])],
+                "functions are nested too deeply",
+            ),
+        ];
+        for (instructions, constants, expected) in tests {
+            let bytecode = Bytecode {
+                instructions: Instructions::from(instructions),
+                source_map: SourceMap::default(),
+                constants,
+                globals: vec!["x".to_string()],
+            };
+            let bytes = bytecode.to_bytes().unwrap();
+            let err = Bytecode::from_bytes(&bytes).unwrap_err();
+            assert!(err.ends_with(expected), "{}", err);
+        }
+
+        // and code that passes those checks fails when it's run if it still can't be
+        let path = |path: Vec<Object>| Object::Array(Rc::new(path));
+        let tests = vec![
+            (vec![make(Pop, &[])], vec![], "stack underflow"),
+            (vec![make(Add, &[])], vec![], "stack underflow"),
+            (
+                vec![make(Closure, &[0]), make(Call, &[0])],
+                vec![function(vec![make(Pop, &[]), make(ReturnValue, &[])], &[])],
+                "stack underflow",
+            ),
+            (
+                vec![make(GetField, &[0])],
+                vec![Object::Integer(1)],
+                "constant 0 is INTEGER, not STRING",
+            ),
+            (
+                vec![make(Null, &[]), make(Null, &[]), make(Assign, &[0, 0])],
+                vec![Object::Integer(1)],
+                "constant 0 is INTEGER, not ARRAY",
+            ),
+            (
+                vec![make(Null, &[]), make(Null, &[]), make(Assign, &[0, 0])],
+                vec![path(vec![])],
+                "constant 0 is an empty path",
+            ),
+            (
+                vec![make(Null, &[]), make(Null, &[]), make(Assign, &[0, 0])],
+                vec![path(vec![Object::String("".into()), Object::Integer(0)])],
+                "constant 0 needs more than 0 indexes",
+            ),
+        ];
+        for (instructions, constants, expected) in tests {
+            let bytecode = Bytecode {
+                instructions: Instructions::from(instructions),
+                source_map: SourceMap::default(),
+                constants,
+                globals: vec![],
+            };
+            let bytecode = Bytecode::from_bytes(&bytecode.to_bytes().unwrap()).unwrap();
+            let err = Vm::with_limits(ExecutionLimits::new()).run(&bytecode);
+            assert_eq!(err.unwrap_err().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_source_map() {
+        let mut map = SourceMap::default();
+        map.add(0, Span::new(1, 1));
+        map.add(3, Span::new(1, 1));
+        map.add(4, Span::new(1, 5));
+        assert_eq!(map.entries().len(), 2);
+        assert_eq!(map.span_at(0), Some(Span::new(1, 1)));
+        assert_eq!(map.span_at(3), Some(Span::new(1, 1)));
+        assert_eq!(map.span_at(7), Some(Span::new(1, 5)));
+        assert_eq!(SourceMap::default().span_at(0), None);
+    }
+
+    #[test]
+    fn test_integer_arithmetic() {
+        use Constant::*;
+
+        let tests = vec![
+            (
+                "1 + 2",
+                vec![Int(1), Int(2)],
+                vec![make(Constant, &[0]), make(Constant, &[1]), make(Add, &[])],
+            ),
+            (
+                "1; 2",
+                vec![Int(1), Int(2)],
+                vec![make(Constant, &[0]), make(Pop, &[]), make(Constant, &[1])],
+            ),
+            (
+                "2 ** 3 % 5",
+                vec![Int(2), Int(3), Int(5)],
+                vec![
+                    make(Constant, &[0]),
+                    make(Constant, &[1]),
+                    make(Pow, &[]),
+                    make(Constant, &[2]),
+                    make(Mod, &[]),
+                ],
+            ),
+            (
+                "-1",
+                vec![Int(1)],
+                vec![make(Constant, &[0]), make(Minus, &[])],
+            ),
+            (
+                "~1 << 2",
+                vec![Int(1), Int(2)],
+                vec![
+                    make(Constant, &[0]),
+                    make(BitNot, &[]),
+                    make(Constant, &[1]),
+                    make(Shl, &[]),
+                ],
+            ),
+            ("", vec![], vec![make(Null, &[])]),
+        ];
+        run_compiler_tests(tests);
+    }
+
+    #[test]
+    fn test_boolean_expressions() {
+        use Constant::*;
+
+        let tests = vec![
+            ("true", vec![], vec![make(True, &[])]),
+            (
+                "1 <= 2",
+                vec![Int(1), Int(2)],
+                vec![
+                    make(Constant, &[0]),
+                    make(Constant, &[1]),
+                    make(LessEqual, &[]),
+                ],
+            ),
+            ("!null", vec![], vec![make(Null, &[]), make(Bang, &[])]),
+            (
+                "true && false",
+                vec![],
+                vec![
+                    make(True, &[]),
+                    make(JumpNotTruthy, &[10]),
+                    make(False, &[]),
+                    make(Bang, &[]),
+                    make(Bang, &[]),
+                    make(Jump, &[11]),
+                    make(False, &[]),
+                ],
+            ),
+            (
+                "false || 1",
+                vec![Int(1)],
+                vec![
+                    make(False, &[]),
+                    make(JumpNotTruthy, &[8]),
+                    make(True, &[]),
+                    make(Jump, &[13]),
+                    make(Constant, &[0]),
+                    make(Bang, &[]),
+                    make(Bang, &[]),
+                ],
+            ),
+            (
+                "null ?? 1",
+                vec![Int(1)],
+                vec![
+                    make(Null, &[]),
+                    make(JumpNotNull, &[7]),
+                    make(Constant, &[0]),
+                ],
+            ),
+        ];
+        run_compiler_tests(tests);
+    }
+
+    #[test]
+    fn test_conditionals() {
+        use Constant::*;
+
+        let tests = vec![
+            (
+                "if (true) { 10 }; 3333;",
+                vec![Int(10), Int(3333)],
+                vec![
+                    make(True, &[]),
+                    make(JumpNotTruthy, &[10]),
+                    make(Constant, &[0]),
+                    make(Jump, &[11]),
+                    make(Null, &[]),
+                    make(Pop, &[]),
+                    make(Constant, &[1]),
+                ],
+            ),
+            (
+                "if (true) { 10 } else { 20 }",
+                vec![Int(10), Int(20)],
+                vec![
+                    make(True, &[]),
+                    make(JumpNotTruthy, &[10]),
+                    make(Constant, &[0]),
+                    make(Jump, &[13]),
+                    make(Constant, &[1]),
+                ],
+            ),
+            (
+                "if (true) { }",
+                vec![],
+                vec![
+                    make(True, &[]),
+                    make(JumpNotTruthy, &[8]),
+                    make(Null, &[]),
+                    make(Jump, &[9]),
+                    make(Null, &[]),
+                ],
+            ),
+        ];
+        run_compiler_tests(tests);
+    }
+
+    #[test]
+    fn test_global_let_statements() {
+        use Constant::*;
+
+        let tests = vec![
+            (
+                "let one = 1; let two = 2;",
+                vec![Int(1), Int(2)],
+                vec![
+                    make(Constant, &[0]),
+                    make(SetGlobal, &[0]),
+                    make(Constant, &[1]),
+                    make(SetGlobal, &[1]),
+                    make(Null, &[]),
+                ],
+            ),
+            (
+                "let one = 1; let one = one; one",
+                vec![Int(1)],
+                vec![
+                    make(Constant, &[0]),
+                    make(SetGlobal, &[0]),
+                    make(GetGlobal, &[0]),
+                    make(SetGlobal, &[0]),
+                    make(GetGlobal, &[0]),
+                ],
+            ),
+            // names that aren't defined yet are globals that may be defined by the time
+            // they're read
+            ("undefined", vec![], vec![make(GetGlobal, &[0])]),
+        ];
+        run_compiler_tests(tests);
+    }
+
+    #[test]
+    fn test_collections() {
+        use Constant::*;
+
+        let tests = vec![
+            (
+                r#""mon" + "key""#,
+                vec![Str("mon"), Str("key")],
+                vec![make(Constant, &[0]), make(Constant, &[1]), make(Add, &[])],
+            ),
+            ("[]", vec![], vec![make(Array, &[0])]),
+            (
+                "[1, 2][0]",
+                vec![Int(1), Int(2), Int(0)],
+                vec![
+                    make(Constant, &[0]),
+                    make(Constant, &[1]),
+                    make(Array, &[2]),
+                    make(Constant, &[2]),
+                    make(Index, &[]),
+                ],
+            ),
+            (
+                "{1: 2}",
+                vec![Int(1), Int(2)],
+                vec![make(Constant, &[0]), make(Constant, &[1]), make(Hash, &[1])],
+            ),
+            (
+                "null?[0]",
+                vec![Int(0)],
+                vec![
+                    make(Null, &[]),
+                    make(JumpNull, &[8]),
+                    make(Constant, &[0]),
+                    make(Index, &[]),
+                ],
+            ),
+            (
+                "{}.key",
+                vec![Str("key")],
+                vec![make(Hash, &[0]), make(GetField, &[0])],
+            ),
+            (
+                "1..=2",
+                vec![Int(1), Int(2)],
+                vec![
+                    make(Constant, &[0]),
+                    make(Constant, &[1]),
+                    make(RangeInclusive, &[]),
+                ],
+            ),
+            (
+                r#""a${1}b""#,
+                vec![Str("a"), Int(1), Str("b")],
+                vec![
+                    make(Constant, &[0]),
+                    make(Constant, &[1]),
+                    make(Constant, &[2]),
+                    make(Interpolate, &[3]),
+                ],
+            ),
+        ];
+        run_compiler_tests(tests);
+    }
+
+    #[test]
+    fn test_functions() {
+        use Constant::*;
+
+        let tests = vec![
+            (
+                "fn() { return 5 + 10 }",
+                vec![
+                    Int(5),
+                    Int(10),
+                    Function(vec![
+                        make(Constant, &[0]),
+                        make(Constant, &[1]),
+                        make(Add, &[]),
+                        make(ReturnValue, &[]),
+                    ]),
+                ],
+                vec![make(Closure, &[2])],
+            ),
+            (
+                "fn() { 1; 2 }",
+                vec![
+                    Int(1),
+                    Int(2),
+                    Function(vec![
+                        make(Constant, &[0]),
+                        make(Pop, &[]),
+                        make(Constant, &[1]),
+                        make(ReturnValue, &[]),
+                    ]),
+                ],
+                vec![make(Closure, &[2])],
+            ),
+            (
+                "fn() { }",
+                vec![Function(vec![make(Null, &[]), make(ReturnValue, &[])])],
+                vec![make(Closure, &[0])],
+            ),
+            (
+                "fn() { if (true) { return 1 }; 2 }",
+                vec![
+                    Int(1),
+                    Int(2),
+                    Function(vec![
+                        make(True, &[]),
+                        make(JumpNotTruthy, &[12]),
+                        make(Constant, &[0]),
+                        make(ReturnValue, &[]),
+                        make(Null, &[]),
+                        make(Jump, &[13]),
+                        make(Null, &[]),
+                        make(Pop, &[]),
+                        make(Constant, &[1]),
+                        make(ReturnValue, &[]),
+                    ]),
+                ],
+                vec![make(Closure, &[2])],
+            ),
+        ];
+        run_compiler_tests(tests);
+    }
+
+    #[test]
+    fn test_calls() {
+        use Constant::*;
+
+        let tests = vec![
+            (
+                "let f = fn(a, b) { a }; f(1, 2)",
+                vec![
+                    Function(vec![make(GetLocal, &[0]), make(ReturnValue, &[])]),
+                    Int(1),
+                    Int(2),
+                ],
+                vec![
+                    make(Closure, &[0]),
+                    make(SetGlobal, &[0]),
+                    make(GetGlobal, &[0]),
+                    make(Constant, &[1]),
+                    make(Constant, &[2]),
+                    make(Call, &[2]),
+                ],
+            ),
+            (
+                "len([])",
+                vec![],
+                vec![make(GetBuiltin, &[0]), make(Array, &[0]), make(Call, &[1])],
+            ),
+            (
+                "[].len()",
+                vec![Str("len")],
+                vec![make(Array, &[0]), make(CallMethod, &[0, 0])],
+            ),
+            // a call whose value is returned is a tail call
+            (
+                "let f = fn(n) { if (n) { f(n - 1) } else { 1 + f(n) } }",
+                vec![
+                    Int(1),
+                    Int(1),
+                    Function(vec![
+                        make(GetLocal, &[0]),
+                        make(JumpNotTruthy, &[19]),
+                        make(GetGlobal, &[0]),
+                        make(GetLocal, &[0]),
+                        make(Constant, &[0]),
+                        make(Sub, &[]),
+                        make(TailCall, &[1]),
+                        make(Jump, &[30]),
+                        make(Constant, &[1]),
+                        make(GetGlobal, &[0]),
+                        make(GetLocal, &[0]),
+                        make(Call, &[1]),
+                        make(Add, &[]),
+                        make(ReturnValue, &[]),
+                    ]),
+                ],
+                vec![make(Closure, &[2]), make(SetGlobal, &[0]), make(Null, &[])],
+            ),
+        ];
+        run_compiler_tests(tests);
+    }
+
+    #[test]
+    fn test_locals_and_closures() {
+        use Constant::*;
+
+        let tests = vec![
+            (
+                "fn() { let num = 55; num }",
+                vec![
+                    Int(55),
+                    Function(vec![
+                        make(Constant, &[0]),
+                        make(SetLocal, &[0]),
+                        make(GetLocal, &[0]),
+                        make(ReturnValue, &[]),
+                    ]),
+                ],
+                vec![make(Closure, &[1])],
+            ),
+            (
+                "fn(a) { fn(b) { fn(c) { a + b + c } } }",
+                vec![
+                    Function(vec![
+                        make(GetFree, &[2, 0]),
+                        make(GetFree, &[1, 0]),
+                        make(Add, &[]),
+                        make(GetLocal, &[0]),
+                        make(Add, &[]),
+                        make(ReturnValue, &[]),
+                    ]),
+                    Function(vec![make(Closure, &[0]), make(ReturnValue, &[])]),
+                    Function(vec![make(Closure, &[1]), make(ReturnValue, &[])]),
+                ],
+                vec![make(Closure, &[2])],
+            ),
+            // names defined later in a function are its locals everywhere in it
+            (
+                "fn() { let g = fn() { x = 2 }; let x = 1; g() }",
+                vec![
+                    Int(2),
+                    Other(Object::Array(Rc::new(vec![Object::String("".into())]))),
+                    Function(vec![
+                        make(Constant, &[0]),
+                        make(GetFree, &[1, 1]),
+                        make(Assign, &[1, 0]),
+                        make(SetFree, &[1, 1]),
+                        make(ReturnValue, &[]),
+                    ]),
+                    Int(1),
+                    Function(vec![
+                        make(Closure, &[2]),
+                        make(SetLocal, &[0]),
+                        make(Constant, &[3]),
+                        make(SetLocal, &[1]),
+                        make(GetLocal, &[0]),
+                        make(TailCall, &[0]),
+                        make(ReturnValue, &[]),
+                    ]),
+                ],
+                vec![make(Closure, &[4])],
+            ),
+        ];
+        run_compiler_tests(tests);
+    }
+
+    #[test]
+    fn test_assignments() {
+        use Constant::*;
+
+        let path = |parts: Vec<Object>| Other(Object::Array(Rc::new(parts)));
+        let tests = vec![
+            (
+                "let a = [1]; a[0] += 2",
+                vec![
+                    Int(1),
+                    Int(0),
+                    Int(2),
+                    path(vec![Object::String("+".into()), Object::Null]),
+                ],
+                vec![
+                    make(Constant, &[0]),
+                    make(Array, &[1]),
+                    make(SetGlobal, &[0]),
+                    make(Constant, &[1]),
+                    make(Constant, &[2]),
+                    make(GetGlobal, &[0]),
+                    make(Assign, &[3, 1]),
+                    make(SetGlobal, &[0]),
+                ],
+            ),
+            (
+                "h.k = 1",
+                vec![
+                    Int(1),
+                    path(vec![Object::String("".into()), Object::String("k".into())]),
+                ],
+                vec![
+                    make(Constant, &[0]),
+                    make(GetGlobal, &[0]),
+                    make(Assign, &[1, 0]),
+                    make(SetGlobal, &[0]),
+                ],
+            ),
+        ];
+        run_compiler_tests(tests);
+    }
+
+    #[test]
+    fn test_loops() {
+        use Constant::*;
+
+        let tests = vec![
+            (
+                "while (true) { break; }",
+                vec![],
+                vec![
+                    make(True, &[]),
+                    make(JumpNotTruthy, &[10]),
+                    make(Jump, &[10]),
+                    make(Jump, &[0]),
+                    make(Null, &[]),
+                ],
+            ),
+            // values being computed when the loop is left are dropped
+            (
+                "while (true) { 1 + if (true) { continue } }",
+                vec![Int(1)],
+                vec![
+                    make(True, &[]),
+                    make(JumpNotTruthy, &[25]),
+                    make(Constant, &[0]),
+                    make(True, &[]),
+                    make(JumpNotTruthy, &[19]),
+                    make(Pop, &[]),
+                    make(Jump, &[0]),
+                    make(Null, &[]),
+                    make(Jump, &[20]),
+                    make(Null, &[]),
+                    make(Add, &[]),
+                    make(Pop, &[]),
+                    make(Jump, &[0]),
+                    make(Null, &[]),
+                ],
+            ),
+            (
+                "for (x in [1]) { x }",
+                vec![Int(1)],
+                vec![
+                    make(Constant, &[0]),
+                    make(Array, &[1]),
+                    make(Iter, &[]),
+                    make(SetGlobal, &[0]),
+                    make(GetGlobal, &[0]),
+                    make(IterNext, &[26]),
+                    make(SetGlobal, &[1]),
+                    make(GetGlobal, &[1]),
+                    make(Pop, &[]),
+                    make(Jump, &[10]),
+                    make(Null, &[]),
+                ],
+            ),
+        ];
+        run_compiler_tests(tests);
+    }
+
+    #[test]
+    fn test_source_spans() {
+        let l = Lexer::new("1;\n  x + 2");
+        let program = Parser::new(l).parse_program().unwrap();
+        let mut compiler = Compiler::new();
+        compiler.compile(&program).unwrap();
+        let bytecode = compiler.bytecode();
+        let map = bytecode.source_map();
+        assert_eq!(map.span_at(0), Some(Span::new(1, 1)));
+        // the operands are located where they are and the operator where the expression is
+        assert_eq!(map.span_at(4), Some(Span::new(2, 3)));
+        assert_eq!(map.span_at(7), Some(Span::new(2, 7)));
+        assert_eq!(map.span_at(10), Some(Span::new(2, 5)));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let tests = vec![
+            (
+                "match (1) { _ => 1 }",
+                "MatchExpression isn't supported by the compiler yet",
+            ),
+            (
+                "struct P { x }",
+                "StructStatement isn't supported by the compiler yet",
+            ),
+            (
+                "let [a] = [1]",
+                "DestructuringLetStatement isn't supported by the compiler yet",
+            ),
+            (
+                "enum E { A }",
+                "EnumStatement isn't supported by the compiler yet",
+            ),
+            (
+                "try { 1 } catch (e) { 2 }",
+                "TryExpression isn't supported by the compiler yet",
+            ),
+            (
+                "throw 1",
+                "ThrowStatement isn't supported by the compiler yet",
+            ),
+            (
+                "fn*() { yield 1 }",
+                "generators aren't supported by the compiler yet",
+            ),
+            (
+                "fn(a = 1) { a }",
+                "default and rest parameters aren't supported by the compiler yet",
+            ),
+            (
+                "f(...xs)",
+                "spread and named arguments aren't supported by the compiler yet",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(compile(input).unwrap_err(), expected, "{}", input);
+        }
+    }
+}
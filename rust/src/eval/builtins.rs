@@ -0,0 +1,141 @@
+use std::{cell::RefCell, rc::Rc};
+
+use super::{iterate, iterator_next};
+use crate::object::{Budget, Builtin, BuiltinFunction, Error, Exception, IteratorState, Object};
+
+const BUILTINS: &[(&str, BuiltinFunction)] = &[
+    ("len", len),
+    ("iter", iter),
+    ("next", next),
+    ("map", map),
+    ("filter", filter),
+    ("collect", collect),
+    ("error", error),
+];
+
+pub fn lookup(name: &str) -> Option<Builtin> {
+    BUILTINS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(name, function)| Builtin::new(name, *function))
+}
+
+/// The built-in function at `index` in the table of them.
+pub fn at(index: usize) -> Option<Builtin> {
+    BUILTINS
+        .get(index)
+        .map(|(name, function)| Builtin::new(name, *function))
+}
+
+/// Where a built-in function is in the table of them, which compiled code refers to them by.
+pub fn index_of(name: &str) -> Option<usize> {
+    BUILTINS.iter().position(|(n, _)| *n == name)
+}
+
+pub(super) fn check_arity(args: &[Object], expected: usize) -> Result<(), String> {
+    if args.len() != expected {
+        return Err(format!(
+            "wrong number of arguments: expected {}, got {}",
+            expected,
+            args.len()
+        ));
+    }
+    Ok(())
+}
+
+fn check_callable(name: &str, function: &Object) -> Result<(), String> {
+    match function {
+        Object::Function(_) | Object::Closure(_) | Object::Builtin(_) | Object::Constructor(_) => {
+            Ok(())
+        },
+        obj => Err(format!(
+            "argument to `{}` must be FUNCTION, got {}",
+            name,
+            obj.type_name()
+        )),
+    }
+}
+
+pub(super) fn len(_: &Budget, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    let len = match &args[0] {
+        Object::Array(elements) => elements.len() as u64,
+        Object::String(s) => s.chars().count() as u64,
+        Object::Hash(pairs) => pairs.len() as u64,
+        Object::Range(range) => range.len(),
+        obj => {
+            return Err(format!("argument to `len` not supported, got {}", obj.type_name()).into())
+        },
+    };
+    i64::try_from(len)
+        .map(Object::Integer)
+        .map_err(|_| "integer overflow".to_string().into())
+}
+
+pub(super) fn iter(_: &Budget, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    let [iterable] = <[Object; 1]>::try_from(args).unwrap();
+    Ok(Object::Iterator(iterate(iterable)?))
+}
+
+/// Advances an iterator, producing `null` once it's exhausted.
+pub(super) fn next(budget: &Budget, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    match &args[0] {
+        Object::Iterator(iter) => Ok(iterator_next(iter, budget)?.unwrap_or(Object::Null)),
+        obj => Err(format!(
+            "argument to `next` must be ITERATOR, got {}",
+            obj.type_name()
+        )
+        .into()),
+    }
+}
+
+pub(super) fn map(_: &Budget, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 2)?;
+    let [iterable, function] = <[Object; 2]>::try_from(args).unwrap();
+    check_callable("map", &function)?;
+    Ok(Object::Iterator(Rc::new(RefCell::new(
+        IteratorState::Map {
+            source: iterate(iterable)?,
+            function,
+        },
+    ))))
+}
+
+pub(super) fn filter(_: &Budget, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 2)?;
+    let [iterable, function] = <[Object; 2]>::try_from(args).unwrap();
+    check_callable("filter", &function)?;
+    Ok(Object::Iterator(Rc::new(RefCell::new(
+        IteratorState::Filter {
+            source: iterate(iterable)?,
+            function,
+        },
+    ))))
+}
+
+pub(super) fn collect(budget: &Budget, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    let [iterable] = <[Object; 1]>::try_from(args).unwrap();
+    let iter = iterate(iterable)?;
+    let mut elements = Vec::new();
+    while let Some(value) = iterator_next(&iter, budget)? {
+        budget.allocate(size_of::<Object>())?;
+        elements.push(value);
+    }
+    Ok(Object::Array(Rc::new(elements)))
+}
+
+/// Creates an error object, which `throw` raises like a built-in runtime error.
+pub(super) fn error(_: &Budget, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    match &args[0] {
+        Object::String(message) => Ok(Object::Error(Rc::new(Error::custom(message)))),
+        obj => Err(format!(
+            "argument to `error` must be STRING, got {}",
+            obj.type_name()
+        )
+        .into()),
+    }
+}
@@ -0,0 +1,201 @@
+use std::{cell::RefCell, rc::Rc};
+
+use super::{downcast, evaluate, iterate, iterator_next, slot, Unwind};
+use crate::{
+    object::{
+        BlockCursor, CallDepth, Cursor, Environment, Exception, GeneratorState, IteratorState,
+        Object,
+    },
+    BlockStatement, ExpressionStatement, ForStatement, IfExpression, Node, NodeType,
+    WhileStatement, YieldStatement,
+};
+
+// generators can't suspend the evaluator's call stack, so their bodies are run by the functions
+// below instead, which keep where they stopped in a cursor and skip ahead to it when resumed.
+// they handle yields in the body and in the blocks of if, while and for statements, and leave
+// everything else to `evaluate`
+
+/// How far a piece of a generator's body got.
+enum Step {
+    /// It yielded a value, and the cursor was left where it should continue from.
+    Yield(Object),
+    /// It ran to the end with this result.
+    Done(Object),
+}
+
+/// Runs a generator until it yields its next value, producing `None` once it has finished.
+pub(super) fn resume(iter: &Rc<RefCell<IteratorState>>) -> Result<Option<Object>, Exception> {
+    let (function, env, mut cursor, _call) = {
+        let mut state = iter.borrow_mut();
+        let IteratorState::Generator(generator) = &mut *state else {
+            unreachable!()
+        };
+        // resuming runs the body on top of the stack like a call does, so it's counted as one
+        let call = CallDepth::enter(generator.env().borrow().call_depth())?;
+        match generator.start() {
+            Ok(cursor) => (
+                generator.function().clone(),
+                generator.env().clone(),
+                cursor,
+                call,
+            ),
+            Err(GeneratorState::Done) => return Ok(None),
+            Err(_) => return Err("generator already running".to_string().into()),
+        }
+    };
+    // the body may use the generator itself, so it can't stay borrowed while it runs
+    let result = resume_block(function.body(), &mut cursor, &env);
+    let IteratorState::Generator(generator) = &mut *iter.borrow_mut() else {
+        unreachable!()
+    };
+    match result {
+        Ok(Step::Yield(value)) => {
+            generator.stop(Some(cursor));
+            Ok(Some(value))
+        },
+        Ok(Step::Done(_)) | Err(Unwind::Return(_)) => {
+            generator.stop(None);
+            Ok(None)
+        },
+        Err(Unwind::Exception(exception)) => {
+            generator.stop(None);
+            Err(exception)
+        },
+        Err(Unwind::Break | Unwind::Continue) => {
+            unreachable!("the parser only accepts break and continue in loops")
+        },
+    }
+}
+
+fn resume_block(
+    block: &BlockStatement,
+    cursor: &mut BlockCursor,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Step, Unwind> {
+    let mut result = Object::Null;
+    while let Some(stmt) = block.statements().get(cursor.index) {
+        match resume_statement(stmt.as_ref(), &mut cursor.statement, env)? {
+            Step::Yield(value) => {
+                // a statement with nowhere to continue from inside it is finished
+                if cursor.statement.is_none() {
+                    cursor.index += 1;
+                }
+                return Ok(Step::Yield(value));
+            },
+            Step::Done(value) => {
+                cursor.index += 1;
+                result = value;
+            },
+        }
+    }
+    Ok(Step::Done(result))
+}
+
+fn resume_statement(
+    stmt: &dyn Node,
+    cursor: &mut Option<Cursor>,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Step, Unwind> {
+    match stmt.node_type() {
+        NodeType::YieldStatement => {
+            let value = evaluate(downcast::<YieldStatement>(stmt).value(), env)?;
+            Ok(Step::Yield(value))
+        },
+        NodeType::ExpressionStatement => {
+            let expr = downcast::<ExpressionStatement>(stmt).expression();
+            if expr.node_type() == NodeType::IfExpression {
+                resume_if_expression(downcast::<IfExpression>(expr), cursor, env)
+            } else {
+                evaluate(stmt, env).map(Step::Done)
+            }
+        },
+        NodeType::WhileStatement => {
+            resume_while_statement(downcast::<WhileStatement>(stmt), cursor, env)
+        },
+        NodeType::ForStatement => resume_for_statement(downcast::<ForStatement>(stmt), cursor, env),
+        _ => evaluate(stmt, env).map(Step::Done),
+    }
+}
+
+fn resume_if_expression(
+    expr: &IfExpression,
+    cursor: &mut Option<Cursor>,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Step, Unwind> {
+    let (consequence, mut block) = match cursor.take() {
+        Some(Cursor::If { consequence, block }) => (consequence, block),
+        _ => (evaluate(expr.condition(), env)?.is_truthy(), Box::default()),
+    };
+    let branch = if consequence {
+        Some(expr.consequence())
+    } else {
+        expr.alternative().as_ref()
+    };
+    let Some(branch) = branch else {
+        return Ok(Step::Done(Object::Null));
+    };
+    let step = resume_block(branch, &mut block, env)?;
+    if let Step::Yield(_) = step {
+        *cursor = Some(Cursor::If { consequence, block });
+    }
+    Ok(step)
+}
+
+fn resume_while_statement(
+    stmt: &WhileStatement,
+    cursor: &mut Option<Cursor>,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Step, Unwind> {
+    loop {
+        let mut block = match cursor.take() {
+            Some(Cursor::While { block }) => block,
+            _ if evaluate(stmt.condition(), env)?.is_truthy() => Box::default(),
+            _ => return Ok(Step::Done(Object::Null)),
+        };
+        match resume_block(stmt.body(), &mut block, env) {
+            Ok(Step::Yield(value)) => {
+                *cursor = Some(Cursor::While { block });
+                return Ok(Step::Yield(value));
+            },
+            Err(Unwind::Break) => return Ok(Step::Done(Object::Null)),
+            Ok(Step::Done(_)) | Err(Unwind::Continue) => {},
+            Err(unwind) => return Err(unwind),
+        }
+    }
+}
+
+fn resume_for_statement(
+    stmt: &ForStatement,
+    cursor: &mut Option<Cursor>,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Step, Unwind> {
+    let (iter, mut block) = match cursor.take() {
+        Some(Cursor::For { iter, block }) => (iter, block),
+        _ => (iterate(evaluate(stmt.iterable(), env)?)?, None),
+    };
+    let budget = env.borrow().budget().clone();
+    loop {
+        let mut body = match block.take() {
+            Some(body) => body,
+            None => {
+                let Some(element) = iterator_next(&iter, &budget)? else {
+                    return Ok(Step::Done(Object::Null));
+                };
+                env.borrow_mut().set(slot(stmt.variable()), element);
+                Box::default()
+            },
+        };
+        match resume_block(stmt.body(), &mut body, env) {
+            Ok(Step::Yield(value)) => {
+                *cursor = Some(Cursor::For {
+                    iter,
+                    block: Some(body),
+                });
+                return Ok(Step::Yield(value));
+            },
+            Err(Unwind::Break) => return Ok(Step::Done(Object::Null)),
+            Ok(Step::Done(_)) | Err(Unwind::Continue) => {},
+            Err(unwind) => return Err(unwind),
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::rc::Rc;
+
+use super::{
+    allocated,
+    builtins::{check_arity, collect, filter, iter, len, map, next},
+    MeteredString,
+};
+use crate::object::{Budget, Exception, HashKey, Object};
+
+/// A method on a built-in type, called with the receiver and the arguments.
+pub type Method = fn(&Budget, Object, Vec<Object>) -> Result<Object, Exception>;
+
+const STRING_METHODS: &[(&str, Method)] = &[
+    ("len", receiver_len),
+    ("iter", receiver_iter),
+    ("upper", string_upper),
+    ("lower", string_lower),
+    ("trim", string_trim),
+    ("contains", string_contains),
+    ("split", string_split),
+];
+
+const ARRAY_METHODS: &[(&str, Method)] = &[
+    ("len", receiver_len),
+    ("iter", receiver_iter),
+    ("first", array_first),
+    ("last", array_last),
+    ("push", array_push),
+    ("contains", array_contains),
+    ("join", array_join),
+    ("map", array_map),
+    ("filter", array_filter),
+];
+
+const HASH_METHODS: &[(&str, Method)] = &[
+    ("len", receiver_len),
+    ("keys", hash_keys),
+    ("values", hash_values),
+    ("contains", hash_contains),
+];
+
+const RANGE_METHODS: &[(&str, Method)] = &[
+    ("len", receiver_len),
+    ("iter", receiver_iter),
+    ("contains", range_contains),
+    ("map", receiver_map),
+    ("filter", receiver_filter),
+    ("collect", receiver_collect),
+];
+
+const ITERATOR_METHODS: &[(&str, Method)] = &[
+    ("next", iterator_next),
+    ("map", receiver_map),
+    ("filter", receiver_filter),
+    ("collect", receiver_collect),
+];
+
+pub fn lookup(receiver: &Object, name: &str) -> Option<Method> {
+    let methods = match receiver {
+        Object::String(_) => STRING_METHODS,
+        Object::Array(_) => ARRAY_METHODS,
+        Object::Hash(_) => HASH_METHODS,
+        Object::Range(_) => RANGE_METHODS,
+        Object::Iterator(_) => ITERATOR_METHODS,
+        _ => &[],
+    };
+    methods
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, method)| *method)
+}
+
+fn string_arg<'a>(method: &str, arg: &'a Object) -> Result<&'a str, String> {
+    match arg {
+        Object::String(s) => Ok(s),
+        obj => Err(format!(
+            "argument to `{}` must be STRING, got {}",
+            method,
+            obj.type_name()
+        )),
+    }
+}
+
+fn receiver_len(budget: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 0)?;
+    len(budget, vec![receiver])
+}
+
+fn receiver_iter(
+    budget: &Budget,
+    receiver: Object,
+    args: Vec<Object>,
+) -> Result<Object, Exception> {
+    check_arity(&args, 0)?;
+    iter(budget, vec![receiver])
+}
+
+fn receiver_map(budget: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    map(budget, [vec![receiver], args].concat())
+}
+
+fn receiver_filter(
+    budget: &Budget,
+    receiver: Object,
+    args: Vec<Object>,
+) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    filter(budget, [vec![receiver], args].concat())
+}
+
+fn receiver_collect(
+    budget: &Budget,
+    receiver: Object,
+    args: Vec<Object>,
+) -> Result<Object, Exception> {
+    check_arity(&args, 0)?;
+    collect(budget, vec![receiver])
+}
+
+// each table is only used for its own type, so the receiver always has the expected variant
+// and the methods below can destructure it unconditionally
+
+fn string_upper(budget: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 0)?;
+    let Object::String(s) = receiver else {
+        unreachable!()
+    };
+    allocated(budget, Object::String(s.to_uppercase().into()))
+}
+
+fn string_lower(budget: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 0)?;
+    let Object::String(s) = receiver else {
+        unreachable!()
+    };
+    allocated(budget, Object::String(s.to_lowercase().into()))
+}
+
+fn string_trim(budget: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 0)?;
+    let Object::String(s) = receiver else {
+        unreachable!()
+    };
+    allocated(budget, Object::String(s.trim().into()))
+}
+
+fn string_contains(_: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    let Object::String(s) = receiver else {
+        unreachable!()
+    };
+    let needle = string_arg("contains", &args[0])?;
+    Ok(Object::Boolean(s.contains(needle)))
+}
+
+fn string_split(budget: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    let Object::String(s) = receiver else {
+        unreachable!()
+    };
+    let separator = string_arg("split", &args[0])?;
+    if separator.is_empty() {
+        return Err("separator to `split` must not be empty".to_string().into());
+    }
+    let parts = s
+        .split(separator)
+        .map(|part| Object::String(part.into()))
+        .collect();
+    allocated(budget, Object::Array(Rc::new(parts)))
+}
+
+fn array_first(_: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 0)?;
+    let Object::Array(elements) = receiver else {
+        unreachable!()
+    };
+    Ok(elements.first().cloned().unwrap_or(Object::Null))
+}
+
+fn array_last(_: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 0)?;
+    let Object::Array(elements) = receiver else {
+        unreachable!()
+    };
+    Ok(elements.last().cloned().unwrap_or(Object::Null))
+}
+
+/// Arrays are values, so this returns a new array rather than changing the receiver.
+fn array_push(budget: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    let Object::Array(elements) = receiver else {
+        unreachable!()
+    };
+    let mut elements = elements.as_ref().clone();
+    elements.extend(args);
+    allocated(budget, Object::Array(Rc::new(elements)))
+}
+
+fn array_contains(_: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    let Object::Array(elements) = receiver else {
+        unreachable!()
+    };
+    Ok(Object::Boolean(elements.contains(&args[0])))
+}
+
+fn array_join(budget: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    let Object::Array(elements) = receiver else {
+        unreachable!()
+    };
+    let separator = string_arg("join", &args[0])?;
+    let mut string = MeteredString::new(budget);
+    for (i, element) in elements.iter().enumerate() {
+        if i > 0 {
+            string.push_str(separator)?;
+        }
+        string.push(element)?;
+    }
+    Ok(string.finish())
+}
+
+fn array_map(budget: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    collect(budget, vec![receiver_map(budget, receiver, args)?])
+}
+
+fn array_filter(budget: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    collect(budget, vec![receiver_filter(budget, receiver, args)?])
+}
+
+fn hash_keys(budget: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 0)?;
+    let Object::Hash(pairs) = receiver else {
+        unreachable!()
+    };
+    let keys = pairs.keys().cloned().map(Object::from).collect();
+    allocated(budget, Object::Array(Rc::new(keys)))
+}
+
+fn hash_values(budget: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 0)?;
+    let Object::Hash(pairs) = receiver else {
+        unreachable!()
+    };
+    allocated(
+        budget,
+        Object::Array(Rc::new(pairs.values().cloned().collect())),
+    )
+}
+
+fn hash_contains(_: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    let Object::Hash(pairs) = receiver else {
+        unreachable!()
+    };
+    Ok(Object::Boolean(
+        pairs.contains_key(&HashKey::try_from(&args[0])?),
+    ))
+}
+
+fn range_contains(_: &Budget, receiver: Object, args: Vec<Object>) -> Result<Object, Exception> {
+    check_arity(&args, 1)?;
+    let Object::Range(range) = receiver else {
+        unreachable!()
+    };
+    match args[0] {
+        Object::Integer(i) => Ok(Object::Boolean(range.contains(i))),
+        ref obj => Err(format!(
+            "argument to `contains` must be INTEGER, got {}",
+            obj.type_name()
+        )
+        .into()),
+    }
+}
+
+fn iterator_next(
+    budget: &Budget,
+    receiver: Object,
+    args: Vec<Object>,
+) -> Result<Object, Exception> {
+    check_arity(&args, 0)?;
+    next(budget, vec![receiver])
+}
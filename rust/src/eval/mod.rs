@@ -1,18 +1,1449 @@
-use std::any::TypeId;
+use std::{cell::RefCell, collections::BTreeMap, fmt::Write, rc::Rc};
 
-use crate::{object::Object, ExpressionStatement, IntegerLiteral, Node, NodeType, Program};
+use crate::{
+    object::{
+        Budget, CallDepth, Constructor, EnumType, Environment, Exception, Function, Generator,
+        HashKey, IteratorState, LimitExceeded, Object, Range, Struct, StructType, TailCall,
+        Variant, VariantType,
+    },
+    vm, ArrayLiteral, ArrayPattern, AssignExpression, Binding, BlockStatement, Boolean,
+    CallExpression, DestructuringLetStatement, EnumStatement, Expression, ExpressionStatement,
+    ForStatement, FunctionLiteral, HashLiteral, HashPattern, Identifier, IfExpression,
+    IndexExpression, InfixExpression, IntegerLiteral, InterpolatedString, LetStatement,
+    MatchExpression, MemberExpression, NamedArgument, Node, NodeType, Pattern, PrefixExpression,
+    Program, RestPattern, ReturnStatement, Span, SpreadExpression, StringLiteral, StringPart,
+    StructLiteral, StructStatement, Symbol, ThrowStatement, TryExpression, VariantPattern,
+    WhileStatement,
+};
 
+pub(crate) mod builtins;
+mod generator;
+mod methods;
 mod tests;
 
-// Rust made this impossible to finish without rewriting the entire parser so here we are
-pub fn eval(node: Box<dyn Node>) -> Object {
+use methods::Method;
+
+fn downcast<T: 'static>(node: &dyn Node) -> &T {
+    node.as_any().downcast_ref::<T>().unwrap()
+}
+
+/// Evaluates a node of a program the [`Resolver`](crate::Resolver) has resolved, in the
+/// environment it was resolved for.
+pub fn eval(node: &dyn Node, env: &Rc<RefCell<Environment>>) -> Result<Object, Exception> {
+    returned(evaluate(node, env))
+}
+
+/// Why evaluating a node stopped before it had a value. A `return`, `break` or `continue` is
+/// passed up through the expressions it's in like an exception is, until it reaches the call
+/// or loop it leaves.
+enum Unwind {
+    Exception(Exception),
+    Return(Object),
+    Break,
+    Continue,
+}
+
+impl Unwind {
+    fn with_span(self, span: Span) -> Self {
+        match self {
+            Unwind::Exception(exception) => Unwind::Exception(exception.with_span(span)),
+            unwind => unwind,
+        }
+    }
+}
+
+impl From<Exception> for Unwind {
+    fn from(exception: Exception) -> Self {
+        Unwind::Exception(exception)
+    }
+}
+
+impl From<String> for Unwind {
+    fn from(message: String) -> Self {
+        Unwind::Exception(message.into())
+    }
+}
+
+impl From<LimitExceeded> for Unwind {
+    fn from(limit: LimitExceeded) -> Self {
+        Unwind::Exception(limit.into())
+    }
+}
+
+/// The value of a function's body or a program, which is what it returns if it does.
+fn returned(result: Result<Object, Unwind>) -> Result<Object, Exception> {
+    match result {
+        Ok(obj) | Err(Unwind::Return(obj)) => Ok(obj),
+        Err(Unwind::Exception(exception)) => Err(exception),
+        Err(Unwind::Break | Unwind::Continue) => {
+            unreachable!("the parser only accepts break and continue in loops")
+        },
+    }
+}
+
+fn evaluate(node: &dyn Node, env: &Rc<RefCell<Environment>>) -> Result<Object, Unwind> {
+    let step = env.borrow().budget().step();
+    // errors are located at the innermost node they come from, which is the first to see them
+    step.map_err(Unwind::from)
+        .and_then(|()| eval_node(node, env))
+        .map_err(|e| e.with_span(node.span()))
+}
+
+/// Like [`evaluate`], but gives `None` for a call or access that's cut short by an optional
+/// access earlier in the chain it's part of, like `h?.a.b` is when `h` is null, so the rest of
+/// the chain can be skipped too.
+fn eval_chain(node: &dyn Node, env: &Rc<RefCell<Environment>>) -> Result<Option<Object>, Unwind> {
+    if grouped(node) {
+        // a chain in parentheses is null when it's cut short, rather than cutting short the
+        // chain it's the start of
+        return evaluate(node, env).map(Some);
+    }
+    let step = env.borrow().budget().step();
+    step.map_err(Unwind::from)
+        .and_then(|()| eval_chain_link(node, env))
+        .map_err(|e| e.with_span(node.span()))
+}
+
+fn eval_chain_link(
+    node: &dyn Node,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Option<Object>, Unwind> {
+    match node.node_type() {
+        NodeType::CallExpression => eval_call_expression(downcast::<CallExpression>(node), env),
+        NodeType::IndexExpression => eval_index_operands(downcast::<IndexExpression>(node), env),
+        NodeType::MemberExpression => eval_member_operands(downcast::<MemberExpression>(node), env),
+        _ => eval_node(node, env).map(Some),
+    }
+}
+
+/// Whether a node is a call or access in parentheses.
+fn grouped(node: &dyn Node) -> bool {
+    match node.node_type() {
+        NodeType::CallExpression => downcast::<CallExpression>(node).grouped(),
+        NodeType::IndexExpression => downcast::<IndexExpression>(node).grouped(),
+        NodeType::MemberExpression => downcast::<MemberExpression>(node).grouped(),
+        _ => false,
+    }
+}
+
+/// Counts a value that was just allocated against the memory limit.
+pub(crate) fn allocated(budget: &Budget, obj: Object) -> Result<Object, Exception> {
+    budget.allocate(obj.allocated_size())?;
+    Ok(obj)
+}
+
+/// A string being built from values, whose bytes are counted against the memory limit as
+/// they're written, along with a step for each piece. A value can hold the same array many
+/// times over, so writing it out can take far longer than building it did.
+pub(crate) struct MeteredString<'a> {
+    budget: &'a Budget,
+    value: String,
+    exceeded: Option<LimitExceeded>,
+}
+
+impl<'a> MeteredString<'a> {
+    pub(crate) fn new(budget: &'a Budget) -> Self {
+        MeteredString {
+            budget,
+            value: String::new(),
+            exceeded: None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, obj: &Object) -> Result<(), Exception> {
+        write!(self, "{}", obj).map_err(|_| self.exceeded.take().unwrap().into())
+    }
+
+    pub(crate) fn push_str(&mut self, s: &str) -> Result<(), Exception> {
+        self.write_str(s)
+            .map_err(|_| self.exceeded.take().unwrap().into())
+    }
+
+    pub(crate) fn finish(self) -> Object {
+        Object::String(self.value.into())
+    }
+}
+
+impl Write for MeteredString<'_> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let budget = self.budget;
+        if let Err(limit) = budget.allocate(s.len()).and_then(|()| budget.step()) {
+            self.exceeded = Some(limit);
+            return Err(std::fmt::Error);
+        }
+        self.value.push_str(s);
+        Ok(())
+    }
+}
+
+fn eval_node(node: &dyn Node, env: &Rc<RefCell<Environment>>) -> Result<Object, Unwind> {
     match node.node_type() {
-        // NodeType::ExpressionStatement => eval(
-        //     node.as_any()
-        //         .downcast_ref::<ExpressionStatement>()
-        //         .unwrap()
-        //         .expression,
-        // ),
-        _ => Object::Null,
+        NodeType::Program => eval_program(downcast::<Program>(node), env),
+        NodeType::ExpressionStatement => {
+            evaluate(downcast::<ExpressionStatement>(node).expression(), env)
+        },
+        NodeType::BlockStatement => eval_block_statement(downcast::<BlockStatement>(node), env),
+        NodeType::ReturnStatement => {
+            let value = evaluate(downcast::<ReturnStatement>(node).return_value(), env)?;
+            Err(Unwind::Return(value))
+        },
+        NodeType::LetStatement => eval_let_statement(downcast::<LetStatement>(node), env),
+        NodeType::DestructuringLetStatement => {
+            eval_destructuring_let_statement(downcast::<DestructuringLetStatement>(node), env)
+        },
+        NodeType::IntegerLiteral => Ok(Object::Integer(downcast::<IntegerLiteral>(node).value())),
+        NodeType::Boolean => Ok(Object::Boolean(downcast::<Boolean>(node).value())),
+        NodeType::PrefixExpression => eval_prefix_operands(downcast::<PrefixExpression>(node), env),
+        NodeType::InfixExpression => eval_infix_operands(downcast::<InfixExpression>(node), env),
+        NodeType::IfExpression => eval_if_expression(downcast::<IfExpression>(node), env),
+        NodeType::Identifier => Ok(eval_identifier(downcast::<Identifier>(node), env)?),
+        NodeType::FunctionLiteral => {
+            let func = downcast::<FunctionLiteral>(node);
+            Ok(Object::Function(Rc::new(Function::new(
+                func.signature().clone(),
+                func.body().clone(),
+                env.clone(),
+                func.generator(),
+            ))))
+        },
+        NodeType::CallExpression | NodeType::IndexExpression | NodeType::MemberExpression => {
+            Ok(eval_chain_link(node, env)?.unwrap_or(Object::Null))
+        },
+        NodeType::WhileStatement => eval_while_statement(downcast::<WhileStatement>(node), env),
+        NodeType::ForStatement => eval_for_statement(downcast::<ForStatement>(node), env),
+        NodeType::BreakStatement => Err(Unwind::Break),
+        NodeType::ContinueStatement => Err(Unwind::Continue),
+        NodeType::ArrayLiteral => eval_array_literal(downcast::<ArrayLiteral>(node), env),
+        NodeType::StringLiteral => Ok(Object::String(
+            downcast::<StringLiteral>(node).value().into(),
+        )),
+        NodeType::HashLiteral => eval_hash_literal(downcast::<HashLiteral>(node), env),
+        NodeType::NullLiteral => Ok(Object::Null),
+        NodeType::InterpolatedString => {
+            eval_interpolated_string(downcast::<InterpolatedString>(node), env)
+        },
+        NodeType::StructStatement => eval_struct_statement(downcast::<StructStatement>(node), env),
+        NodeType::EnumStatement => eval_enum_statement(downcast::<EnumStatement>(node), env),
+        NodeType::StructLiteral => eval_struct_literal(downcast::<StructLiteral>(node), env),
+        NodeType::MatchExpression => eval_match_expression(downcast::<MatchExpression>(node), env),
+        NodeType::SpreadExpression | NodeType::NamedArgument => {
+            unreachable!("arguments are only evaluated by calls")
+        },
+        NodeType::WildcardPattern
+        | NodeType::RestPattern
+        | NodeType::ArrayPattern
+        | NodeType::HashPattern
+        | NodeType::VariantPattern => unreachable!("patterns are only evaluated by match_pattern"),
+        NodeType::AssignExpression => {
+            eval_assign_expression(downcast::<AssignExpression>(node), env)
+        },
+        NodeType::ThrowStatement => {
+            let value = evaluate(downcast::<ThrowStatement>(node).value(), env)?;
+            Err(Exception::new(value).into())
+        },
+        NodeType::TryExpression => eval_try_expression(downcast::<TryExpression>(node), env),
+        // the parser only accepts yields the generator evaluator knows how to suspend at
+        NodeType::YieldStatement => unreachable!("yields are only evaluated by generators"),
+    }
+}
+
+fn eval_program(program: &Program, env: &Rc<RefCell<Environment>>) -> Result<Object, Unwind> {
+    let mut result = Object::Null;
+    for stmt in program.statements() {
+        result = match evaluate(stmt.as_ref(), env) {
+            Err(Unwind::Return(value)) => return Ok(value),
+            result => result?,
+        };
+    }
+    Ok(result)
+}
+
+fn eval_block_statement(
+    block: &BlockStatement,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let mut result = Object::Null;
+    for stmt in block.statements() {
+        result = evaluate(stmt.as_ref(), env)?;
+    }
+    Ok(result)
+}
+
+fn eval_let_statement(
+    stmt: &LetStatement,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let value = evaluate(stmt.value(), env)?;
+    env.borrow_mut().set(slot(stmt.name()), value);
+    Ok(Object::Null)
+}
+
+fn eval_destructuring_let_statement(
+    stmt: &DestructuringLetStatement,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let value = evaluate(stmt.value(), env)?;
+    match match_pattern(stmt.pattern(), &value, env) {
+        Err(Mismatch::Value(reason)) => {
+            Err(format!("cannot destructure {}: {}", value, reason).into())
+        },
+        Err(Mismatch::Pattern(error)) => Err(Exception::from(error)
+            .with_span(stmt.pattern().span())
+            .into()),
+        Ok(()) => Ok(Object::Null),
+    }
+}
+
+fn eval_call_expression(
+    call: &CallExpression,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Option<Object>, Unwind> {
+    if call.function().node_type() == NodeType::MemberExpression {
+        let member = downcast::<MemberExpression>(call.function());
+        return eval_method_call(member, call.arguments(), env);
+    }
+    let Some(function) = eval_chain(call.function(), env)? else {
+        return Ok(None);
+    };
+    let (args, named) = eval_arguments(call.arguments(), env)?;
+    let budget = env.borrow().budget().clone();
+    Ok(Some(apply_function(function, args, named, &budget)?))
+}
+
+fn eval_array_literal(
+    literal: &ArrayLiteral,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let elements = literal
+        .elements()
+        .iter()
+        .map(|e| evaluate(e.as_ref(), env))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(allocated(
+        env.borrow().budget(),
+        Object::Array(Rc::new(elements)),
+    )?)
+}
+
+fn eval_hash_literal(
+    literal: &HashLiteral,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let mut pairs = BTreeMap::new();
+    for (key, value) in literal.pairs() {
+        let key = HashKey::try_from(&evaluate(key.as_ref(), env)?)?;
+        pairs.insert(key, evaluate(value.as_ref(), env)?);
+    }
+    Ok(allocated(
+        env.borrow().budget(),
+        Object::Hash(Rc::new(pairs)),
+    )?)
+}
+
+fn eval_interpolated_string(
+    string: &InterpolatedString,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let budget = env.borrow().budget().clone();
+    let mut value = MeteredString::new(&budget);
+    for part in string.parts() {
+        match part {
+            StringPart::Literal(s) => value.push_str(s)?,
+            StringPart::Expression(expr) => value.push(&evaluate(expr.as_ref(), env)?)?,
+        }
+    }
+    Ok(value.finish())
+}
+
+fn eval_struct_statement(
+    stmt: &StructStatement,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let fields = stmt
+        .fields()
+        .iter()
+        .map(|f| f.value().to_string())
+        .collect();
+    let struct_type = StructType::new(stmt.name().value(), fields);
+    env.borrow_mut()
+        .set(slot(stmt.name()), Object::StructType(Rc::new(struct_type)));
+    Ok(Object::Null)
+}
+
+fn eval_enum_statement(
+    stmt: &EnumStatement,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let variants = stmt
+        .variants()
+        .iter()
+        .map(|v| {
+            let fields = v.fields().iter().map(|f| f.value().to_string()).collect();
+            VariantType::new(v.name().value(), fields)
+        })
+        .collect();
+    let enum_type = EnumType::new(stmt.name().value(), variants);
+    env.borrow_mut()
+        .set(slot(stmt.name()), Object::EnumType(Rc::new(enum_type)));
+    Ok(Object::Null)
+}
+
+fn eval_prefix_operands(
+    expr: &PrefixExpression,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let right = evaluate(expr.right(), env)?;
+    Ok(eval_prefix_expression(expr.operator(), right)?)
+}
+
+fn eval_infix_operands(
+    expr: &InfixExpression,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let left = evaluate(expr.left(), env)?;
+    match expr.operator() {
+        // the right side must only be evaluated when it can change the result
+        "&&" if !left.is_truthy() => Ok(Object::Boolean(false)),
+        "||" if left.is_truthy() => Ok(Object::Boolean(true)),
+        "&&" | "||" => Ok(Object::Boolean(evaluate(expr.right(), env)?.is_truthy())),
+        "??" if left != Object::Null => Ok(left),
+        "??" => evaluate(expr.right(), env),
+        operator => {
+            let right = evaluate(expr.right(), env)?;
+            let result = eval_infix_expression(operator, left, right)?;
+            Ok(allocated(env.borrow().budget(), result)?)
+        },
+    }
+}
+
+fn eval_index_operands(
+    expr: &IndexExpression,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Option<Object>, Unwind> {
+    let left = match eval_chain(expr.left(), env)? {
+        Some(Object::Null) if expr.optional() => return Ok(None),
+        Some(left) => left,
+        None => return Ok(None),
+    };
+    let index = evaluate(expr.index(), env)?;
+    let budget = env.borrow().budget().clone();
+    Ok(Some(eval_index_expression(left, index, &budget)?))
+}
+
+fn eval_member_operands(
+    expr: &MemberExpression,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Option<Object>, Unwind> {
+    let object = match eval_chain(expr.object(), env)? {
+        Some(Object::Null) if expr.optional() => return Ok(None),
+        Some(object) => object,
+        None => return Ok(None),
+    };
+    Ok(Some(eval_member_expression(
+        object,
+        expr.property().value(),
+    )?))
+}
+
+pub(crate) fn eval_prefix_expression(operator: &str, right: Object) -> Result<Object, String> {
+    match (operator, &right) {
+        ("!", _) => Ok(Object::Boolean(!right.is_truthy())),
+        ("-", Object::Integer(i)) => i
+            .checked_neg()
+            .map(Object::Integer)
+            .ok_or_else(|| "integer overflow".to_string()),
+        ("~", Object::Integer(i)) => Ok(Object::Integer(!i)),
+        _ => Err(format!(
+            "unknown operator: {}{}",
+            operator,
+            right.type_name()
+        )),
+    }
+}
+
+pub(crate) fn eval_infix_expression(
+    operator: &str,
+    left: Object,
+    right: Object,
+) -> Result<Object, String> {
+    match (&left, &right) {
+        (Object::Integer(l), Object::Integer(r)) => eval_integer_infix_expression(operator, *l, *r),
+        (Object::Boolean(l), Object::Boolean(r)) => eval_boolean_infix_expression(operator, *l, *r),
+        (Object::String(l), Object::String(r)) => eval_string_infix_expression(operator, l, r),
+        _ if operator == "==" => Ok(Object::Boolean(left == right)),
+        _ if operator == "!=" => Ok(Object::Boolean(left != right)),
+        _ if left.type_name() != right.type_name() => Err(format!(
+            "type mismatch: {} {} {}",
+            left.type_name(),
+            operator,
+            right.type_name()
+        )),
+        _ => Err(format!(
+            "unknown operator: {} {} {}",
+            left.type_name(),
+            operator,
+            right.type_name()
+        )),
+    }
+}
+
+fn eval_integer_infix_expression(operator: &str, left: i64, right: i64) -> Result<Object, String> {
+    let overflow = || "integer overflow".to_string();
+    let value = match operator {
+        "+" => left.checked_add(right).ok_or_else(overflow)?,
+        "-" => left.checked_sub(right).ok_or_else(overflow)?,
+        "*" => left.checked_mul(right).ok_or_else(overflow)?,
+        "/" | "%" if right == 0 => return Err("division by zero".to_string()),
+        "/" => left.checked_div(right).ok_or_else(overflow)?,
+        "%" => left.checked_rem(right).ok_or_else(overflow)?,
+        "**" => {
+            let exp = u32::try_from(right).map_err(|_| format!("negative exponent: {}", right))?;
+            left.checked_pow(exp).ok_or_else(overflow)?
+        },
+        "&" => left & right,
+        "|" => left | right,
+        "^" => left ^ right,
+        "<<" | ">>" => {
+            let shift = u32::try_from(right)
+                .ok()
+                .filter(|s| *s < i64::BITS)
+                .ok_or_else(|| format!("invalid shift amount: {}", right))?;
+            if operator == "<<" {
+                left << shift
+            } else {
+                left >> shift
+            }
+        },
+        "<" => return Ok(Object::Boolean(left < right)),
+        ">" => return Ok(Object::Boolean(left > right)),
+        "<=" => return Ok(Object::Boolean(left <= right)),
+        ">=" => return Ok(Object::Boolean(left >= right)),
+        "==" => return Ok(Object::Boolean(left == right)),
+        "!=" => return Ok(Object::Boolean(left != right)),
+        ".." => return Ok(Object::Range(Range::new(left, right, false))),
+        "..=" => return Ok(Object::Range(Range::new(left, right, true))),
+        _ => return Err(format!("unknown operator: INTEGER {} INTEGER", operator)),
+    };
+    Ok(Object::Integer(value))
+}
+
+fn eval_boolean_infix_expression(
+    operator: &str,
+    left: bool,
+    right: bool,
+) -> Result<Object, String> {
+    let value = match operator {
+        "==" => left == right,
+        "!=" => left != right,
+        "&" => left & right,
+        "|" => left | right,
+        "^" => left ^ right,
+        _ => return Err(format!("unknown operator: BOOLEAN {} BOOLEAN", operator)),
+    };
+    Ok(Object::Boolean(value))
+}
+
+fn eval_string_infix_expression(operator: &str, left: &str, right: &str) -> Result<Object, String> {
+    match operator {
+        "+" => Ok(Object::String(format!("{}{}", left, right).into())),
+        "==" => Ok(Object::Boolean(left == right)),
+        "!=" => Ok(Object::Boolean(left != right)),
+        _ => Err(format!("unknown operator: STRING {} STRING", operator)),
+    }
+}
+
+fn eval_if_expression(
+    expr: &IfExpression,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    if evaluate(expr.condition(), env)?.is_truthy() {
+        evaluate(expr.consequence(), env)
+    } else if let Some(alt) = expr.alternative() {
+        evaluate(alt, env)
+    } else {
+        Ok(Object::Null)
+    }
+}
+
+fn eval_while_statement(
+    stmt: &WhileStatement,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    while evaluate(stmt.condition(), env)?.is_truthy() {
+        match eval_block_statement(stmt.body(), env) {
+            Err(Unwind::Break) => break,
+            Ok(_) | Err(Unwind::Continue) => {},
+            Err(unwind) => return Err(unwind),
+        }
+    }
+    Ok(Object::Null)
+}
+
+fn eval_for_statement(
+    stmt: &ForStatement,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let iter = iterate(evaluate(stmt.iterable(), env)?)?;
+    let budget = env.borrow().budget().clone();
+    while let Some(element) = iterator_next(&iter, &budget)? {
+        env.borrow_mut().set(slot(stmt.variable()), element);
+        match eval_block_statement(stmt.body(), env) {
+            Err(Unwind::Break) => break,
+            Ok(_) | Err(Unwind::Continue) => {},
+            Err(unwind) => return Err(unwind),
+        }
+    }
+    Ok(Object::Null)
+}
+
+fn eval_try_expression(
+    expr: &TryExpression,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let result = match (eval_block_statement(expr.body(), env), expr.catch()) {
+        (Err(Unwind::Exception(Exception::Thrown(value, _))), Some((name, body))) => {
+            // the caught value is only visible to the catch block
+            let catch_env = Environment::new_enclosed(env.clone());
+            catch_env.borrow_mut().set(slot(name), value);
+            eval_block_statement(body, &catch_env)
+        },
+        (result, _) => result,
+    };
+    // running into a limit must stop evaluation, so not even the finally block runs
+    if let Err(Unwind::Exception(Exception::LimitExceeded(..))) = result {
+        return result;
+    }
+    if let Some(finally) = expr.finally() {
+        // the finally block always runs, and leaving it early by returning, breaking,
+        // continuing or throwing replaces the result of the rest of the expression
+        eval_block_statement(finally, env)?;
+    }
+    result
+}
+
+/// Starts iterating over a value. Iterators are returned as is, so iterating over one again
+/// continues where it left off.
+pub(crate) fn iterate(obj: Object) -> Result<Rc<RefCell<IteratorState>>, String> {
+    let state = match obj {
+        Object::Iterator(iter) => return Ok(iter),
+        Object::Range(range) => IteratorState::Range(range),
+        Object::Array(elements) => IteratorState::Array { elements, index: 0 },
+        Object::String(value) => IteratorState::String { value, offset: 0 },
+        obj => return Err(format!("not iterable: {}", obj.type_name())),
+    };
+    Ok(Rc::new(RefCell::new(state)))
+}
+
+pub(crate) fn iterator_next(
+    iter: &Rc<RefCell<IteratorState>>,
+    budget: &Budget,
+) -> Result<Option<Object>, Exception> {
+    // ranges can be iterated over for a long time without evaluating anything, so each element
+    // counts as a step
+    budget.step()?;
+    let mut state = iter.borrow_mut();
+    let (source, function) = match &mut *state {
+        IteratorState::Range(range) => return Ok(range.pop_front().map(Object::Integer)),
+        IteratorState::Array { elements, index } => {
+            let element = elements.get(*index).cloned();
+            *index += element.is_some() as usize;
+            return Ok(element);
+        },
+        IteratorState::String { value, offset } => {
+            let ch = value[*offset..].chars().next();
+            *offset += ch.map_or(0, char::len_utf8);
+            return Ok(ch.map(|ch| Object::String(ch.to_string().into())));
+        },
+        IteratorState::Map { source, function } | IteratorState::Filter { source, function } => {
+            (source.clone(), function.clone())
+        },
+        IteratorState::Generator(_) => {
+            drop(state);
+            return generator::resume(iter);
+        },
+    };
+    // the function may use the iterator itself, so it can't stay borrowed while it's called
+    let is_map = matches!(*state, IteratorState::Map { .. });
+    drop(state);
+    while let Some(value) = iterator_next(&source, budget)? {
+        if is_map {
+            return apply_function(function, vec![value], vec![], budget).map(Some);
+        }
+        if apply_function(function.clone(), vec![value.clone()], vec![], budget)?.is_truthy() {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+pub(crate) fn eval_index_expression(
+    left: Object,
+    index: Object,
+    budget: &Budget,
+) -> Result<Object, Exception> {
+    match (&left, &index) {
+        (Object::Array(elements), Object::Integer(i)) => Ok(usize::try_from(*i)
+            .ok()
+            .and_then(|i| elements.get(i).cloned())
+            .unwrap_or(Object::Null)),
+        (Object::Array(elements), Object::Range(range)) => {
+            let (start, end) = slice_bounds(range, elements.len())?;
+            allocated(
+                budget,
+                Object::Array(Rc::new(elements[start..end].to_vec())),
+            )
+        },
+        (Object::String(s), Object::Range(range)) => {
+            let (start, end) = slice_bounds(range, s.chars().count())?;
+            let slice = s.chars().skip(start).take(end - start).collect::<String>();
+            allocated(budget, Object::String(slice.into()))
+        },
+        (Object::Hash(pairs), _) => {
+            let key = HashKey::try_from(&index)?;
+            Ok(pairs.get(&key).cloned().unwrap_or(Object::Null))
+        },
+        _ => Err(format!(
+            "index operator not supported: {}[{}]",
+            left.type_name(),
+            index.type_name()
+        )
+        .into()),
+    }
+}
+
+fn eval_struct_literal(
+    literal: &StructLiteral,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let struct_type = match eval_identifier(literal.name(), env)? {
+        Object::StructType(struct_type) => struct_type,
+        obj => return Err(format!("not a struct: {}", obj.type_name()).into()),
+    };
+    let mut values = vec![None; struct_type.fields().len()];
+    for (field, value) in literal.fields() {
+        let field_error = |error| format!("{}: {}.{}", error, struct_type.name(), field);
+        let i = struct_type
+            .field_index(field.value())
+            .ok_or_else(|| field_error("unknown field"))?;
+        if values[i].replace(evaluate(value.as_ref(), env)?).is_some() {
+            return Err(field_error("duplicate field").into());
+        }
+    }
+    let values = values
+        .into_iter()
+        .zip(struct_type.fields())
+        .map(|(value, field)| {
+            value.ok_or_else(|| format!("missing field: {}.{}", struct_type.name(), field))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Object::Struct(Rc::new(Struct::new(struct_type, values))))
+}
+
+pub(crate) fn eval_member_expression(object: Object, property: &str) -> Result<Object, String> {
+    match &object {
+        Object::Hash(pairs) => Ok(pairs
+            .get(&HashKey::String(property.into()))
+            .cloned()
+            .unwrap_or(Object::Null)),
+        Object::Struct(instance) => instance.get(property).cloned().ok_or_else(|| {
+            format!(
+                "unknown field: {}.{}",
+                instance.struct_type().name(),
+                property
+            )
+        }),
+        Object::EnumType(enum_type) => {
+            let index = enum_type
+                .variant_index(property)
+                .ok_or_else(|| format!("unknown variant: {}.{}", enum_type.name(), property))?;
+            // variants without fields are values rather than constructors
+            if enum_type.variants()[index].fields().is_empty() {
+                let variant = Variant::new(enum_type.clone(), index, Vec::new());
+                Ok(Object::Variant(Rc::new(variant)))
+            } else {
+                let constructor = Constructor::new(enum_type.clone(), index);
+                Ok(Object::Constructor(constructor))
+            }
+        },
+        Object::Error(error) => match property {
+            "message" => Ok(Object::String(error.message().into())),
+            "kind" => Ok(Object::String(error.kind().into())),
+            "span" => Ok(error.span().map_or(Object::Null, |span| {
+                let position = [("line", span.line()), ("column", span.column())];
+                let pairs = position.map(|(key, value)| {
+                    (HashKey::String(key.into()), Object::Integer(value as i64))
+                });
+                Object::Hash(Rc::new(pairs.into()))
+            })),
+            "line" => Ok(error
+                .span()
+                .map_or(Object::Null, |span| Object::Integer(span.line() as i64))),
+            "column" => Ok(error
+                .span()
+                .map_or(Object::Null, |span| Object::Integer(span.column() as i64))),
+            _ => Err(format!("unknown field: ERROR.{}", property)),
+        },
+        Object::Variant(variant) => variant.get(property).cloned().ok_or_else(|| {
+            format!(
+                "unknown field: {}.{}.{}",
+                variant.enum_type().name(),
+                variant.variant_type().name(),
+                property
+            )
+        }),
+        _ => Err(format!(
+            "property access not supported: {}.{}",
+            object.type_name(),
+            property
+        )),
+    }
+}
+
+fn eval_method_call(
+    member: &MemberExpression,
+    arguments: &[Box<dyn Expression>],
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Option<Object>, Unwind> {
+    let object = match eval_chain(member.object(), env)? {
+        Some(Object::Null) if member.optional() => return Ok(None),
+        Some(object) => object,
+        None => return Ok(None),
+    };
+    let method = method_target(&object, member.property().value())?;
+    let (args, named) = eval_arguments(arguments, env)?;
+    let budget = env.borrow().budget().clone();
+    match method {
+        MethodTarget::Field(function) => Ok(Some(apply_function(function, args, named, &budget)?)),
+        MethodTarget::Method(method) => {
+            if let Some((name, _)) = named.first() {
+                return Err(format!("unexpected named argument: {}", name).into());
+            }
+            Ok(Some(method(&budget, object, args)?))
+        },
+    }
+}
+
+/// What a method call calls.
+pub(crate) enum MethodTarget {
+    /// A function stored in the receiver, which is called with just the arguments.
+    Field(Object),
+    /// A method of the receiver's type.
+    Method(Method),
+}
+
+pub(crate) fn method_target(object: &Object, name: &str) -> Result<MethodTarget, String> {
+    // a function stored in a hash, struct or variant is called like a method, which lets hashes
+    // act as modules, and `Shape.Circle(1)` calls the variant's constructor
+    let field = match object {
+        Object::Hash(pairs) => pairs.get(&HashKey::String(name.into())).cloned(),
+        Object::Struct(instance) => instance.get(name).cloned(),
+        Object::Variant(variant) => variant.get(name).cloned(),
+        Object::EnumType(_) => Some(eval_member_expression(object.clone(), name)?),
+        _ => None,
+    };
+    if let Some(function) = field {
+        return Ok(MethodTarget::Field(function));
+    }
+    methods::lookup(object, name)
+        .map(MethodTarget::Method)
+        .ok_or_else(|| format!("unknown method: {}.{}", object.type_name(), name))
+}
+
+fn construct_variant(
+    constructor: Constructor,
+    args: Vec<Object>,
+    named: Vec<(Symbol, Object)>,
+) -> Result<Object, String> {
+    let fields = constructor.variant_type().fields();
+    let given = args.len() + named.len();
+    if given != fields.len() {
+        return Err(format!(
+            "wrong number of arguments: expected {}, got {}",
+            fields.len(),
+            given
+        ));
+    }
+    let mut values = args.into_iter().map(Some).collect::<Vec<_>>();
+    values.resize(fields.len(), None);
+    for (name, value) in named {
+        let index = fields
+            .iter()
+            .position(|f| *f == name.as_str())
+            .ok_or_else(|| format!("unexpected named argument: {}", name))?;
+        if values[index].replace(value).is_some() {
+            return Err(format!("multiple values for argument: {}", name));
+        }
+    }
+    // every field has a value, since there are exactly as many values as fields and none was
+    // given twice
+    let values = values.into_iter().map(Option::unwrap).collect();
+    let variant = Variant::new(constructor.enum_type().clone(), constructor.index(), values);
+    Ok(Object::Variant(Rc::new(variant)))
+}
+
+fn slice_bounds(range: &Range, len: usize) -> Result<(usize, usize), String> {
+    range
+        .bounds(len)
+        .ok_or_else(|| format!("range out of bounds: {} (length {})", range, len))
+}
+
+/// One step from an assignment target's root name towards the place being assigned.
+pub(crate) enum Accessor<'a> {
+    Index(Object),
+    Member(&'a str),
+}
+
+fn eval_assign_expression(
+    expr: &AssignExpression,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    // the parser only accepts a name followed by any number of indexes and members as a target
+    let mut target = expr.target();
+    let mut accesses = Vec::new();
+    loop {
+        match target.node_type() {
+            NodeType::IndexExpression => {
+                accesses.push(target);
+                target = downcast::<IndexExpression>(target).left();
+            },
+            NodeType::MemberExpression => {
+                accesses.push(target);
+                target = downcast::<MemberExpression>(target).object();
+            },
+            _ => break,
+        }
+    }
+    let name = downcast::<Identifier>(target);
+    let accessors = accesses
+        .into_iter()
+        .rev()
+        .map(|access| match access.node_type() {
+            NodeType::IndexExpression => Ok(Accessor::Index(evaluate(
+                downcast::<IndexExpression>(access).index(),
+                env,
+            )?)),
+            _ => Ok(Accessor::Member(
+                downcast::<MemberExpression>(access).property().value(),
+            )),
+        })
+        .collect::<Result<Vec<_>, Unwind>>()?;
+    let value = evaluate(expr.value(), env)?;
+    let operator = expr.operator().strip_suffix('=').unwrap();
+    let Some(Binding::Variable { depth, slot }) = name.binding() else {
+        unreachable!("assignments are resolved to variables")
+    };
+    let budget = env.borrow().budget().clone();
+    env.borrow_mut()
+        .update(depth, slot, |obj| {
+            assign(obj, &accessors, operator, value, &budget)
+        })
+        .unwrap_or_else(|| Err(format!("identifier not found: {}", name).into()))
+        .map_err(Unwind::from)
+}
+
+/// Stores `value` at the place `accessors` lead to inside `target`, combining it with the value
+/// already there when `operator` is a compound assignment's infix operator. Arrays, hashes and
+/// structs on the way that are shared are copied first, which counts against the memory limit.
+pub(crate) fn assign(
+    target: &mut Object,
+    accessors: &[Accessor],
+    operator: &str,
+    value: Object,
+    budget: &Budget,
+) -> Result<Object, Exception> {
+    let Some((accessor, rest)) = accessors.split_first() else {
+        let value = if operator.is_empty() {
+            value
+        } else {
+            eval_infix_expression(operator, target.clone(), value)?
+        };
+        *target = value.clone();
+        return Ok(value);
+    };
+    let size = target.allocated_size();
+    match (target, accessor) {
+        (Object::Array(elements), Accessor::Index(Object::Integer(i))) => {
+            let len = elements.len();
+            let element = usize::try_from(*i)
+                .ok()
+                .filter(|i| *i < len)
+                .ok_or_else(|| format!("index out of bounds: {} (length {})", i, len))?;
+            let element = &mut make_mut(elements, size, budget)?[element];
+            assign(element, rest, operator, value, budget)
+        },
+        (Object::Hash(pairs), accessor) => {
+            // `h.key` is the same place as `h["key"]`
+            let key = match accessor {
+                Accessor::Index(index) => HashKey::try_from(index)?,
+                Accessor::Member(name) => HashKey::String((*name).into()),
+            };
+            let pairs = make_mut(pairs, size, budget)?;
+            if rest.is_empty() && operator.is_empty() {
+                pairs.insert(key, value.clone());
+                return Ok(value);
+            }
+            let entry = pairs
+                .get_mut(&key)
+                .ok_or_else(|| format!("key not found: {}", key))?;
+            assign(entry, rest, operator, value, budget)
+        },
+        (Object::Struct(instance), Accessor::Member(name)) => {
+            let instance = make_mut(instance, size, budget)?;
+            let type_name = instance.struct_type().name().to_string();
+            let field = instance
+                .get_mut(name)
+                .ok_or_else(|| format!("unknown field: {}.{}", type_name, name))?;
+            assign(field, rest, operator, value, budget)
+        },
+        (target, Accessor::Index(index)) => Err(format!(
+            "index assignment not supported: {}[{}]",
+            target.type_name(),
+            index.type_name()
+        )
+        .into()),
+        (target, Accessor::Member(name)) => Err(format!(
+            "property assignment not supported: {}.{}",
+            target.type_name(),
+            name
+        )
+        .into()),
+    }
+}
+
+/// The value `rc` points to, to update in place, which is a copy of it if it's shared. `size` is
+/// how much copying it allocates.
+fn make_mut<'a, T: Clone>(
+    rc: &'a mut Rc<T>,
+    size: usize,
+    budget: &Budget,
+) -> Result<&'a mut T, LimitExceeded> {
+    if Rc::strong_count(rc) > 1 {
+        budget.allocate(size)?;
+    }
+    Ok(Rc::make_mut(rc))
+}
+
+fn eval_match_expression(
+    expr: &MatchExpression,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Object, Unwind> {
+    let (body, arm_env) = match_arm(expr, env)?;
+    evaluate(body, &arm_env)
+}
+
+/// Finds the first arm whose pattern and guard match the subject, returning its body and the
+/// environment with the pattern's bindings to evaluate it in.
+fn match_arm<'a>(
+    expr: &'a MatchExpression,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(&'a dyn Expression, Rc<RefCell<Environment>>), Unwind> {
+    let subject = evaluate(expr.subject(), env)?;
+    for arm in expr.arms() {
+        // bindings made by the pattern are only visible to this arm's guard and body
+        let arm_env = Environment::new_enclosed(env.clone());
+        match match_pattern(arm.pattern(), &subject, &arm_env) {
+            Ok(()) => {},
+            Err(Mismatch::Value(_)) => continue,
+            Err(Mismatch::Pattern(error)) => {
+                return Err(Exception::from(error)
+                    .with_span(arm.pattern().span())
+                    .into())
+            },
+        }
+        if let Some(guard) = arm.guard() {
+            if !evaluate(guard, &arm_env)?.is_truthy() {
+                continue;
+            }
+        }
+        return Ok((arm.body(), arm_env));
+    }
+    Err(format!("no match arm matched value: {}", subject).into())
+}
+
+/// Why a value failed to match a pattern.
+enum Mismatch {
+    /// The value isn't what the pattern describes, for this reason.
+    Value(String),
+    /// The pattern can't describe any value, like one naming a variant its enum doesn't have.
+    Pattern(String),
+}
+
+impl From<String> for Mismatch {
+    fn from(reason: String) -> Self {
+        Mismatch::Value(reason)
+    }
+}
+
+/// Matches `value` against `pattern`, binding names in `env` as it goes. On failure `env` may
+/// contain some of the pattern's bindings.
+fn match_pattern(
+    pattern: &dyn Pattern,
+    value: &Object,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(), Mismatch> {
+    match pattern.node_type() {
+        NodeType::WildcardPattern => Ok(()),
+        NodeType::Identifier => {
+            env.borrow_mut()
+                .set(slot(downcast::<Identifier>(pattern)), value.clone());
+            Ok(())
+        },
+        NodeType::ArrayPattern => {
+            match_array_pattern(downcast::<ArrayPattern>(pattern), value, env)
+        },
+        NodeType::HashPattern => match_hash_pattern(downcast::<HashPattern>(pattern), value, env),
+        NodeType::VariantPattern => {
+            match_variant_pattern(downcast::<VariantPattern>(pattern), value, env)
+        },
+        _ => {
+            // literals can't fail to evaluate
+            let expected = eval(pattern, env).unwrap();
+            if expected == *value {
+                Ok(())
+            } else {
+                Err(format!("expected {}, got {}", expected, value).into())
+            }
+        },
+    }
+}
+
+fn match_array_pattern(
+    pattern: &ArrayPattern,
+    value: &Object,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(), Mismatch> {
+    let Object::Array(elements) = value else {
+        return Err(format!("expected ARRAY, got {}", value.type_name()).into());
+    };
+    let patterns = pattern.elements();
+    let rest = patterns
+        .iter()
+        .position(|p| p.node_type() == NodeType::RestPattern);
+    let Some(rest) = rest else {
+        if elements.len() != patterns.len() {
+            return Err(format!(
+                "expected ARRAY of length {}, got ARRAY of length {}",
+                patterns.len(),
+                elements.len()
+            )
+            .into());
+        }
+        for (pattern, element) in patterns.iter().zip(elements.iter()) {
+            match_pattern(pattern.as_ref(), element, env)?;
+        }
+        return Ok(());
+    };
+    let (before, after) = (&patterns[..rest], &patterns[rest + 1..]);
+    if elements.len() < before.len() + after.len() {
+        return Err(format!(
+            "expected ARRAY of length at least {}, got ARRAY of length {}",
+            before.len() + after.len(),
+            elements.len()
+        )
+        .into());
+    }
+    let after_start = elements.len() - after.len();
+    for (pattern, element) in before.iter().zip(elements.iter()) {
+        match_pattern(pattern.as_ref(), element, env)?;
+    }
+    for (pattern, element) in after.iter().zip(elements[after_start..].iter()) {
+        match_pattern(pattern.as_ref(), element, env)?;
+    }
+    if let Some(name) = downcast::<RestPattern>(patterns[rest].as_ref()).name() {
+        let rest = elements[before.len()..after_start].to_vec();
+        env.borrow_mut()
+            .set(slot(name), Object::Array(Rc::new(rest)));
+    }
+    Ok(())
+}
+
+fn match_variant_pattern(
+    pattern: &VariantPattern,
+    value: &Object,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(), Mismatch> {
+    let enum_type = match eval_identifier(pattern.enum_name(), env).map_err(Mismatch::Pattern)? {
+        Object::EnumType(enum_type) => enum_type,
+        obj => {
+            return Err(Mismatch::Pattern(format!(
+                "not an enum: {}",
+                obj.type_name()
+            )))
+        },
+    };
+    let index = enum_type
+        .variant_index(pattern.variant().value())
+        .ok_or_else(|| {
+            Mismatch::Pattern(format!(
+                "unknown variant: {}.{}",
+                enum_type.name(),
+                pattern.variant()
+            ))
+        })?;
+    let expected = enum_type.variants()[index].fields().len();
+    if let Some(fields) = pattern.fields().filter(|fields| fields.len() != expected) {
+        return Err(Mismatch::Pattern(format!(
+            "expected {} field patterns for {}.{}, got {}",
+            expected,
+            enum_type.name(),
+            pattern.variant(),
+            fields.len()
+        )));
+    }
+    let variant = match value {
+        Object::Variant(variant)
+            if Rc::ptr_eq(variant.enum_type(), &enum_type) && variant.index() == index =>
+        {
+            variant
+        },
+        _ => return Err(format!("expected {}, got {}", pattern, value).into()),
+    };
+    for (pattern, value) in pattern.fields().into_iter().flatten().zip(variant.values()) {
+        match_pattern(pattern.as_ref(), value, env)?;
+    }
+    Ok(())
+}
+
+fn match_hash_pattern(
+    pattern: &HashPattern,
+    value: &Object,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(), Mismatch> {
+    let Object::Hash(pairs) = value else {
+        return Err(format!("expected HASH, got {}", value.type_name()).into());
+    };
+    for (key, pattern) in pattern.pairs() {
+        let key = eval(key.as_ref(), env).map_err(|e| e.to_string())?;
+        let key = HashKey::try_from(&key)?;
+        let value = pairs
+            .get(&key)
+            .ok_or_else(|| format!("expected HASH with key {}", key))?;
+        match_pattern(pattern.as_ref(), value, env)?;
+    }
+    Ok(())
+}
+
+fn eval_identifier(ident: &Identifier, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
+    let value = match ident.binding() {
+        Some(Binding::Variable { depth, slot }) => env.borrow().get(depth, slot),
+        Some(Binding::Builtin(index)) => builtins::at(index).map(Object::Builtin),
+        None => unreachable!("{} hasn't been resolved", ident),
+    };
+    // a variable the resolver found may not have been set yet, or ever
+    value.ok_or_else(|| format!("identifier not found: {}", ident.value()))
+}
+
+/// The slot of the environment a name is defined in that it's kept in.
+fn slot(name: &Identifier) -> usize {
+    match name.binding() {
+        Some(Binding::Variable { depth: 0, slot }) => slot,
+        binding => unreachable!("{} is defined with binding {:?}", name, binding),
+    }
+}
+
+type Arguments = (Vec<Object>, Vec<(Symbol, Object)>);
+
+/// Evaluates call arguments into positional and named values, spreading any `...array`s.
+fn eval_arguments(
+    arguments: &[Box<dyn Expression>],
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Arguments, Unwind> {
+    let mut args = Vec::new();
+    let mut named = Vec::new();
+    for arg in arguments {
+        match arg.node_type() {
+            NodeType::SpreadExpression => {
+                match evaluate(downcast::<SpreadExpression>(arg.as_ref()).value(), env)? {
+                    Object::Array(elements) => args.extend(elements.iter().cloned()),
+                    obj => return Err(format!("cannot spread {}", obj.type_name()).into()),
+                }
+            },
+            NodeType::NamedArgument => {
+                let arg = downcast::<NamedArgument>(arg.as_ref());
+                named.push((arg.name().symbol(), evaluate(arg.value(), env)?));
+            },
+            _ => args.push(evaluate(arg.as_ref(), env)?),
+        }
+    }
+    Ok((args, named))
+}
+
+pub(crate) fn apply_function(
+    function: Object,
+    args: Vec<Object>,
+    named: Vec<(Symbol, Object)>,
+    budget: &Budget,
+) -> Result<Object, Exception> {
+    let mut result = call_function(function, args, named, budget)?;
+    // calls in tail position are made here, once the call that made them has returned, so
+    // recursion in tail position runs in constant stack space
+    while let Object::TailCall(call) = result {
+        let span = call.span();
+        let (function, args, named) = call.into_call();
+        result = call_function(function, args, named, budget).map_err(|e| e.with_span(span))?;
+    }
+    Ok(result)
+}
+
+/// Calls a function, except for a call the function's body makes in tail position, which is
+/// returned as an [`Object::TailCall`] instead.
+fn call_function(
+    function: Object,
+    args: Vec<Object>,
+    named: Vec<(Symbol, Object)>,
+    budget: &Budget,
+) -> Result<Object, Exception> {
+    let func = match function {
+        Object::Function(func) => func,
+        Object::Builtin(builtin) => {
+            if let Some((name, _)) = named.first() {
+                return Err(format!("unexpected named argument: {}", name).into());
+            }
+            return builtin.call(budget, args);
+        },
+        Object::Constructor(constructor) => {
+            return Ok(construct_variant(constructor, args, named)?)
+        },
+        Object::Closure(closure) => {
+            if let Some((name, _)) = named.first() {
+                return Err(format!("unexpected named argument: {}", name).into());
+            }
+            return vm::call_closure(closure, args, budget);
+        },
+        obj => return Err(format!("not a function: {}", obj.type_name()).into()),
+    };
+    // the call is counted until its body has been evaluated
+    let _call = CallDepth::enter(func.env().borrow().call_depth())?;
+    let env = match bind_arguments(&func, args, named) {
+        Ok(env) => env,
+        // a default value can leave the call before its body is evaluated
+        Err(unwind) => return returned(Err(unwind)),
+    };
+    if func.generator() {
+        let generator = Generator::new(func, env);
+        return Ok(Object::Iterator(Rc::new(RefCell::new(
+            IteratorState::Generator(generator),
+        ))));
+    }
+    returned(eval_tail(func.body(), &env))
+}
+
+/// Creates the environment for a call to `func`, with its parameters bound to the arguments.
+fn bind_arguments(
+    func: &Function,
+    args: Vec<Object>,
+    named: Vec<(Symbol, Object)>,
+) -> Result<Rc<RefCell<Environment>>, Unwind> {
+    let params = func.parameters();
+    let names = params.names();
+    let given = args.len() + named.len();
+    let arity_error = || {
+        let expected = match (params.rest(), params.required(), names.len()) {
+            (Some(_), required, _) => format!("at least {}", required),
+            (None, required, total) if required == total => required.to_string(),
+            (None, required, total) => format!("{} to {}", required, total),
+        };
+        format!(
+            "wrong number of arguments: expected {}, got {}",
+            expected, given
+        )
+    };
+
+    let mut positional = args.into_iter();
+    let mut values = names.iter().map(|_| positional.next()).collect::<Vec<_>>();
+    let rest = positional.collect::<Vec<_>>();
+    if !rest.is_empty() && params.rest().is_none() {
+        return Err(arity_error().into());
+    }
+    for (name, value) in named {
+        let index = names
+            .iter()
+            .position(|n| n.symbol() == name)
+            .ok_or_else(|| format!("unexpected named argument: {}", name))?;
+        if values[index].replace(value).is_some() {
+            return Err(format!("multiple values for argument: {}", name).into());
+        }
+    }
+    if (0..names.len()).any(|i| values[i].is_none() && params.default_value(i).is_none()) {
+        return Err(arity_error().into());
+    }
+
+    let env = Environment::new_enclosed(func.env().clone());
+    for (i, (name, value)) in names.iter().zip(values).enumerate() {
+        // defaults are evaluated in the call's environment so they can refer to earlier
+        // parameters
+        let value = match value {
+            Some(value) => value,
+            None => evaluate(params.default_value(i).unwrap(), &env)?,
+        };
+        env.borrow_mut().set(slot(name), value);
+    }
+    if let Some(name) = params.rest() {
+        env.borrow_mut()
+            .set(slot(name), Object::Array(Rc::new(rest)));
+    }
+    Ok(env)
+}
+
+/// Evaluates a node in tail position of a function's body, where the result of a call would be
+/// the function's result. Such calls aren't made, but are returned as an [`Object::TailCall`]
+/// for [`apply_function`] to make.
+fn eval_tail(node: &dyn Node, env: &Rc<RefCell<Environment>>) -> Result<Object, Unwind> {
+    // this walks down to the tail position in a loop rather than recursively, so it only takes
+    // one stack frame however deeply the tail position is nested
+    let mut node = node;
+    let mut env = env.clone();
+    loop {
+        match node.node_type() {
+            NodeType::BlockStatement => {
+                let block = downcast::<BlockStatement>(node);
+                let Some((last, rest)) = block.statements().split_last() else {
+                    return Ok(Object::Null);
+                };
+                for stmt in rest {
+                    evaluate(stmt.as_ref(), &env)?;
+                }
+                node = last.as_ref();
+            },
+            NodeType::ExpressionStatement => {
+                node = downcast::<ExpressionStatement>(node).expression();
+            },
+            // the function returns whatever its tail position produces, so a return there can
+            // be skipped
+            NodeType::ReturnStatement => {
+                node = downcast::<ReturnStatement>(node).return_value();
+            },
+            NodeType::IfExpression => {
+                let expr = downcast::<IfExpression>(node);
+                if evaluate(expr.condition(), &env)?.is_truthy() {
+                    node = expr.consequence();
+                } else if let Some(alt) = expr.alternative() {
+                    node = alt;
+                } else {
+                    return Ok(Object::Null);
+                }
+            },
+            NodeType::MatchExpression => {
+                let (body, arm_env) = match_arm(downcast::<MatchExpression>(node), &env)
+                    .map_err(|e| e.with_span(node.span()))?;
+                node = body;
+                env = arm_env;
+            },
+            // method calls are left to `eval`, since most methods are builtins, which don't grow
+            // the stack much
+            NodeType::CallExpression
+                if downcast::<CallExpression>(node).function().node_type()
+                    != NodeType::MemberExpression =>
+            {
+                let call = downcast::<CallExpression>(node);
+                let Some(function) = eval_chain(call.function(), &env)? else {
+                    return Ok(Object::Null);
+                };
+                let (args, named) = eval_arguments(call.arguments(), &env)?;
+                let call = TailCall::new(function, args, named, node.span());
+                return Ok(Object::TailCall(Box::new(call)));
+            },
+            _ => return evaluate(node, &env),
+        }
     }
 }
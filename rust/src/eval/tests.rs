@@ -1,20 +1,1539 @@
 #[cfg(test)]
 mod tests {
-    use crate::{eval::eval, object::Object, Lexer, Parser};
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        eval::eval,
+        object::{Environment, Exception, ExecutionLimits, LimitExceeded, Object},
+        Lexer, Parser, Resolver, Span,
+    };
 
     fn test_eval(s: impl ToString) -> Result<Object, String> {
-        let l = Lexer::new(s.to_string());
+        test_eval_with_limits(s, ExecutionLimits::new())
+    }
+
+    fn test_eval_with_limits(s: impl ToString, limits: ExecutionLimits) -> Result<Object, String> {
+        let input = s.to_string();
+        let l = Lexer::new(&input);
         let mut p = Parser::new(l);
         let program = p.parse_program()?;
-        Ok(eval(Box::new(program)))
+        Resolver::new()
+            .resolve(&program)
+            .map_err(|e| e.to_string())?;
+        let env = Environment::with_limits(limits);
+        eval(&program, &env).map_err(|e| e.to_string())
     }
 
     #[test]
     fn test_eval_integer_expression() {
-        let inputs = vec![("5", 5), ("10", 10)];
+        let inputs = vec![
+            ("5", 5),
+            ("10", 10),
+            ("-5", -5),
+            ("-10", -10),
+            ("5 + 5 + 5 + 5 - 10", 10),
+            ("2 * 2 * 2 * 2 * 2", 32),
+            ("-50 + 100 + -50", 0),
+            ("5 * 2 + 10", 20),
+            ("5 + 2 * 10", 25),
+            ("20 + 2 * -10", 0),
+            ("50 / 2 * 2 + 10", 60),
+            ("2 * (5 + 10)", 30),
+            ("3 * 3 * 3 + 10", 37),
+            ("3 * (3 * 3) + 10", 37),
+            ("(5 + 10 * 2 + 15 / 3) * 2 + -10", 50),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_eval_boolean_expression() {
+        let inputs = vec![
+            ("true", true),
+            ("false", false),
+            ("1 < 2", true),
+            ("1 > 2", false),
+            ("1 < 1", false),
+            ("1 > 1", false),
+            ("1 == 1", true),
+            ("1 != 1", false),
+            ("1 == 2", false),
+            ("1 != 2", true),
+            ("true == true", true),
+            ("false == false", true),
+            ("true == false", false),
+            ("true != false", true),
+            ("(1 < 2) == true", true),
+            ("(1 < 2) == false", false),
+            ("(1 > 2) == true", false),
+            ("(1 > 2) == false", true),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_bang_operator() {
+        let inputs = vec![
+            ("!true", false),
+            ("!false", true),
+            ("!5", false),
+            ("!!true", true),
+            ("!!false", false),
+            ("!!5", true),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_if_else_expressions() {
+        let inputs = vec![
+            ("if (true) { 10 }", Object::Integer(10)),
+            ("if (false) { 10 }", Object::Null),
+            ("if (1) { 10 }", Object::Integer(10)),
+            ("if (1 < 2) { 10 }", Object::Integer(10)),
+            ("if (1 > 2) { 10 }", Object::Null),
+            ("if (1 > 2) { 10 } else { 20 }", Object::Integer(20)),
+            ("if (1 < 2) { 10 } else { 20 }", Object::Integer(10)),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_return_statements() {
+        let inputs = vec![
+            ("return 10;", 10),
+            ("return 10; 9;", 10),
+            ("return 2 * 5; 9;", 10),
+            ("9; return 2 * 5; 9;", 10),
+            ("if (10 > 1) { if (10 > 1) { return 10; } return 1; }", 10),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_error_handling() {
+        let inputs = vec![
+            ("5 + true;", "type mismatch: INTEGER + BOOLEAN"),
+            ("5 + true; 5;", "type mismatch: INTEGER + BOOLEAN"),
+            ("-true", "unknown operator: -BOOLEAN"),
+            ("true + false;", "unknown operator: BOOLEAN + BOOLEAN"),
+            ("5; true + false; 5", "unknown operator: BOOLEAN + BOOLEAN"),
+            (
+                "if (10 > 1) { true + false; }",
+                "unknown operator: BOOLEAN + BOOLEAN",
+            ),
+            (
+                "if (10 > 1) { if (10 > 1) { return true + false; } return 1; }",
+                "unknown operator: BOOLEAN + BOOLEAN",
+            ),
+            ("foobar", "identifier not found: foobar"),
+            ("5 / 0", "division by zero"),
+            ("5 % 0", "division by zero"),
+            ("2 ** -1", "negative exponent: -1"),
+            ("1 << 64", "invalid shift amount: 64"),
+            ("9223372036854775807 + 1", "integer overflow"),
+            ("~true", "unknown operator: ~BOOLEAN"),
+        ];
+        for (input, expected) in inputs {
+            let err = test_eval(input).unwrap_err();
+            assert_eq!(err, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_let_statements() {
+        let inputs = vec![
+            ("let a = 5; a;", 5),
+            ("let a = 5 * 5; a;", 25),
+            ("let a = 5; let b = a; b;", 5),
+            ("let a = 5; let b = a; let c = a + b + 5; c;", 15),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_function_application() {
+        let inputs = vec![
+            ("let identity = fn(x) { x; }; identity(5);", 5),
+            ("let identity = fn(x) { return x; }; identity(5);", 5),
+            ("let double = fn(x) { x * 2; }; double(5);", 10),
+            ("let add = fn(x, y) { x + y; }; add(5, 5);", 10),
+            ("let add = fn(x, y) { x + y; }; add(5 + 5, add(5, 5));", 20),
+            ("fn(x) { x; }(5)", 5),
+            (
+                "let newAdder = fn(x) { fn(y) { x + y } }; let addTwo = newAdder(2); addTwo(2);",
+                4,
+            ),
+            (
+                "let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(5);",
+                120,
+            ),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_operators() {
+        let inputs = vec![
+            ("7 % 3", Object::Integer(1)),
+            ("-7 % 3", Object::Integer(-1)),
+            ("2 ** 10", Object::Integer(1024)),
+            ("2 ** 3 ** 2", Object::Integer(512)),
+            ("-2 ** 2", Object::Integer(-4)),
+            ("6 & 3", Object::Integer(2)),
+            ("6 | 3", Object::Integer(7)),
+            ("6 ^ 3", Object::Integer(5)),
+            ("1 << 4", Object::Integer(16)),
+            ("-16 >> 2", Object::Integer(-4)),
+            ("~5", Object::Integer(-6)),
+            ("1 + 2 << 1", Object::Integer(6)),
+            ("1 <= 1", Object::Boolean(true)),
+            ("2 <= 1", Object::Boolean(false)),
+            ("1 >= 2", Object::Boolean(false)),
+            ("2 >= 2", Object::Boolean(true)),
+            ("1 < 2 && 2 < 3", Object::Boolean(true)),
+            ("1 < 2 && 3 < 2", Object::Boolean(false)),
+            ("1 > 2 || 2 < 3", Object::Boolean(true)),
+            ("false || false", Object::Boolean(false)),
+            ("true ^ true", Object::Boolean(false)),
+            ("3 & 1 == 1", Object::Boolean(true)),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_logical_operators_short_circuit() {
+        let inputs = vec![
+            ("false && 1 / 0", false),
+            ("true || 1 / 0", true),
+            ("let f = fn() { 1 / 0 }; 1 > 2 && f()", false),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval("true && foobar").unwrap_err(),
+            "identifier not found: foobar"
+        );
+    }
+
+    #[test]
+    fn test_array_literals() {
+        let obj = test_eval("[1, 2 * 2, 3 + 3]").unwrap();
+        assert_eq!(obj.to_string(), "[1, 4, 6]");
+    }
+
+    #[test]
+    fn test_array_index_expressions() {
+        let inputs = vec![
+            ("[1, 2, 3][0]", Object::Integer(1)),
+            ("[1, 2, 3][1]", Object::Integer(2)),
+            ("let i = 0; [1][i];", Object::Integer(1)),
+            ("[1, 2, 3][1 + 1];", Object::Integer(3)),
+            (
+                "let myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2];",
+                Object::Integer(6),
+            ),
+            ("[1, 2, 3][3]", Object::Null),
+            ("[1, 2, 3][-1]", Object::Null),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_loops() {
+        let inputs = vec![
+            ("let i = 0; while (i < 5) { let i = i + 1; } i", 5),
+            ("let i = 0; while (true) { let i = i + 1; if (i == 3) { break; } } i", 3),
+            ("let s = 0; for (x in [1, 2, 3, 4]) { let s = s + x; } s", 10),
+            (
+                "let s = 0; for (x in [1, 2, 3, 4]) { if (x % 2 == 0) { continue; } let s = s + x; } s",
+                4,
+            ),
+            (
+                "let s = 0; for (x in [1, 2, 3]) { for (y in [10, 20]) { if (y == 20) { break; } let s = s + x * y; } } s",
+                60,
+            ),
+            (
+                "let f = fn(xs) { for (x in xs) { if (x > 2) { return x; } } 0 }; f([1, 2, 3, 4])",
+                3,
+            ),
+            ("let f = fn() { while (true) { if (true) { return 7; } } }; f()", 7),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+        assert_eq!(test_eval("while (false) { 1 }").unwrap(), Object::Null);
+        assert_eq!(
+            test_eval("for (x in 5) { x }").unwrap_err(),
+            "not iterable: INTEGER"
+        );
+    }
+
+    #[test]
+    fn test_control_flow_in_expressions() {
+        let inputs = vec![
+            (
+                "let s = 0; for (x in [1, 2, 3]) { let y = if (x == 2) { continue; } else { x }; s += y } s",
+                4,
+            ),
+            (
+                "let s = 0; for (x in [1, 2, 3]) { s += 1 + if (x == 2) { break; } else { x } } s",
+                2,
+            ),
+            (
+                "let n = 0; while (n < 5) { n += 1; [n, if (n > 2) { break; }]; } n",
+                3,
+            ),
+            ("let f = fn() { let y = if (true) { return 5; }; 10 }; f()", 5),
+            ("let f = fn() { 1 + if (true) { return 5; } else { 2 } }; f()", 5),
+            ("let f = fn() { [1, match (2) { 2 => { return 6; } _ => 0 }] }; f()", 6),
+            ("let f = fn(g) { g(if (true) { return 7; }) }; f(len)", 7),
+            ("let f = fn() { let h = {\"a\": try { return 8; } catch (e) { 0 }}; 0 }; f()", 8),
+            ("let y = if (true) { return 9; }; 10", 9),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap_or_else(|e| panic!("{}: {}", input, e));
+            assert_eq!(obj, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_string_literals() {
+        let inputs = vec![
+            (r#""Hello World!""#, "Hello World!"),
+            (r#""Hello" + " " + "World!""#, "Hello World!"),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, Object::String(expected.into()), "{}", input);
+        }
+        assert_eq!(test_eval(r#""a" == "a""#).unwrap(), true);
+        assert_eq!(
+            test_eval(r#""a" - "b""#).unwrap_err(),
+            "unknown operator: STRING - STRING"
+        );
+    }
+
+    #[test]
+    fn test_hash_literals() {
+        let input = r#"let two = "two";
+{"one": 10 - 9, two: 1 + 1, "thr" + "ee": 6 / 2, 4: 4, true: 5, false: 6}"#;
+        let obj = test_eval(input).unwrap();
+        assert_eq!(
+            obj.to_string(),
+            "{4: 4, false: 6, true: 5, one: 1, three: 3, two: 2}"
+        );
+    }
+
+    #[test]
+    fn test_hash_index_expressions() {
+        let inputs = vec![
+            (r#"{"foo": 5}["foo"]"#, Object::Integer(5)),
+            (r#"{"foo": 5}["bar"]"#, Object::Null),
+            (r#"let key = "foo"; {"foo": 5}[key]"#, Object::Integer(5)),
+            (r#"{}["foo"]"#, Object::Null),
+            ("{5: 5}[5]", Object::Integer(5)),
+            ("{true: 5}[true]", Object::Integer(5)),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval(r#"{"name": "Monkey"}[fn(x) { x }];"#).unwrap_err(),
+            "unusable as hash key: FUNCTION"
+        );
+    }
+
+    #[test]
+    fn test_assignment() {
+        let inputs = vec![
+            ("let x = 1; x = 2; x", Object::Integer(2)),
+            ("let x = 1; x = 2", Object::Integer(2)),
+            ("let x = 1; let y = 1; x = y = 5; x + y", Object::Integer(10)),
+            ("let x = 5; x += 2; x", Object::Integer(7)),
+            ("let x = 5; x -= 2; x", Object::Integer(3)),
+            ("let x = 5; x *= 2; x", Object::Integer(10)),
+            ("let x = 5; x /= 2; x", Object::Integer(2)),
+            ("let x = 5; x %= 2; x", Object::Integer(1)),
+            ("let x = 5; x **= 2; x", Object::Integer(25)),
+            ("let x = 5; x &= 4; x", Object::Integer(4)),
+            ("let x = 5; x |= 2; x", Object::Integer(7)),
+            ("let x = 5; x ^= 1; x", Object::Integer(4)),
+            ("let x = 5; x <<= 1; x", Object::Integer(10)),
+            ("let x = 5; x >>= 1; x", Object::Integer(2)),
+            (r#"let s = "a"; s += "b"; s"#, Object::String("ab".into())),
+            (
+                "let i = 0; let s = 0; while (i < 4) { i += 1; s += i; } s",
+                Object::Integer(10),
+            ),
+            (
+                "let x = 1; let f = fn() { x = 2; }; f(); x",
+                Object::Integer(2),
+            ),
+            (
+                "let x = 1; let f = fn() { let x = 5; x = 2; }; f(); x",
+                Object::Integer(1),
+            ),
+            (
+                "let counter = fn() { let n = 0; fn() { n += 1 } }; let c = counter(); c(); c(); c()",
+                Object::Integer(3),
+            ),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        let inputs = vec![
+            ("let a = [1, 2, 3]; a[1] = 5; a", "[1, 5, 3]"),
+            ("let a = [1, 2, 3]; a[2] += 5; a", "[1, 2, 8]"),
+            ("let a = [[1], [2]]; a[1][0] = 5; a", "[[1], [5]]"),
+            ("let a = [1]; let b = a; a[0] = 2; b", "[1]"),
+            (r#"let h = {}; h["k"] = 1; h"#, "{k: 1}"),
+            (r#"let h = {"k": 1}; h["k"] -= 3; h"#, "{k: -2}"),
+            (r#"let h = {"k": [1, 2]}; h["k"][0] = 9; h"#, "{k: [9, 2]}"),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_assignment_errors() {
+        let inputs = vec![
+            ("x = 1", "identifier not found: x"),
+            ("let f = fn() { y += 1 }; f()", "identifier not found: y"),
+            ("let x = true; x += 1", "type mismatch: BOOLEAN + INTEGER"),
+            ("let a = [1]; a[1] = 2", "index out of bounds: 1 (length 1)"),
+            (r#"let h = {}; h["k"] += 1"#, "key not found: k"),
+            ("let h = {}; h[[]] = 1", "unusable as hash key: ARRAY"),
+            (
+                "let x = 1; x[0] = 1",
+                "index assignment not supported: INTEGER[INTEGER]",
+            ),
+        ];
+        for (input, expected) in inputs {
+            let err = test_eval(input).unwrap_err();
+            assert_eq!(err, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_null_and_optional_access() {
+        let inputs = vec![
+            ("null", Object::Null),
+            ("null == null", Object::Boolean(true)),
+            ("null != 1", Object::Boolean(true)),
+            ("!null", Object::Boolean(true)),
+            ("null ?? 5", Object::Integer(5)),
+            ("3 ?? 5", Object::Integer(3)),
+            ("false ?? 5", Object::Boolean(false)),
+            ("null ?? null ?? 7", Object::Integer(7)),
+            ("1 ?? 1 / 0", Object::Integer(1)),
+            (r#"let h = {"a": {"b": 2}}; h?.a?.b"#, Object::Integer(2)),
+            (r#"let h = {"a": {"b": 2}}; h?.c?.b"#, Object::Null),
+            (
+                r#"let h = null; h?.a ?? "none""#,
+                Object::String("none".into()),
+            ),
+            ("let a = null; a?[0]", Object::Null),
+            ("let a = [[1, 2]]; a?[0]?[1]", Object::Integer(2)),
+            ("let a = [[1, 2]]; a?[5]?[1]", Object::Null),
+            ("let a = [1]; a[3] ?? 0", Object::Integer(0)),
+            // an optional access cuts short the rest of the chain it's in
+            ("let h = null; h?.a.b", Object::Null),
+            ("let h = null; h?.a[0].b", Object::Null),
+            ("let h = null; h?[0].a.len()", Object::Null),
+            ("let h = null; h?.f(1)(2)", Object::Null),
+            ("let h = null; [h?.a.b, 1][1]", Object::Integer(1)),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval("let a = null; a[0]").unwrap_err(),
+            "index operator not supported: NULL[INTEGER]"
+        );
+        assert_eq!(
+            test_eval("1?.a").unwrap_err(),
+            "property access not supported: INTEGER.a"
+        );
+        // but only for what's accessed after it
+        assert_eq!(
+            test_eval(r#"let h = {"a": null}; h?.a.b"#).unwrap_err(),
+            "property access not supported: NULL.b"
+        );
+        // and only up to the parentheses it's in
+        assert_eq!(
+            test_eval(r#"(null?["a"])["b"]"#).unwrap_err(),
+            "index operator not supported: NULL[STRING]"
+        );
+        assert_eq!(
+            test_eval("let h = null; (h?.f(1))(2)").unwrap_err(),
+            "not a function: NULL"
+        );
+        assert_eq!(
+            test_eval("let h = null; (h?.a).len()").unwrap_err(),
+            "unknown method: NULL.len"
+        );
+        assert_eq!(test_eval("let h = null; (h?.a)?.b").unwrap(), Object::Null);
+    }
+
+    #[test]
+    fn test_match_expressions() {
+        let classify = r#"let classify = fn(x) {
+    match (x) {
+        0 => "zero",
+        -1 => "minus one",
+        true => "yes",
+        null => "nothing",
+        "hi" => "greeting",
+        [] => "empty",
+        [only] => "one: " + only,
+        [first, ..rest] if first == "a" => rest,
+        [first, .., last] => [last, first],
+        {"kind": "point", x, y: why} => x + why,
+        {name} => name,
+        n if n > 100 => "big",
+        _ => "other",
+    }
+};
+"#;
+        let inputs = vec![
+            ("classify(0)", "zero"),
+            ("classify(-1)", "minus one"),
+            ("classify(true)", "yes"),
+            ("classify(null)", "nothing"),
+            (r#"classify("hi")"#, "greeting"),
+            ("classify([])", "empty"),
+            (r#"classify(["x"])"#, "one: x"),
+            (r#"classify(["a", "b", "c"])"#, "[b, c]"),
+            (r#"classify(["b", "c", "d"])"#, "[d, b]"),
+            (r#"classify({"kind": "point", "x": 1, "y": 2})"#, "3"),
+            (r#"classify({"kind": "line", "name": "l"})"#, "l"),
+            ("classify(101)", "big"),
+            ("classify(5)", "other"),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(format!("{}{}", classify, input)).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_match_scoping_and_errors() {
+        assert_eq!(test_eval("let x = 1; match (5) { x => x }; x").unwrap(), 1);
+        assert_eq!(
+            test_eval(
+                "let f = fn(x) { match (x) { 1 => if (true) { return 10; }, _ => 0 }; 20 }; f(1)"
+            )
+            .unwrap(),
+            10
+        );
+        assert_eq!(
+            test_eval(
+                "let i = 0; while (true) { i += 1; match (i) { 3 => { break; } _ => {} } } i"
+            )
+            .unwrap(),
+            3
+        );
+        assert_eq!(
+            test_eval("match (2) { n => { let m = n * 2; m + 1 }, }").unwrap(),
+            5
+        );
+        assert_eq!(
+            test_eval("match ([1, 2]) { [a] => a, 3 => 3 }").unwrap_err(),
+            "no match arm matched value: [1, 2]"
+        );
+        assert_eq!(
+            test_eval("match (1) { n if n > foo => n }").unwrap_err(),
+            "identifier not found: foo"
+        );
+    }
+
+    #[test]
+    fn test_destructuring_let_statements() {
+        let inputs = vec![
+            ("let [a, b] = [1, 2]; a + b", "3"),
+            ("let [a, b, ..rest] = [1, 2, 3, 4]; rest", "[3, 4]"),
+            ("let [a, ..rest] = [1]; rest", "[]"),
+            ("let [.., last] = [1, 2, 3]; last", "3"),
+            ("let [_, [x, y]] = [0, [1, 2]]; x * 10 + y", "12"),
+            (
+                r#"let person = {"name": "Ann", "age": 30}; let {name, age: years} = person; name + " " + "is"; years"#,
+                "30",
+            ),
+            (r#"let {name} = {"name": "Ann", "extra": 1}; name"#, "Ann"),
+            (r#"let {pos: [x, y]} = {"pos": [3, 4]}; x + y"#, "7"),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_destructuring_let_errors() {
+        let inputs = vec![
+            (
+                "let [a, b] = [1, 2, 3];",
+                "cannot destructure [1, 2, 3]: expected ARRAY of length 2, got ARRAY of length 3",
+            ),
+            (
+                "let [a, b, ..rest] = [1];",
+                "cannot destructure [1]: expected ARRAY of length at least 2, got ARRAY of length 1",
+            ),
+            ("let [a] = 5;", "cannot destructure 5: expected ARRAY, got INTEGER"),
+            (
+                r#"let {name} = {"age": 1};"#,
+                "cannot destructure {age: 1}: expected HASH with key name",
+            ),
+            ("let {name} = [1];", "cannot destructure [1]: expected HASH, got ARRAY"),
+            ("let [1, a] = [2, 3];", "cannot destructure [2, 3]: expected 1, got 2"),
+        ];
+        for (input, expected) in inputs {
+            let err = test_eval(input).unwrap_err();
+            assert_eq!(err, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_function_parameters() {
+        let inputs = vec![
+            ("let f = fn(a, b = 2) { a + b }; f(1)", "3"),
+            ("let f = fn(a, b = 2) { a + b }; f(1, 5)", "6"),
+            ("let f = fn(a, b = a * 10) { a + b }; f(1)", "11"),
+            ("let n = 0; let f = fn(a = n) { a }; n = 5; f()", "5"),
+            ("let f = fn(a, ...rest) { rest }; f(1, 2, 3)", "[2, 3]"),
+            ("let f = fn(a, ...rest) { rest }; f(1)", "[]"),
+            ("let f = fn(a, b, c) { a + b + c }; f(...[1, 2, 3])", "6"),
+            (
+                "let f = fn(a, b, c) { a + b + c }; f(1, ...[2], ...[3])",
+                "6",
+            ),
+            (
+                "let f = fn(...xs) { xs }; f(...[], 1, ...[2, 3])",
+                "[1, 2, 3]",
+            ),
+            (
+                "let f = fn(a, b = 2, c = 3) { [a, b, c] }; f(1, c: 5)",
+                "[1, 2, 5]",
+            ),
+            ("let f = fn(a, b) { a - b }; f(b: 1, a: 5)", "4"),
+        ];
         for (input, expected) in inputs {
-            let _ = test_eval(input).unwrap();
-            // assert_eq!(obj, expected);
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_function_arity_errors() {
+        let inputs = vec![
+            (
+                "fn(a, b) { a }(1)",
+                "wrong number of arguments: expected 2, got 1",
+            ),
+            (
+                "fn(a) { a }(1, 2)",
+                "wrong number of arguments: expected 1, got 2",
+            ),
+            (
+                "fn(a, b = 1, c = 2) { a }(1, 2, 3, 4)",
+                "wrong number of arguments: expected 1 to 3, got 4",
+            ),
+            (
+                "fn(a, b, ...c) { a }(1)",
+                "wrong number of arguments: expected at least 2, got 1",
+            ),
+            ("fn(a, b) { a }(a: 1, c: 2)", "unexpected named argument: c"),
+            ("fn(a, b) { a }(1, a: 2)", "multiple values for argument: a"),
+            ("fn(a) { a }(...5)", "cannot spread INTEGER"),
+        ];
+        for (input, expected) in inputs {
+            let err = test_eval(input).unwrap_err();
+            assert_eq!(err, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_interpolated_strings() {
+        let inputs = vec![
+            (
+                r#"let name = "Ann"; let age = 30; "hello ${name}, you are ${age + 1}""#,
+                "hello Ann, you are 31",
+            ),
+            (r#""${1}${2}""#, "12"),
+            (r#""${[1, "a"]} ${null} ${true}""#, "[1, a] null true"),
+            (r#"let x = "y"; "${"nested ${x}"}!""#, "nested y!"),
+            (r#"let h = {"k": 2}; "${h["k"] * 2}""#, "4"),
+            (r#""cost: \${5}""#, "cost: ${5}"),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj, Object::String(expected.into()), "{}", input);
+        }
+        assert_eq!(
+            test_eval(r#""${missing}""#).unwrap_err(),
+            "identifier not found: missing"
+        );
+
+        // values nested too deeply to write are cut short rather than overflowing the stack
+        let obj = test_eval("let a = 1; for (i in 0..3000) { a = [a] }; \"${a}\"").unwrap();
+        let expected = format!("{}...{}", "[".repeat(100), "]".repeat(100));
+        assert_eq!(obj, Object::String(expected.into()));
+    }
+
+    #[test]
+    fn test_ranges() {
+        let inputs = vec![
+            ("1..4", "1..4"),
+            ("let n = 3; 0..=n * 2", "0..=6"),
+            ("collect(1..4)", "[1, 2, 3]"),
+            ("collect(1..=4)", "[1, 2, 3, 4]"),
+            ("collect(4..1)", "[]"),
+            (
+                "collect(9223372036854775806..=9223372036854775807)",
+                "[9223372036854775806, 9223372036854775807]",
+            ),
+            ("len(-5..5)", "10"),
+            ("len(3..=3)", "1"),
+            ("len(5..0)", "0"),
+            ("(1..3) == (1..3)", "true"),
+            ("(1..3) == (1..=2)", "false"),
+            (
+                "let total = 0; for (i in 1..=100) { total += i; } total",
+                "5050",
+            ),
+            ("let a = [1, 2, 3, 4, 5]; a[1..3]", "[2, 3]"),
+            ("let a = [1, 2, 3, 4, 5]; a[1..=3]", "[2, 3, 4]"),
+            ("let a = [1, 2, 3]; a[0..len(a)]", "[1, 2, 3]"),
+            ("let a = [1, 2, 3]; a[3..3]", "[]"),
+            (r#""héllo"[1..4]"#, "éll"),
+            (r#""abc"[0..=0]"#, "a"),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+
+        let errors = vec![
+            ("[1, 2, 3][1..4]", "range out of bounds: 1..4 (length 3)"),
+            ("[1, 2, 3][-1..2]", "range out of bounds: -1..2 (length 3)"),
+            ("[1, 2, 3][2..1]", "range out of bounds: 2..1 (length 3)"),
+            (r#""abc"[0..=3]"#, "range out of bounds: 0..=3 (length 3)"),
+            (r#"1.."a""#, "type mismatch: INTEGER .. STRING"),
+            ("true..false", "unknown operator: BOOLEAN .. BOOLEAN"),
+            ("{1: 2}[0..1]", "unusable as hash key: RANGE"),
+            (
+                "len(1..=9223372036854775807 - 1 + 1) + len(-9223372036854775807 - 1..0)",
+                "integer overflow",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(test_eval(input).unwrap_err(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_iterators() {
+        let inputs =
+            vec![
+            ("collect(map([1, 2, 3], fn(x) { x * 2 }))", "[2, 4, 6]"),
+            ("collect(filter(1..10, fn(x) { x % 3 == 0 }))", "[3, 6, 9]"),
+            (
+                "collect(map(filter(0..10, fn(x) { x % 2 == 0 }), fn(x) { x * x }))",
+                "[0, 4, 16, 36, 64]",
+            ),
+            (r#"collect("héy")"#, "[h, é, y]"),
+            (r#"collect(map("ab", fn(c) { c + c }))"#, "[aa, bb]"),
+            ("collect(map([[1], [2, 3]], len))", "[1, 2]"),
+            ("let it = iter([1, 2]); [next(it), next(it), next(it)]", "[1, 2, null]"),
+            ("let it = iter(1..5); next(it); collect(it)", "[2, 3, 4]"),
+            ("let it = iter(1..3); collect(it); collect(it)", "[]"),
+            (
+                "let total = 0; for (x in map(1..4, fn(x) { x * 10 })) { total += x; } total",
+                "60",
+            ),
+            // nothing is computed until a value is asked for, so huge ranges are fine
+            (
+                "let calls = 0; let it = map(0..9223372036854775807, fn(x) { calls += 1; x }); \
+                 next(it); next(it); calls",
+                "2",
+            ),
+            (
+                "let big = filter(0..9223372036854775807, fn(x) { x > 5 }); \
+                 for (x in big) { if (x > 7) { break; } } next(big)",
+                "9",
+            ),
+            ("len({1: 2, 3: 4})", "2"),
+            (r#"len("héllo")"#, "5"),
+            ("map([], len) == map([], len)", "false"),
+            ("let it = iter([]); it == it", "true"),
+            ("len", "builtin len"),
+            ("iter([])", "<iterator>"),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+
+        let errors = vec![
+            ("collect(map(1, len))", "not iterable: INTEGER"),
+            (
+                "map([1], 1)",
+                "argument to `map` must be FUNCTION, got INTEGER",
+            ),
+            (
+                "next([1])",
+                "argument to `next` must be ITERATOR, got ARRAY",
+            ),
+            ("len(1)", "argument to `len` not supported, got INTEGER"),
+            ("len(1, 2)", "wrong number of arguments: expected 1, got 2"),
+            ("len(x: 1)", "unexpected named argument: x"),
+            (
+                "collect(map([1], fn(x, y) { x }))",
+                "wrong number of arguments: expected 2, got 1",
+            ),
+            (
+                "collect(filter([1], fn(x) { x + true }))",
+                "type mismatch: INTEGER + BOOLEAN",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(test_eval(input).unwrap_err(), expected, "{}", input);
+        }
+
+        // user definitions shadow builtins
+        let obj = test_eval("let len = fn(x) { 0 }; len([1, 2])").unwrap();
+        assert_eq!(obj, 0);
+    }
+
+    #[test]
+    fn test_member_access_and_methods() {
+        let inputs = vec![
+            (r#"let h = {"name": "Ann", "age": 30}; h.name"#, "Ann"),
+            (r#"let h = {"a": {"b": [1, 2]}}; h.a.b[1]"#, "2"),
+            (r#"let h = {}; h.missing"#, "null"),
+            (r#"let h = {"a": {"b": 1}}; h.a.b = 5; h"#, "{a: {b: 5}}"),
+            (
+                r#"let h = {"a": [1]}; h.a[0] += 2; h.c = 3; h"#,
+                "{a: [3], c: 3}",
+            ),
+            (r#"let h = {"n": 1}; let g = h; g.n = 2; h.n"#, "1"),
+            (r#""abc".upper()"#, "ABC"),
+            (r#""ÀBC".lower()"#, "àbc"),
+            (r#""  hi ".trim().len()"#, "2"),
+            (r#""a,b,c".split(",")"#, "[a, b, c]"),
+            (r#""hello".contains("ell")"#, "true"),
+            (r#"collect("ab".iter())"#, "[a, b]"),
+            ("[1, 2].map(fn(x) { x * 2 })", "[2, 4]"),
+            ("[1, 2, 3, 4].filter(fn(x) { x % 2 == 0 }).len()", "2"),
+            ("[1, 2].push(3)", "[1, 2, 3]"),
+            ("let a = [1]; a.push(2); a", "[1]"),
+            ("[1, 2].first() + [1, 2].last()", "3"),
+            ("[].first()", "null"),
+            (r#"[1, "a", true].join("-")"#, "1-a-true"),
+            ("[1, 2].contains(2)", "true"),
+            (r#"{"b": 1, "a": 2}.keys()"#, "[a, b]"),
+            (r#"{"b": 1, "a": 2}.values()"#, "[2, 1]"),
+            (r#"{"a": 1}.contains("a")"#, "true"),
+            ("(1..10).filter(fn(x) { x > 7 }).collect()", "[8, 9]"),
+            ("(1..=3).map(fn(x) { x * x }).collect()", "[1, 4, 9]"),
+            ("(1..=3).contains(3) && !(1..3).contains(3)", "true"),
+            ("let it = [1, 2].iter(); it.next(); it.next()", "2"),
+            // a function stored in a hash is called instead of a hash method
+            (r#"let m = {"len": fn() { 42 }}; m.len()"#, "42"),
+            (
+                r#"let m = {"double": fn(x) { x * 2 }}; m.double(x: 4)"#,
+                "8",
+            ),
+            ("let h = null; h?.len()", "null"),
+            (r#"let h = {"a": null}; h.a?.b?.len()"#, "null"),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+
+        let errors = vec![
+            ("1.foo", "property access not supported: INTEGER.foo"),
+            (r#""abc".len"#, "property access not supported: STRING.len"),
+            ("1.len()", "unknown method: INTEGER.len"),
+            ("[1].upper()", "unknown method: ARRAY.upper"),
+            (r#"{"a": 1}.a()"#, "not a function: INTEGER"),
+            ("[1].len(2)", "wrong number of arguments: expected 0, got 1"),
+            (
+                "[1].join(1)",
+                "argument to `join` must be STRING, got INTEGER",
+            ),
+            (r#""a".split("")"#, "separator to `split` must not be empty"),
+            ("[1].push(x: 2)", "unexpected named argument: x"),
+            (
+                "(1..2).contains(true)",
+                "argument to `contains` must be INTEGER, got BOOLEAN",
+            ),
+            ("let h = null; h.a", "property access not supported: NULL.a"),
+            (
+                "let a = [1]; a.b = 1",
+                "property assignment not supported: ARRAY.b",
+            ),
+            ("b.c = 1", "identifier not found: b"),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(test_eval(input).unwrap_err(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_structs() {
+        let point = "struct Point { x, y }";
+        let inputs = vec![
+            ("Point { x: 1, y: 2 }", "Point { x: 1, y: 2 }"),
+            ("Point { y: 2, x: 1 }", "Point { x: 1, y: 2 }"),
+            ("let x = 3; let y = 4; Point { x, y }", "Point { x: 3, y: 4 }"),
+            (r#"Point { x: "a", y: [1] }"#, "Point { x: a, y: [1] }"),
+            ("let p = Point { x: 1, y: 2 }; p.x + p.y", "3"),
+            ("let p = Point { x: 1, y: 2 }; p.x = 10; p.y *= 3; p", "Point { x: 10, y: 6 }"),
+            ("let p = Point { x: 1, y: 2 }; let q = p; q.x = 5; p.x", "1"),
+            ("let p = Point { x: [1], y: 0 }; p.x[0] = 9; p", "Point { x: [9], y: 0 }"),
+            ("Point { x: 1, y: 2 } == Point { x: 1, y: 2 }", "true"),
+            ("Point { x: 1, y: 2 } == Point { x: 1, y: 3 }", "false"),
+            ("Point { x: 1, y: 2 } != 1", "true"),
+            ("Point", "struct Point { x, y }"),
+            (
+                "struct Line { from, to } Line { from: Point { x: 0, y: 0 }, to: Point { x: 1, y: 1 } }.to.x",
+                "1",
+            ),
+            ("struct Unit {} Unit {}", "Unit {}"),
+            ("let p = Point { x: fn(n) { n * 2 }, y: 0 }; p.x(21)", "42"),
+            ("let move = fn(p, dx) { p.x += dx; p }; move(Point { x: 1, y: 1 }, 2)", "Point { x: 3, y: 1 }"),
+        ];
+        for (input, expected) in inputs {
+            let input = format!("{} {}", point, input);
+            let obj = test_eval(&input).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+
+        // equality is by declaration, not by shape
+        let obj = test_eval(
+            "let a = fn() { struct P { x } P { x: 1 } }; let b = fn() { struct P { x } P { x: 1 } }; a() == b()",
+        )
+        .unwrap();
+        assert_eq!(obj, false);
+
+        let errors = vec![
+            ("Point { x: 1 }", "missing field: Point.y"),
+            ("Point { x: 1, y: 2, z: 3 }", "unknown field: Point.z"),
+            ("Point { x: 1, x: 2, y: 3 }", "duplicate field: Point.x"),
+            ("Point { x: 1, y: 2 }.z", "unknown field: Point.z"),
+            (
+                "let p = Point { x: 1, y: 2 }; p.z = 1",
+                "unknown field: Point.z",
+            ),
+            (
+                "let p = Point { x: 1, y: 2 }; p[0] = 1",
+                "index assignment not supported: STRUCT[INTEGER]",
+            ),
+            (
+                "let p = Point { x: 1, y: 2 }; p.len()",
+                "unknown method: STRUCT.len",
+            ),
+            ("let n = 1; n { x: 1 }", "not a struct: INTEGER"),
+            ("Nope { x: 1 }", "identifier not found: Nope"),
+            (
+                "Point { x: 1, y: 2 } + 1",
+                "type mismatch: STRUCT + INTEGER",
+            ),
+        ];
+        for (input, expected) in errors {
+            let input = format!("{} {}", point, input);
+            assert_eq!(test_eval(&input).unwrap_err(), expected, "{}", input);
         }
     }
+
+    #[test]
+    fn test_enums() {
+        let shape = r#"enum Shape { Circle(r), Rect(w, h), Empty }
+let area = fn(s) {
+    match (s) {
+        Shape.Circle(r) => 3 * r * r,
+        Shape.Rect(w, h) if w == h => "square",
+        Shape.Rect(w, h) => w * h,
+        Shape.Empty => 0,
+    }
+};"#;
+        let inputs = vec![
+            ("Shape.Circle(2)", "Shape.Circle(2)"),
+            ("Shape.Rect(h: 3, w: 2)", "Shape.Rect(2, 3)"),
+            ("Shape.Empty", "Shape.Empty"),
+            ("Shape.Circle", "constructor Shape.Circle(r)"),
+            ("Shape", "enum Shape { Circle(r), Rect(w, h), Empty }"),
+            ("area(Shape.Circle(2))", "12"),
+            ("area(Shape.Rect(2, 3))", "6"),
+            ("area(Shape.Rect(2, 2))", "square"),
+            ("area(Shape.Empty)", "0"),
+            ("Shape.Rect(2, 3).h", "3"),
+            ("Shape.Circle(1) == Shape.Circle(1)", "true"),
+            ("Shape.Circle(1) == Shape.Circle(2)", "false"),
+            ("Shape.Empty == Shape.Empty", "true"),
+            ("let c = Shape.Circle; c(5)", "Shape.Circle(5)"),
+            (
+                "[1, 2].map(Shape.Circle)",
+                "[Shape.Circle(1), Shape.Circle(2)]",
+            ),
+            (
+                "match (Shape.Rect(1, 2)) { Shape.Circle => 1, Shape.Rect => 2 }",
+                "2",
+            ),
+            (
+                "match (Shape.Circle([1, 2])) { Shape.Circle([a, b]) => a + b }",
+                "3",
+            ),
+        ];
+        for (input, expected) in inputs {
+            let input = format!("{}\n{}", shape, input);
+            let obj = test_eval(&input).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+
+        let option = r#"enum Option { Some(value), None }
+let find = fn(xs, f) {
+    for (x in xs) {
+        if (f(x)) { return Option.Some(x); }
+    }
+    Option.None
+};
+let unwrap_or = fn(opt, default) {
+    match (opt) {
+        Option.Some(x) => x,
+        Option.None => default,
+    }
+};"#;
+        let inputs = vec![
+            ("unwrap_or(find([1, 4, 9], fn(x) { x > 3 }), 0)", 4),
+            ("unwrap_or(find([1, 4, 9], fn(x) { x > 10 }), 0)", 0),
+        ];
+        for (input, expected) in inputs {
+            let input = format!("{}\n{}", option, input);
+            let obj = test_eval(&input).unwrap();
+            assert_eq!(obj, expected, "{}", input);
+        }
+
+        // variants of different declarations never match or compare equal
+        let obj = test_eval(
+            "enum A { X } enum B { X } let a = A.X; enum A { X } [a == A.X, match (a) { A.X => 1, _ => 2 }]",
+        )
+        .unwrap();
+        assert_eq!(obj.to_string(), "[false, 2]");
+
+        let errors = vec![
+            ("Shape.Square", "unknown variant: Shape.Square"),
+            ("Shape.Square(1)", "unknown variant: Shape.Square"),
+            (
+                "Shape.Circle(1, 2)",
+                "wrong number of arguments: expected 1, got 2",
+            ),
+            ("Shape.Rect(1, w: 2)", "multiple values for argument: w"),
+            ("Shape.Circle(d: 1)", "unexpected named argument: d"),
+            ("Shape.Empty()", "not a function: VARIANT"),
+            ("Shape.Circle(1).d", "unknown field: Shape.Circle.d"),
+            ("area(1)", "no match arm matched value: 1"),
+            // a pattern that can't describe any value is an error rather than not matching
+            (
+                "match (Shape.Circle(1)) { Shape.Circle(a, b) => 0 }",
+                "expected 1 field patterns for Shape.Circle, got 2",
+            ),
+            (
+                "match (Shape.Empty) { Shape.Nope => 0, _ => 1 }",
+                "unknown variant: Shape.Nope",
+            ),
+            (
+                "let [Shape.Nope(x)] = [Shape.Empty]",
+                "unknown variant: Shape.Nope",
+            ),
+            ("match (1) { Nope.A => 0 }", "identifier not found: Nope"),
+            ("match (1) { area.A => 0 }", "not an enum: FUNCTION"),
+            (
+                "{1: Shape.Empty}[Shape.Empty]",
+                "unusable as hash key: VARIANT",
+            ),
+        ];
+        for (input, expected) in errors {
+            let input = format!("{}\n{}", shape, input);
+            assert_eq!(test_eval(&input).unwrap_err(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let inputs = vec![
+            ("try { throw 1; 2 } catch (e) { e + 10 }", "11"),
+            ("try { 1 } catch (e) { 2 }", "1"),
+            ("try { throw \"oops\"; } catch (e) { e }", "oops"),
+            ("try { 1 / 0 } catch (e) { e.message }", "division by zero"),
+            // a name that's used before it's defined is only found missing as it runs
+            (
+                "let f = fn() { x }; let m = try { f() } catch (e) { e.message }; let x = 1; m",
+                "identifier not found: x",
+            ),
+            ("try { 1 + true } catch (e) { e }", "error: type mismatch: INTEGER + BOOLEAN"),
+            (
+                "try { 1 + true } catch (e) { [e.kind, e.span] }",
+                "[type mismatch, {column: 9, line: 1}]",
+            ),
+            (
+                "try { [1 / 0, 2] } catch (e) { [e.kind, e.span.line, e.span.column] }",
+                "[division by zero, 1, 10]",
+            ),
+            (
+                "try { throw error(\"a: b\"); } catch (e) { [e.kind, e.message] }",
+                "[error, a: b]",
+            ),
+            (
+                "try { throw error(\"custom\"); } catch (e) { [e.message, e.line, e.column] }",
+                "[custom, 1, 7]",
+            ),
+            ("let e = error(\"x\"); [e.line, e.column, e.span]", "[null, null, null]"),
+            (
+                "let f = fn(x) { x / 0 };\nlet y = 1;\n\ntry { f(y) } catch (e) { [e.line, e.column] }",
+                "[1, 19]",
+            ),
+            (
+                "try { try { throw 1; } catch (e) { throw e + 1; } } catch (e) { e }",
+                "2",
+            ),
+            ("try { try { throw 1; } finally { 5 } } catch (e) { e }", "1"),
+            ("let e = 1; try { throw 2; } catch (e) { e }; e", "1"),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+
+        // finally always runs, and leaving it early replaces the result
+        let inputs = vec![
+            (
+                "let log = []; try { log = log.push(1); } finally { log = log.push(2); }; log",
+                "[1, 2]",
+            ),
+            (
+                "let log = []; try { try { throw 1; } finally { log = log.push(2); } } catch (e) { log = log.push(e); }; log",
+                "[2, 1]",
+            ),
+            (
+                "let f = fn() { try { return 1; } finally { log = log.push(2); } }; let log = []; [f(), log]",
+                "[1, [2]]",
+            ),
+            ("let f = fn() { try { return 1; } finally { return 2; } }; f()", "2"),
+            (
+                "let f = fn() { try { throw 1; } finally { return 2; } }; f()",
+                "2",
+            ),
+            (
+                "let n = 0; while (true) { try { throw 1; } finally { break; } } n",
+                "0",
+            ),
+            (
+                "try { try { throw 1; } finally { throw 2; } } catch (e) { e }",
+                "2",
+            ),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+
+        let errors = vec![
+            ("throw 1;", "uncaught exception: 1"),
+            ("throw error(\"custom\");", "custom"),
+            ("try { throw 1; } finally { 2 }", "uncaught exception: 1"),
+            ("try { 1 } catch (e) { 2 }; e", "identifier not found: e"),
+            // names nothing defines are reported before the program runs, so can't be caught
+            ("try { nope } catch (e) { 1 }", "identifier not found: nope"),
+            (
+                "let f = fn() { nope }; try { f() } catch (e) { 1 }",
+                "identifier not found: nope",
+            ),
+            (
+                "error(1)",
+                "argument to `error` must be STRING, got INTEGER",
+            ),
+            ("error(\"x\").name", "unknown field: ERROR.name"),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(test_eval(input).unwrap_err(), expected, "{}", input);
+        }
+
+        // values that aren't errors are located where they were thrown
+        for (input, span) in [
+            ("throw 5;", Span::new(1, 1)),
+            ("let f = fn() {\n  throw [1];\n};\nf()", Span::new(2, 3)),
+            ("throw error(\"a\");", Span::new(1, 1)),
+        ] {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            Resolver::new().resolve(&program).unwrap();
+            let env = Environment::with_limits(ExecutionLimits::new());
+            assert_eq!(
+                eval(&program, &env).unwrap_err().span(),
+                Some(span),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_generators() {
+        let inputs = vec![
+            (
+                "let g = fn*() { yield 1; yield 2; }; let it = g(); [next(it), next(it), next(it)]",
+                "[1, 2, null]",
+            ),
+            ("let g = fn*(n) { yield n; yield n * 2; }; collect(g(5))", "[5, 10]"),
+            (
+                "let count = fn*(n) { let i = 0; while (i < n) { yield i; i += 1; } }; \
+                 collect(count(4))",
+                "[0, 1, 2, 3]",
+            ),
+            (
+                "let evens = fn*(xs) { for (x in xs) { if (x % 2 == 0) { yield x; } else { yield -x; } } }; \
+                 collect(evens(1..5))",
+                "[-1, 2, -3, 4]",
+            ),
+            (
+                "let pairs = fn*(n) { for (i in 0..n) { for (j in 0..i) { yield [j, i]; } } }; \
+                 collect(pairs(3))",
+                "[[0, 1], [0, 2], [1, 2]]",
+            ),
+            (
+                "let g = fn*() { yield 1; return 5; yield 2; }; collect(g())",
+                "[1]",
+            ),
+            (
+                "let g = fn*() { let i = 0; while (true) { i += 1; if (i == 3) { break; } yield i; } yield 10; }; \
+                 collect(g())",
+                "[1, 2, 10]",
+            ),
+            (
+                "let g = fn*() { for (x in [1, 2, 3, 4]) { if (x == 2) { continue; } yield x; } }; \
+                 collect(g())",
+                "[1, 3, 4]",
+            ),
+            // generators are lazy, so they can be infinite and only run as far as they're asked to
+            (
+                "let naturals = fn*() { let n = 0; while (true) { yield n; n += 1; } }; \
+                 let total = 0; for (n in naturals()) { if (n > 4) { break; } total += n; } total",
+                "10",
+            ),
+            (
+                "let log = []; let g = fn*() { log = log.push(1); yield 1; log = log.push(2); }; \
+                 let it = g(); let before = len(log); next(it); [before, len(log)]",
+                "[0, 1]",
+            ),
+            (
+                "let ran = 0; let g = fn*() { ran += 1; yield ran; }; let it = g(); [ran, next(it), ran]",
+                "[0, 1, 1]",
+            ),
+            (
+                "let fib = fn*() { let a = 0; let b = 1; while (true) { yield a; let t = a + b; a = b; b = t; } }; \
+                 let it = fib(); collect(map(0..8, fn(_) { next(it) }))",
+                "[0, 1, 1, 2, 3, 5, 8, 13]",
+            ),
+            (
+                "let g = fn*(xs) { for (x in xs) { yield x * 10; } }; g([1, 2]).map(fn(x) { x + 1 }).collect()",
+                "[11, 21]",
+            ),
+            ("let g = fn*() {}; [collect(g()), g]", "[[], fn*() {}]"),
+            (
+                "let g = fn*() { yield 1; }; let a = g(); let b = g(); [next(a), next(a), next(b)]",
+                "[1, null, 1]",
+            ),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+
+        // an error finishes the generator
+        let obj = test_eval(
+            "let g = fn*() { yield 1; yield 1 / 0; yield 2; }; let it = g(); next(it); \
+             let caught = try { next(it) } catch (e) { e.message }; [caught, next(it)]",
+        )
+        .unwrap();
+        assert_eq!(obj.to_string(), "[division by zero, null]");
+
+        let errors = vec![
+            (
+                "let g = fn*() { yield next(it); }; let it = g(); next(it)",
+                "generator already running",
+            ),
+            (
+                "let g = fn*(x) { yield x; }; g()",
+                "wrong number of arguments: expected 1, got 0",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(test_eval(input).unwrap_err(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_tail_calls() {
+        // each of these recurses far deeper than the stack could hold without tail calls
+        let inputs = vec![
+            (
+                "let countdown = fn(n) { if (n == 0) { return 0; } countdown(n - 1) }; \
+                 countdown(1000000)",
+                "0",
+            ),
+            (
+                "let sum = fn(n, acc) { if (n == 0) { acc } else { sum(n - 1, acc + n) } }; \
+                 sum(100000, 0)",
+                "5000050000",
+            ),
+            (
+                "let even = fn(n) { if (n == 0) { true } else { return odd(n - 1); } }; \
+                 let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } }; \
+                 [even(100000), odd(100001)]",
+                "[true, true]",
+            ),
+            (
+                "let loop = fn(n) { match (n) { 0 => \"done\", _ => loop(n - 1) } }; loop(100000)",
+                "done",
+            ),
+            (
+                "let count = fn(n, acc = 0) { if (n == 0) { acc } else { count(n - 1, acc: acc + 1) } }; \
+                 count(100000)",
+                "100000",
+            ),
+            // calls that aren't in tail position still work
+            (
+                "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(15)",
+                "610",
+            ),
+            ("let f = fn() { len([1, 2]) }; f()", "2"),
+            (
+                "let f = fn(n) { if (n == 0) { return 0; } try { f(n - 1) } catch (e) { -1 } }; f(3)",
+                "0",
+            ),
+        ];
+        for (input, expected) in inputs {
+            let obj = test_eval(input).unwrap();
+            assert_eq!(obj.to_string(), expected, "{}", input);
+        }
+
+        let errors = vec![
+            (
+                "let f = fn(n) { g(n) }; let g = fn() { 1 }; f(1)",
+                "wrong number of arguments: expected 0, got 1",
+            ),
+            ("let f = fn() { 1() }; f()", "not a function: INTEGER"),
+            (
+                "let f = fn(n) { if (n == 0) { 1 / 0 } else { f(n - 1) } }; f(100000)",
+                "division by zero",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(test_eval(input).unwrap_err(), expected, "{}", input);
+        }
+
+        // errors from a call in tail position are located at the call
+        let obj = test_eval(
+            "let f = fn() { 1 };\nlet g = fn() {\n  f(2)\n};\ntry { g() } catch (e) { [e.line, e.column] }",
+        )
+        .unwrap();
+        assert_eq!(obj.to_string(), "[3, 4]");
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        let inputs = vec![
+            (
+                "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(50)",
+                "50",
+            ),
+            (
+                "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(100)",
+                "maximum recursion depth exceeded",
+            ),
+            (
+                "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } };\ntry { f(100) } catch (e) { [e.message, e.line, e.column] }",
+                "[maximum recursion depth exceeded, 1, 47]",
+            ),
+            // the depth is unwound when the error is caught
+            (
+                "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } };\nlet g = fn() { try { f(100) } catch (e) { f(60) } }; g() + g()",
+                "120",
+            ),
+            // calls in tail position don't nest
+            (
+                "let f = fn(n) { if (n == 0) { 0 } else { f(n - 1) } }; f(1000)",
+                "0",
+            ),
+            (
+                "let f = fn(n) { let g = fn*() { yield f(n - 1); }; g().next() }; f(100)",
+                "maximum recursion depth exceeded",
+            ),
+        ];
+
+        for (input, expected) in inputs {
+            let limits = ExecutionLimits::new().with_max_call_depth(80);
+            let result = match test_eval_with_limits(input, limits) {
+                Ok(obj) => obj.to_string(),
+                Err(err) => err,
+            };
+            assert_eq!(result, expected, "{}", input);
+        }
+
+        // the default depth fits in the stack a spawned thread like this test's gets
+        let input = "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(1000)";
+        assert_eq!(
+            test_eval(input).unwrap_err(),
+            "maximum recursion depth exceeded"
+        );
+    }
+
+    #[test]
+    fn test_execution_limits() {
+        let steps = ExecutionLimits::new().with_max_steps(1000);
+        let memory = || ExecutionLimits::new().with_max_memory(10_000);
+        let past = ExecutionLimits::new().with_deadline(Instant::now());
+        let tests = vec![
+            ("let f = fn(n) { n + 1 }; f(1)", steps.clone(), "2"),
+            ("while (true) {}", steps.clone(), "step limit exceeded"),
+            (
+                "let f = fn() { f() }; f()",
+                steps.clone(),
+                "step limit exceeded",
+            ),
+            (
+                "collect(0..1000000000000)",
+                steps.clone(),
+                "step limit exceeded",
+            ),
+            // limits can't be caught, and leave finally blocks unrun
+            (
+                "try { while (true) {} } catch (e) { 1 }",
+                steps.clone(),
+                "step limit exceeded",
+            ),
+            (
+                "let f = fn() { try { while (true) {} } finally { return 1 } }; f()",
+                steps.clone(),
+                "step limit exceeded",
+            ),
+            ("len(collect(0..100))", memory(), "100"),
+            (
+                "collect(0..1000000000000)",
+                memory(),
+                "memory limit exceeded",
+            ),
+            (
+                "let a = []; for (i in 0..1000) { a = a.push(i) }",
+                memory(),
+                "memory limit exceeded",
+            ),
+            (
+                r#"let s = "s"; while (true) { s = s + s }"#,
+                memory(),
+                "memory limit exceeded",
+            ),
+            (
+                r#"let s = "s"; while (true) { s = "${s}${s}" }"#,
+                memory(),
+                "memory limit exceeded",
+            ),
+            // slices and the copies made when assigning into shared values are allocations too
+            (
+                "let a = collect(0..100); while (true) { a[0..100] }",
+                memory().with_max_steps(100_000),
+                "memory limit exceeded",
+            ),
+            (
+                r#"let s = "abcdefghij"; while (true) { s[0..10] }"#,
+                memory().with_max_steps(100_000),
+                "memory limit exceeded",
+            ),
+            (
+                "let a = collect(0..100); while (true) { let b = a; b[0] = 1 }",
+                memory().with_max_steps(100_000),
+                "memory limit exceeded",
+            ),
+            // and so is writing out a value that holds the same array many times over
+            (
+                r#"let a = [1]; for (i in 0..40) { a = [a, a] } "${a}""#,
+                steps.clone(),
+                "step limit exceeded",
+            ),
+            (
+                r#"let a = [1]; for (i in 0..40) { a = [a, a] } a.join(",")"#,
+                memory(),
+                "memory limit exceeded",
+            ),
+            ("while (true) {}", past, "deadline exceeded"),
+        ];
+        for (input, limits, expected) in tests {
+            let result = match test_eval_with_limits(input, limits) {
+                Ok(obj) => obj.to_string(),
+                Err(err) => err,
+            };
+            assert_eq!(result, expected, "{}", input);
+        }
+
+        let limits = ExecutionLimits::new();
+        let cancel = limits.cancel_handle();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            cancel.cancel();
+        });
+        let program = Parser::new(Lexer::new("1;\nwhile (true) {}"))
+            .parse_program()
+            .unwrap();
+        Resolver::new().resolve(&program).unwrap();
+        let err = eval(&program, &Environment::with_limits(limits)).unwrap_err();
+        canceller.join().unwrap();
+        assert!(
+            matches!(err, Exception::LimitExceeded(LimitExceeded::Cancelled, _)),
+            "{:?}",
+            err
+        );
+        assert_eq!(err.span().map(|span| span.line()), Some(2));
+    }
 }
@@ -1,98 +1,227 @@
+mod symbol;
 mod tests;
 mod token;
 
+pub use symbol::Symbol;
 pub use token::*;
 
+/// Splits source into tokens, which borrow their literals from it. Used as an iterator, it
+/// yields every token up to and including the `Eof` one.
 #[derive(Debug)]
-pub struct Lexer {
-    input: String,
+pub struct Lexer<'src> {
+    input: &'src str,
     position: usize,
     read_position: usize,
     ch: Option<char>,
+    /// One entry per `${` currently being lexed, counting the braces opened inside it so the
+    /// `}` that closes it can be told apart from the others.
+    interpolations: Vec<usize>,
+    line: usize,
+    column: usize,
+    /// Whether the `Eof` token has been yielded by the iterator.
+    finished: bool,
 }
 
-impl Lexer {
-    pub fn new(input: String) -> Self {
+impl<'src> Lexer<'src> {
+    pub fn new(input: &'src str) -> Self {
         let mut this = Self {
             input,
             position: 0,
             read_position: 0,
             ch: None,
+            interpolations: Vec::new(),
+            line: 1,
+            column: 0,
+            finished: false,
         };
         this.read_char();
         this
     }
 
     pub fn read_char(&mut self) {
+        if self.ch == Some('\n') {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
         if self.read_position >= self.input.len() {
             self.ch = None;
         } else {
             self.ch = Some(self.input[self.read_position..].chars().next().unwrap());
         }
         self.position = self.read_position;
-        self.read_position += 1;
+        self.read_position += self.ch.map_or(1, char::len_utf8);
+    }
+
+    pub fn next_token(&mut self) -> Token<'src> {
+        while matches!(self.ch, Some(' ' | '\t' | '\n' | '\r')) {
+            self.read_char();
+        }
+        let span = Span::new(self.line, self.column);
+        self.read_token().with_span(span)
     }
 
-    pub fn next_token(&mut self) -> Token {
+    fn read_token(&mut self) -> Token<'src> {
+        use TokenType::*;
+
         let tok = match self.ch {
-            None => Token::new(TokenType::Eof, "".to_string()),
+            None => Token::new(TokenType::Eof, ""),
             Some(ch) => match ch {
-                '=' => {
-                    if let Some('=') = self.peek_char() {
-                        self.read_char();
-                        Token::new(TokenType::Eq, "==".to_string())
-                    } else {
-                        Token::new(TokenType::Assign, ch.to_string())
-                    }
+                '=' => self.read_operator(&[("==", Eq), ("=>", FatArrow), ("=", Assign)]),
+                '+' => self.read_operator(&[("+=", PlusAssign), ("+", Plus)]),
+                '-' => self.read_operator(&[("-=", MinusAssign), ("-", Minus)]),
+                '!' => self.read_operator(&[("!=", NotEq), ("!", Bang)]),
+                '/' => self.read_operator(&[("/=", SlashAssign), ("/", Slash)]),
+                '*' => self.read_operator(&[
+                    ("**=", PowerAssign),
+                    ("**", Power),
+                    ("*=", AsteriskAssign),
+                    ("*", Asterisk),
+                ]),
+                '%' => self.read_operator(&[("%=", PercentAssign), ("%", Percent)]),
+                '<' => {
+                    self.read_operator(&[("<<=", ShlAssign), ("<<", Shl), ("<=", LtEq), ("<", Lt)])
                 },
-                '+' => Token::new(TokenType::Plus, ch.to_string()),
-                '-' => Token::new(TokenType::Minus, ch.to_string()),
-                '!' => {
-                    if let Some('=') = self.peek_char() {
-                        self.read_char();
-                        Token::new(TokenType::NotEq, "!=".to_string())
-                    } else {
-                        Token::new(TokenType::Bang, ch.to_string())
-                    }
+                '>' => {
+                    self.read_operator(&[(">>=", ShrAssign), (">>", Shr), (">=", GtEq), (">", Gt)])
                 },
-                '/' => Token::new(TokenType::Slash, ch.to_string()),
-                '*' => Token::new(TokenType::Asterisk, ch.to_string()),
-                '<' => Token::new(TokenType::Lt, ch.to_string()),
-                '>' => Token::new(TokenType::Gt, ch.to_string()),
-                ';' => Token::new(TokenType::Semicolon, ch.to_string()),
-                '(' => Token::new(TokenType::Lparen, ch.to_string()),
-                ')' => Token::new(TokenType::Rparen, ch.to_string()),
-                ',' => Token::new(TokenType::Comma, ch.to_string()),
-                '{' => Token::new(TokenType::Lbrace, ch.to_string()),
-                '}' => Token::new(TokenType::Rbrace, ch.to_string()),
-                'a'..='z' | 'A'..='Z' | '_' => {
-                    return match self.read_ident_literal() {
-                        "fn" => Token::new(TokenType::Function, "fn"),
-                        "let" => Token::new(TokenType::Let, "let"),
-                        "true" => Token::new(TokenType::True, "true"),
-                        "false" => Token::new(TokenType::False, "false"),
-                        "if" => Token::new(TokenType::If, "if"),
-                        "else" => Token::new(TokenType::Else, "else"),
-                        "return" => Token::new(TokenType::Return, "return"),
-                        ident => Token::new(TokenType::Ident, ident),
+                '&' => self.read_operator(&[("&&", And), ("&=", BitAndAssign), ("&", BitAnd)]),
+                '|' => self.read_operator(&[("||", Or), ("|=", BitOrAssign), ("|", BitOr)]),
+                '^' => self.read_operator(&[("^=", BitXorAssign), ("^", BitXor)]),
+                '~' => Token::new(TokenType::Tilde, "~"),
+                '?' => self.read_operator(&[
+                    ("??", Nullish),
+                    ("?.", QuestionDot),
+                    ("?[", QuestionLbracket),
+                    ("?", Illegal),
+                ]),
+                '.' => self.read_operator(&[
+                    ("...", Ellipsis),
+                    ("..=", DotDotEq),
+                    ("..", DotDot),
+                    (".", Dot),
+                ]),
+                ':' => Token::new(TokenType::Colon, ":"),
+                '"' => self.read_string_literal(true),
+                ';' => Token::new(TokenType::Semicolon, ";"),
+                '(' => Token::new(TokenType::Lparen, "("),
+                ')' => Token::new(TokenType::Rparen, ")"),
+                ',' => Token::new(TokenType::Comma, ","),
+                '{' => {
+                    if let Some(depth) = self.interpolations.last_mut() {
+                        *depth += 1;
                     }
+                    Token::new(TokenType::Lbrace, "{")
                 },
-                ' ' | '\t' | '\n' | '\r' => {
-                    self.read_char();
-                    return self.next_token();
+                '}' => match self.interpolations.last_mut() {
+                    Some(0) => {
+                        self.interpolations.pop();
+                        self.read_string_literal(false)
+                    },
+                    Some(depth) => {
+                        *depth -= 1;
+                        Token::new(TokenType::Rbrace, "}")
+                    },
+                    None => Token::new(TokenType::Rbrace, "}"),
+                },
+                '[' => Token::new(TokenType::Lbracket, "["),
+                ']' => Token::new(TokenType::Rbracket, "]"),
+                'a'..='z' | 'A'..='Z' | '_' => {
+                    let literal = self.read_ident_literal();
+                    let token_type = match literal {
+                        "fn" => TokenType::Function,
+                        "let" => TokenType::Let,
+                        "true" => TokenType::True,
+                        "false" => TokenType::False,
+                        "null" => TokenType::Null,
+                        "if" => TokenType::If,
+                        "else" => TokenType::Else,
+                        "return" => TokenType::Return,
+                        "while" => TokenType::While,
+                        "for" => TokenType::For,
+                        "in" => TokenType::In,
+                        "break" => TokenType::Break,
+                        "continue" => TokenType::Continue,
+                        "match" => TokenType::Match,
+                        "struct" => TokenType::Struct,
+                        "enum" => TokenType::Enum,
+                        "throw" => TokenType::Throw,
+                        "try" => TokenType::Try,
+                        "catch" => TokenType::Catch,
+                        "finally" => TokenType::Finally,
+                        "yield" => TokenType::Yield,
+                        _ => TokenType::Ident,
+                    };
+                    return Token::new(token_type, literal);
                 },
                 '0'..='9' => return Token::new(TokenType::Int, self.read_int_literal()),
-                _ => Token::new(TokenType::Illegal, ch.to_string()),
+                _ => {
+                    let end = self.position + ch.len_utf8();
+                    Token::new(TokenType::Illegal, &self.input[self.position..end])
+                },
             },
         };
         self.read_char();
         tok
     }
 
-    pub fn read_ident_literal(&mut self) -> &str {
+    /// Reads the longest operator in `operators` (which must be ordered longest first) that
+    /// the input continues with at the current character.
+    pub fn read_operator(&mut self, operators: &[(&'static str, TokenType)]) -> Token<'src> {
+        let (literal, token_type) = operators
+            .iter()
+            .find(|(op, _)| self.input[self.position..].starts_with(op))
+            .unwrap();
+        for _ in 1..literal.len() {
+            self.read_char();
+        }
+        Token::new(*token_type, *literal)
+    }
+
+    /// Reads the part of a string literal following its opening `"` (when `start` is set) or
+    /// the `}` closing an interpolation, up to and including the closing `"` or the next `${`.
+    /// The token's literal is the part as it's written, with its escapes left in.
+    pub fn read_string_literal(&mut self, start: bool) -> Token<'src> {
+        let position = self.position + 1;
+        loop {
+            self.read_char();
+            match self.ch {
+                Some('"') => break,
+                None => return Token::new(TokenType::Illegal, "unterminated string"),
+                Some('$') if self.peek_char() == Some('{') => {
+                    let literal = &self.input[position..self.position];
+                    self.read_char();
+                    self.interpolations.push(0);
+                    let token_type = if start {
+                        TokenType::StrStart
+                    } else {
+                        TokenType::StrMiddle
+                    };
+                    return Token::new(token_type, literal);
+                },
+                Some('\\') => {
+                    self.read_char();
+                    if self.ch.is_none() {
+                        return Token::new(TokenType::Illegal, "unterminated string");
+                    }
+                },
+                Some(_) => {},
+            }
+        }
+        let literal = &self.input[position..self.position];
+        let token_type = if start {
+            TokenType::Str
+        } else {
+            TokenType::StrEnd
+        };
+        Token::new(token_type, literal)
+    }
+
+    pub fn read_ident_literal(&mut self) -> &'src str {
         let position = self.position;
         while let Some(ch) = self.ch {
-            if ch.is_alphanumeric() {
+            if ch.is_alphanumeric() || ch == '_' {
                 self.read_char();
             } else {
                 break;
@@ -101,7 +230,7 @@ impl Lexer {
         &self.input[position..self.position]
     }
 
-    pub fn read_int_literal(&mut self) -> &str {
+    pub fn read_int_literal(&mut self) -> &'src str {
         let position = self.position;
         while let Some(ch) = self.ch {
             if ch.is_numeric() {
@@ -121,3 +250,50 @@ impl Lexer {
         }
     }
 }
+
+impl<'src> Iterator for Lexer<'src> {
+    type Item = Token<'src>;
+
+    fn next(&mut self) -> Option<Token<'src>> {
+        if self.finished {
+            return None;
+        }
+        let token = self.next_token();
+        self.finished = token.token_type() == TokenType::Eof;
+        Some(token)
+    }
+}
+
+/// The value of the contents of a string literal as they're written, starting at `start`, with
+/// their escapes replaced by the characters they stand for. Escapes other than `\n`, `\t`,
+/// `\r`, `\"`, `\\` and `\$` are errors.
+pub fn unescape(literal: &str, start: Span) -> Result<String, String> {
+    let mut value = String::with_capacity(literal.len());
+    let (mut line, mut column) = (start.line(), start.column());
+    let mut chars = literal.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            value.push(ch);
+            if ch == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some(ch @ ('"' | '\\' | '$')) => value.push(ch),
+            Some(ch) => {
+                let span = Span::new(line, column);
+                return Err(format!("unknown escape sequence \\{} at {}", ch, span));
+            },
+            None => {},
+        }
+        column += 2;
+    }
+    Ok(value)
+}
@@ -0,0 +1,121 @@
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    sync::{LazyLock, Mutex},
+};
+
+/// A string interned for the life of the program, so that copying and comparing it is as cheap
+/// as for an integer. Only names are interned, since there's a bounded number of those in any
+/// program, and interned strings are never freed.
+#[derive(Copy, Clone)]
+pub struct Symbol {
+    index: u32,
+    /// The string itself, kept alongside the index so reading it doesn't need the interner.
+    string: &'static str,
+}
+
+/// Every string interned so far, which are leaked since symbols can be held anywhere.
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>,
+}
+
+static INTERNER: LazyLock<Mutex<Interner>> = LazyLock::new(|| {
+    let symbols = KEYWORDS
+        .iter()
+        .map(|symbol| (symbol.string, *symbol))
+        .collect();
+    Mutex::new(Interner { symbols })
+});
+
+impl Interner {
+    fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(string) {
+            return *symbol;
+        }
+        let string: &'static str = Box::leak(string.into());
+        let symbol = Symbol {
+            index: self.symbols.len() as u32,
+            string,
+        };
+        self.symbols.insert(string, symbol);
+        symbol
+    }
+}
+
+/// Defines the symbols interned before any others, so they can be matched on as constants.
+macro_rules! keywords {
+    ($($name:ident => $string:literal,)*) => {
+        #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+        enum Keyword {
+            $($name),*
+        }
+
+        impl Symbol {
+            $(pub const $name: Symbol = Symbol { index: Keyword::$name as u32, string: $string };)*
+        }
+
+        const KEYWORDS: &[Symbol] = &[$(Symbol::$name),*];
+    };
+}
+
+keywords! {
+    FN => "fn",
+    LET => "let",
+    TRUE => "true",
+    FALSE => "false",
+    NULL => "null",
+    IF => "if",
+    ELSE => "else",
+    RETURN => "return",
+    WHILE => "while",
+    FOR => "for",
+    IN => "in",
+    BREAK => "break",
+    CONTINUE => "continue",
+    MATCH => "match",
+    STRUCT => "struct",
+    ENUM => "enum",
+    THROW => "throw",
+    TRY => "try",
+    CATCH => "catch",
+    FINALLY => "finally",
+    YIELD => "yield",
+    UNDERSCORE => "_",
+}
+
+impl Symbol {
+    pub fn intern(string: &str) -> Self {
+        INTERNER.lock().unwrap().intern(string)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.string
+    }
+}
+
+// a string is only ever interned once, so its index says which string it is
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
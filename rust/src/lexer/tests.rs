@@ -103,11 +103,530 @@ if (5 < 10) {
             Token::new(Eof, ""),
         ];
 
-        let mut lexer = Lexer::new(input);
+        let mut lexer = Lexer::new(&input);
 
         for test in tests.iter() {
             let token = lexer.next_token();
             assert_eq!(token, *test);
         }
     }
+
+    #[test]
+    fn test_next_token_operators() {
+        let input = String::from("a <= b >= c % d ** e && f || g & h | i ^ j << k >> l ~m * n");
+
+        let tests = [
+            Token::new(Ident, "a"),
+            Token::new(LtEq, "<="),
+            Token::new(Ident, "b"),
+            Token::new(GtEq, ">="),
+            Token::new(Ident, "c"),
+            Token::new(Percent, "%"),
+            Token::new(Ident, "d"),
+            Token::new(Power, "**"),
+            Token::new(Ident, "e"),
+            Token::new(And, "&&"),
+            Token::new(Ident, "f"),
+            Token::new(Or, "||"),
+            Token::new(Ident, "g"),
+            Token::new(BitAnd, "&"),
+            Token::new(Ident, "h"),
+            Token::new(BitOr, "|"),
+            Token::new(Ident, "i"),
+            Token::new(BitXor, "^"),
+            Token::new(Ident, "j"),
+            Token::new(Shl, "<<"),
+            Token::new(Ident, "k"),
+            Token::new(Shr, ">>"),
+            Token::new(Ident, "l"),
+            Token::new(Tilde, "~"),
+            Token::new(Ident, "m"),
+            Token::new(Asterisk, "*"),
+            Token::new(Ident, "n"),
+            Token::new(Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(&input);
+
+        for test in tests.iter() {
+            let token = lexer.next_token();
+            assert_eq!(token, *test);
+        }
+    }
+
+    #[test]
+    fn test_next_token_loops() {
+        let input = String::from(
+            r#"while (true) { break; }
+for (x in [1, 2]) { continue; }"#,
+        );
+
+        let tests = [
+            Token::new(While, "while"),
+            Token::new(Lparen, "("),
+            Token::new(True, "true"),
+            Token::new(Rparen, ")"),
+            Token::new(Lbrace, "{"),
+            Token::new(Break, "break"),
+            Token::new(Semicolon, ";"),
+            Token::new(Rbrace, "}"),
+            Token::new(For, "for"),
+            Token::new(Lparen, "("),
+            Token::new(Ident, "x"),
+            Token::new(In, "in"),
+            Token::new(Lbracket, "["),
+            Token::new(Int, "1"),
+            Token::new(Comma, ","),
+            Token::new(Int, "2"),
+            Token::new(Rbracket, "]"),
+            Token::new(Rparen, ")"),
+            Token::new(Lbrace, "{"),
+            Token::new(Continue, "continue"),
+            Token::new(Semicolon, ";"),
+            Token::new(Rbrace, "}"),
+            Token::new(Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(&input);
+
+        for test in tests.iter() {
+            let token = lexer.next_token();
+            assert_eq!(token, *test);
+        }
+    }
+
+    #[test]
+    fn test_next_token_assignment() {
+        let input = String::from(
+            r#"x += 1; x -= 1; x *= 1; x /= 1; x %= 1; x **= 1;
+x &= 1; x |= 1; x ^= 1; x <<= 1; x >>= 1;
+h["foo bar"] = {"a": 1};"#,
+        );
+
+        let mut tests = Vec::new();
+        for (op, t) in [
+            ("+=", PlusAssign),
+            ("-=", MinusAssign),
+            ("*=", AsteriskAssign),
+            ("/=", SlashAssign),
+            ("%=", PercentAssign),
+            ("**=", PowerAssign),
+            ("&=", BitAndAssign),
+            ("|=", BitOrAssign),
+            ("^=", BitXorAssign),
+            ("<<=", ShlAssign),
+            (">>=", ShrAssign),
+        ] {
+            tests.push(Token::new(Ident, "x"));
+            tests.push(Token::new(t, op));
+            tests.push(Token::new(Int, "1"));
+            tests.push(Token::new(Semicolon, ";"));
+        }
+        tests.extend([
+            Token::new(Ident, "h"),
+            Token::new(Lbracket, "["),
+            Token::new(Str, "foo bar"),
+            Token::new(Rbracket, "]"),
+            Token::new(Assign, "="),
+            Token::new(Lbrace, "{"),
+            Token::new(Str, "a"),
+            Token::new(Colon, ":"),
+            Token::new(Int, "1"),
+            Token::new(Rbrace, "}"),
+            Token::new(Semicolon, ";"),
+            Token::new(Eof, ""),
+        ]);
+
+        let mut lexer = Lexer::new(&input);
+
+        for test in tests.iter() {
+            let token = lexer.next_token();
+            assert_eq!(token, *test);
+        }
+    }
+
+    #[test]
+    fn test_next_token_nullish() {
+        let input = String::from("null ?? a?.b?[0] ? c");
+
+        let tests = [
+            Token::new(Null, "null"),
+            Token::new(Nullish, "??"),
+            Token::new(Ident, "a"),
+            Token::new(QuestionDot, "?."),
+            Token::new(Ident, "b"),
+            Token::new(QuestionLbracket, "?["),
+            Token::new(Int, "0"),
+            Token::new(Rbracket, "]"),
+            Token::new(Illegal, "?"),
+            Token::new(Ident, "c"),
+            Token::new(Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(&input);
+
+        for test in tests.iter() {
+            let token = lexer.next_token();
+            assert_eq!(token, *test);
+        }
+    }
+
+    #[test]
+    fn test_next_token_match() {
+        let input = String::from("match (x) { [a, ..rest] => a, _ => 0 }");
+
+        let tests = [
+            Token::new(Match, "match"),
+            Token::new(Lparen, "("),
+            Token::new(Ident, "x"),
+            Token::new(Rparen, ")"),
+            Token::new(Lbrace, "{"),
+            Token::new(Lbracket, "["),
+            Token::new(Ident, "a"),
+            Token::new(Comma, ","),
+            Token::new(DotDot, ".."),
+            Token::new(Ident, "rest"),
+            Token::new(Rbracket, "]"),
+            Token::new(FatArrow, "=>"),
+            Token::new(Ident, "a"),
+            Token::new(Comma, ","),
+            Token::new(Ident, "_"),
+            Token::new(FatArrow, "=>"),
+            Token::new(Int, "0"),
+            Token::new(Rbrace, "}"),
+            Token::new(Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(&input);
+
+        for test in tests.iter() {
+            let token = lexer.next_token();
+            assert_eq!(token, *test);
+        }
+    }
+
+    #[test]
+    fn test_next_token_interpolated_strings() {
+        let input = String::from(
+            r#""hi ${name}, ${ {"a": 1}["a"] + 1 }!" "${"in ${x}"}" "a\"\$\{b}\n" "héllo""#,
+        );
+
+        let tests = [
+            Token::new(StrStart, "hi "),
+            Token::new(Ident, "name"),
+            Token::new(StrMiddle, ", "),
+            Token::new(Lbrace, "{"),
+            Token::new(Str, "a"),
+            Token::new(Colon, ":"),
+            Token::new(Int, "1"),
+            Token::new(Rbrace, "}"),
+            Token::new(Lbracket, "["),
+            Token::new(Str, "a"),
+            Token::new(Rbracket, "]"),
+            Token::new(Plus, "+"),
+            Token::new(Int, "1"),
+            Token::new(StrEnd, "!"),
+            Token::new(StrStart, ""),
+            Token::new(StrStart, "in "),
+            Token::new(Ident, "x"),
+            Token::new(StrEnd, ""),
+            Token::new(StrEnd, ""),
+            Token::new(Str, r#"a\"\$\{b}\n"#),
+            Token::new(Str, "héllo"),
+            Token::new(Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(&input);
+
+        for test in tests.iter() {
+            let token = lexer.next_token();
+            assert_eq!(token, *test);
+        }
+    }
+
+    #[test]
+    fn test_next_token_ranges() {
+        let input = String::from("0..n 1..=10 a[1..]");
+
+        let tests = [
+            Token::new(Int, "0"),
+            Token::new(DotDot, ".."),
+            Token::new(Ident, "n"),
+            Token::new(Int, "1"),
+            Token::new(DotDotEq, "..="),
+            Token::new(Int, "10"),
+            Token::new(Ident, "a"),
+            Token::new(Lbracket, "["),
+            Token::new(Int, "1"),
+            Token::new(DotDot, ".."),
+            Token::new(Rbracket, "]"),
+            Token::new(Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(&input);
+
+        for test in tests.iter() {
+            let token = lexer.next_token();
+            assert_eq!(token, *test);
+        }
+    }
+
+    #[test]
+    fn test_next_token_member_access() {
+        let input = String::from("a.b.len() x?.y 0..1");
+
+        let tests = [
+            Token::new(Ident, "a"),
+            Token::new(Dot, "."),
+            Token::new(Ident, "b"),
+            Token::new(Dot, "."),
+            Token::new(Ident, "len"),
+            Token::new(Lparen, "("),
+            Token::new(Rparen, ")"),
+            Token::new(Ident, "x"),
+            Token::new(QuestionDot, "?."),
+            Token::new(Ident, "y"),
+            Token::new(Int, "0"),
+            Token::new(DotDot, ".."),
+            Token::new(Int, "1"),
+            Token::new(Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(&input);
+
+        for test in tests.iter() {
+            let token = lexer.next_token();
+            assert_eq!(token, *test);
+        }
+    }
+
+    #[test]
+    fn test_next_token_unterminated_strings() {
+        let tests = [
+            (r#""abc"#, vec![Token::new(Illegal, "unterminated string")]),
+            (
+                r#""hi ${x}"#,
+                vec![
+                    Token::new(StrStart, "hi "),
+                    Token::new(Ident, "x"),
+                    Token::new(Illegal, "unterminated string"),
+                ],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut lexer = Lexer::new(input);
+            for test in expected.iter().chain([&Token::new(Eof, "")]) {
+                assert_eq!(lexer.next_token(), *test, "{}", input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_next_token_struct() {
+        let input = String::from("struct Point { x, y } Point { x: 1, y }");
+
+        let tests = [
+            Token::new(Struct, "struct"),
+            Token::new(Ident, "Point"),
+            Token::new(Lbrace, "{"),
+            Token::new(Ident, "x"),
+            Token::new(Comma, ","),
+            Token::new(Ident, "y"),
+            Token::new(Rbrace, "}"),
+            Token::new(Ident, "Point"),
+            Token::new(Lbrace, "{"),
+            Token::new(Ident, "x"),
+            Token::new(Colon, ":"),
+            Token::new(Int, "1"),
+            Token::new(Comma, ","),
+            Token::new(Ident, "y"),
+            Token::new(Rbrace, "}"),
+            Token::new(Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(&input);
+
+        for test in tests.iter() {
+            let token = lexer.next_token();
+            assert_eq!(token, *test);
+        }
+    }
+
+    #[test]
+    fn test_next_token_exceptions() {
+        let input = String::from("try { throw e; } catch (e) {} finally {}");
+
+        let tests = [
+            Token::new(Try, "try"),
+            Token::new(Lbrace, "{"),
+            Token::new(Throw, "throw"),
+            Token::new(Ident, "e"),
+            Token::new(Semicolon, ";"),
+            Token::new(Rbrace, "}"),
+            Token::new(Catch, "catch"),
+            Token::new(Lparen, "("),
+            Token::new(Ident, "e"),
+            Token::new(Rparen, ")"),
+            Token::new(Lbrace, "{"),
+            Token::new(Rbrace, "}"),
+            Token::new(Finally, "finally"),
+            Token::new(Lbrace, "{"),
+            Token::new(Rbrace, "}"),
+            Token::new(Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(&input);
+
+        for test in tests.iter() {
+            let token = lexer.next_token();
+            assert_eq!(token, *test);
+        }
+    }
+
+    #[test]
+    fn test_next_token_spans() {
+        let input = String::from("let x = 5;\n  x + 10\n");
+
+        let tests = [
+            (1, 1),
+            (1, 5),
+            (1, 7),
+            (1, 9),
+            (1, 10),
+            (2, 3),
+            (2, 5),
+            (2, 7),
+            (3, 1),
+        ];
+
+        let mut lexer = Lexer::new(&input);
+
+        for (line, column) in tests {
+            let token = lexer.next_token();
+            assert_eq!(token.span(), Span::new(line, column), "{:?}", token);
+        }
+    }
+
+    #[test]
+    fn test_next_token_generators() {
+        let input = String::from("fn*() { yield 1; }");
+
+        let tests = [
+            Token::new(Function, "fn"),
+            Token::new(Asterisk, "*"),
+            Token::new(Lparen, "("),
+            Token::new(Rparen, ")"),
+            Token::new(Lbrace, "{"),
+            Token::new(Yield, "yield"),
+            Token::new(Int, "1"),
+            Token::new(Semicolon, ";"),
+            Token::new(Rbrace, "}"),
+            Token::new(Eof, ""),
+        ];
+
+        let mut lexer = Lexer::new(&input);
+
+        for test in tests.iter() {
+            let token = lexer.next_token();
+            assert_eq!(token, *test);
+        }
+    }
+
+    #[test]
+    fn test_symbols() {
+        let foo = Symbol::intern("foo");
+        assert_eq!(foo, Symbol::intern(&String::from("foo")));
+        assert_ne!(foo, Symbol::intern("bar"));
+        assert_eq!(foo.as_str(), "foo");
+        assert_eq!(foo.to_string(), "foo");
+        // keywords are interned before anything else
+        assert_eq!(Symbol::intern("while"), Symbol::WHILE);
+        assert_eq!(Symbol::YIELD.as_str(), "yield");
+
+        let symbols = Lexer::new("let foo = foo;")
+            .map(|token| token.symbol())
+            .collect::<Vec<_>>();
+        assert_eq!(symbols[0], Symbol::LET);
+        assert_eq!(symbols[1], foo);
+        assert_eq!(symbols[3], foo);
+
+        // owned tokens outlive the source they were read from, with names interned and the
+        // contents of literals copied
+        let tokens = {
+            let source = String::from("foo \"bar\"");
+            Lexer::new(&source)
+                .map(Token::into_owned)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tokens[0], Token::new(Ident, "foo"));
+        assert_eq!(tokens[0].literal().as_ptr(), foo.as_str().as_ptr());
+        assert_eq!(tokens[1], Token::new(Str, "bar"));
+    }
+
+    #[test]
+    fn test_lexer_iterator() {
+        let input = "let x = 5; x";
+        let tokens = Lexer::new(input).collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [
+                Token::new(Let, "let"),
+                Token::new(Ident, "x"),
+                Token::new(Assign, "="),
+                Token::new(Int, "5"),
+                Token::new(Semicolon, ";"),
+                Token::new(Ident, "x"),
+                Token::new(Eof, ""),
+            ]
+        );
+        // names and numbers are borrowed from the input rather than copied
+        let range = input.as_bytes().as_ptr_range();
+        assert!(tokens
+            .iter()
+            .filter(|token| matches!(token.token_type(), Let | Ident | Int))
+            .all(|token| range.contains(&token.literal().as_ptr())));
+
+        let mut lexer = Lexer::new("");
+        assert_eq!(lexer.next(), Some(Token::new(Eof, "")));
+        assert_eq!(lexer.next(), None);
+
+        let idents = Lexer::new("a + b * (c - a)")
+            .filter(|token| token.token_type() == Ident)
+            .map(|token| token.literal().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(idents, ["a", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let input = r#""a\"b\n" "${x}\$\{\q}" "c\"#;
+        let tokens = Lexer::new(input).collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [
+                Token::new(Str, r#"a\"b\n"#),
+                Token::new(StrStart, ""),
+                Token::new(Ident, "x"),
+                Token::new(StrEnd, r"\$\{\q}"),
+                Token::new(Illegal, "unterminated string"),
+                Token::new(Eof, ""),
+            ]
+        );
+        let start = Span::new(1, 2);
+        let values = [r#"a\"b\n"#, r"\$\\\t", r"c\"].map(|s| unescape(s, start));
+        assert_eq!(
+            values,
+            [Ok("a\"b\n".into()), Ok("$\\\t".into()), Ok("c".into())]
+        );
+        // other escapes are errors, located at their backslash
+        assert_eq!(
+            unescape(r"\$\{", start),
+            Err(r"unknown escape sequence \{ at 1:4".into())
+        );
+        assert_eq!(
+            unescape("ab\ncd \\u{41}", start),
+            Err(r"unknown escape sequence \u at 2:4".into())
+        );
+    }
 }
@@ -1,10 +1,29 @@
+use std::borrow::Cow;
+
+use super::Symbol;
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum TokenType {
     Illegal,
     Eof,
     Ident,
     Int,
+    Str,
+    StrStart,
+    StrMiddle,
+    StrEnd,
     Assign,
+    PlusAssign,
+    MinusAssign,
+    AsteriskAssign,
+    SlashAssign,
+    PercentAssign,
+    PowerAssign,
+    BitAndAssign,
+    BitOrAssign,
+    BitXorAssign,
+    ShlAssign,
+    ShrAssign,
     Plus,
     Minus,
     Bang,
@@ -13,36 +32,207 @@ pub enum TokenType {
     Lt,
     Gt,
     Comma,
+    Colon,
     Semicolon,
     Lparen,
     Rparen,
     Lbrace,
     Rbrace,
+    Lbracket,
+    Rbracket,
     Function,
     Let,
     True,
     False,
+    Null,
     If,
     Else,
     Return,
+    While,
+    For,
+    In,
+    Break,
+    Continue,
+    Match,
+    Struct,
+    Enum,
+    Throw,
+    Try,
+    Catch,
+    Finally,
+    Yield,
     Eq,
     NotEq,
+    LtEq,
+    GtEq,
+    Percent,
+    Power,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Tilde,
+    Nullish,
+    QuestionDot,
+    QuestionLbracket,
+    FatArrow,
+    Dot,
+    DotDot,
+    DotDotEq,
+    Ellipsis,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Token {
+impl TokenType {
+    /// The symbol for tokens of this type, if they're keywords.
+    fn keyword(self) -> Option<Symbol> {
+        use TokenType::*;
+
+        Some(match self {
+            Function => Symbol::FN,
+            Let => Symbol::LET,
+            True => Symbol::TRUE,
+            False => Symbol::FALSE,
+            Null => Symbol::NULL,
+            If => Symbol::IF,
+            Else => Symbol::ELSE,
+            Return => Symbol::RETURN,
+            While => Symbol::WHILE,
+            For => Symbol::FOR,
+            In => Symbol::IN,
+            Break => Symbol::BREAK,
+            Continue => Symbol::CONTINUE,
+            Match => Symbol::MATCH,
+            Struct => Symbol::STRUCT,
+            Enum => Symbol::ENUM,
+            Throw => Symbol::THROW,
+            Try => Symbol::TRY,
+            Catch => Symbol::CATCH,
+            Finally => Symbol::FINALLY,
+            Yield => Symbol::YIELD,
+            _ => return None,
+        })
+    }
+
+    /// The literal every token of this type is read from, if there's only one.
+    fn fixed_literal(self) -> Option<&'static str> {
+        use TokenType::*;
+
+        if let Some(keyword) = self.keyword() {
+            return Some(keyword.as_str());
+        }
+        Some(match self {
+            Eof => "",
+            Assign => "=",
+            PlusAssign => "+=",
+            MinusAssign => "-=",
+            AsteriskAssign => "*=",
+            SlashAssign => "/=",
+            PercentAssign => "%=",
+            PowerAssign => "**=",
+            BitAndAssign => "&=",
+            BitOrAssign => "|=",
+            BitXorAssign => "^=",
+            ShlAssign => "<<=",
+            ShrAssign => ">>=",
+            Plus => "+",
+            Minus => "-",
+            Bang => "!",
+            Asterisk => "*",
+            Slash => "/",
+            Lt => "<",
+            Gt => ">",
+            Comma => ",",
+            Colon => ":",
+            Semicolon => ";",
+            Lparen => "(",
+            Rparen => ")",
+            Lbrace => "{",
+            Rbrace => "}",
+            Lbracket => "[",
+            Rbracket => "]",
+            Eq => "==",
+            NotEq => "!=",
+            LtEq => "<=",
+            GtEq => ">=",
+            Percent => "%",
+            Power => "**",
+            And => "&&",
+            Or => "||",
+            BitAnd => "&",
+            BitOr => "|",
+            BitXor => "^",
+            Shl => "<<",
+            Shr => ">>",
+            Tilde => "~",
+            Nullish => "??",
+            QuestionDot => "?.",
+            QuestionLbracket => "?[",
+            FatArrow => "=>",
+            Dot => ".",
+            DotDot => "..",
+            DotDotEq => "..=",
+            Ellipsis => "...",
+            _ => return None,
+        })
+    }
+}
+
+/// Where something starts in the source, counting lines and columns from 1.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Span {
+    line: usize,
+    column: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize) -> Self {
+        Span { line, column }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A token read from source, which borrows its literal from it until it's made to own it.
+#[derive(Clone)]
+pub struct Token<'src> {
     pub token_type: TokenType,
-    literal: String,
+    literal: Cow<'src, str>,
+    span: Span,
 }
 
-impl Token {
-    pub fn new(token_type: TokenType, literal: impl ToString) -> Self {
+impl<'src> Token<'src> {
+    pub fn new(token_type: TokenType, literal: impl Into<Cow<'src, str>>) -> Self {
         Token {
             token_type,
-            literal: literal.to_string(),
+            literal: literal.into(),
+            span: Span::default(),
         }
     }
 
+    /// A token for a literal that's already been interned.
+    pub fn from_symbol(token_type: TokenType, literal: Symbol) -> Self {
+        Token::new(token_type, literal.as_str())
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        Token { span, ..self }
+    }
+
     pub fn token_type(&self) -> TokenType {
         self.token_type
     }
@@ -50,4 +240,52 @@ impl Token {
     pub fn literal(&self) -> &str {
         &self.literal
     }
+
+    pub fn symbol(&self) -> Symbol {
+        match self.token_type.keyword() {
+            Some(keyword) => keyword,
+            None => Symbol::intern(&self.literal),
+        }
+    }
+
+    /// The token with a literal of its own, so it can be kept after the source it was read
+    /// from is gone. Only identifiers are interned. Keywords and operators are always read
+    /// from the same literal, and the contents of integer and string literals are copied, since
+    /// there's no bound on how many different ones there can be.
+    pub fn into_owned(self) -> Token<'static> {
+        let fixed = self.token_type.fixed_literal();
+        let literal = match self.token_type {
+            TokenType::Ident => Cow::Borrowed(self.symbol().as_str()),
+            _ => match fixed.filter(|fixed| *fixed == self.literal) {
+                Some(fixed) => Cow::Borrowed(fixed),
+                None => Cow::Owned(self.literal.into_owned()),
+            },
+        };
+        Token {
+            token_type: self.token_type,
+            literal,
+            span: self.span,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+// the span only matters for reporting errors, so tokens are compared and printed by what they
+// are rather than where they are
+impl PartialEq for Token<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type && self.literal == other.literal
+    }
+}
+
+impl std::fmt::Debug for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Token")
+            .field("token_type", &self.token_type)
+            .field("literal", &self.literal)
+            .finish()
+    }
 }
@@ -1,15 +1,152 @@
+#![allow(clippy::module_inception)]
+
 mod ast;
+mod compiler;
 mod eval;
 mod lexer;
 mod object;
 mod parser;
 mod repl;
+mod vm;
+
+use std::io::{ErrorKind, Write};
 
 pub use ast::*;
+pub use compiler::*;
 pub use lexer::*;
+pub use object::{CancelHandle, ExecutionLimits, LimitExceeded};
 pub use parser::*;
 pub use repl::*;
+pub use vm::Vm;
+
+const USAGE: &str =
+    "usage: interpreter-book [--engine=vm|tree] [--optimize] [--disassemble | --compile=OUT.mkc] [FILE]";
+
+/// What's done with the program in the file given.
+enum Mode {
+    Run,
+    /// Prints the bytecode the program compiles to.
+    Disassemble,
+    /// Writes the bytecode the program compiles to to a file.
+    Compile(String),
+}
 
 fn main() {
-    start_repl(std::io::stdin(), std::io::stdout());
+    let mut engine = None;
+    let mut mode = Mode::Run;
+    let mut optimized = false;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        if let Some(name) = arg.strip_prefix("--engine=") {
+            engine = Some(name.parse().unwrap_or_else(|err| {
+                eprintln!("{err}\n{USAGE}");
+                std::process::exit(2);
+            }));
+        } else if arg == "--optimize" {
+            optimized = true;
+        } else if arg == "--disassemble" && matches!(mode, Mode::Run) {
+            mode = Mode::Disassemble;
+        } else if let Some(out) = arg
+            .strip_prefix("--compile=")
+            .filter(|_| matches!(mode, Mode::Run))
+        {
+            mode = Mode::Compile(out.to_string());
+        } else if path.is_none() && !arg.starts_with('-') {
+            path = Some(arg);
+        } else {
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    }
+    let input = path.map(|path| {
+        let read = if path.ends_with(".mkc") {
+            if engine == Some(Engine::Tree) {
+                eprintln!("compiled programs can only be run by the vm");
+                std::process::exit(2);
+            }
+            std::fs::read(&path).map(|bytes| Input::Compiled(path.clone(), bytes))
+        } else {
+            std::fs::read_to_string(&path).map(Input::Source)
+        };
+        read.unwrap_or_else(|err| {
+            eprintln!("cannot read {path}: {err}");
+            std::process::exit(1);
+        })
+    });
+    if input.is_none() && !matches!(mode, Mode::Run) {
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    }
+    let engine = engine.unwrap_or_default();
+
+    // evaluation recurses for nested calls, so it runs on a thread with a larger stack
+    let succeeded = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || match input {
+            Some(input) => run(input, engine, optimized, mode),
+            None => {
+                start_repl(std::io::stdin(), std::io::stdout(), engine, optimized);
+                true
+            },
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    if !succeeded {
+        std::process::exit(1);
+    }
+}
+
+/// The contents of the file given.
+enum Input {
+    Source(String),
+    /// A program compiled to a `.mkc` file, and the file's path.
+    Compiled(String, Vec<u8>),
+}
+
+/// Runs, disassembles or compiles the program in a file, depending on `mode`.
+fn run(input: Input, engine: Engine, optimized: bool, mode: Mode) -> bool {
+    let bytecode = match (input, &mode) {
+        (Input::Source(source), Mode::Run) => {
+            return run_file(
+                source,
+                engine,
+                optimized,
+                std::io::stdout(),
+                std::io::stderr(),
+            )
+        },
+        (Input::Source(source), _) => compile_file(source, optimized),
+        (Input::Compiled(path, bytes), _) => {
+            Bytecode::from_bytes(&bytes).map_err(|err| format!("cannot load {path}: {err}"))
+        },
+    };
+    let bytecode = match bytecode {
+        Ok(bytecode) => bytecode,
+        Err(err) => {
+            eprintln!("{err}");
+            return false;
+        },
+    };
+    match mode {
+        Mode::Run => run_bytecode(&bytecode, std::io::stdout(), std::io::stderr()),
+        Mode::Disassemble => match write!(std::io::stdout().lock(), "{bytecode}") {
+            Ok(()) => true,
+            // what's reading the output can stop once it's seen enough, like `head` does
+            Err(err) if err.kind() == ErrorKind::BrokenPipe => true,
+            Err(err) => {
+                eprintln!("cannot write the disassembly: {err}");
+                false
+            },
+        },
+        Mode::Compile(out) => {
+            let written = bytecode
+                .to_bytes()
+                .and_then(|bytes| std::fs::write(&out, bytes).map_err(|err| err.to_string()));
+            if let Err(err) = &written {
+                eprintln!("cannot write {out}: {err}");
+            }
+            written.is_ok()
+        },
+    }
 }
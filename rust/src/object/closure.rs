@@ -0,0 +1,164 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::object::{CallDepth, CompiledFunction, Object};
+
+/// A compiled function made into a value, which shares the locals of the calls it was created
+/// in, like functions share the environments they're created in.
+pub struct Closure {
+    function: Rc<CompiledFunction>,
+    outer: Option<Rc<Locals>>,
+    runtime: Rc<Runtime>,
+}
+
+impl Closure {
+    pub fn new(
+        function: Rc<CompiledFunction>,
+        outer: Option<Rc<Locals>>,
+        runtime: Rc<Runtime>,
+    ) -> Self {
+        Closure {
+            function,
+            outer,
+            runtime,
+        }
+    }
+
+    pub fn function(&self) -> &Rc<CompiledFunction> {
+        &self.function
+    }
+
+    /// The locals of the call the closure was created in, or `None` if it was created outside
+    /// any function.
+    pub fn outer(&self) -> Option<&Rc<Locals>> {
+        self.outer.as_ref()
+    }
+
+    pub fn runtime(&self) -> &Rc<Runtime> {
+        &self.runtime
+    }
+}
+
+// the locals can (and usually do) contain the closure itself, so they're left out
+impl std::fmt::Debug for Closure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Closure")
+            .field("function", &self.function)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// The locals of a call to a compiled function, which are `None` until they're set.
+#[derive(Debug)]
+pub struct Locals {
+    function: Rc<CompiledFunction>,
+    values: RefCell<Vec<Option<Object>>>,
+    outer: Option<Rc<Locals>>,
+}
+
+impl Locals {
+    /// The locals of a call to `closure`, with its parameters set to `args`.
+    pub fn new(closure: &Closure, args: Vec<Object>) -> Self {
+        let mut values = args.into_iter().map(Some).collect::<Vec<_>>();
+        values.resize(closure.function.locals().len(), None);
+        Locals {
+            function: closure.function.clone(),
+            values: RefCell::new(values),
+            outer: closure.outer.clone(),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<Object> {
+        self.values.borrow()[index].clone()
+    }
+
+    pub fn set(&self, index: usize, value: Option<Object>) {
+        self.values.borrow_mut()[index] = value;
+    }
+
+    /// The name of the local at `index`, which is empty if it's one the compiler made.
+    pub fn name(&self, index: usize) -> &str {
+        &self.function.locals()[index]
+    }
+
+    /// The locals of the call `depth` calls out from this one.
+    pub fn outer(self: &Rc<Self>, depth: usize) -> Option<&Rc<Locals>> {
+        let mut locals = self;
+        for _ in 0..depth {
+            locals = locals.outer.as_ref()?;
+        }
+        Some(locals)
+    }
+
+    /// Looks a name up in these locals and those of the calls they're enclosed in.
+    pub fn lookup(&self, name: &str) -> Option<Object> {
+        let index = self.function.locals().iter().position(|n| n == name);
+        index
+            .and_then(|index| self.get(index))
+            .or_else(|| self.outer.as_ref()?.lookup(name))
+    }
+}
+
+/// What all the code a VM runs shares: the constant pool, the globals and how deeply calls
+/// are nested. The constants and globals grow as more programs are run.
+#[derive(Debug)]
+pub struct Runtime {
+    constants: RefCell<Vec<Object>>,
+    globals: RefCell<Vec<Option<Object>>>,
+    global_names: RefCell<Vec<String>>,
+    call_depth: Rc<CallDepth>,
+}
+
+impl Runtime {
+    pub fn new(max_call_depth: usize) -> Self {
+        Runtime {
+            constants: RefCell::new(Vec::new()),
+            globals: RefCell::new(Vec::new()),
+            global_names: RefCell::new(Vec::new()),
+            call_depth: Rc::new(CallDepth::new(max_call_depth)),
+        }
+    }
+
+    /// Makes way for a program compiled after the ones run before, whose constants and globals
+    /// start with theirs.
+    pub fn load(&self, constants: &[Object], global_names: &[String]) {
+        *self.constants.borrow_mut() = constants.to_vec();
+        self.define_globals(global_names);
+    }
+
+    /// Makes room for the globals a compiler has defined so far, which start out unset.
+    pub fn define_globals(&self, global_names: &[String]) {
+        self.globals.borrow_mut().resize(global_names.len(), None);
+        *self.global_names.borrow_mut() = global_names.to_vec();
+    }
+
+    pub fn constant(&self, index: usize) -> Option<Object> {
+        self.constants.borrow().get(index).cloned()
+    }
+
+    pub fn global(&self, index: usize) -> Option<Object> {
+        self.globals.borrow()[index].clone()
+    }
+
+    pub fn set_global(&self, index: usize, value: Option<Object>) {
+        self.globals.borrow_mut()[index] = value;
+    }
+
+    pub fn global_name(&self, index: usize) -> String {
+        self.global_names.borrow()[index].clone()
+    }
+
+    pub fn lookup_global(&self, name: &str) -> Option<Object> {
+        let index = self.global_names.borrow().iter().position(|n| n == name);
+        index.and_then(|index| self.global(index))
+    }
+
+    pub fn call_depth(&self) -> &Rc<CallDepth> {
+        &self.call_depth
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use crate::object::{Budget, ExecutionLimits, Object};
+
+/// The variables of a program, a call or a scope inside one, which are kept in the slots the
+/// resolver gave them.
+#[derive(Debug, Default)]
+pub struct Environment {
+    /// The value of each slot, which is `None` until it's set.
+    slots: Vec<Option<Object>>,
+    outer: Option<Rc<RefCell<Environment>>>,
+    call_depth: Rc<CallDepth>,
+    budget: Rc<Budget>,
+}
+
+impl Environment {
+    /// A new environment where evaluation is bounded by `limits`.
+    pub fn with_limits(limits: ExecutionLimits) -> Rc<RefCell<Self>> {
+        let call_depth = Rc::new(CallDepth::new(limits.max_call_depth()));
+        Self::sharing(call_depth, Rc::new(Budget::new(limits)))
+    }
+
+    /// A new environment where evaluation counts towards the calls and budget of other code,
+    /// like a VM's.
+    pub fn sharing(call_depth: Rc<CallDepth>, budget: Rc<Budget>) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self {
+            call_depth,
+            budget,
+            ..Self::default()
+        }))
+    }
+
+    pub fn new_enclosed(outer: Rc<RefCell<Environment>>) -> Rc<RefCell<Self>> {
+        let call_depth = outer.borrow().call_depth.clone();
+        let budget = outer.borrow().budget.clone();
+        Rc::new(RefCell::new(Self {
+            slots: Vec::new(),
+            outer: Some(outer),
+            call_depth,
+            budget,
+        }))
+    }
+
+    /// How deeply calls are nested, which is shared with every environment enclosed in this one.
+    pub fn call_depth(&self) -> &Rc<CallDepth> {
+        &self.call_depth
+    }
+
+    /// How much of its limits evaluation has used up, which is shared with every environment
+    /// enclosed in this one.
+    pub fn budget(&self) -> &Rc<Budget> {
+        &self.budget
+    }
+
+    /// The value of slot `slot` of the environment `depth` environments out from this one, if
+    /// it's been set.
+    pub fn get(&self, depth: usize, slot: usize) -> Option<Object> {
+        match depth {
+            0 => self.slots.get(slot).cloned().flatten(),
+            _ => self.outer.as_ref()?.borrow().get(depth - 1, slot),
+        }
+    }
+
+    /// Runs `f` on the value of slot `slot` of the environment `depth` environments out from
+    /// this one, or returns `None` if it hasn't been set.
+    pub fn update<T>(
+        &mut self,
+        depth: usize,
+        slot: usize,
+        f: impl FnOnce(&mut Object) -> T,
+    ) -> Option<T> {
+        match depth {
+            0 => self.slots.get_mut(slot)?.as_mut().map(f),
+            _ => self.outer.as_ref()?.borrow_mut().update(depth - 1, slot, f),
+        }
+    }
+
+    pub fn set(&mut self, slot: usize, value: Object) {
+        if slot >= self.slots.len() {
+            self.slots.resize(slot + 1, None);
+        }
+        self.slots[slot] = Some(value);
+    }
+}
+
+/// Counts the calls currently being evaluated, so that runaway recursion fails with an error
+/// instead of overflowing the stack.
+#[derive(Debug)]
+pub struct CallDepth {
+    depth: Cell<usize>,
+    max_depth: usize,
+}
+
+impl CallDepth {
+    /// How deeply calls can be nested by default. A call to a small function takes about 14KB
+    /// of the evaluator's stack in debug builds and 4KB in release builds, so this many fit in
+    /// the 2MB a spawned thread gets, with room for functions with more deeply nested bodies.
+    pub const DEFAULT_MAX_DEPTH: usize = 100;
+
+    pub fn new(max_depth: usize) -> Self {
+        CallDepth {
+            depth: Cell::new(0),
+            max_depth,
+        }
+    }
+
+    /// Counts a call until the returned guard is dropped, failing if that would make the calls
+    /// nest too deeply.
+    pub fn enter(this: &Rc<Self>) -> Result<CallGuard, String> {
+        if this.depth.get() >= this.max_depth {
+            return Err("maximum recursion depth exceeded".to_string());
+        }
+        this.depth.set(this.depth.get() + 1);
+        Ok(CallGuard(this.clone()))
+    }
+}
+
+impl Default for CallDepth {
+    fn default() -> Self {
+        CallDepth::new(CallDepth::DEFAULT_MAX_DEPTH)
+    }
+}
+
+/// A call counted by [`CallDepth::enter`], which stops being counted when this is dropped.
+#[derive(Debug)]
+pub struct CallGuard(Rc<CallDepth>);
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        self.0.depth.set(self.0.depth.get() - 1);
+    }
+}
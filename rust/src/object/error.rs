@@ -0,0 +1,121 @@
+use std::{fmt::Display, rc::Rc};
+
+use crate::{
+    object::{LimitExceeded, Object},
+    Span,
+};
+
+/// An error raised by the interpreter, like a type mismatch or a division by zero, or created
+/// with the `error` builtin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    message: String,
+    custom: bool,
+    span: Option<Span>,
+}
+
+impl Error {
+    /// An error raised by the interpreter, whose message starts with its kind.
+    pub fn new(message: impl ToString) -> Self {
+        Error {
+            message: message.to_string(),
+            custom: false,
+            span: None,
+        }
+    }
+
+    /// An error created by a program with the `error` builtin.
+    pub fn custom(message: impl ToString) -> Self {
+        Error {
+            custom: true,
+            ..Error::new(message)
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// What went wrong, like `type mismatch` or `division by zero`, which is the part of the
+    /// interpreter's messages before any details, or `error` for errors a program created.
+    pub fn kind(&self) -> &str {
+        if self.custom {
+            "error"
+        } else {
+            self.message.split(':').next().unwrap()
+        }
+    }
+
+    /// Where the error happened, once it's known.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Why evaluation stopped early.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Exception {
+    /// An [`Error`], or any value thrown with `throw`, either of which can be caught with `try`,
+    /// and where a value that isn't an error was thrown. Errors keep track of that themselves.
+    Thrown(Object, Option<Span>),
+    /// Evaluation ran into one of its limits, and where. This can't be caught, so that the code
+    /// being limited can't keep going.
+    LimitExceeded(LimitExceeded, Option<Span>),
+}
+
+impl Exception {
+    pub fn new(value: Object) -> Self {
+        Exception::Thrown(value, None)
+    }
+
+    /// Where the exception was raised, if it's an error that knows.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Exception::Thrown(Object::Error(error), _) => error.span,
+            Exception::Thrown(_, span) => *span,
+            Exception::LimitExceeded(_, span) => *span,
+        }
+    }
+
+    /// Records where an error happened, unless it already knows.
+    pub fn with_span(mut self, span: Span) -> Self {
+        match &mut self {
+            Exception::Thrown(Object::Error(error), _) if error.span.is_none() => {
+                Rc::make_mut(error).span = Some(span);
+            },
+            Exception::Thrown(Object::Error(_), _) => {},
+            Exception::Thrown(_, location @ None)
+            | Exception::LimitExceeded(_, location @ None) => *location = Some(span),
+            _ => {},
+        }
+        self
+    }
+}
+
+impl From<String> for Exception {
+    fn from(message: String) -> Self {
+        Exception::new(Object::Error(Rc::new(Error::new(message))))
+    }
+}
+
+impl From<LimitExceeded> for Exception {
+    fn from(limit: LimitExceeded) -> Self {
+        Exception::LimitExceeded(limit, None)
+    }
+}
+
+impl Display for Exception {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Exception::Thrown(Object::Error(error), _) => write!(f, "{}", error),
+            Exception::Thrown(value, _) => write!(f, "uncaught exception: {}", value),
+            Exception::LimitExceeded(limit, _) => write!(f, "{}", limit),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::object::{Environment, Function, IteratorState};
+
+/// A call to a `fn*` function, which runs its body a piece at a time, up to each `yield`.
+#[derive(Debug)]
+pub struct Generator {
+    function: Rc<Function>,
+    env: Rc<RefCell<Environment>>,
+    state: GeneratorState,
+}
+
+impl Generator {
+    /// A generator that hasn't started running `function`'s body in `env` yet.
+    pub fn new(function: Rc<Function>, env: Rc<RefCell<Environment>>) -> Self {
+        Generator {
+            function,
+            env,
+            state: GeneratorState::Suspended(BlockCursor::default()),
+        }
+    }
+
+    pub fn function(&self) -> &Rc<Function> {
+        &self.function
+    }
+
+    pub fn env(&self) -> &Rc<RefCell<Environment>> {
+        &self.env
+    }
+
+    /// Marks the generator as running, returning where it was suspended, or `Err` with the
+    /// state it's in if it can't be resumed.
+    pub fn start(&mut self) -> Result<BlockCursor, &GeneratorState> {
+        match std::mem::replace(&mut self.state, GeneratorState::Running) {
+            GeneratorState::Suspended(cursor) => Ok(cursor),
+            state => {
+                self.state = state;
+                Err(&self.state)
+            },
+        }
+    }
+
+    /// Suspends the generator at `cursor`, or finishes it when there's nowhere left to resume.
+    pub fn stop(&mut self, cursor: Option<BlockCursor>) {
+        self.state = cursor.map_or(GeneratorState::Done, GeneratorState::Suspended);
+    }
+}
+
+#[derive(Debug)]
+pub enum GeneratorState {
+    Suspended(BlockCursor),
+    Running,
+    Done,
+}
+
+/// Where a suspended generator is inside a block: at the statement with this index, and inside
+/// that statement when it contains blocks of its own.
+#[derive(Debug, Default)]
+pub struct BlockCursor {
+    pub index: usize,
+    pub statement: Option<Cursor>,
+}
+
+/// Where a suspended generator is inside a statement that contains blocks.
+#[derive(Debug)]
+pub enum Cursor {
+    If {
+        consequence: bool,
+        block: Box<BlockCursor>,
+    },
+    While {
+        block: Box<BlockCursor>,
+    },
+    For {
+        iter: Rc<RefCell<IteratorState>>,
+        block: Option<Box<BlockCursor>>,
+    },
+}
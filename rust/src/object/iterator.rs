@@ -0,0 +1,111 @@
+use std::{cell::RefCell, fmt::Display, rc::Rc};
+
+use crate::object::{Generator, Object};
+
+/// An integer range, `start..end` or `start..=end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    start: i64,
+    end: i64,
+    inclusive: bool,
+}
+
+impl Range {
+    pub fn new(start: i64, end: i64, inclusive: bool) -> Self {
+        Range {
+            start,
+            end,
+            inclusive,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        if self.inclusive {
+            self.start > self.end
+        } else {
+            self.start >= self.end
+        }
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        if self.inclusive {
+            (self.start..=self.end).contains(&value)
+        } else {
+            (self.start..self.end).contains(&value)
+        }
+    }
+
+    /// The number of integers in the range, which can be larger than an `i64` can hold.
+    pub fn len(&self) -> u64 {
+        if self.is_empty() {
+            return 0;
+        }
+        // the difference can't overflow an i128 and is never negative here
+        (self.end as i128 - self.start as i128 + self.inclusive as i128) as u64
+    }
+
+    /// The range as `start..end` bounds into a sequence of length `len`, if it fits.
+    pub fn bounds(&self, len: usize) -> Option<(usize, usize)> {
+        let start = usize::try_from(self.start).ok()?;
+        let end = if self.inclusive {
+            self.end.checked_add(1)?
+        } else {
+            self.end
+        };
+        let end = usize::try_from(end).ok()?;
+        (start <= end && end <= len).then_some((start, end))
+    }
+
+    /// Removes and returns the first integer of the range.
+    pub fn pop_front(&mut self) -> Option<i64> {
+        if self.is_empty() {
+            return None;
+        }
+        let value = self.start;
+        if self.start == self.end {
+            // only reachable for inclusive ranges, which may end at i64::MAX, so the range is
+            // emptied without incrementing
+            self.inclusive = false;
+        } else {
+            self.start += 1;
+        }
+        Some(value)
+    }
+}
+
+impl Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operator = if self.inclusive { "..=" } else { ".." };
+        write!(f, "{}{}{}", self.start, operator, self.end)
+    }
+}
+
+/// The state of a lazy iterator. Iterators are shared and advanced in place, so every handle
+/// to one sees the values it has already produced as consumed.
+#[derive(Debug)]
+pub enum IteratorState {
+    Range(Range),
+    Array {
+        elements: Rc<Vec<Object>>,
+        index: usize,
+    },
+    String {
+        value: Rc<str>,
+        offset: usize,
+    },
+    Map {
+        source: Rc<RefCell<IteratorState>>,
+        function: Object,
+    },
+    Filter {
+        source: Rc<RefCell<IteratorState>>,
+        function: Object,
+    },
+    Generator(Generator),
+}
+
+impl PartialEq for IteratorState {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    cell::Cell,
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use crate::object::CallDepth;
+
+/// Bounds on how much work evaluating code can do, so that code that can't be trusted can't
+/// take the host down. Nothing is limited by default besides the call depth.
+#[derive(Debug, Clone)]
+pub struct ExecutionLimits {
+    max_call_depth: usize,
+    max_steps: Option<u64>,
+    max_memory: Option<usize>,
+    deadline: Option<Instant>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ExecutionLimits {
+    pub fn new() -> Self {
+        ExecutionLimits {
+            max_call_depth: CallDepth::DEFAULT_MAX_DEPTH,
+            max_steps: None,
+            max_memory: None,
+            deadline: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Limits how deeply calls can be nested. Unlike the other limits, exceeding this is an
+    /// ordinary error, which `try` can catch.
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+
+    /// Limits how many steps evaluation can take, where a step is evaluating a node or getting
+    /// the next element from an iterator.
+    pub fn with_max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Limits roughly how many bytes of strings, arrays and hashes can be allocated in total,
+    /// including those that have been freed since.
+    pub fn with_max_memory(mut self, max_memory: usize) -> Self {
+        self.max_memory = Some(max_memory);
+        self
+    }
+
+    /// Limits when evaluation must have finished by.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn max_call_depth(&self) -> usize {
+        self.max_call_depth
+    }
+
+    /// A handle that stops evaluation under these limits when it's cancelled.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle(self.cancelled.clone())
+    }
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        ExecutionLimits::new()
+    }
+}
+
+/// Stops evaluation from another thread, which fails with [`LimitExceeded::Cancelled`] at its
+/// next step.
+#[derive(Debug, Clone)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Which of its [`ExecutionLimits`] evaluation ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Steps,
+    Memory,
+    Deadline,
+    Cancelled,
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitExceeded::Steps => write!(f, "step limit exceeded"),
+            LimitExceeded::Memory => write!(f, "memory limit exceeded"),
+            LimitExceeded::Deadline => write!(f, "deadline exceeded"),
+            LimitExceeded::Cancelled => write!(f, "evaluation cancelled"),
+        }
+    }
+}
+
+/// How much of its [`ExecutionLimits`] evaluation has used up so far.
+#[derive(Debug, Default)]
+pub struct Budget {
+    limits: ExecutionLimits,
+    steps: Cell<u64>,
+    memory: Cell<usize>,
+}
+
+impl Budget {
+    pub fn new(limits: ExecutionLimits) -> Self {
+        Budget {
+            limits,
+            steps: Cell::new(0),
+            memory: Cell::new(0),
+        }
+    }
+
+    /// Counts a step, failing if there are no steps left, the deadline has passed or
+    /// evaluation has been cancelled.
+    pub fn step(&self) -> Result<(), LimitExceeded> {
+        let limits = &self.limits;
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+        if limits.max_steps.is_some_and(|max| steps > max) {
+            return Err(LimitExceeded::Steps);
+        }
+        if limits.cancelled.load(Ordering::Relaxed) {
+            return Err(LimitExceeded::Cancelled);
+        }
+        if limits.deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(LimitExceeded::Deadline);
+        }
+        Ok(())
+    }
+
+    /// Counts `bytes` as allocated, failing if that's more than the memory limit allows.
+    pub fn allocate(&self, bytes: usize) -> Result<(), LimitExceeded> {
+        let memory = self.memory.get().saturating_add(bytes);
+        self.memory.set(memory);
+        if self.limits.max_memory.is_some_and(|max| memory > max) {
+            return Err(LimitExceeded::Memory);
+        }
+        Ok(())
+    }
+}
@@ -1,38 +1,658 @@
-use std::fmt::Display;
+mod closure;
+mod environment;
+mod error;
+mod generator;
+mod iterator;
+mod limits;
+
+use std::{cell::RefCell, collections::BTreeMap, fmt::Display, rc::Rc};
+
+pub use closure::*;
+pub use environment::*;
+pub use error::*;
+pub use generator::*;
+pub use iterator::*;
+pub use limits::*;
+
+use crate::{BlockStatement, Instructions, Parameters, SourceMap, Span, Symbol};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Object {
     Integer(i64),
     Boolean(bool),
     Null,
+    TailCall(Box<TailCall>),
+    Function(Rc<Function>),
+    Array(Rc<Vec<Object>>),
+    String(Rc<str>),
+    Hash(Rc<BTreeMap<HashKey, Object>>),
+    Range(Range),
+    Iterator(Rc<RefCell<IteratorState>>),
+    Builtin(Builtin),
+    StructType(Rc<StructType>),
+    Struct(Rc<Struct>),
+    EnumType(Rc<EnumType>),
+    Constructor(Constructor),
+    Variant(Rc<Variant>),
+    Error(Rc<Error>),
+    CompiledFunction(Rc<CompiledFunction>),
+    Closure(Rc<Closure>),
 }
 
+impl Object {
+    pub fn type_name(&self) -> &'static str {
+        use Object::*;
+
+        match self {
+            Integer(_) => "INTEGER",
+            Boolean(_) => "BOOLEAN",
+            Null => "NULL",
+            TailCall(_) => "TAIL_CALL",
+            Function(_) => "FUNCTION",
+            Array(_) => "ARRAY",
+            String(_) => "STRING",
+            Hash(_) => "HASH",
+            Range(_) => "RANGE",
+            Iterator(_) => "ITERATOR",
+            Builtin(_) => "BUILTIN",
+            StructType(_) => "STRUCT_TYPE",
+            Struct(_) => "STRUCT",
+            EnumType(_) => "ENUM_TYPE",
+            Constructor(_) => "CONSTRUCTOR",
+            Variant(_) => "VARIANT",
+            Error(_) => "ERROR",
+            CompiledFunction(_) => "COMPILED_FUNCTION",
+            // they're called like functions, whichever engine made them
+            Closure(_) => "FUNCTION",
+        }
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Object::Null | Object::Boolean(false))
+    }
+
+    /// Roughly how many bytes were allocated for the value itself, not counting the values it
+    /// holds, which were allocated separately.
+    pub fn allocated_size(&self) -> usize {
+        match self {
+            Object::String(s) => s.len(),
+            Object::Array(elements) => elements.len() * size_of::<Object>(),
+            Object::Hash(pairs) => pairs.len() * size_of::<(HashKey, Object)>(),
+            _ => 0,
+        }
+    }
+}
+
+/// How deeply values nested in arrays, hashes, structs and variants are written before the
+/// ones inside them are left out. Writing each level takes over 1KB of stack in debug builds,
+/// so writing a value nested much deeper than this could overflow it.
+const MAX_DISPLAY_DEPTH: usize = 100;
+
 impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl Object {
+    /// Writes the value like [`Display`] does, as part of one it's nested `depth` levels in.
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
         use Object::*;
 
+        if depth == MAX_DISPLAY_DEPTH {
+            return write!(f, "...");
+        }
         match self {
             Integer(i) => write!(f, "{}", i),
             Boolean(b) => write!(f, "{}", b),
             Null => write!(f, "null"),
+            TailCall(call) => write!(f, "tail call to {}", call.function),
+            Function(func) => write!(f, "{}", func),
+            Array(elements) => {
+                write!(f, "[")?;
+                write_separated(f, elements.iter(), |f, e| e.write(f, depth + 1))?;
+                write!(f, "]")
+            },
+            String(s) => write!(f, "{}", s),
+            Hash(pairs) => {
+                write!(f, "{{")?;
+                write_separated(f, pairs.iter(), |f, (k, v)| {
+                    write!(f, "{}: ", k)?;
+                    v.write(f, depth + 1)
+                })?;
+                write!(f, "}}")
+            },
+            Range(range) => write!(f, "{}", range),
+            Iterator(_) => write!(f, "<iterator>"),
+            Builtin(builtin) => write!(f, "builtin {}", builtin.name()),
+            StructType(struct_type) => write!(f, "{}", struct_type),
+            Struct(value) => value.write(f, depth),
+            EnumType(enum_type) => write!(f, "{}", enum_type),
+            Constructor(constructor) => write!(f, "constructor {}", constructor),
+            Variant(variant) => variant.write(f, depth),
+            Error(error) => write!(f, "error: {}", error),
+            CompiledFunction(func) => write!(f, "{}", func),
+            Closure(closure) => write!(f, "{}", closure.function()),
+        }
+    }
+}
+
+/// Writes `items` separated by commas. They're written one by one rather than joined, so that
+/// writing a value that holds the same array many times over can be stopped part way.
+fn write_separated<T>(
+    f: &mut std::fmt::Formatter<'_>,
+    items: impl IntoIterator<Item = T>,
+    mut write_item: impl FnMut(&mut std::fmt::Formatter<'_>, T) -> std::fmt::Result,
+) -> std::fmt::Result {
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
         }
+        write_item(f, item)?;
     }
+    Ok(())
 }
 
 impl PartialEq<i64> for Object {
     fn eq(&self, other: &i64) -> bool {
-        match self {
-            Self::Integer(i) if i == other => true,
-            _ => false,
-        }
+        matches!(self, Self::Integer(i) if i == other)
     }
 }
 
 impl PartialEq<bool> for Object {
     fn eq(&self, other: &bool) -> bool {
+        matches!(self, Self::Boolean(b) if b == other)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HashKey {
+    Integer(i64),
+    Boolean(bool),
+    String(Rc<str>),
+}
+
+impl TryFrom<&Object> for HashKey {
+    type Error = String;
+
+    fn try_from(obj: &Object) -> Result<Self, Self::Error> {
+        match obj {
+            Object::Integer(i) => Ok(HashKey::Integer(*i)),
+            Object::Boolean(b) => Ok(HashKey::Boolean(*b)),
+            Object::String(s) => Ok(HashKey::String(s.clone())),
+            _ => Err(format!("unusable as hash key: {}", obj.type_name())),
+        }
+    }
+}
+
+impl From<HashKey> for Object {
+    fn from(key: HashKey) -> Self {
+        match key {
+            HashKey::Integer(i) => Object::Integer(i),
+            HashKey::Boolean(b) => Object::Boolean(b),
+            HashKey::String(s) => Object::String(s),
+        }
+    }
+}
+
+impl Display for HashKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Boolean(i) if i == other => true,
-            _ => false,
+            HashKey::Integer(i) => write!(f, "{}", i),
+            HashKey::Boolean(b) => write!(f, "{}", b),
+            HashKey::String(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+pub struct Function {
+    parameters: Rc<Parameters>,
+    body: Rc<BlockStatement>,
+    env: Rc<RefCell<Environment>>,
+    generator: bool,
+}
+
+impl Function {
+    pub fn new(
+        parameters: Rc<Parameters>,
+        body: Rc<BlockStatement>,
+        env: Rc<RefCell<Environment>>,
+        generator: bool,
+    ) -> Self {
+        Function {
+            parameters,
+            body,
+            env,
+            generator,
+        }
+    }
+
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+
+    pub fn body(&self) -> &BlockStatement {
+        &self.body
+    }
+
+    pub fn env(&self) -> &Rc<RefCell<Environment>> {
+        &self.env
+    }
+
+    /// Whether calling the function creates a generator rather than running its body.
+    pub fn generator(&self) -> bool {
+        self.generator
+    }
+}
+
+// the environment can (and usually does) contain the function itself, so it's left out
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Function")
+            .field("parameters", &self.parameters)
+            .field("body", &self.body)
+            .field("generator", &self.generator)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let star = if self.generator { "*" } else { "" };
+        write!(f, "fn{}({}) {}", star, self.parameters, self.body)
+    }
+}
+
+/// A function compiled to bytecode, which the constant pool holds for closures to be made
+/// from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledFunction {
+    instructions: Instructions,
+    source_map: SourceMap,
+    locals: Vec<String>,
+    num_parameters: usize,
+    display: String,
+}
+
+impl CompiledFunction {
+    pub fn new(
+        instructions: Instructions,
+        source_map: SourceMap,
+        locals: Vec<String>,
+        num_parameters: usize,
+        display: impl ToString,
+    ) -> Self {
+        CompiledFunction {
+            instructions,
+            source_map,
+            locals,
+            num_parameters,
+            display: display.to_string(),
+        }
+    }
+
+    pub fn instructions(&self) -> &Instructions {
+        &self.instructions
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// The names of the function's locals by slot, starting with its parameters, which are
+    /// empty for slots the compiler uses for itself.
+    pub fn locals(&self) -> &[String] {
+        &self.locals
+    }
+
+    pub fn num_parameters(&self) -> usize {
+        self.num_parameters
+    }
+}
+
+impl Display for CompiledFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // it's shown like the function literal it was compiled from
+        write!(f, "{}", self.display)
+    }
+}
+
+/// A call in tail position, which the function making it returns instead of making the call
+/// itself, so the call doesn't need a new stack frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TailCall {
+    function: Object,
+    args: Vec<Object>,
+    named: Vec<(Symbol, Object)>,
+    span: Span,
+}
+
+impl TailCall {
+    pub fn new(
+        function: Object,
+        args: Vec<Object>,
+        named: Vec<(Symbol, Object)>,
+        span: Span,
+    ) -> Self {
+        TailCall {
+            function,
+            args,
+            named,
+            span,
+        }
+    }
+
+    /// Where the call is made.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The function and the positional and named arguments to call it with.
+    pub fn into_call(self) -> (Object, Vec<Object>, Vec<(Symbol, Object)>) {
+        (self.function, self.args, self.named)
+    }
+}
+
+pub type BuiltinFunction = fn(&Budget, Vec<Object>) -> Result<Object, Exception>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Builtin {
+    name: &'static str,
+    function: BuiltinFunction,
+}
+
+impl Builtin {
+    pub fn new(name: &'static str, function: BuiltinFunction) -> Self {
+        Builtin { name, function }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn call(&self, budget: &Budget, args: Vec<Object>) -> Result<Object, Exception> {
+        (self.function)(budget, args)
+    }
+}
+
+// builtins are unique by name, and comparing function pointers isn't reliable
+impl PartialEq for Builtin {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+/// A declared struct, which constructs values with exactly its fields.
+#[derive(Debug)]
+pub struct StructType {
+    name: String,
+    fields: Vec<String>,
+}
+
+impl StructType {
+    pub fn new(name: impl ToString, fields: Vec<String>) -> Self {
+        StructType {
+            name: name.to_string(),
+            fields,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f == name)
+    }
+}
+
+// two declarations with the same name and fields are still different types
+impl PartialEq for StructType {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Display for StructType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.fields.is_empty() {
+            return write!(f, "struct {} {{}}", self.name);
+        }
+        write!(f, "struct {} {{ {} }}", self.name, self.fields.join(", "))
+    }
+}
+
+/// An instance of a struct. Like arrays and hashes, structs are values, and equal when they
+/// have the same type and equal fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Struct {
+    struct_type: Rc<StructType>,
+    values: Vec<Object>,
+}
+
+impl Struct {
+    /// `values` are in the order the type declares its fields.
+    pub fn new(struct_type: Rc<StructType>, values: Vec<Object>) -> Self {
+        Struct {
+            struct_type,
+            values,
+        }
+    }
+
+    pub fn struct_type(&self) -> &Rc<StructType> {
+        &self.struct_type
+    }
+
+    pub fn get(&self, field: &str) -> Option<&Object> {
+        self.struct_type.field_index(field).map(|i| &self.values[i])
+    }
+
+    pub fn get_mut(&mut self, field: &str) -> Option<&mut Object> {
+        self.struct_type
+            .field_index(field)
+            .map(|i| &mut self.values[i])
+    }
+}
+
+impl Display for Struct {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl Struct {
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        if self.values.is_empty() {
+            return write!(f, "{} {{}}", self.struct_type.name);
+        }
+        write!(f, "{} {{ ", self.struct_type.name)?;
+        let fields = self.struct_type.fields.iter().zip(&self.values);
+        write_separated(f, fields, |f, (name, value)| {
+            write!(f, "{}: ", name)?;
+            value.write(f, depth + 1)
+        })?;
+        write!(f, " }}")
+    }
+}
+
+#[derive(Debug)]
+pub struct VariantType {
+    name: String,
+    fields: Vec<String>,
+}
+
+impl VariantType {
+    pub fn new(name: impl ToString, fields: Vec<String>) -> Self {
+        VariantType {
+            name: name.to_string(),
+            fields,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+}
+
+impl Display for VariantType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.fields.is_empty() {
+            return write!(f, "{}", self.name);
+        }
+        write!(f, "{}({})", self.name, self.fields.join(", "))
+    }
+}
+
+/// A declared enum, whose values are each one of its variants.
+#[derive(Debug)]
+pub struct EnumType {
+    name: String,
+    variants: Vec<VariantType>,
+}
+
+impl EnumType {
+    pub fn new(name: impl ToString, variants: Vec<VariantType>) -> Self {
+        EnumType {
+            name: name.to_string(),
+            variants,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn variants(&self) -> &[VariantType] {
+        &self.variants
+    }
+
+    pub fn variant_index(&self, name: &str) -> Option<usize> {
+        self.variants.iter().position(|v| v.name == name)
+    }
+}
+
+// like structs, enums are only equal to themselves
+impl PartialEq for EnumType {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Display for EnumType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.variants.is_empty() {
+            return write!(f, "enum {} {{}}", self.name);
+        }
+        let variants = self
+            .variants
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "enum {} {{ {} }}", self.name, variants.join(", "))
+    }
+}
+
+/// The function that builds values of an enum variant with fields, like `Shape.Circle`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constructor {
+    enum_type: Rc<EnumType>,
+    index: usize,
+}
+
+impl Constructor {
+    pub fn new(enum_type: Rc<EnumType>, index: usize) -> Self {
+        Constructor { enum_type, index }
+    }
+
+    pub fn enum_type(&self) -> &Rc<EnumType> {
+        &self.enum_type
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn variant_type(&self) -> &VariantType {
+        &self.enum_type.variants[self.index]
+    }
+}
+
+impl Display for Constructor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.enum_type.name, self.variant_type())
+    }
+}
+
+/// A value of an enum variant, along with its fields' values in declaration order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    enum_type: Rc<EnumType>,
+    index: usize,
+    values: Vec<Object>,
+}
+
+impl Variant {
+    pub fn new(enum_type: Rc<EnumType>, index: usize, values: Vec<Object>) -> Self {
+        Variant {
+            enum_type,
+            index,
+            values,
+        }
+    }
+
+    pub fn enum_type(&self) -> &Rc<EnumType> {
+        &self.enum_type
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn variant_type(&self) -> &VariantType {
+        &self.enum_type.variants[self.index]
+    }
+
+    pub fn values(&self) -> &[Object] {
+        &self.values
+    }
+
+    pub fn get(&self, field: &str) -> Option<&Object> {
+        self.variant_type()
+            .fields
+            .iter()
+            .position(|f| f == field)
+            .map(|i| &self.values[i])
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl Variant {
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        write!(f, "{}.{}", self.enum_type.name, self.variant_type().name)?;
+        if !self.values.is_empty() {
+            write!(f, "(")?;
+            write_separated(f, self.values.iter(), |f, v| v.write(f, depth + 1))?;
+            write!(f, ")")?;
         }
+        Ok(())
     }
 }
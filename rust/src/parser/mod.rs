@@ -1,46 +1,96 @@
 mod tests;
 
 use crate::{
-    BlockStatement, Boolean, CallExpression, Expression, ExpressionStatement, FunctionLiteral,
-    Identifier, IfExpression, InfixExpression, IntegerLiteral, LetStatement, Lexer,
-    PrefixExpression, Program, ReturnStatement, Statement, Token, TokenType,
+    lexer::unescape, ArrayLiteral, ArrayPattern, AssignExpression, BlockStatement, Boolean,
+    BreakStatement, CallExpression, ContinueStatement, DestructuringLetStatement, EnumStatement,
+    EnumVariant, Expression, ExpressionStatement, ForStatement, FunctionLiteral, HashLiteral,
+    HashPattern, Identifier, IfExpression, IndexExpression, InfixExpression, IntegerLiteral,
+    InterpolatedString, LetStatement, Lexer, MatchArm, MatchExpression, MemberExpression,
+    NamedArgument, Node, NodeType, NullLiteral, Parameters, Pattern, PrefixExpression, Program,
+    RestPattern, ReturnStatement, Span, SpreadExpression, Statement, StringLiteral, StringPart,
+    StructField, StructLiteral, StructStatement, ThrowStatement, Token, TokenType, TryExpression,
+    VariantPattern, WhileStatement, WildcardPattern, YieldStatement,
 };
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Precedence {
     Lowest,
+    Assign,
+    Range,
+    Nullish,
+    LogicalOr,
+    LogicalAnd,
     Equals,
     LessGreater,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
     Sum,
     Product,
     Prefix,
+    Power,
     Call,
+    Index,
 }
 
 #[derive(Debug)]
-pub struct Parser {
-    lexer: Lexer,
-    cur_token: Option<Token>,
-    peek_token: Option<Token>,
+pub struct Parser<'src> {
+    lexer: Lexer<'src>,
+    /// The tokens being parsed, which are made to own their literals once they're part of the
+    /// program, so it can outlive the source.
+    cur_token: Option<Token<'src>>,
+    peek_token: Option<Token<'src>>,
+    loop_depth: usize,
+    in_generator: bool,
+    /// Where the yields in the generator being parsed are.
+    yields: Vec<Span>,
+    depth: usize,
+    max_depth: usize,
 }
 
-impl Parser {
-    pub fn new(lexer: Lexer) -> Self {
+impl<'src> Parser<'src> {
+    /// How deeply expressions and patterns can be nested by default.
+    pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+    pub fn new(lexer: Lexer<'src>) -> Self {
         let mut this = Parser {
             lexer,
             cur_token: None,
             peek_token: None,
+            loop_depth: 0,
+            in_generator: false,
+            yields: Vec::new(),
+            depth: 0,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
         };
         this.next_token();
         this.next_token();
         this
     }
 
+    /// Limits how deeply expressions and patterns can be nested, since each level of nesting
+    /// takes up some of the stack.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
     pub fn next_token(&mut self) {
         self.cur_token = self.peek_token.take();
         self.peek_token = Some(self.lexer.next_token());
     }
 
+    /// Takes the current token to make it part of the program.
+    fn take_token(&mut self) -> Token<'static> {
+        self.cur_token.take().unwrap().into_owned()
+    }
+
+    /// Takes the current token, which is an identifier, to make it part of the program.
+    fn take_identifier(&mut self) -> Identifier {
+        identifier(self.cur_token.take().unwrap())
+    }
+
     pub fn parse_program(&mut self) -> Result<Program, String> {
         let mut program = Program::new();
         while let Some(token) = &self.cur_token {
@@ -63,13 +113,44 @@ impl Parser {
                 token_type: TokenType::Return,
                 ..
             }) => self.parse_return_statement(),
+            Some(Token {
+                token_type: TokenType::While,
+                ..
+            }) => self.parse_while_statement(),
+            Some(Token {
+                token_type: TokenType::For,
+                ..
+            }) => self.parse_for_statement(),
+            Some(Token {
+                token_type: TokenType::Break | TokenType::Continue,
+                ..
+            }) => self.parse_loop_control_statement(),
+            Some(Token {
+                token_type: TokenType::Struct,
+                ..
+            }) => self.parse_struct_statement(),
+            Some(Token {
+                token_type: TokenType::Enum,
+                ..
+            }) => self.parse_enum_statement(),
+            Some(Token {
+                token_type: TokenType::Throw,
+                ..
+            }) => self.parse_throw_statement(),
+            Some(Token {
+                token_type: TokenType::Yield,
+                ..
+            }) => self.parse_yield_statement(),
             Some(_) => self.parse_expression_statement(),
             _ => Err(format!("unexpected token: {:?}", self.cur_token)),
         }
     }
 
     pub fn parse_let_statement(&mut self) -> Result<Box<dyn Statement>, String> {
-        let token = self.cur_token.take().unwrap();
+        if self.peek_token_is(TokenType::Lbracket) || self.peek_token_is(TokenType::Lbrace) {
+            return self.parse_destructuring_let_statement();
+        }
+        let token = self.take_token();
         if !self.expect_peek(TokenType::Ident) {
             return Err(format!(
                 "expected next token to be Ident, got {:?} instead",
@@ -77,9 +158,7 @@ impl Parser {
             ));
         }
 
-        let ident = self.cur_token.take().unwrap();
-        let name = ident.literal().to_string();
-        let name = Identifier::new(ident, name);
+        let name = self.take_identifier();
 
         if !self.expect_peek(TokenType::Assign) {
             return Err(format!(
@@ -98,6 +177,31 @@ impl Parser {
         Ok(Box::new(LetStatement::new(token, name, expr)))
     }
 
+    pub fn parse_destructuring_let_statement(&mut self) -> Result<Box<dyn Statement>, String> {
+        let token = self.take_token();
+        self.next_token();
+        let pattern = self.parse_pattern()?;
+        check_bindings(pattern.as_ref(), &mut Vec::new())?;
+
+        if !self.expect_peek(TokenType::Assign) {
+            return Err(format!(
+                "expected next token to be Assign, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        self.next_token();
+
+        let expr = self.parse_expression(Precedence::Lowest)?;
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Box::new(DestructuringLetStatement::new(
+            token, pattern, expr,
+        )))
+    }
+
     pub fn cur_token_is(&self, token_type: TokenType) -> bool {
         match self.cur_token {
             Some(Token { token_type: t, .. }) => t == token_type,
@@ -122,7 +226,7 @@ impl Parser {
     }
 
     pub fn parse_return_statement(&mut self) -> Result<Box<dyn Statement>, String> {
-        let token = self.cur_token.take().unwrap();
+        let token = self.take_token();
         self.next_token();
         let expr = self.parse_expression(Precedence::Lowest)?;
         if self.peek_token_is(TokenType::Semicolon) {
@@ -132,7 +236,7 @@ impl Parser {
     }
 
     pub fn parse_expression_statement(&mut self) -> Result<Box<dyn Statement>, String> {
-        let token = self.cur_token.clone().unwrap();
+        let token = self.cur_token.clone().unwrap().into_owned();
         let expr = self.parse_expression(Precedence::Lowest)?;
         if self.peek_token_is(TokenType::Semicolon) {
             self.next_token();
@@ -147,11 +251,19 @@ impl Parser {
         match token_type {
             TokenType::Ident => self.parse_identifier(),
             TokenType::Int => self.parse_integer_literal(),
-            TokenType::Bang | TokenType::Minus => self.parse_prefix_expression(),
+            TokenType::Bang | TokenType::Minus | TokenType::Tilde => self.parse_prefix_expression(),
             TokenType::True | TokenType::False => self.parse_boolean(),
             TokenType::Lparen => self.parse_grouped_expression(),
             TokenType::If => self.parse_if_expression(),
             TokenType::Function => self.parse_function_literal(),
+            TokenType::Lbracket => self.parse_array_literal(),
+            TokenType::Str => self.parse_string_literal(),
+            TokenType::StrStart => self.parse_interpolated_string(),
+            TokenType::Lbrace => self.parse_hash_literal(),
+            TokenType::Null => self.parse_null_literal(),
+            TokenType::Match => self.parse_match_expression(),
+            TokenType::Try => self.parse_try_expression(),
+            TokenType::Illegal => Err(illegal_token(self.cur_token.as_ref().unwrap())),
             _ => Err(format!("no prefix parse function for {:?}", token_type)),
         }
     }
@@ -169,8 +281,36 @@ impl Parser {
             | TokenType::Eq
             | TokenType::NotEq
             | TokenType::Lt
-            | TokenType::Gt => self.parse_infix_expression(left),
+            | TokenType::Gt
+            | TokenType::LtEq
+            | TokenType::GtEq
+            | TokenType::Percent
+            | TokenType::Power
+            | TokenType::And
+            | TokenType::Or
+            | TokenType::BitAnd
+            | TokenType::BitOr
+            | TokenType::BitXor
+            | TokenType::Shl
+            | TokenType::Shr
+            | TokenType::Nullish
+            | TokenType::DotDot
+            | TokenType::DotDotEq => self.parse_infix_expression(left),
             TokenType::Lparen => self.parse_call_expression(left),
+            TokenType::Lbracket | TokenType::QuestionLbracket => self.parse_index_expression(left),
+            TokenType::Dot | TokenType::QuestionDot => self.parse_member_expression(left),
+            TokenType::Assign
+            | TokenType::PlusAssign
+            | TokenType::MinusAssign
+            | TokenType::AsteriskAssign
+            | TokenType::SlashAssign
+            | TokenType::PercentAssign
+            | TokenType::PowerAssign
+            | TokenType::BitAndAssign
+            | TokenType::BitOrAssign
+            | TokenType::BitXorAssign
+            | TokenType::ShlAssign
+            | TokenType::ShrAssign => self.parse_assign_expression(left),
             _ => Err(format!("no infix parse function for {:?}", token_type)),
         }
     }
@@ -179,29 +319,50 @@ impl Parser {
         &mut self,
         precedence: Precedence,
     ) -> Result<Box<dyn Expression>, String> {
+        self.enter_nesting()?;
+        let depth = self.depth;
         let mut expr = self.prefix_parse_fn(self.cur_token.as_ref().unwrap().token_type())?;
         while !self.peek_token_is(TokenType::Semicolon) && precedence < self.peek_precedence() {
             let token_type = self.peek_token.as_ref().unwrap().token_type();
             self.next_token();
+            // every operand folded in nests the expression one level deeper too
+            self.enter_nesting()?;
             expr = self.infix_parse_fn(token_type, expr)?;
         }
+        self.depth = depth - 1;
         Ok(expr)
     }
 
+    /// Goes one level deeper into nested expressions or patterns. The caller leaves the level
+    /// again once it has parsed it, though not on errors, since those end the parse.
+    fn enter_nesting(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > self.max_depth {
+            let span = self.cur_token.as_ref().unwrap().span();
+            return Err(format!("maximum recursion depth exceeded at {}", span));
+        }
+        Ok(())
+    }
+
     pub fn parse_identifier(&mut self) -> Result<Box<dyn Expression>, String> {
-        let token = self.cur_token.take().unwrap();
-        let value = token.literal().to_string();
-        Ok(Box::new(Identifier::new(token, value)))
+        let ident = self.take_identifier();
+        // conditions are always parenthesized, so a name directly followed by a brace can only
+        // be a struct constructor
+        if self.peek_token_is(TokenType::Lbrace) {
+            self.next_token();
+            return self.parse_struct_literal(ident);
+        }
+        Ok(Box::new(ident))
     }
 
     pub fn parse_integer_literal(&mut self) -> Result<Box<dyn Expression>, String> {
-        let token = self.cur_token.take().unwrap();
-        let value = token.literal().parse::<i64>().unwrap();
+        let token = self.take_token();
+        let value = parse_integer(&token, false)?;
         Ok(Box::new(IntegerLiteral::new(token, value)))
     }
 
     pub fn parse_prefix_expression(&mut self) -> Result<Box<dyn Expression>, String> {
-        let token = self.cur_token.take().unwrap();
+        let token = self.take_token();
         let operator = token.literal().to_string();
         self.next_token();
         let right = self.parse_expression(Precedence::Prefix)?;
@@ -212,9 +373,14 @@ impl Parser {
         &mut self,
         left: Box<dyn Expression>,
     ) -> Result<Box<dyn Expression>, String> {
-        let token = self.cur_token.take().unwrap();
+        let token = self.take_token();
         let operator = token.literal().to_string();
-        let precedence = Self::get_precedence(token.token_type());
+        let precedence = match token.token_type() {
+            // `**` is right-associative, so the right operand has to be allowed to pick up
+            // another `**` before this one is reduced
+            TokenType::Power => Precedence::Prefix,
+            t => Self::get_precedence(t),
+        };
         self.next_token();
         let right = self.parse_expression(precedence)?;
         Ok(Box::new(InfixExpression::new(token, left, operator, right)))
@@ -224,11 +390,26 @@ impl Parser {
         use TokenType::*;
 
         match t {
+            Assign | PlusAssign | MinusAssign | AsteriskAssign | SlashAssign | PercentAssign
+            | PowerAssign | BitAndAssign | BitOrAssign | BitXorAssign | ShlAssign | ShrAssign => {
+                Precedence::Assign
+            },
+            DotDot | DotDotEq => Precedence::Range,
+            Nullish => Precedence::Nullish,
+            Or => Precedence::LogicalOr,
+            And => Precedence::LogicalAnd,
             Eq | NotEq => Precedence::Equals,
-            Lt | Gt => Precedence::LessGreater,
+            Lt | Gt | LtEq | GtEq => Precedence::LessGreater,
+            BitOr => Precedence::BitOr,
+            BitXor => Precedence::BitXor,
+            BitAnd => Precedence::BitAnd,
+            Shl | Shr => Precedence::Shift,
             Plus | Minus => Precedence::Sum,
-            Slash | Asterisk => Precedence::Product,
+            Slash | Asterisk | Percent => Precedence::Product,
+            Power => Precedence::Power,
             Lparen => Precedence::Call,
+            Lbracket | QuestionLbracket => Precedence::Index,
+            Dot | QuestionDot => Precedence::Call,
             _ => Precedence::Lowest,
         }
     }
@@ -248,7 +429,7 @@ impl Parser {
     }
 
     pub fn parse_boolean(&mut self) -> Result<Box<dyn Expression>, String> {
-        let token = self.cur_token.take().unwrap();
+        let token = self.take_token();
         let value = match token.token_type() {
             TokenType::True => true,
             TokenType::False => false,
@@ -259,18 +440,28 @@ impl Parser {
 
     pub fn parse_grouped_expression(&mut self) -> Result<Box<dyn Expression>, String> {
         self.next_token();
-        let expr = self.parse_expression(Precedence::Lowest)?;
+        let mut expr = self.parse_expression(Precedence::Lowest)?;
         if !self.expect_peek(TokenType::Rparen) {
             return Err(format!(
                 "expected next token to be Rparen, got {:?} instead",
                 self.peek_token
             ));
         }
+        // an optional access in parentheses only cuts short what's in them, so `(h?.a).b`
+        // fails when `h` is null
+        let any = expr.as_any_mut();
+        if let Some(call) = any.downcast_mut::<CallExpression>() {
+            call.set_grouped();
+        } else if let Some(index) = any.downcast_mut::<IndexExpression>() {
+            index.set_grouped();
+        } else if let Some(member) = any.downcast_mut::<MemberExpression>() {
+            member.set_grouped();
+        }
         Ok(expr)
     }
 
     pub fn parse_if_expression(&mut self) -> Result<Box<dyn Expression>, String> {
-        let token = self.cur_token.take().unwrap();
+        let token = self.take_token();
         if !self.expect_peek(TokenType::Lparen) {
             return Err(format!(
                 "expected next token to be Lparen, got {:?} instead",
@@ -313,7 +504,7 @@ impl Parser {
     }
 
     pub fn parse_block_statement(&mut self) -> Result<BlockStatement, String> {
-        let token = self.cur_token.take().unwrap();
+        let token = self.take_token();
         let mut block = BlockStatement::new(token);
         self.next_token();
         while !self.cur_token_is(TokenType::Rbrace) && !self.cur_token_is(TokenType::Eof) {
@@ -324,7 +515,11 @@ impl Parser {
     }
 
     pub fn parse_function_literal(&mut self) -> Result<Box<dyn Expression>, String> {
-        let token = self.cur_token.take().unwrap();
+        let token = self.take_token();
+        let generator = self.peek_token_is(TokenType::Asterisk);
+        if generator {
+            self.next_token();
+        }
         if !self.expect_peek(TokenType::Lparen) {
             return Err(format!(
                 "expected next token to be Lparen, got {:?} instead",
@@ -338,30 +533,73 @@ impl Parser {
                 self.peek_token
             ));
         }
-        let body = self.parse_block_statement()?;
-        Ok(Box::new(FunctionLiteral::new(token, parameters, body)))
-    }
-
-    pub fn parse_function_parameters(&mut self) -> Result<Vec<Identifier>, String> {
-        let mut identifiers = Vec::new();
-        if self.peek_token_is(TokenType::Rparen) {
-            self.next_token();
-            return Ok(identifiers);
+        // loops don't extend into function bodies, so `break` in a function declared inside a
+        // loop can't reach the loop
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let in_generator = std::mem::replace(&mut self.in_generator, generator);
+        let outer_yields = std::mem::take(&mut self.yields);
+        let body = self.parse_block_statement();
+        self.loop_depth = loop_depth;
+        self.in_generator = in_generator;
+        let yields = std::mem::replace(&mut self.yields, outer_yields);
+        let body = body?;
+        // a generator can only be suspended at some of the places yield can be written
+        let mut suspendable = Vec::new();
+        suspendable_yields(&body, &mut suspendable);
+        if let Some(span) = yields.iter().find(|span| !suspendable.contains(span)) {
+            return Err(format!(
+                "yield is only supported directly in a generator's body or its if, while and \
+                 for blocks, not at {}",
+                span
+            ));
         }
-        self.next_token();
-
-        let token = self.cur_token.take().unwrap();
-        let literal = token.literal().to_string();
-        let ident = Identifier::new(token, literal);
-        identifiers.push(ident);
+        Ok(Box::new(FunctionLiteral::new(
+            token, parameters, body, generator,
+        )))
+    }
 
-        while self.peek_token_is(TokenType::Comma) {
-            self.next_token();
-            self.next_token();
-            let token = self.cur_token.take().unwrap();
-            let literal = token.literal().to_string();
-            let ident = Identifier::new(token, literal);
-            identifiers.push(ident);
+    pub fn parse_function_parameters(&mut self) -> Result<Parameters, String> {
+        let mut parameters = Parameters::new();
+        while !self.peek_token_is(TokenType::Rparen) {
+            if self.expect_peek(TokenType::Ellipsis) {
+                if !self.expect_peek(TokenType::Ident) {
+                    return Err(format!(
+                        "expected next token to be Ident, got {:?} instead",
+                        self.peek_token
+                    ));
+                }
+                let rest = self.take_identifier();
+                check_parameter(&parameters, &rest)?;
+                parameters.set_rest(rest);
+                // the rest parameter has to be the last one
+                break;
+            }
+            if !self.expect_peek(TokenType::Ident) {
+                return Err(format!(
+                    "expected next token to be Ident, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+            let ident = self.take_identifier();
+            check_parameter(&parameters, &ident)?;
+            let default = if self.expect_peek(TokenType::Assign) {
+                self.next_token();
+                Some(self.parse_expression(Precedence::Lowest)?)
+            } else if parameters.required() < parameters.names().len() {
+                return Err(format!(
+                    "parameter {} without a default follows a parameter with a default",
+                    ident
+                ));
+            } else {
+                None
+            };
+            parameters.add_parameter(ident, default);
+            if !self.peek_token_is(TokenType::Rparen) && !self.expect_peek(TokenType::Comma) {
+                return Err(format!(
+                    "expected next token to be Comma, got {:?} instead",
+                    self.peek_token
+                ));
+            }
         }
 
         if !self.expect_peek(TokenType::Rparen) {
@@ -370,22 +608,61 @@ impl Parser {
                 self.peek_token
             ));
         }
-        Ok(identifiers)
+        Ok(parameters)
     }
 
     pub fn parse_call_expression(
         &mut self,
         left: Box<dyn Expression>,
     ) -> Result<Box<dyn Expression>, String> {
-        let token = self.cur_token.take().unwrap();
+        let token = self.take_token();
         let arguments = self.parse_call_arguments()?;
         Ok(Box::new(CallExpression::new(token, left, arguments)))
     }
 
+    /// Parses positional arguments, `...spread` arguments and `name: value` arguments, the last
+    /// of which have to come after all the others.
     pub fn parse_call_arguments(&mut self) -> Result<Vec<Box<dyn Expression>>, String> {
+        let mut args: Vec<Box<dyn Expression>> = Vec::new();
+        let mut named = false;
+        while !self.peek_token_is(TokenType::Rparen) {
+            self.next_token();
+            if self.cur_token_is(TokenType::Ident) && self.peek_token_is(TokenType::Colon) {
+                named = true;
+                let name = self.take_identifier();
+                let token = name.token().clone();
+                self.next_token();
+                self.next_token();
+                let value = self.parse_expression(Precedence::Lowest)?;
+                args.push(Box::new(NamedArgument::new(token, name, value)));
+            } else if named {
+                return Err("positional argument follows named argument".to_string());
+            } else if self.cur_token_is(TokenType::Ellipsis) {
+                let token = self.take_token();
+                self.next_token();
+                let value = self.parse_expression(Precedence::Lowest)?;
+                args.push(Box::new(SpreadExpression::new(token, value)));
+            } else {
+                args.push(self.parse_expression(Precedence::Lowest)?);
+            }
+            if !self.peek_token_is(TokenType::Rparen) && !self.expect_peek(TokenType::Comma) {
+                return Err(format!(
+                    "expected next token to be Comma, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+        }
+        self.next_token();
+        Ok(args)
+    }
+
+    pub fn parse_expression_list(
+        &mut self,
+        end: TokenType,
+    ) -> Result<Vec<Box<dyn Expression>>, String> {
         let mut args = Vec::new();
 
-        if self.peek_token_is(TokenType::Rparen) {
+        if self.peek_token_is(end) {
             self.next_token();
             return Ok(args);
         }
@@ -398,13 +675,808 @@ impl Parser {
             self.next_token();
             args.push(self.parse_expression(Precedence::Lowest)?);
         }
-        if !self.peek_token_is(TokenType::Rparen) {
+        if !self.peek_token_is(end) {
+            return Err(format!(
+                "expected next token to be {:?}, got {:?} instead",
+                end, self.peek_token
+            ));
+        }
+        self.next_token();
+        Ok(args)
+    }
+
+    pub fn parse_array_literal(&mut self) -> Result<Box<dyn Expression>, String> {
+        let token = self.take_token();
+        let elements = self.parse_expression_list(TokenType::Rbracket)?;
+        Ok(Box::new(ArrayLiteral::new(token, elements)))
+    }
+
+    pub fn parse_index_expression(
+        &mut self,
+        left: Box<dyn Expression>,
+    ) -> Result<Box<dyn Expression>, String> {
+        let token = self.take_token();
+        let optional = token.token_type() == TokenType::QuestionLbracket;
+        self.next_token();
+        let index = self.parse_expression(Precedence::Lowest)?;
+        if !self.expect_peek(TokenType::Rbracket) {
+            return Err(format!(
+                "expected next token to be Rbracket, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        Ok(Box::new(IndexExpression::new(token, left, index, optional)))
+    }
+
+    pub fn parse_member_expression(
+        &mut self,
+        object: Box<dyn Expression>,
+    ) -> Result<Box<dyn Expression>, String> {
+        let token = self.take_token();
+        let optional = token.token_type() == TokenType::QuestionDot;
+        if !self.expect_peek(TokenType::Ident) {
+            return Err(format!(
+                "expected next token to be Ident, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        let property = self.take_identifier();
+        Ok(Box::new(MemberExpression::new(
+            token, object, property, optional,
+        )))
+    }
+
+    pub fn parse_null_literal(&mut self) -> Result<Box<dyn Expression>, String> {
+        let token = self.take_token();
+        Ok(Box::new(NullLiteral::new(token)))
+    }
+
+    pub fn parse_string_literal(&mut self) -> Result<Box<dyn Expression>, String> {
+        let token = self.take_token();
+        let value = string_value(&token)?;
+        Ok(Box::new(StringLiteral::new(token, value)))
+    }
+
+    pub fn parse_interpolated_string(&mut self) -> Result<Box<dyn Expression>, String> {
+        let token = self.take_token();
+        let mut parts = vec![StringPart::Literal(string_value(&token)?)];
+        loop {
+            self.next_token();
+            parts.push(StringPart::Expression(
+                self.parse_expression(Precedence::Lowest)?,
+            ));
+            self.next_token();
+            let part = self.take_token();
+            match part.token_type() {
+                TokenType::StrMiddle => parts.push(StringPart::Literal(string_value(&part)?)),
+                TokenType::StrEnd => {
+                    parts.push(StringPart::Literal(string_value(&part)?));
+                    break;
+                },
+                TokenType::Illegal => return Err(illegal_token(&part)),
+                t => {
+                    return Err(format!(
+                        "expected interpolation to be closed by Rbrace, got {:?} instead",
+                        t
+                    ))
+                },
+            }
+        }
+        parts.retain(|part| !matches!(part, StringPart::Literal(s) if s.is_empty()));
+        Ok(Box::new(InterpolatedString::new(token, parts)))
+    }
+
+    pub fn parse_hash_literal(&mut self) -> Result<Box<dyn Expression>, String> {
+        let token = self.take_token();
+        let mut pairs = Vec::new();
+        while !self.peek_token_is(TokenType::Rbrace) {
+            self.next_token();
+            let key = self.parse_expression(Precedence::Lowest)?;
+            if !self.expect_peek(TokenType::Colon) {
+                return Err(format!(
+                    "expected next token to be Colon, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+            self.next_token();
+            let value = self.parse_expression(Precedence::Lowest)?;
+            pairs.push((key, value));
+            if !self.peek_token_is(TokenType::Rbrace) && !self.expect_peek(TokenType::Comma) {
+                return Err(format!(
+                    "expected next token to be Comma, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+        }
+        self.next_token();
+        Ok(Box::new(HashLiteral::new(token, pairs)))
+    }
+
+    pub fn parse_assign_expression(
+        &mut self,
+        target: Box<dyn Expression>,
+    ) -> Result<Box<dyn Expression>, String> {
+        // only names and (possibly nested) index and member expressions on names can be
+        // assigned to
+        let mut root: &dyn Expression = target.as_ref();
+        loop {
+            if let Some(index) = root.as_any().downcast_ref::<IndexExpression>() {
+                if index.optional() {
+                    return Err(format!("invalid assignment target: {}", target));
+                }
+                root = index.left();
+            } else if let Some(member) = root.as_any().downcast_ref::<MemberExpression>() {
+                if member.optional() {
+                    return Err(format!("invalid assignment target: {}", target));
+                }
+                root = member.object();
+            } else {
+                break;
+            }
+        }
+        if root.node_type() != NodeType::Identifier {
+            return Err(format!("invalid assignment target: {}", target));
+        }
+        let token = self.take_token();
+        let operator = token.literal().to_string();
+        self.next_token();
+        // assignment is right-associative, so `a = b = c` assigns `c` to `b` first
+        let value = self.parse_expression(Precedence::Lowest)?;
+        Ok(Box::new(AssignExpression::new(
+            token, target, operator, value,
+        )))
+    }
+
+    pub fn parse_match_expression(&mut self) -> Result<Box<dyn Expression>, String> {
+        let token = self.take_token();
+        if !self.expect_peek(TokenType::Lparen) {
+            return Err(format!(
+                "expected next token to be Lparen, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        self.next_token();
+        let subject = self.parse_expression(Precedence::Lowest)?;
+        if !self.expect_peek(TokenType::Rparen) {
             return Err(format!(
                 "expected next token to be Rparen, got {:?} instead",
                 self.peek_token
             ));
         }
+        if !self.expect_peek(TokenType::Lbrace) {
+            return Err(format!(
+                "expected next token to be Lbrace, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        let mut arms = Vec::new();
+        while !self.peek_token_is(TokenType::Rbrace) {
+            self.next_token();
+            let pattern = self.parse_pattern()?;
+            check_bindings(pattern.as_ref(), &mut Vec::new())?;
+            let guard = if self.peek_token_is(TokenType::If) {
+                self.next_token();
+                self.next_token();
+                Some(self.parse_expression(Precedence::Lowest)?)
+            } else {
+                None
+            };
+            if !self.expect_peek(TokenType::FatArrow) {
+                return Err(format!(
+                    "expected next token to be FatArrow, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+            self.next_token();
+            // a brace starts a block rather than a hash, which needn't be followed by a comma
+            if self.cur_token_is(TokenType::Lbrace) {
+                let body = self.parse_block_statement()?;
+                arms.push(MatchArm::new(pattern, guard, Box::new(body)));
+                if self.peek_token_is(TokenType::Comma) {
+                    self.next_token();
+                }
+                continue;
+            }
+            let body = self.parse_expression(Precedence::Lowest)?;
+            arms.push(MatchArm::new(pattern, guard, body));
+            if !self.peek_token_is(TokenType::Rbrace) && !self.expect_peek(TokenType::Comma) {
+                return Err(format!(
+                    "expected next token to be Comma, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+        }
         self.next_token();
-        Ok(args)
+        Ok(Box::new(MatchExpression::new(token, subject, arms)))
+    }
+
+    pub fn parse_pattern(&mut self) -> Result<Box<dyn Pattern>, String> {
+        self.enter_nesting()?;
+        let pattern = self.parse_pattern_token()?;
+        self.depth -= 1;
+        Ok(pattern)
+    }
+
+    fn parse_pattern_token(&mut self) -> Result<Box<dyn Pattern>, String> {
+        let token = self.cur_token.take().unwrap();
+        if token.token_type() == TokenType::Ident && token.literal() != "_" {
+            let ident = identifier(token);
+            if self.peek_token_is(TokenType::Dot) {
+                return self.parse_variant_pattern(ident);
+            }
+            return Ok(Box::new(ident));
+        }
+        let token = token.into_owned();
+        match token.token_type() {
+            TokenType::Ident => Ok(Box::new(WildcardPattern::new(token))),
+            TokenType::Int => {
+                let value = parse_integer(&token, false)?;
+                Ok(Box::new(IntegerLiteral::new(token, value)))
+            },
+            TokenType::Minus if self.peek_token_is(TokenType::Int) => {
+                self.next_token();
+                let token = self.take_token();
+                let value = parse_integer(&token, true)?;
+                Ok(Box::new(IntegerLiteral::new(token, value)))
+            },
+            TokenType::Str => {
+                let value = string_value(&token)?;
+                Ok(Box::new(StringLiteral::new(token, value)))
+            },
+            TokenType::True | TokenType::False => {
+                let value = token.token_type() == TokenType::True;
+                Ok(Box::new(Boolean::new(token, value)))
+            },
+            TokenType::Null => Ok(Box::new(NullLiteral::new(token))),
+            TokenType::Lbracket => self.parse_array_pattern(token),
+            TokenType::Lbrace => self.parse_hash_pattern(token),
+            t => Err(format!("expected a pattern, got {:?} instead", t)),
+        }
+    }
+
+    pub fn parse_variant_pattern(
+        &mut self,
+        enum_name: Identifier,
+    ) -> Result<Box<dyn Pattern>, String> {
+        let token = enum_name.token().clone();
+        self.next_token();
+        if !self.expect_peek(TokenType::Ident) {
+            return Err(format!(
+                "expected next token to be Ident, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        let variant = self.take_identifier();
+        let fields = if self.expect_peek(TokenType::Lparen) {
+            let mut fields = Vec::new();
+            while !self.peek_token_is(TokenType::Rparen) {
+                self.next_token();
+                fields.push(self.parse_pattern()?);
+                if !self.peek_token_is(TokenType::Rparen) && !self.expect_peek(TokenType::Comma) {
+                    return Err(format!(
+                        "expected next token to be Comma, got {:?} instead",
+                        self.peek_token
+                    ));
+                }
+            }
+            self.next_token();
+            Some(fields)
+        } else {
+            None
+        };
+        Ok(Box::new(VariantPattern::new(
+            token, enum_name, variant, fields,
+        )))
+    }
+
+    pub fn parse_array_pattern(
+        &mut self,
+        token: Token<'static>,
+    ) -> Result<Box<dyn Pattern>, String> {
+        let mut elements: Vec<Box<dyn Pattern>> = Vec::new();
+        let mut has_rest = false;
+        while !self.peek_token_is(TokenType::Rbracket) {
+            self.next_token();
+            if self.cur_token_is(TokenType::DotDot) {
+                if has_rest {
+                    return Err("only one rest pattern is allowed in an array pattern".to_string());
+                }
+                has_rest = true;
+                let token = self.take_token();
+                let name = if self.expect_peek(TokenType::Ident) {
+                    Some(self.take_identifier())
+                } else {
+                    None
+                };
+                elements.push(Box::new(RestPattern::new(token, name)));
+            } else {
+                elements.push(self.parse_pattern()?);
+            }
+            if !self.peek_token_is(TokenType::Rbracket) && !self.expect_peek(TokenType::Comma) {
+                return Err(format!(
+                    "expected next token to be Comma, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+        }
+        self.next_token();
+        Ok(Box::new(ArrayPattern::new(token, elements)))
+    }
+
+    /// Hash patterns are keyed by names (`{name, age: years}`), strings or integers; a bare
+    /// name binds the value under that key to the same name.
+    pub fn parse_hash_pattern(
+        &mut self,
+        token: Token<'static>,
+    ) -> Result<Box<dyn Pattern>, String> {
+        let mut pairs: Vec<(Box<dyn Expression>, Box<dyn Pattern>)> = Vec::new();
+        while !self.peek_token_is(TokenType::Rbrace) {
+            self.next_token();
+            let key_token = self.cur_token.take().unwrap();
+            let name =
+                (key_token.token_type() == TokenType::Ident).then(|| identifier(key_token.clone()));
+            let key_token = match &name {
+                Some(name) => name.token().clone(),
+                None => key_token.into_owned(),
+            };
+            let key: Box<dyn Expression> = match key_token.token_type() {
+                TokenType::Ident | TokenType::Str => {
+                    let value = string_value(&key_token)?;
+                    Box::new(StringLiteral::new(key_token.clone(), value))
+                },
+                TokenType::Int => {
+                    let value = parse_integer(&key_token, false)?;
+                    Box::new(IntegerLiteral::new(key_token.clone(), value))
+                },
+                t => return Err(format!("expected a hash pattern key, got {:?} instead", t)),
+            };
+            let pattern: Box<dyn Pattern> = if self.expect_peek(TokenType::Colon) {
+                self.next_token();
+                self.parse_pattern()?
+            } else if let Some(name) = name {
+                Box::new(name)
+            } else {
+                return Err(format!(
+                    "expected next token to be Colon, got {:?} instead",
+                    self.peek_token
+                ));
+            };
+            pairs.push((key, pattern));
+            if !self.peek_token_is(TokenType::Rbrace) && !self.expect_peek(TokenType::Comma) {
+                return Err(format!(
+                    "expected next token to be Comma, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+        }
+        self.next_token();
+        Ok(Box::new(HashPattern::new(token, pairs)))
+    }
+
+    pub fn parse_while_statement(&mut self) -> Result<Box<dyn Statement>, String> {
+        let token = self.take_token();
+        if !self.expect_peek(TokenType::Lparen) {
+            return Err(format!(
+                "expected next token to be Lparen, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        self.next_token();
+        let condition = self.parse_expression(Precedence::Lowest)?;
+        if !self.expect_peek(TokenType::Rparen) {
+            return Err(format!(
+                "expected next token to be Rparen, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        let body = self.parse_loop_body()?;
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+        Ok(Box::new(WhileStatement::new(token, condition, body)))
+    }
+
+    pub fn parse_for_statement(&mut self) -> Result<Box<dyn Statement>, String> {
+        let token = self.take_token();
+        if !self.expect_peek(TokenType::Lparen) {
+            return Err(format!(
+                "expected next token to be Lparen, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        if !self.expect_peek(TokenType::Ident) {
+            return Err(format!(
+                "expected next token to be Ident, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        let variable = self.take_identifier();
+        if !self.expect_peek(TokenType::In) {
+            return Err(format!(
+                "expected next token to be In, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        self.next_token();
+        let iterable = self.parse_expression(Precedence::Lowest)?;
+        if !self.expect_peek(TokenType::Rparen) {
+            return Err(format!(
+                "expected next token to be Rparen, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        let body = self.parse_loop_body()?;
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+        Ok(Box::new(ForStatement::new(token, variable, iterable, body)))
+    }
+
+    pub fn parse_loop_body(&mut self) -> Result<BlockStatement, String> {
+        if !self.expect_peek(TokenType::Lbrace) {
+            return Err(format!(
+                "expected next token to be Lbrace, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    pub fn parse_loop_control_statement(&mut self) -> Result<Box<dyn Statement>, String> {
+        let token = self.take_token();
+        if self.loop_depth == 0 {
+            return Err(format!("{} outside of a loop", token.literal()));
+        }
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+        Ok(match token.token_type() {
+            TokenType::Break => Box::new(BreakStatement::new(token)),
+            _ => Box::new(ContinueStatement::new(token)),
+        })
+    }
+
+    pub fn parse_struct_statement(&mut self) -> Result<Box<dyn Statement>, String> {
+        let token = self.take_token();
+        if !self.expect_peek(TokenType::Ident) {
+            return Err(format!(
+                "expected next token to be Ident, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        let name = self.take_identifier();
+        if !self.expect_peek(TokenType::Lbrace) {
+            return Err(format!(
+                "expected next token to be Lbrace, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        let mut fields: Vec<Identifier> = Vec::new();
+        while !self.peek_token_is(TokenType::Rbrace) {
+            if !self.expect_peek(TokenType::Ident) {
+                return Err(format!(
+                    "expected next token to be Ident, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+            let field = self.take_identifier();
+            if fields.iter().any(|f| f.symbol() == field.symbol()) {
+                return Err(format!("duplicate field {} in struct {}", field, name));
+            }
+            fields.push(field);
+            if !self.peek_token_is(TokenType::Rbrace) && !self.expect_peek(TokenType::Comma) {
+                return Err(format!(
+                    "expected next token to be Comma, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+        }
+        self.next_token();
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+        Ok(Box::new(StructStatement::new(token, name, fields)))
+    }
+
+    pub fn parse_struct_literal(
+        &mut self,
+        name: Identifier,
+    ) -> Result<Box<dyn Expression>, String> {
+        let token = self.take_token();
+        let mut fields: Vec<StructField> = Vec::new();
+        while !self.peek_token_is(TokenType::Rbrace) {
+            if !self.expect_peek(TokenType::Ident) {
+                return Err(format!(
+                    "expected next token to be Ident, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+            let field = self.take_identifier();
+            let value: Box<dyn Expression> = if self.expect_peek(TokenType::Colon) {
+                self.next_token();
+                self.parse_expression(Precedence::Lowest)?
+            } else {
+                Box::new(Identifier::new(field.token().clone(), field.symbol()))
+            };
+            fields.push((field, value));
+            if !self.peek_token_is(TokenType::Rbrace) && !self.expect_peek(TokenType::Comma) {
+                return Err(format!(
+                    "expected next token to be Comma, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+        }
+        self.next_token();
+        Ok(Box::new(StructLiteral::new(token, name, fields)))
+    }
+
+    pub fn parse_enum_statement(&mut self) -> Result<Box<dyn Statement>, String> {
+        let token = self.take_token();
+        if !self.expect_peek(TokenType::Ident) {
+            return Err(format!(
+                "expected next token to be Ident, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        let name = self.take_identifier();
+        if !self.expect_peek(TokenType::Lbrace) {
+            return Err(format!(
+                "expected next token to be Lbrace, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        let mut variants: Vec<EnumVariant> = Vec::new();
+        while !self.peek_token_is(TokenType::Rbrace) {
+            if !self.expect_peek(TokenType::Ident) {
+                return Err(format!(
+                    "expected next token to be Ident, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+            let variant = self.take_identifier();
+            if variants
+                .iter()
+                .any(|v| v.name().symbol() == variant.symbol())
+            {
+                return Err(format!("duplicate variant {} in enum {}", variant, name));
+            }
+            let mut fields: Vec<Identifier> = Vec::new();
+            if self.expect_peek(TokenType::Lparen) {
+                while !self.peek_token_is(TokenType::Rparen) {
+                    if !self.expect_peek(TokenType::Ident) {
+                        return Err(format!(
+                            "expected next token to be Ident, got {:?} instead",
+                            self.peek_token
+                        ));
+                    }
+                    let field = self.take_identifier();
+                    if fields.iter().any(|f| f.symbol() == field.symbol()) {
+                        return Err(format!("duplicate field {} in variant {}", field, variant));
+                    }
+                    fields.push(field);
+                    if !self.peek_token_is(TokenType::Rparen) && !self.expect_peek(TokenType::Comma)
+                    {
+                        return Err(format!(
+                            "expected next token to be Comma, got {:?} instead",
+                            self.peek_token
+                        ));
+                    }
+                }
+                self.next_token();
+            }
+            variants.push(EnumVariant::new(variant, fields));
+            if !self.peek_token_is(TokenType::Rbrace) && !self.expect_peek(TokenType::Comma) {
+                return Err(format!(
+                    "expected next token to be Comma, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+        }
+        self.next_token();
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+        Ok(Box::new(EnumStatement::new(token, name, variants)))
+    }
+
+    pub fn parse_throw_statement(&mut self) -> Result<Box<dyn Statement>, String> {
+        let token = self.take_token();
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+        Ok(Box::new(ThrowStatement::new(token, value)))
+    }
+
+    pub fn parse_yield_statement(&mut self) -> Result<Box<dyn Statement>, String> {
+        let token = self.take_token();
+        if !self.in_generator {
+            return Err("yield outside of a generator".to_string());
+        }
+        self.yields.push(token.span());
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+        Ok(Box::new(YieldStatement::new(token, value)))
+    }
+
+    pub fn parse_try_expression(&mut self) -> Result<Box<dyn Expression>, String> {
+        let token = self.take_token();
+        if !self.expect_peek(TokenType::Lbrace) {
+            return Err(format!(
+                "expected next token to be Lbrace, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        let body = self.parse_block_statement()?;
+        let catch = if self.peek_token_is(TokenType::Catch) {
+            self.next_token();
+            if !self.expect_peek(TokenType::Lparen) {
+                return Err(format!(
+                    "expected next token to be Lparen, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+            if !self.expect_peek(TokenType::Ident) {
+                return Err(format!(
+                    "expected next token to be Ident, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+            let name = self.take_identifier();
+            if !self.expect_peek(TokenType::Rparen) {
+                return Err(format!(
+                    "expected next token to be Rparen, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+            if !self.expect_peek(TokenType::Lbrace) {
+                return Err(format!(
+                    "expected next token to be Lbrace, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+            Some((name, self.parse_block_statement()?))
+        } else {
+            None
+        };
+        let finally = if self.peek_token_is(TokenType::Finally) {
+            self.next_token();
+            if !self.expect_peek(TokenType::Lbrace) {
+                return Err(format!(
+                    "expected next token to be Lbrace, got {:?} instead",
+                    self.peek_token
+                ));
+            }
+            Some(self.parse_block_statement()?)
+        } else {
+            None
+        };
+        if catch.is_none() && finally.is_none() {
+            return Err(format!(
+                "expected next token to be Catch or Finally, got {:?} instead",
+                self.peek_token
+            ));
+        }
+        Ok(Box::new(TryExpression::new(token, body, catch, finally)))
+    }
+}
+
+/// The value of an integer literal, negated when it's written after a `-` in a pattern.
+fn parse_integer(token: &Token, negative: bool) -> Result<i64, String> {
+    let value = match negative {
+        true => format!("-{}", token.literal()).parse::<i64>(),
+        false => token.literal().parse::<i64>(),
+    };
+    value.map_err(|_| format!("integer literal out of range at {}", token.span()))
+}
+
+/// The value of a string literal's contents, with their escapes replaced.
+fn string_value(token: &Token) -> Result<String, String> {
+    // the contents start after the `"` or `}` the token does
+    let span = token.span();
+    unescape(token.literal(), Span::new(span.line(), span.column() + 1))
+}
+
+/// Fails if a function already has a parameter named like `param`, which both engines would
+/// otherwise have to pick one of.
+fn check_parameter(parameters: &Parameters, param: &Identifier) -> Result<(), String> {
+    if parameters
+        .names()
+        .iter()
+        .any(|p| p.symbol() == param.symbol())
+    {
+        return Err(format!("duplicate parameter {} at {}", param, param.span()));
+    }
+    Ok(())
+}
+
+/// Describes a token the lexer couldn't make sense of, such as a string that never ends.
+fn illegal_token(token: &Token) -> String {
+    format!("illegal token: {} at {}", token.literal(), token.span())
+}
+
+/// Fails if a pattern binds a name that it's already bound to in `names`, which both engines
+/// would otherwise have to pick one of the values for.
+fn check_bindings<'a>(
+    pattern: &'a dyn Node,
+    names: &mut Vec<&'a Identifier>,
+) -> Result<(), String> {
+    let any = pattern.as_any();
+    let name = match pattern.node_type() {
+        NodeType::Identifier => any.downcast_ref::<Identifier>(),
+        NodeType::RestPattern => any.downcast_ref::<RestPattern>().unwrap().name(),
+        NodeType::ArrayPattern => {
+            let elements = any.downcast_ref::<ArrayPattern>().unwrap().elements();
+            return elements
+                .iter()
+                .try_for_each(|element| check_bindings(element.as_ref(), names));
+        },
+        NodeType::HashPattern => {
+            let pairs = any.downcast_ref::<HashPattern>().unwrap().pairs();
+            return pairs
+                .iter()
+                .try_for_each(|(_, value)| check_bindings(value.as_ref(), names));
+        },
+        NodeType::VariantPattern => {
+            let fields = any.downcast_ref::<VariantPattern>().unwrap().fields();
+            return fields
+                .into_iter()
+                .flatten()
+                .try_for_each(|field| check_bindings(field.as_ref(), names));
+        },
+        _ => None,
+    };
+    if let Some(name) = name {
+        if names.iter().any(|n| n.symbol() == name.symbol()) {
+            return Err(format!("duplicate binding {} at {}", name, name.span()));
+        }
+        names.push(name);
+    }
+    Ok(())
+}
+
+/// The identifier an identifier token is, which owns its name by interning it.
+fn identifier(token: Token) -> Identifier {
+    let symbol = token.symbol();
+    let span = token.span();
+    Identifier::new(
+        Token::from_symbol(token.token_type(), symbol).with_span(span),
+        symbol,
+    )
+}
+
+/// Adds where the yields a generator's evaluation can be suspended at are in a block of it,
+/// which are those directly in it or in the blocks of the if expressions, while loops and for
+/// loops directly in it.
+fn suspendable_yields(block: &BlockStatement, spans: &mut Vec<Span>) {
+    for stmt in block.statements() {
+        match stmt.node_type() {
+            NodeType::YieldStatement => spans.push(stmt.span()),
+            NodeType::ExpressionStatement => {
+                let expr = stmt.as_any().downcast_ref::<ExpressionStatement>().unwrap();
+                if let Some(expr) = expr.expression().as_any().downcast_ref::<IfExpression>() {
+                    suspendable_yields(expr.consequence(), spans);
+                    if let Some(alternative) = expr.alternative() {
+                        suspendable_yields(alternative, spans);
+                    }
+                }
+            },
+            NodeType::WhileStatement => {
+                let stmt = stmt.as_any().downcast_ref::<WhileStatement>().unwrap();
+                suspendable_yields(stmt.body(), spans);
+            },
+            NodeType::ForStatement => {
+                let stmt = stmt.as_any().downcast_ref::<ForStatement>().unwrap();
+                suspendable_yields(stmt.body(), spans);
+            },
+            _ => {},
+        }
     }
 }
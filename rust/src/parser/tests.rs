@@ -10,7 +10,7 @@ mod tests {
 let y = 10;
 let foobar = 838383;"#,
         );
-        let l = Lexer::new(input);
+        let l = Lexer::new(&input);
         let mut p = Parser::new(l);
         let program = p.parse_program();
         assert!(program.is_ok());
@@ -36,7 +36,7 @@ return 993322;
 "#,
         );
 
-        let l = Lexer::new(input);
+        let l = Lexer::new(&input);
         let mut p = Parser::new(l);
         let program = p.parse_program();
         assert!(program.is_ok());
@@ -51,7 +51,7 @@ return 993322;
     #[test]
     fn test_identifier() {
         let input = String::from("foobar;");
-        let l = Lexer::new(input);
+        let l = Lexer::new(&input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
         assert_eq!(program.statements().len(), 1);
@@ -69,7 +69,7 @@ return 993322;
     #[test]
     fn test_integer_literal_expression() {
         let input = String::from("5");
-        let l = Lexer::new(input);
+        let l = Lexer::new(&input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
         assert_eq!(program.statements().len(), 1);
@@ -87,13 +87,19 @@ return 993322;
             .unwrap();
         assert_eq!(literal.value(), 5);
         assert_eq!(literal.token_literal(), "5");
+
+        let mut p = Parser::new(Lexer::new("1 + 9223372036854775808"));
+        assert_eq!(
+            p.parse_program().unwrap_err(),
+            "integer literal out of range at 1:5"
+        );
     }
 
     #[test]
     fn test_parsing_prefix_expressions() {
         let prefix_tests = vec![("!5", "!", 5), ("-15", "-", 15)];
         for (input, operator, value) in prefix_tests {
-            let l = Lexer::new(input.to_string());
+            let l = Lexer::new(input);
             let mut p = Parser::new(l);
             let program = p.parse_program().unwrap();
             assert_eq!(program.statements().len(), 1);
@@ -124,10 +130,21 @@ return 993322;
             ("5 < 5", 5, "<", 5),
             ("5 == 5", 5, "==", 5),
             ("5 != 5", 5, "!=", 5),
+            ("5 <= 5", 5, "<=", 5),
+            ("5 >= 5", 5, ">=", 5),
+            ("5 % 5", 5, "%", 5),
+            ("5 ** 5", 5, "**", 5),
+            ("5 && 5", 5, "&&", 5),
+            ("5 || 5", 5, "||", 5),
+            ("5 & 5", 5, "&", 5),
+            ("5 | 5", 5, "|", 5),
+            ("5 ^ 5", 5, "^", 5),
+            ("5 << 5", 5, "<<", 5),
+            ("5 >> 5", 5, ">>", 5),
         ];
 
         for (input, expected_left, op, expected_right) in infix_tests {
-            let l = Lexer::new(input.to_string());
+            let l = Lexer::new(input);
             let mut p = Parser::new(l);
             let program = p.parse_program().unwrap();
             assert_eq!(program.statements().len(), 1);
@@ -186,10 +203,29 @@ return 993322;
                 "add(a + b + c * d / f + g)",
                 "add((((a + b) + ((c * d) / f)) + g))",
             ),
+            ("a <= b == b >= a", "((a <= b) == (b >= a))"),
+            ("a * b % c", "((a * b) % c)"),
+            ("a ** b ** c", "(a ** (b ** c))"),
+            ("-a ** b", "(-(a ** b))"),
+            ("a * b ** c", "(a * (b ** c))"),
+            ("a || b && c", "(a || (b && c))"),
+            ("a && b || c", "((a && b) || c)"),
+            ("a == b && c != d", "((a == b) && (c != d))"),
+            ("a | b ^ c & d", "(a | (b ^ (c & d)))"),
+            ("a & b == c", "((a & b) == c)"),
+            ("a << b + c", "(a << (b + c))"),
+            ("a < b << c", "(a < (b << c))"),
+            ("~a & b", "((~a) & b)"),
+            ("!~a", "(!(~a))"),
+            ("0..n + 1", "(0 .. (n + 1))"),
+            ("a..=b * 2", "(a ..= (b * 2))"),
+            ("a ?? 0..b || c", "((a ?? 0) .. (b || c))"),
+            ("x = 1..2", "(x = (1 .. 2))"),
+            ("a[1..len(a)]", "(a[(1 .. len(a))])"),
         ];
 
         for (input, output) in precedence_tests {
-            let l = Lexer::new(input.to_string());
+            let l = Lexer::new(input);
             let mut p = Parser::new(l);
             let program = p.parse_program().unwrap();
             let actual = program.to_string().trim().to_string();
@@ -202,7 +238,7 @@ return 993322;
         let tests = vec![("true", true), ("false", false)];
 
         for (input, expected) in tests {
-            let l = Lexer::new(input.to_string());
+            let l = Lexer::new(input);
             let mut p = Parser::new(l);
             let program = p.parse_program().unwrap();
             assert_eq!(program.statements().len(), 1);
@@ -222,7 +258,7 @@ return 993322;
     #[test]
     fn test_if_expression() {
         let input = String::from("if (x < y) { x }");
-        let l = Lexer::new(input);
+        let l = Lexer::new(&input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
         assert_eq!(program.statements().len(), 1);
@@ -269,7 +305,7 @@ return 993322;
     #[test]
     fn test_if_else_expression() {
         let input = String::from("if (x < y) { x } else { y }");
-        let l = Lexer::new(input);
+        let l = Lexer::new(&input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
         assert_eq!(program.statements().len(), 1);
@@ -331,7 +367,7 @@ return 993322;
     #[test]
     fn test_function_literal() {
         let input = String::from("fn(x, y) { x + y; }");
-        let l = Lexer::new(input);
+        let l = Lexer::new(&input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
         assert_eq!(program.statements().len(), 1);
@@ -379,7 +415,7 @@ return 993322;
             ("fn(x, y, z) {};", vec!["x", "y", "z"]),
         ];
         for (input, expected_params) in input {
-            let l = Lexer::new(input.to_string());
+            let l = Lexer::new(input);
             let mut p = Parser::new(l);
             let program = p.parse_program().unwrap();
             let stmt = program
@@ -401,7 +437,7 @@ return 993322;
     #[test]
     fn test_call_expression_parsing() {
         let input = String::from("add(1, 2 * 3, 4 + 5);");
-        let l = Lexer::new(input);
+        let l = Lexer::new(&input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
         let stmt = program
@@ -440,4 +476,858 @@ return 993322;
         let int = right.as_any().downcast_ref::<IntegerLiteral>().unwrap();
         assert_eq!(int.value(), 5);
     }
+
+    #[test]
+    fn test_array_literal_parsing() {
+        let input = String::from("[1, 2 * 2, 3 + 3]");
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let stmt = program
+            .statements()
+            .first()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()
+            .unwrap();
+        let array = stmt
+            .expression()
+            .as_any()
+            .downcast_ref::<ArrayLiteral>()
+            .unwrap();
+        assert_eq!(array.elements().len(), 3);
+        let int = array.elements()[0]
+            .as_any()
+            .downcast_ref::<IntegerLiteral>()
+            .unwrap();
+        assert_eq!(int.value(), 1);
+        assert_eq!(array.elements()[1].to_string(), "(2 * 2)");
+        assert_eq!(array.elements()[2].to_string(), "(3 + 3)");
+    }
+
+    #[test]
+    fn test_index_expression_parsing() {
+        let tests = vec![
+            ("myArray[1 + 1]", "(myArray[(1 + 1)])"),
+            (
+                "a * [1, 2, 3, 4][b * c] * d",
+                "((a * ([1, 2, 3, 4][(b * c)])) * d)",
+            ),
+            (
+                "add(a * b[2], b[1], 2 * [1, 2][1])",
+                "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])))",
+            ),
+        ];
+        for (input, output) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program().unwrap();
+            assert_eq!(program.to_string(), output);
+        }
+    }
+
+    #[test]
+    fn test_while_statement() {
+        let input = String::from("while (x < y) { x; break; }");
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.statements().len(), 1);
+        let stmt = program
+            .statements()
+            .first()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<WhileStatement>()
+            .unwrap();
+        assert_eq!(stmt.condition().to_string(), "(x < y)");
+        assert_eq!(stmt.body().statements().len(), 2);
+        assert_eq!(stmt.body().statements()[1].token_type(), Break);
+    }
+
+    #[test]
+    fn test_for_statement() {
+        let input = String::from("for (x in xs) { if (x) { continue; } x }");
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.statements().len(), 1);
+        let stmt = program
+            .statements()
+            .first()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ForStatement>()
+            .unwrap();
+        assert_eq!(stmt.variable().value(), "x");
+        assert_eq!(stmt.iterable().to_string(), "xs");
+        assert_eq!(stmt.body().statements().len(), 2);
+        assert_eq!(program.to_string(), "for (x in xs) {if x {continue;}x}");
+    }
+
+    #[test]
+    fn test_loops_followed_by_semicolons() {
+        let tests = vec![
+            ("while (c) {}; x", "while c {}x"),
+            ("for (x in xs) {}; x", "for (x in xs) {}x"),
+            (
+                "while (c) { for (x in xs) { x }; };",
+                "while c {for (x in xs) {x}}",
+            ),
+        ];
+        for (input, output) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p
+                .parse_program()
+                .unwrap_or_else(|e| panic!("{}: {}", input, e));
+            assert_eq!(program.to_string(), output, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_loop_control_outside_of_loop() {
+        let tests = vec![
+            ("break;", "break outside of a loop"),
+            ("if (true) { continue; }", "continue outside of a loop"),
+            (
+                "while (true) { fn() { break; } }",
+                "break outside of a loop",
+            ),
+        ];
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn test_string_literal_expression() {
+        let input = String::from(r#""hello world";"#);
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let stmt = program
+            .statements()
+            .first()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()
+            .unwrap();
+        let literal = stmt
+            .expression()
+            .as_any()
+            .downcast_ref::<StringLiteral>()
+            .unwrap();
+        assert_eq!(literal.value(), "hello world");
+    }
+
+    #[test]
+    fn test_hash_literal_parsing() {
+        let tests = vec![
+            ("{}", "{}"),
+            (r#"{"one": 1, "two": 2}"#, r#"{"one": 1, "two": 2}"#),
+            (
+                r#"{"one": 0 + 1, "two": 10 - 8, true: 15 / 5,}"#,
+                r#"{"one": (0 + 1), "two": (10 - 8), true: (15 / 5)}"#,
+            ),
+        ];
+        for (input, output) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program().unwrap();
+            let stmt = program
+                .statements()
+                .first()
+                .unwrap()
+                .as_any()
+                .downcast_ref::<ExpressionStatement>()
+                .unwrap();
+            let hash = stmt
+                .expression()
+                .as_any()
+                .downcast_ref::<HashLiteral>()
+                .unwrap();
+            assert_eq!(hash.to_string(), output);
+        }
+    }
+
+    #[test]
+    fn test_assign_expression_parsing() {
+        let tests = vec![
+            ("x = 5", "(x = 5)"),
+            ("x += 5 * 2", "(x += (5 * 2))"),
+            ("x = y = 5", "(x = (y = 5))"),
+            ("x -= y || z", "(x -= (y || z))"),
+            ("a[1] = 2", "((a[1]) = 2)"),
+            (r#"h["k"][0] **= 2"#, r#"(((h["k"])[0]) **= 2)"#),
+        ];
+        for (input, output) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program().unwrap();
+            let stmt = program
+                .statements()
+                .first()
+                .unwrap()
+                .as_any()
+                .downcast_ref::<ExpressionStatement>()
+                .unwrap();
+            let assign = stmt
+                .expression()
+                .as_any()
+                .downcast_ref::<AssignExpression>()
+                .unwrap();
+            assert_eq!(assign.to_string(), output);
+        }
+    }
+
+    #[test]
+    fn test_invalid_assignment_target() {
+        let tests = vec![
+            ("1 = 2", "invalid assignment target: 1"),
+            ("a + b = c", "invalid assignment target: (a + b)"),
+            ("f()[0] = 1", "invalid assignment target: (f()[0])"),
+        ];
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn test_nullish_and_optional_parsing() {
+        let tests = vec![
+            ("null", "null"),
+            ("a ?? b", "(a ?? b)"),
+            ("a ?? b || c", "(a ?? (b || c))"),
+            ("x = a ?? b", "(x = (a ?? b))"),
+            ("a?[0]", "(a?[0])"),
+            ("a?.b", "(a?.b)"),
+            ("a?.b?[c + 1][2]", "(((a?.b)?[(c + 1)])[2])"),
+            ("a?.b ?? 1 + 2", "((a?.b) ?? (1 + 2))"),
+        ];
+        for (input, output) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program().unwrap();
+            assert_eq!(program.to_string(), output);
+        }
+
+        let l = Lexer::new("a?.b = 1");
+        let mut p = Parser::new(l);
+        assert_eq!(
+            p.parse_program().unwrap_err(),
+            "invalid assignment target: (a?.b)"
+        );
+    }
+
+    #[test]
+    fn test_match_expression() {
+        let input = String::from(
+            r#"match (x + 1) {
+    0 => "zero",
+    -1 => "minus one",
+    [first, ..rest] if first > 1 => rest,
+    {name, "age": years, 3: [_, ..]} => name,
+    n => n * 2,
+}"#,
+        );
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.statements().len(), 1);
+        let stmt = program
+            .statements()
+            .first()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()
+            .unwrap();
+        let expr = stmt
+            .expression()
+            .as_any()
+            .downcast_ref::<MatchExpression>()
+            .unwrap();
+        assert_eq!(expr.subject().to_string(), "(x + 1)");
+        assert_eq!(expr.arms().len(), 5);
+        let arm = &expr.arms()[2];
+        let pattern = arm
+            .pattern()
+            .as_any()
+            .downcast_ref::<ArrayPattern>()
+            .unwrap();
+        assert_eq!(pattern.elements().len(), 2);
+        assert_eq!(pattern.elements()[1].node_type(), NodeType::RestPattern);
+        assert_eq!(arm.guard().unwrap().to_string(), "(first > 1)");
+        assert_eq!(
+            program.to_string(),
+            r#"match ((x + 1)) {0 => "zero", -1 => "minus one", [first, ..rest] if (first > 1) => rest, {"name": name, "age": years, 3: [_, ..]} => name, n => (n * 2)}"#
+        );
+
+        // braces after an arrow start a block, which needn't be followed by a comma
+        let input = "match (x) { 0 => { y; 1 } 1 => {} _ => { 2 }, }";
+        let mut p = Parser::new(Lexer::new(input));
+        let program = p.parse_program().unwrap();
+        let stmt = program.statements()[0].as_any();
+        let stmt = stmt.downcast_ref::<ExpressionStatement>().unwrap();
+        let expr = stmt.expression().as_any();
+        let arms = expr.downcast_ref::<MatchExpression>().unwrap().arms();
+        assert_eq!(arms.len(), 3);
+        for arm in arms {
+            assert_eq!(arm.body().node_type(), NodeType::BlockStatement);
+        }
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        let tests = vec![
+            (
+                "match (x) { 1 + 2 => 3 }",
+                "expected next token to be FatArrow",
+            ),
+            ("match (x) { [..a, ..b] => 3 }", "only one rest pattern"),
+            (
+                "match (x) { {\"a\"} => 3 }",
+                "expected next token to be Colon",
+            ),
+            ("match (x) { fn => 3 }", "expected a pattern, got Function"),
+            (
+                r#"match (x) { "\q" => 3 }"#,
+                r"unknown escape sequence \q at 1:14",
+            ),
+            (
+                "match (x) { -9223372036854775809 => 3 }",
+                "integer literal out of range at 1:14",
+            ),
+            (
+                "match (x) { {9223372036854775808: a} => 3 }",
+                "integer literal out of range at 1:14",
+            ),
+            (
+                "match (x) { 1 => { 2 } 3 => 4 5 => 6 }",
+                "expected next token to be Comma",
+            ),
+            ("match (x) { [a, a] => 3 }", "duplicate binding a at 1:17"),
+            (
+                "match (x) { [a, [b, ..a]] => 3 }",
+                "duplicate binding a at 1:23",
+            ),
+            (
+                "match (x) { {a, b: a} => 3 }",
+                "duplicate binding a at 1:20",
+            ),
+            (
+                "match (x) { E.V(a, {k: a}) => 3 }",
+                "duplicate binding a at 1:24",
+            ),
+            ("let [a, a] = [1, 2]", "duplicate binding a at 1:9"),
+            ("let {a, a} = {}", "duplicate binding a at 1:9"),
+        ];
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let err = p.parse_program().unwrap_err();
+            assert!(err.starts_with(expected), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_destructuring_let_statements() {
+        let tests = vec![
+            ("let [a, b, ..rest] = arr;", "let [a, b, ..rest] = arr;"),
+            (
+                "let {name, age: years} = person;",
+                r#"let {"name": name, "age": years} = person;"#,
+            ),
+            (
+                "let [{x}, [_, y]] = points",
+                r#"let [{"x": x}, [_, y]] = points;"#,
+            ),
+        ];
+        for (input, output) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program().unwrap();
+            assert_eq!(program.statements().len(), 1);
+            let stmt = program
+                .statements()
+                .first()
+                .unwrap()
+                .as_any()
+                .downcast_ref::<DestructuringLetStatement>()
+                .unwrap();
+            assert_eq!(stmt.token_type(), Let);
+            assert_eq!(stmt.to_string(), output);
+        }
+    }
+
+    #[test]
+    fn test_function_parameter_defaults_and_rest() {
+        let input = String::from("fn(a, b = 2, c = a + b, ...rest) { a }");
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let stmt = program
+            .statements()
+            .first()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()
+            .unwrap();
+        let func = stmt
+            .expression()
+            .as_any()
+            .downcast_ref::<FunctionLiteral>()
+            .unwrap();
+        let signature = func.signature();
+        assert_eq!(func.parameters().len(), 3);
+        assert!(signature.default_value(0).is_none());
+        assert_eq!(signature.default_value(1).unwrap().to_string(), "2");
+        assert_eq!(signature.default_value(2).unwrap().to_string(), "(a + b)");
+        assert_eq!(signature.rest().unwrap().value(), "rest");
+        assert_eq!(signature.required(), 1);
+        assert_eq!(
+            program.to_string(),
+            "fn(a, b = 2, c = (a + b), ...rest) {a}"
+        );
+    }
+
+    #[test]
+    fn test_invalid_function_parameters() {
+        let tests = vec![
+            ("fn(1) {}", "expected next token to be Ident"),
+            ("fn(a, 1) {}", "expected next token to be Ident"),
+            ("fn(a b) {}", "expected next token to be Comma"),
+            ("fn(...rest, a) {}", "expected next token to be Rparen"),
+            (
+                "fn(a = 1, b) {}",
+                "parameter b without a default follows a parameter with a default",
+            ),
+            ("fn(a, a) {}", "duplicate parameter a at 1:7"),
+            ("fn(a, b = 1, ...a) {}", "duplicate parameter a at 1:17"),
+        ];
+        for (input, expected) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let err = p.parse_program().unwrap_err();
+            assert!(err.starts_with(expected), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_call_arguments_spread_and_named() {
+        let l = Lexer::new("f(1, ...xs, ...[2], b: 3, c: 4 * 5)");
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.to_string(), "f(1, ...xs, ...[2], b: 3, c: (4 * 5))");
+
+        let l = Lexer::new("f(b: 3, 1)");
+        let mut p = Parser::new(l);
+        assert_eq!(
+            p.parse_program().unwrap_err(),
+            "positional argument follows named argument"
+        );
+    }
+
+    #[test]
+    fn test_interpolated_string_parsing() {
+        let input = String::from(r#""hello ${name}, you are ${age + 1}""#);
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let stmt = program
+            .statements()
+            .first()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()
+            .unwrap();
+        let string = stmt
+            .expression()
+            .as_any()
+            .downcast_ref::<InterpolatedString>()
+            .unwrap();
+        assert_eq!(string.parts().len(), 4);
+        assert!(matches!(&string.parts()[0], StringPart::Literal(s) if s == "hello "));
+        assert!(matches!(&string.parts()[1], StringPart::Expression(e) if e.to_string() == "name"));
+        assert!(matches!(&string.parts()[2], StringPart::Literal(s) if s == ", you are "));
+        assert!(
+            matches!(&string.parts()[3], StringPart::Expression(e) if e.to_string() == "(age + 1)")
+        );
+        assert_eq!(
+            program.to_string(),
+            r#""hello ${name}, you are ${(age + 1)}""#
+        );
+
+        let l = Lexer::new(r#""a ${1 2}""#);
+        let mut p = Parser::new(l);
+        assert_eq!(
+            p.parse_program().unwrap_err(),
+            "expected interpolation to be closed by Rbrace, got Int instead"
+        );
+
+        let l = Lexer::new("x;\n\"${1}\\n\\d\"");
+        let mut p = Parser::new(l);
+        assert_eq!(
+            p.parse_program().unwrap_err(),
+            r"unknown escape sequence \d at 2:8"
+        );
+
+        for (input, expected) in [
+            (
+                r#"let s = "abc"#,
+                "illegal token: unterminated string at 1:9",
+            ),
+            (r#""hi ${x}"#, "illegal token: unterminated string at 1:8"),
+            (r#""ab\"#, "illegal token: unterminated string at 1:1"),
+        ] {
+            let mut p = Parser::new(Lexer::new(input));
+            assert_eq!(p.parse_program().unwrap_err(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_member_expression_parsing() {
+        let tests = vec![
+            ("a.b", "(a.b)"),
+            ("a.b.c", "((a.b).c)"),
+            ("a.b[0].c", "(((a.b)[0]).c)"),
+            ("a.len()", "(a.len)()"),
+            ("a.map(f).len()", "((a.map)(f).len)()"),
+            ("-a.b", "(-(a.b))"),
+            ("a.b * c.d", "((a.b) * (c.d))"),
+            ("a?.b.c", "((a?.b).c)"),
+            ("a.b = 1", "((a.b) = 1)"),
+            ("a.b[0].c += 1", "((((a.b)[0]).c) += 1)"),
+        ];
+        for (input, output) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program().unwrap();
+            assert_eq!(program.to_string(), output);
+        }
+
+        let l = Lexer::new("a.b");
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let stmt = program
+            .statements()
+            .first()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()
+            .unwrap();
+        let member = stmt
+            .expression()
+            .as_any()
+            .downcast_ref::<MemberExpression>()
+            .unwrap();
+        assert_eq!(member.object().to_string(), "a");
+        assert_eq!(member.property().value(), "b");
+        assert!(!member.optional());
+
+        let errors = vec![
+            ("a.1", "expected next token to be Ident, got Some(Token { token_type: Int, literal: \"1\" }) instead"),
+            ("a.b() = 1", "invalid assignment target: (a.b)()"),
+            ("a?.b.c = 1", "invalid assignment target: ((a?.b).c)"),
+        ];
+        for (input, expected) in errors {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn test_struct_parsing() {
+        let input = String::from("struct Point { x, y, }");
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.statements().len(), 1);
+        let stmt = program
+            .statements()
+            .first()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StructStatement>()
+            .unwrap();
+        assert_eq!(stmt.name().value(), "Point");
+        let fields = stmt.fields().iter().map(|f| f.value()).collect::<Vec<_>>();
+        assert_eq!(fields, ["x", "y"]);
+
+        let tests = vec![
+            ("struct Point { x, y }", "struct Point { x, y }"),
+            ("struct Unit {}", "struct Unit {}"),
+            ("Point { x: 1 + 2, y }", "Point { x: (1 + 2), y: y }"),
+            ("Unit {}", "Unit {}"),
+            (
+                "Line { from: Point { x: 0, y: 0 } }",
+                "Line { from: Point { x: 0, y: 0 } }",
+            ),
+            ("p.x + Point { x: 1 }.x", "((p.x) + (Point { x: 1 }.x))"),
+            ("p.x = 5", "((p.x) = 5)"),
+            ("if (p) { q } else { r }", "if p {q} else {r}"),
+        ];
+        for (input, output) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program().unwrap();
+            assert_eq!(program.to_string(), output);
+        }
+
+        let errors = vec![
+            (
+                "struct Point { x, x }",
+                "duplicate field x in struct Point",
+            ),
+            (
+                "struct { x }",
+                "expected next token to be Ident, got Some(Token { token_type: Lbrace, literal: \"{\" }) instead",
+            ),
+            (
+                "struct Point { 1 }",
+                "expected next token to be Ident, got Some(Token { token_type: Int, literal: \"1\" }) instead",
+            ),
+            (
+                "Point { x 1 }",
+                "expected next token to be Comma, got Some(Token { token_type: Int, literal: \"1\" }) instead",
+            ),
+        ];
+        for (input, expected) in errors {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn test_enum_parsing() {
+        let input = String::from("enum Shape { Circle(r), Rect(w, h,), Empty, }");
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.statements().len(), 1);
+        let stmt = program
+            .statements()
+            .first()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<EnumStatement>()
+            .unwrap();
+        assert_eq!(stmt.name().value(), "Shape");
+        let variants = stmt
+            .variants()
+            .iter()
+            .map(|v| {
+                let fields = v.fields().iter().map(|f| f.value()).collect::<Vec<_>>();
+                (v.name().value(), fields)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            variants,
+            [
+                ("Circle", vec!["r"]),
+                ("Rect", vec!["w", "h"]),
+                ("Empty", vec![])
+            ]
+        );
+
+        let tests = vec![
+            (
+                "enum Shape { Circle(r), Rect(w, h), Empty }",
+                "enum Shape { Circle(r), Rect(w, h), Empty }",
+            ),
+            ("enum Never {}", "enum Never {}"),
+            ("Shape.Circle(1)", "(Shape.Circle)(1)"),
+            (
+                "match (s) { Shape.Circle(r) => r, Shape.Rect(w, _) if w > 1 => w, Shape.Empty => 0, Opt.Some([a, ..]) => a }",
+                "match (s) {Shape.Circle(r) => r, Shape.Rect(w, _) if (w > 1) => w, Shape.Empty => 0, Opt.Some([a, ..]) => a}",
+            ),
+        ];
+        for (input, output) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program().unwrap();
+            assert_eq!(program.to_string(), output);
+        }
+
+        let errors = vec![
+            ("enum Shape { A, A }", "duplicate variant A in enum Shape"),
+            ("enum Shape { A(x, x) }", "duplicate field x in variant A"),
+            (
+                "enum Shape { A(1) }",
+                "expected next token to be Ident, got Some(Token { token_type: Int, literal: \"1\" }) instead",
+            ),
+            (
+                "match (s) { Shape.1 => 0 }",
+                "expected next token to be Ident, got Some(Token { token_type: Int, literal: \"1\" }) instead",
+            ),
+        ];
+        for (input, expected) in errors {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let tests = vec![
+            ("throw 1 + 2;", "throw (1 + 2);"),
+            ("try { f() } catch (e) { e }", "try {f()} catch (e) {e}"),
+            ("try { f() } finally { g() }", "try {f()} finally {g()}"),
+            (
+                "let x = try { f() } catch (e) { 0 } finally { g() };",
+                "let x = try {f()} catch (e) {0} finally {g()};",
+            ),
+        ];
+        for (input, output) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program().unwrap();
+            assert_eq!(program.to_string(), output);
+        }
+
+        let errors = vec![
+            (
+                "try { f() }",
+                "expected next token to be Catch or Finally, got Some(Token { token_type: Eof, literal: \"\" }) instead",
+            ),
+            (
+                "try { f() } catch { 0 }",
+                "expected next token to be Lparen, got Some(Token { token_type: Lbrace, literal: \"{\" }) instead",
+            ),
+            (
+                "try { f() } catch (1) { 0 }",
+                "expected next token to be Ident, got Some(Token { token_type: Int, literal: \"1\" }) instead",
+            ),
+        ];
+        for (input, expected) in errors {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn test_generators() {
+        let tests = vec![
+            (
+                "fn*(n) { yield n; yield n + 1; }",
+                "fn*(n) {yield n;yield (n + 1);}",
+            ),
+            ("fn * () { yield 1 }", "fn*() {yield 1;}"),
+            (
+                "fn*() { let f = fn*() { yield 1; }; }",
+                "fn*() {let f = fn*() {yield 1;};}",
+            ),
+        ];
+        for (input, output) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program().unwrap();
+            assert_eq!(program.to_string(), output);
+        }
+
+        let errors = vec![
+            ("yield 1;", "yield outside of a generator"),
+            ("fn() { yield 1; }", "yield outside of a generator"),
+            (
+                "fn*() { fn() { yield 1; } }",
+                "yield outside of a generator",
+            ),
+            (
+                "fn*() { try { yield 1; } catch (e) { 0 } }",
+                "yield is only supported directly in a generator's body or its if, while and for \
+                 blocks, not at 1:15",
+            ),
+            (
+                "fn*() { let x = if (true) { yield 1; }; }",
+                "yield is only supported directly in a generator's body or its if, while and for \
+                 blocks, not at 1:29",
+            ),
+            (
+                "fn*(x) { match (x) { _ => { yield 1; } } }",
+                "yield is only supported directly in a generator's body or its if, while and for \
+                 blocks, not at 1:29",
+            ),
+            (
+                "fn*() { while (true) { [if (true) { yield 1; }]; } }",
+                "yield is only supported directly in a generator's body or its if, while and for \
+                 blocks, not at 1:37",
+            ),
+        ];
+        for (input, expected) in errors {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |open: &str, inner: &str, close: &str, n| {
+            format!("{}{}{}", open.repeat(n), inner, close.repeat(n))
+        };
+        let tests = vec![
+            nested("(", "1", ")", Parser::DEFAULT_MAX_DEPTH - 1),
+            nested("-", "1", "", Parser::DEFAULT_MAX_DEPTH - 1),
+            nested("[", "1", "]", Parser::DEFAULT_MAX_DEPTH - 1),
+            format!("1{}", " + 1".repeat(Parser::DEFAULT_MAX_DEPTH - 2)),
+            format!("a{}", ".b".repeat(Parser::DEFAULT_MAX_DEPTH - 2)),
+        ];
+        for input in tests {
+            let l = Lexer::new(&input);
+            let mut p = Parser::new(l);
+            assert!(p.parse_program().is_ok(), "{}", input);
+        }
+
+        let errors = vec![
+            (
+                nested("(", "1", ")", 20),
+                "maximum recursion depth exceeded at 1:11",
+            ),
+            (
+                nested("-", "1", "", 20),
+                "maximum recursion depth exceeded at 1:11",
+            ),
+            (
+                format!("let x = 1;\nlet {} = x;", nested("[", "a", "]", 20)),
+                "maximum recursion depth exceeded at 2:15",
+            ),
+            (
+                format!(
+                    "{};\n{}",
+                    nested("(", "1", ")", 9),
+                    nested("(", "1", ")", 10)
+                ),
+                "maximum recursion depth exceeded at 2:11",
+            ),
+            (
+                format!("1{}", " + 1".repeat(20)),
+                "maximum recursion depth exceeded at 1:37",
+            ),
+            (
+                format!("f(){}", "(1)".repeat(20)),
+                "maximum recursion depth exceeded at 1:26",
+            ),
+        ];
+        for (input, expected) in errors {
+            let l = Lexer::new(&input);
+            let mut p = Parser::new(l).with_max_depth(10);
+            assert_eq!(p.parse_program().unwrap_err(), expected, "{}", input);
+        }
+
+        // long chains of operators nest as deeply as parentheses do
+        let input = format!("1{}", " + 1".repeat(100_000));
+        let mut p = Parser::new(Lexer::new(&input));
+        assert_eq!(
+            p.parse_program().unwrap_err(),
+            "maximum recursion depth exceeded at 1:1021"
+        );
+    }
 }
@@ -1,26 +1,236 @@
-use std::io::{BufRead, Read, Write};
+use std::{
+    cell::RefCell,
+    io::{BufRead, Read, Write},
+    rc::Rc,
+    str::FromStr,
+};
 
-use crate::{Lexer, Parser};
+use crate::{
+    eval::eval,
+    object::{Environment, Exception, ExecutionLimits, Object},
+    optimize,
+    vm::Vm,
+    Bytecode, Compiler, Lexer, Parser, Program, Resolver, SymbolTable,
+};
 
-pub fn start_repl(read: impl Read, write: impl Write) {
+/// The size of the stack the REPL should run with, which is large enough for
+/// [`MAX_CALL_DEPTH`] nested calls even in debug builds.
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// How deeply calls can be nested in the REPL, which is deeper than usual since it has a
+/// larger stack than usual.
+const MAX_CALL_DEPTH: usize = 10_000;
+
+/// What runs programs: the tree-walking evaluator, or the bytecode compiler and VM.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Engine {
+    #[default]
+    Tree,
+    /// Runs the same programs to the same results. Programs that use what the compiler doesn't
+    /// support, like `match` or `try`, are evaluated by the tree-walker instead, with the
+    /// globals the VM has. A function one engine made that's called while the other is
+    /// running a program sees the globals as they were when the program started.
+    Vm,
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tree" => Ok(Engine::Tree),
+            "vm" => Ok(Engine::Vm),
+            _ => Err(format!("unknown engine: {} (expected vm or tree)", s)),
+        }
+    }
+}
+
+/// Runs programs one after another, each of which sees the globals the ones before it defined.
+pub struct Interpreter {
+    state: State,
+    resolver: Resolver,
+    optimized: bool,
+}
+
+enum State {
+    Tree(Rc<RefCell<Environment>>),
+    Vm {
+        vm: Vm,
+        symbol_table: SymbolTable,
+        constants: Vec<Object>,
+        /// Where the programs the compiler can't compile are evaluated, with copies of the
+        /// VM's globals that are copied back once they've run.
+        fallback: Rc<RefCell<Environment>>,
+    },
+}
+
+impl Interpreter {
+    pub fn new(engine: Engine, limits: ExecutionLimits) -> Self {
+        let state = match engine {
+            Engine::Tree => State::Tree(Environment::with_limits(limits)),
+            Engine::Vm => {
+                let vm = Vm::with_limits(limits);
+                State::Vm {
+                    fallback: vm.environment(),
+                    vm,
+                    symbol_table: SymbolTable::new(),
+                    constants: Vec::new(),
+                }
+            },
+        };
+        Interpreter {
+            state,
+            resolver: Resolver::new(),
+            optimized: false,
+        }
+    }
+
+    /// Lets functions use globals that only later programs define, like those on the REPL's
+    /// later lines, rather than failing to resolve programs that use names nothing defines.
+    pub fn incremental(mut self) -> Self {
+        self.resolver = Resolver::incremental();
+        self
+    }
+
+    /// Optimizes programs before running them, once their names have been resolved.
+    pub fn with_optimization(mut self, optimized: bool) -> Self {
+        self.optimized = optimized;
+        self
+    }
+
+    /// Resolves and runs a program, failing before running any of it if it uses names that
+    /// can't refer to anything.
+    pub fn run(&mut self, mut program: Program) -> Result<Object, Exception> {
+        self.resolver.resolve(&program)?;
+        if self.optimized {
+            optimize(&mut program);
+        }
+        match &mut self.state {
+            State::Tree(env) => eval(&program, env),
+            State::Vm {
+                vm,
+                symbol_table,
+                constants,
+                fallback,
+            } => {
+                let mut compiler =
+                    Compiler::with_state(std::mem::take(symbol_table), std::mem::take(constants));
+                let compiled = compiler.compile(&program);
+                let bytecode = compiler.bytecode();
+                (*symbol_table, *constants) = compiler.into_state();
+                if compiled.is_ok() {
+                    return vm.run(&bytecode);
+                }
+                for (name, slot) in self.resolver.globals() {
+                    if let Some(value) = vm.global(name) {
+                        fallback.borrow_mut().set(slot, value);
+                    }
+                }
+                let result = eval(&program, fallback);
+                // the globals the program set are kept even if it failed, like they are when
+                // the VM runs one
+                for (name, slot) in self.resolver.globals() {
+                    if let Some(value) = fallback.borrow().get(0, slot) {
+                        let index = symbol_table.global_index(name);
+                        vm.set_global(symbol_table.names(), index, value);
+                    }
+                }
+                result
+            },
+        }
+    }
+}
+
+/// Parses and runs a program, describing its value or what went wrong.
+fn run_source(interpreter: &mut Interpreter, source: String) -> Result<String, String> {
+    let program = parse(source)?;
+    interpreter
+        .run(program)
+        .map(|obj| obj.to_string())
+        .map_err(|err| describe_error(&err))
+}
+
+fn describe_error(err: &Exception) -> String {
+    match err.span() {
+        Some(span) => format!("ERROR at {span}: {err}"),
+        None => format!("ERROR: {err}"),
+    }
+}
+
+fn parse(source: String) -> Result<Program, String> {
+    let mut parser = Parser::new(Lexer::new(&source));
+    parser
+        .parse_program()
+        .map_err(|err| format!("ERROR: {err}"))
+}
+
+/// Parses, resolves, optimizes if asked to and compiles a file, describing what went wrong if
+/// it can't be, which includes using what the compiler doesn't support.
+pub fn compile_file(source: String, optimized: bool) -> Result<Bytecode, String> {
+    let mut program = parse(source)?;
+    Resolver::new()
+        .resolve(&program)
+        .map_err(|err| describe_error(&err))?;
+    if optimized {
+        optimize(&mut program);
+    }
+    let mut compiler = Compiler::new();
+    compiler
+        .compile(&program)
+        .map_err(|err| format!("ERROR: {err}"))?;
+    Ok(compiler.bytecode())
+}
+
+/// Runs a compiled file like [`run_file`] runs one that isn't.
+pub fn run_bytecode(bytecode: &Bytecode, mut write: impl Write, mut error: impl Write) -> bool {
+    let limits = ExecutionLimits::new().with_max_call_depth(MAX_CALL_DEPTH);
+    match Vm::with_limits(limits).run(bytecode) {
+        Ok(obj) => writeln!(write, "{obj}").is_ok(),
+        Err(err) => {
+            let _ = writeln!(error, "{}", describe_error(&err));
+            false
+        },
+    }
+}
+
+/// Runs a file, writing its value to `write`, or what went wrong to `error` and returning
+/// false.
+pub fn run_file(
+    source: String,
+    engine: Engine,
+    optimized: bool,
+    mut write: impl Write,
+    mut error: impl Write,
+) -> bool {
+    let limits = ExecutionLimits::new().with_max_call_depth(MAX_CALL_DEPTH);
+    let mut interpreter = Interpreter::new(engine, limits).with_optimization(optimized);
+    match run_source(&mut interpreter, source) {
+        Ok(output) => writeln!(write, "{output}").is_ok(),
+        Err(output) => {
+            let _ = writeln!(error, "{output}");
+            false
+        },
+    }
+}
+
+pub fn start_repl(read: impl Read, write: impl Write, engine: Engine, optimized: bool) {
     let mut reader = std::io::BufReader::new(read);
     let mut writer = std::io::BufWriter::new(write);
+    let limits = ExecutionLimits::new().with_max_call_depth(MAX_CALL_DEPTH);
+    let mut interpreter = Interpreter::new(engine, limits)
+        .incremental()
+        .with_optimization(optimized);
     loop {
         writer.write_all(b"> ").unwrap();
         writer.flush().unwrap();
         let mut line = String::new();
-        reader.read_line(&mut line).unwrap();
-        let lexer = Lexer::new(line);
-        let mut parser = Parser::new(lexer);
-        let program = parser.parse_program();
-        match program {
-            Ok(program) => writer.write_all(program.to_string().as_bytes()).unwrap(),
-            Err(err) => {
-                writer
-                    .write_all(format!("ERROR: {err}").as_bytes())
-                    .unwrap();
-            },
+        if reader.read_line(&mut line).unwrap() == 0 {
+            // the input has ended
+            writer.write_all(b"\n").unwrap();
+            return;
         }
+        let output = run_source(&mut interpreter, line).unwrap_or_else(|err| err);
+        writer.write_all(output.as_bytes()).unwrap();
         writer.write_all(b"\n").unwrap();
     }
 }
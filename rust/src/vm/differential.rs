@@ -0,0 +1,506 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        object::{Exception, ExecutionLimits, LimitExceeded, Object},
+        Engine, Interpreter, Lexer, Parser, Span,
+    };
+
+    const MAX_STEPS: u64 = 100_000;
+
+    const MAX_CALL_DEPTH: usize = 100;
+
+    /// What running a program came to, in terms the two engines should agree on.
+    #[derive(Debug, PartialEq)]
+    enum Outcome {
+        /// The program's type and how it's shown, since the functions the engines make are
+        /// different kinds of objects.
+        Value(&'static str, String),
+        Error(String, Option<Span>),
+        Thrown(String),
+        LimitExceeded(LimitExceeded),
+    }
+
+    impl Outcome {
+        /// Whether the program was stopped by one of its limits.
+        fn stopped(&self) -> bool {
+            match self {
+                Outcome::LimitExceeded(_) => true,
+                Outcome::Error(message, _) => message == "maximum recursion depth exceeded",
+                _ => false,
+            }
+        }
+    }
+
+    fn run(engine: Engine, source: &str, limits: &ExecutionLimits) -> Outcome {
+        let program = Parser::new(Lexer::new(source))
+            .parse_program()
+            .unwrap_or_else(|err| panic!("{}\n{}", err, source));
+        let mut interpreter = Interpreter::new(engine, limits.clone());
+        match interpreter.run(program) {
+            Ok(obj) => Outcome::Value(obj.type_name(), obj.to_string()),
+            Err(Exception::Thrown(Object::Error(error), _)) => {
+                Outcome::Error(error.message().to_string(), error.span())
+            },
+            Err(Exception::Thrown(obj, _)) => Outcome::Thrown(obj.to_string()),
+            Err(Exception::LimitExceeded(limit, _)) => Outcome::LimitExceeded(limit),
+        }
+    }
+
+    /// Runs a program with both engines, checking they agree, and returns what it came to.
+    /// They count steps and calls differently, so they can be stopped at different points,
+    /// and programs either is stopped in aren't compared.
+    fn run_both(source: &str, name: &str, limits: &ExecutionLimits) -> Outcome {
+        let tree = run(Engine::Tree, source, limits);
+        let vm = run(Engine::Vm, source, limits);
+        if !tree.stopped() && !vm.stopped() {
+            assert_eq!(vm, tree, "the engines disagree on {}:\n{}", name, source);
+        }
+        tree
+    }
+
+    #[test]
+    fn test_corpus() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/corpus");
+        let mut paths = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "mk"))
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert!(!paths.is_empty(), "no scripts in {}", dir);
+        for path in paths {
+            let source = std::fs::read_to_string(&path).unwrap();
+            let name = path.file_name().unwrap().to_string_lossy();
+            let outcome = run_both(&source, &name, &ExecutionLimits::new());
+            // the scripts named for errors should fail, and only those
+            let failed = !matches!(outcome, Outcome::Value(..));
+            assert_eq!(
+                failed,
+                name.ends_with("_error.mk"),
+                "{}: {:?}",
+                name,
+                outcome
+            );
+        }
+    }
+
+    #[test]
+    fn test_generated_programs() {
+        // functions can be made to call themselves, so the programs need to be stopped
+        let limits = ExecutionLimits::new()
+            .with_max_steps(MAX_STEPS)
+            .with_max_call_depth(MAX_CALL_DEPTH);
+        let mut values = 0;
+        for seed in 0..500 {
+            let source = Generator::new(seed).program();
+            if let Outcome::Value(..) = run_both(&source, &format!("seed {}", seed), &limits) {
+                values += 1;
+            }
+        }
+        // most programs should get far enough to be worth comparing
+        assert!(values > 250, "only {} programs ran to the end", values);
+    }
+
+    /// A xorshift generator, so the same programs are generated every time.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn one_in(&mut self, n: usize) -> bool {
+            self.below(n) == 0
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Type {
+        Int,
+        Bool,
+        Str,
+        /// An array of integers.
+        Array,
+        /// A function from an integer to an integer.
+        Function,
+    }
+
+    const TYPES: &[Type] = &[
+        Type::Int,
+        Type::Bool,
+        Type::Str,
+        Type::Array,
+        Type::Function,
+    ];
+
+    /// Generates programs that only use variables where they're defined, with the types
+    /// they have, so they mostly run to the end. They can still fail where the types don't
+    /// rule it out, like dividing by zero or overflowing.
+    struct Generator {
+        rng: Rng,
+        variables: Vec<(String, Type)>,
+        names: usize,
+        in_loop: bool,
+        in_function: bool,
+        /// Whether the program can use `match`, `try`, `throw` and destructuring `let`, which
+        /// the VM leaves to the tree-walker. Most programs don't, so they're compiled.
+        uncompiled: bool,
+    }
+
+    impl Generator {
+        fn new(seed: u64) -> Self {
+            let mut rng = Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1);
+            Generator {
+                uncompiled: rng.one_in(4),
+                rng,
+                variables: Vec::new(),
+                names: 0,
+                in_loop: false,
+                in_function: false,
+            }
+        }
+
+        /// Some statements, followed by an array of every variable they defined.
+        fn program(&mut self) -> String {
+            let count = 1 + self.rng.below(8);
+            let mut statements = (0..count).map(|_| self.statement(2)).collect::<Vec<_>>();
+            let names = self.variables.iter().map(|(name, _)| name.as_str());
+            statements.push(format!("[{}]", names.collect::<Vec<_>>().join(", ")));
+            statements.join("\n")
+        }
+
+        fn fresh(&mut self, prefix: &str) -> String {
+            self.names += 1;
+            format!("{}{}", prefix, self.names)
+        }
+
+        fn pick(&mut self, ty: Type) -> Option<String> {
+            let names = self
+                .variables
+                .iter()
+                .filter(|(_, t)| *t == ty)
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+            match names.len() {
+                0 => None,
+                n => Some(names[self.rng.below(n)].clone()),
+            }
+        }
+
+        /// A few statements, whose variables go out of scope after them.
+        fn statements(&mut self, depth: usize) -> Vec<String> {
+            let scope = self.variables.len();
+            let count = 1 + self.rng.below(3);
+            let statements = (0..count)
+                .map(|_| self.statement(depth))
+                .collect::<Vec<_>>();
+            self.variables.truncate(scope);
+            statements
+        }
+
+        fn block(&mut self, depth: usize) -> String {
+            let statements = self.statements(depth);
+            braced(&statements)
+        }
+
+        fn statement(&mut self, depth: usize) -> String {
+            match self.rng.below(if depth == 0 { 3 } else { 7 }) {
+                1 => self.assignment(),
+                2 => {
+                    let ty = self.random_type();
+                    format!("{};", self.expression(ty, 2))
+                },
+                3 => format!(
+                    "if ({}) {} else {};",
+                    self.expression(Type::Bool, 2),
+                    self.block(depth - 1),
+                    self.block(depth - 1),
+                ),
+                4 => {
+                    let counter = self.fresh("i");
+                    let limit = self.rng.below(4);
+                    let in_loop = std::mem::replace(&mut self.in_loop, true);
+                    let mut body = vec![format!("{} += 1;", counter)];
+                    body.extend(self.statements(depth - 1));
+                    self.in_loop = in_loop;
+                    format!(
+                        "let {} = 0;\nwhile ({} < {}) {}",
+                        counter,
+                        counter,
+                        limit,
+                        braced(&body)
+                    )
+                },
+                5 => {
+                    let iterable = self.expression(Type::Array, 1);
+                    let variable = self.fresh("x");
+                    self.variables.push((variable.clone(), Type::Int));
+                    let in_loop = std::mem::replace(&mut self.in_loop, true);
+                    let body = self.block(depth - 1);
+                    self.in_loop = in_loop;
+                    self.variables.pop();
+                    format!("for ({} in {}) {}", variable, iterable, body)
+                },
+                6 if self.in_loop && self.rng.one_in(2) => {
+                    let jump = if self.rng.one_in(2) {
+                        "break"
+                    } else {
+                        "continue"
+                    };
+                    format!("if ({}) {{ {} }};", self.expression(Type::Bool, 1), jump)
+                },
+                6 if self.in_function => format!(
+                    "if ({}) {{ return {} }};",
+                    self.expression(Type::Bool, 1),
+                    self.expression(Type::Int, 1)
+                ),
+                6 if self.uncompiled && self.rng.one_in(2) => {
+                    let (first, second) = (self.fresh("v"), self.fresh("v"));
+                    let value = format!(
+                        "[{}, {}]",
+                        self.expression(Type::Int, 1),
+                        self.expression(Type::Int, 1)
+                    );
+                    self.variables.push((first.clone(), Type::Int));
+                    self.variables.push((second.clone(), Type::Int));
+                    format!("let [{}, {}] = {};", first, second, value)
+                },
+                6 if self.uncompiled => format!(
+                    "if ({}) {{ throw {}; }};",
+                    self.expression(Type::Bool, 1),
+                    self.expression(Type::Int, 1)
+                ),
+                _ => {
+                    let ty = self.random_type();
+                    let value = self.expression(ty, 2);
+                    let name = self.fresh("v");
+                    self.variables.push((name.clone(), ty));
+                    format!("let {} = {};", name, value)
+                },
+            }
+        }
+
+        fn assignment(&mut self) -> String {
+            let ty = self.random_type();
+            let Some(name) = self.pick(ty) else {
+                return format!("{};", self.expression(ty, 1));
+            };
+            match ty {
+                Type::Int if self.rng.one_in(2) => {
+                    let operator = ["+=", "-=", "*="][self.rng.below(3)];
+                    format!("{} {} {};", name, operator, self.expression(Type::Int, 1))
+                },
+                Type::Array if self.rng.one_in(3) => format!(
+                    "if (len({name}) > 0) {{ {name}[0] = {} }};",
+                    self.expression(Type::Int, 1)
+                ),
+                _ => format!("{} = {};", name, self.expression(ty, 2)),
+            }
+        }
+
+        fn random_type(&mut self) -> Type {
+            TYPES[self.rng.below(TYPES.len())]
+        }
+
+        fn expression(&mut self, ty: Type, depth: usize) -> String {
+            if depth == 0 || self.rng.one_in(4) {
+                if let Some(name) = self.pick(ty).filter(|_| self.rng.one_in(2)) {
+                    return name;
+                }
+                return self.literal(ty);
+            }
+            let depth = depth - 1;
+            match ty {
+                Type::Int => match self.rng.below(9) {
+                    0 => {
+                        let operator = ["+", "-", "*", "/", "%", "&", "|", "^"][self.rng.below(8)];
+                        let left = self.expression(Type::Int, depth);
+                        format!(
+                            "({} {} {})",
+                            left,
+                            operator,
+                            self.expression(Type::Int, depth)
+                        )
+                    },
+                    1 => format!("len({})", self.expression(Type::Str, depth)),
+                    2 => format!("{}.len()", self.expression(Type::Array, depth)),
+                    3 => {
+                        let array = self.expression(Type::Array, depth);
+                        format!("({}[{}] ?? 0)", array, self.expression(Type::Int, depth))
+                    },
+                    4 => {
+                        let function = self.expression(Type::Function, depth);
+                        format!("{}({})", function, self.expression(Type::Int, depth))
+                    },
+                    5 => format!(
+                        "if ({}) {{ {} }} else {{ {} }}",
+                        self.expression(Type::Bool, depth),
+                        self.expression(Type::Int, depth),
+                        self.expression(Type::Int, depth)
+                    ),
+                    6 if self.in_loop || self.in_function => self.jump(depth),
+                    7 if self.uncompiled && self.rng.one_in(2) => {
+                        let value = self.expression(Type::Int, depth);
+                        let name = self.fresh("m");
+                        let first = self.expression(Type::Int, depth);
+                        self.variables.push((name.clone(), Type::Int));
+                        let rest = self.expression(Type::Int, depth);
+                        self.variables.pop();
+                        format!(
+                            "match ({}) {{ {} => {}, {} => {} }}",
+                            value,
+                            self.rng.below(3),
+                            first,
+                            name,
+                            rest
+                        )
+                    },
+                    // errors like dividing by zero can be caught
+                    7 if self.uncompiled => format!(
+                        "try {{ {} }} catch (e) {{ {} }}",
+                        self.expression(Type::Int, depth),
+                        self.expression(Type::Int, depth)
+                    ),
+                    _ => format!("-{}", self.expression(Type::Int, depth)),
+                },
+                Type::Bool => match self.rng.below(5) {
+                    0 => {
+                        let operator = ["<", ">", "<=", ">=", "==", "!="][self.rng.below(6)];
+                        let left = self.expression(Type::Int, depth);
+                        format!(
+                            "({} {} {})",
+                            left,
+                            operator,
+                            self.expression(Type::Int, depth)
+                        )
+                    },
+                    1 => format!("!{}", self.expression(Type::Bool, depth)),
+                    2 => {
+                        let operator = ["&&", "||"][self.rng.below(2)];
+                        let left = self.expression(Type::Bool, depth);
+                        format!(
+                            "({} {} {})",
+                            left,
+                            operator,
+                            self.expression(Type::Bool, depth)
+                        )
+                    },
+                    3 => {
+                        let left = self.expression(Type::Str, depth);
+                        format!("({} == {})", left, self.expression(Type::Str, depth))
+                    },
+                    _ => {
+                        let array = self.expression(Type::Array, depth);
+                        format!("{}.contains({})", array, self.expression(Type::Int, depth))
+                    },
+                },
+                // strings and arrays only grow by a little at a time, so loops can't make
+                // them huge
+                Type::Str => match self.rng.below(3) {
+                    0 => format!(
+                        "({} + {})",
+                        self.literal(Type::Str),
+                        self.expression(Type::Str, depth)
+                    ),
+                    1 => format!("\"<${{{}}}>\"", self.expression(Type::Int, depth)),
+                    _ => format!("{}.upper()", self.expression(Type::Str, depth)),
+                },
+                Type::Array => match self.rng.below(5) {
+                    0 => {
+                        let array = self.expression(Type::Array, depth);
+                        format!("{}.push({})", array, self.expression(Type::Int, depth))
+                    },
+                    1 => {
+                        let array = self.expression(Type::Array, depth);
+                        format!("{}.map({})", array, self.function(depth))
+                    },
+                    2 => {
+                        let array = self.expression(Type::Array, depth);
+                        let parameter = self.fresh("p");
+                        self.variables.push((parameter.clone(), Type::Int));
+                        let in_loop = std::mem::replace(&mut self.in_loop, false);
+                        let in_function = std::mem::replace(&mut self.in_function, true);
+                        let condition = self.expression(Type::Bool, depth);
+                        self.in_loop = in_loop;
+                        self.in_function = in_function;
+                        self.variables.pop();
+                        format!("{}.filter(fn({}) {{ {} }})", array, parameter, condition)
+                    },
+                    3 => {
+                        let elements = (0..self.rng.below(3))
+                            .map(|_| self.expression(Type::Int, depth))
+                            .collect::<Vec<_>>();
+                        format!("[{}]", elements.join(", "))
+                    },
+                    _ => format!("(0..{}).collect()", self.rng.below(4)),
+                },
+                Type::Function => self.function(depth),
+            }
+        }
+
+        fn literal(&mut self, ty: Type) -> String {
+            match ty {
+                Type::Int => self.rng.below(10).to_string(),
+                Type::Bool => ["true", "false"][self.rng.below(2)].to_string(),
+                Type::Str => format!("\"{}\"", ["", "a", "mon", "key"][self.rng.below(4)]),
+                Type::Array => {
+                    let elements = (0..self.rng.below(4)).map(|_| self.rng.below(10).to_string());
+                    format!("[{}]", elements.collect::<Vec<_>>().join(", "))
+                },
+                Type::Function => self.function(0),
+            }
+        }
+
+        /// An integer that, depending on a condition, is worked out or leaves the loop or
+        /// function it's in, from wherever in an expression it is.
+        fn jump(&mut self, depth: usize) -> String {
+            let condition = self.expression(Type::Bool, depth);
+            let mut jumps = Vec::new();
+            if self.in_loop {
+                jumps.push("break;".to_string());
+                jumps.push("continue;".to_string());
+            }
+            if self.in_function {
+                jumps.push(format!("return {};", self.expression(Type::Int, depth)));
+            }
+            let jump = jumps.swap_remove(self.rng.below(jumps.len()));
+            format!(
+                "if ({}) {{ {} }} else {{ {} }}",
+                condition,
+                jump,
+                self.expression(Type::Int, depth)
+            )
+        }
+
+        /// A function from an integer to an integer, which can use the variables where it's
+        /// defined.
+        fn function(&mut self, depth: usize) -> String {
+            let parameter = self.fresh("p");
+            let scope = self.variables.len();
+            self.variables.push((parameter.clone(), Type::Int));
+            let in_loop = std::mem::replace(&mut self.in_loop, false);
+            let in_function = std::mem::replace(&mut self.in_function, true);
+            let statements = (0..self.rng.below(depth + 1))
+                .map(|_| self.statement(depth.min(1)))
+                .collect::<Vec<_>>();
+            let value = self.expression(Type::Int, depth);
+            self.in_loop = in_loop;
+            self.in_function = in_function;
+            self.variables.truncate(scope);
+            let mut body = statements;
+            body.push(value);
+            format!("fn({}) {}", parameter, braced(&body))
+        }
+    }
+
+    fn braced(statements: &[String]) -> String {
+        format!("{{\n{}\n}}", statements.join("\n"))
+    }
+}
@@ -0,0 +1,552 @@
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+use crate::{
+    compiler::{read_u16, Bytecode, Opcode},
+    eval::{
+        allocated, apply_function, assign, builtins, eval_index_expression, eval_infix_expression,
+        eval_member_expression, eval_prefix_expression, iterate, iterator_next, method_target,
+        Accessor, MeteredString, MethodTarget,
+    },
+    object::{
+        Budget, CallDepth, CallGuard, Closure, CompiledFunction, Environment, Exception,
+        ExecutionLimits, HashKey, Locals, Object, Runtime,
+    },
+};
+
+mod differential;
+mod tests;
+
+/// Runs bytecode, producing the same results as evaluating the programs it was compiled from.
+pub struct Vm {
+    runtime: Rc<Runtime>,
+    budget: Rc<Budget>,
+}
+
+impl Vm {
+    /// A VM where running programs is bounded by `limits`.
+    pub fn with_limits(limits: ExecutionLimits) -> Self {
+        Vm {
+            runtime: Rc::new(Runtime::new(limits.max_call_depth())),
+            budget: Rc::new(Budget::new(limits)),
+        }
+    }
+
+    /// Runs a program, returning its value. A program compiled by a compiler carrying on from
+    /// the programs run before shares their globals.
+    pub fn run(&mut self, bytecode: &Bytecode) -> Result<Object, Exception> {
+        self.runtime.load(bytecode.constants(), bytecode.globals());
+        let main = CompiledFunction::new(
+            bytecode.instructions().clone(),
+            bytecode.source_map().clone(),
+            Vec::new(),
+            0,
+            "<program>",
+        );
+        let main = Rc::new(Closure::new(Rc::new(main), None, self.runtime.clone()));
+        let frame = Frame {
+            closure: main,
+            locals: None,
+            ip: 0,
+            base: 0,
+            call: None,
+        };
+        Execution::new(frame, &self.budget).run()
+    }
+
+    /// An environment for evaluating programs in place of running them, whose calls and
+    /// budget count towards the VM's.
+    pub fn environment(&self) -> Rc<RefCell<Environment>> {
+        Environment::sharing(self.runtime.call_depth().clone(), self.budget.clone())
+    }
+
+    /// The value of the global named `name`, if it's been set.
+    pub fn global(&self, name: &str) -> Option<Object> {
+        self.runtime.lookup_global(name)
+    }
+
+    /// Sets global `index` of programs compiled with the globals `global_names`, like running
+    /// one that defines it would.
+    pub fn set_global(&mut self, global_names: &[String], index: usize, value: Object) {
+        self.runtime.define_globals(global_names);
+        self.runtime.set_global(index, Some(value));
+    }
+}
+
+/// Calls a closure from outside the VM, like builtins that take functions do.
+pub fn call_closure(
+    closure: Rc<Closure>,
+    args: Vec<Object>,
+    budget: &Budget,
+) -> Result<Object, Exception> {
+    let call = CallDepth::enter(closure.runtime().call_depth())?;
+    let frame = Frame::new(closure, args, 0, call)?;
+    Execution::new(frame, budget).run()
+}
+
+/// A call being run.
+struct Frame {
+    closure: Rc<Closure>,
+    /// The call's locals, or `None` for the code outside any function.
+    locals: Option<Rc<Locals>>,
+    ip: usize,
+    /// How high the stack was when the call was made, which it's cut back to when it returns.
+    base: usize,
+    /// The call is counted until it returns.
+    call: Option<CallGuard>,
+}
+
+impl Frame {
+    fn new(
+        closure: Rc<Closure>,
+        args: Vec<Object>,
+        base: usize,
+        call: CallGuard,
+    ) -> Result<Self, String> {
+        let expected = closure.function().num_parameters();
+        if args.len() != expected {
+            return Err(format!(
+                "wrong number of arguments: expected {}, got {}",
+                expected,
+                args.len()
+            ));
+        }
+        Ok(Frame {
+            locals: Some(Rc::new(Locals::new(&closure, args))),
+            closure,
+            ip: 0,
+            base,
+            call: Some(call),
+        })
+    }
+
+    fn locals(&self) -> &Rc<Locals> {
+        // only functions are compiled to use locals
+        self.locals.as_ref().unwrap()
+    }
+
+    fn instructions(&self) -> &[u8] {
+        &self.closure.function().instructions().0
+    }
+}
+
+/// The state of running a call until it returns.
+struct Execution<'a> {
+    stack: Vec<Object>,
+    frames: Vec<Frame>,
+    budget: &'a Budget,
+}
+
+impl<'a> Execution<'a> {
+    fn new(frame: Frame, budget: &'a Budget) -> Self {
+        Execution {
+            stack: Vec::new(),
+            frames: vec![frame],
+            budget,
+        }
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames.last().unwrap()
+    }
+
+    fn push(&mut self, obj: Object) {
+        self.stack.push(obj);
+    }
+
+    /// How many values the current call has on the stack. Code read from a file may take more
+    /// off than it put on, which is an error rather than taking the caller's.
+    fn available(&self, n: usize) -> Result<(), String> {
+        if self.stack.len() - self.frame().base < n {
+            return Err("stack underflow".to_string());
+        }
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Object, String> {
+        self.available(1)?;
+        Ok(self.stack.pop().unwrap())
+    }
+
+    fn pop_n(&mut self, n: usize) -> Result<Vec<Object>, String> {
+        self.available(n)?;
+        Ok(self.stack.split_off(self.stack.len() - n))
+    }
+
+    fn top(&self) -> Result<&Object, String> {
+        self.available(1)?;
+        Ok(self.stack.last().unwrap())
+    }
+
+    fn run(mut self) -> Result<Object, Exception> {
+        loop {
+            let depth = self.frames.len();
+            let ip = self.frame().ip;
+            let result = self
+                .budget
+                .step()
+                .map_err(Exception::from)
+                .and_then(|()| self.execute());
+            match result {
+                Ok(Some(value)) => return Ok(value),
+                Ok(None) => {},
+                // errors are located at the instruction they come from, like the evaluator
+                // locates them at the innermost node
+                Err(e) => {
+                    let function = self.frames[depth - 1].closure.function().clone();
+                    return Err(match function.source_map().span_at(ip) {
+                        Some(span) => e.with_span(span),
+                        None => e,
+                    });
+                },
+            }
+        }
+    }
+
+    /// Executes the next instruction, returning the result once the first call returns.
+    fn execute(&mut self) -> Result<Option<Object>, Exception> {
+        let frame = self.frames.last_mut().unwrap();
+        let ip = frame.ip;
+        let code = &frame.closure.function().instructions().0;
+        let Some(&byte) = code.get(ip) else {
+            // the code outside functions leaves its value on the stack at the end
+            let value = self.pop().unwrap_or(Object::Null);
+            return Ok(self.return_value(value));
+        };
+        let op = Opcode::try_from(byte)?;
+        let widths = op.definition().operand_widths;
+        let len = widths.iter().sum::<usize>();
+        if code.len() < ip + 1 + len {
+            return Err(format!("instruction at {} is cut off", ip).into());
+        }
+        let mut operands = [0; 2];
+        let mut offset = ip + 1;
+        for (operand, width) in operands.iter_mut().zip(widths) {
+            *operand = match width {
+                2 => read_u16(code, offset) as usize,
+                _ => code[offset] as usize,
+            };
+            offset += width;
+        }
+        frame.ip = offset;
+
+        match op {
+            Opcode::Constant => {
+                let constant = self.constant(operands[0])?;
+                self.push(constant);
+            },
+            Opcode::Pop => {
+                self.pop()?;
+            },
+            Opcode::Minus | Opcode::Bang | Opcode::BitNot => {
+                let right = self.pop()?;
+                self.push(eval_prefix_expression(op.operator().unwrap(), right)?);
+            },
+            Opcode::True => self.push(Object::Boolean(true)),
+            Opcode::False => self.push(Object::Boolean(false)),
+            Opcode::Null => self.push(Object::Null),
+            Opcode::Jump => self.jump(operands[0]),
+            Opcode::JumpNotTruthy => {
+                if !self.pop()?.is_truthy() {
+                    self.jump(operands[0]);
+                }
+            },
+            Opcode::JumpNull => {
+                if *self.top()? == Object::Null {
+                    self.jump(operands[0]);
+                }
+            },
+            Opcode::JumpNotNull => {
+                if *self.top()? != Object::Null {
+                    self.jump(operands[0]);
+                } else {
+                    self.pop()?;
+                }
+            },
+            Opcode::GetGlobal | Opcode::GetLocal | Opcode::GetFree => {
+                let value = self.load(op, operands)?;
+                self.push(value);
+            },
+            Opcode::SetGlobal | Opcode::SetLocal | Opcode::SetFree => {
+                let value = self.pop()?;
+                self.store(op, operands, Some(value));
+            },
+            Opcode::GetBuiltin => {
+                let builtin = builtins::at(operands[0])
+                    .ok_or_else(|| format!("builtin {} undefined", operands[0]))?;
+                self.push(Object::Builtin(builtin));
+            },
+            Opcode::Array => {
+                let elements = self.pop_n(operands[0])?;
+                let array = allocated(self.budget, Object::Array(Rc::new(elements)))?;
+                self.push(array);
+            },
+            Opcode::Hash => {
+                let values = self.pop_n(operands[0] * 2)?;
+                let mut pairs = BTreeMap::new();
+                let mut values = values.into_iter();
+                while let (Some(key), Some(value)) = (values.next(), values.next()) {
+                    pairs.insert(HashKey::try_from(&key)?, value);
+                }
+                let hash = allocated(self.budget, Object::Hash(Rc::new(pairs)))?;
+                self.push(hash);
+            },
+            Opcode::Index => {
+                let index = self.pop()?;
+                let left = self.pop()?;
+                self.push(eval_index_expression(left, index, self.budget)?);
+            },
+            Opcode::GetField => {
+                let name = self.constant_string(operands[0])?;
+                let object = self.pop()?;
+                self.push(eval_member_expression(object, &name)?);
+            },
+            Opcode::Call | Opcode::TailCall => {
+                let args = self.pop_n(operands[0])?;
+                let function = self.pop()?;
+                return self.call(function, args, op == Opcode::TailCall);
+            },
+            Opcode::CallMethod => {
+                let name = self.constant_string(operands[0])?;
+                let args = self.pop_n(operands[1])?;
+                let receiver = self.pop()?;
+                match method_target(&receiver, &name)? {
+                    MethodTarget::Field(function) => return self.call(function, args, false),
+                    MethodTarget::Method(method) => {
+                        let result = method(self.budget, receiver, args)?;
+                        self.push(result);
+                    },
+                }
+            },
+            Opcode::ReturnValue => {
+                let value = self.pop()?;
+                return Ok(self.return_value(value));
+            },
+            Opcode::Closure => {
+                let function = match self.constant(operands[0])? {
+                    Object::CompiledFunction(function) => function,
+                    obj => return Err(format!("not a function: {}", obj.type_name()).into()),
+                };
+                let frame = self.frame();
+                let closure = Closure::new(
+                    function,
+                    frame.locals.clone(),
+                    frame.closure.runtime().clone(),
+                );
+                self.push(Object::Closure(Rc::new(closure)));
+            },
+            Opcode::Iter => {
+                let iterable = self.pop()?;
+                self.push(Object::Iterator(iterate(iterable)?));
+            },
+            Opcode::IterNext => {
+                let Object::Iterator(iter) = self.pop()? else {
+                    return Err("not an iterator".to_string().into());
+                };
+                match iterator_next(&iter, self.budget)? {
+                    Some(element) => self.push(element),
+                    None => self.jump(operands[0]),
+                }
+            },
+            Opcode::Interpolate => {
+                let mut string = MeteredString::new(self.budget);
+                for part in self.pop_n(operands[0])? {
+                    string.push(&part)?;
+                }
+                self.push(string.finish());
+            },
+            Opcode::Assign => self.assign(operands)?,
+            // the rest are infix operators
+            _ => {
+                let right = self.pop()?;
+                let left = self.pop()?;
+                let result = eval_infix_expression(op.operator().unwrap(), left, right)?;
+                let result = allocated(self.budget, result)?;
+                self.push(result);
+            },
+        }
+        Ok(None)
+    }
+
+    fn jump(&mut self, target: usize) {
+        self.frames.last_mut().unwrap().ip = target;
+    }
+
+    fn constant(&self, index: usize) -> Result<Object, String> {
+        self.frame()
+            .closure
+            .runtime()
+            .constant(index)
+            .ok_or_else(|| format!("constant {} undefined", index))
+    }
+
+    fn constant_string(&self, index: usize) -> Result<Rc<str>, String> {
+        match self.constant(index)? {
+            Object::String(s) => Ok(s),
+            obj => Err(format!(
+                "constant {} is {}, not STRING",
+                index,
+                obj.type_name()
+            )),
+        }
+    }
+
+    /// Reads a variable. One that hasn't been set yet is looked up by name in the calls it's
+    /// enclosed in, the globals and the builtins instead, which is where the evaluator would
+    /// find it, since it doesn't know about it before it's set.
+    fn load(&self, op: Opcode, operands: [usize; 2]) -> Result<Object, String> {
+        let frame = self.frame();
+        let runtime = frame.closure.runtime();
+        let (value, name, outer) = match op {
+            Opcode::GetGlobal => {
+                let index = operands[0];
+                (runtime.global(index), runtime.global_name(index), None)
+            },
+            Opcode::GetLocal => {
+                let locals = frame.locals();
+                let index = operands[0];
+                let outer = frame.closure.outer();
+                (locals.get(index), locals.name(index).to_string(), outer)
+            },
+            _ => {
+                let locals = frame
+                    .closure
+                    .outer()
+                    .unwrap()
+                    .outer(operands[0] - 1)
+                    .unwrap();
+                let index = operands[1];
+                let outer = locals.outer(1);
+                (locals.get(index), locals.name(index).to_string(), outer)
+            },
+        };
+        value
+            .or_else(|| outer?.lookup(&name))
+            .or_else(|| runtime.lookup_global(&name))
+            .or_else(|| builtins::lookup(&name).map(Object::Builtin))
+            .ok_or_else(|| format!("identifier not found: {}", name))
+    }
+
+    fn store(&self, op: Opcode, operands: [usize; 2], value: Option<Object>) {
+        let frame = self.frame();
+        match op {
+            Opcode::SetGlobal => frame.closure.runtime().set_global(operands[0], value),
+            Opcode::SetLocal => frame.locals().set(operands[0], value),
+            _ => {
+                let locals = frame
+                    .closure
+                    .outer()
+                    .unwrap()
+                    .outer(operands[0] - 1)
+                    .unwrap();
+                locals.set(operands[1], value);
+            },
+        }
+    }
+
+    /// Calls a function with arguments taken off the stack. Closures are run in a new frame,
+    /// or the current one for a tail call, so calls between them don't grow the native stack.
+    fn call(
+        &mut self,
+        function: Object,
+        args: Vec<Object>,
+        tail: bool,
+    ) -> Result<Option<Object>, Exception> {
+        let Object::Closure(closure) = function else {
+            let result = apply_function(function, args, Vec::new(), self.budget)?;
+            if tail {
+                return Ok(self.return_value(result));
+            }
+            self.push(result);
+            return Ok(None);
+        };
+        if tail {
+            // the call replaces the one making it, which stays counted for it
+            let frame = self.frames.last_mut().unwrap();
+            let call = frame.call.take().unwrap();
+            let base = frame.base;
+            let frame = Frame::new(closure, args, base, call)?;
+            self.stack.truncate(base);
+            *self.frames.last_mut().unwrap() = frame;
+        } else {
+            let call = CallDepth::enter(closure.runtime().call_depth())?;
+            let frame = Frame::new(closure, args, self.stack.len(), call)?;
+            self.frames.push(frame);
+        }
+        Ok(None)
+    }
+
+    /// Returns from the current call, producing the value once the first call returns.
+    fn return_value(&mut self, value: Object) -> Option<Object> {
+        let frame = self.frames.pop().unwrap();
+        self.stack.truncate(frame.base);
+        if self.frames.is_empty() {
+            return Some(value);
+        }
+        self.push(value);
+        None
+    }
+
+    fn assign(&mut self, operands: [usize; 2]) -> Result<(), Exception> {
+        let path = match self.constant(operands[0])? {
+            Object::Array(path) => path,
+            obj => {
+                return Err(
+                    format!("constant {} is {}, not ARRAY", operands[0], obj.type_name()).into(),
+                )
+            },
+        };
+        let mut root = self.pop()?;
+        let value = self.pop()?;
+        let mut indexes = self.pop_n(operands[1])?.into_iter();
+        let (operator, path) = path
+            .split_first()
+            .ok_or_else(|| format!("constant {} is an empty path", operands[0]))?;
+        let accessors = path
+            .iter()
+            .map(|member| match member {
+                Object::String(name) => Ok(Accessor::Member(name)),
+                _ => indexes.next().map(Accessor::Index).ok_or_else(|| {
+                    format!(
+                        "constant {} needs more than {} indexes",
+                        operands[0], operands[1]
+                    )
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // the variable is set to its new value by the instruction that follows, so it's cleared
+        // meanwhile, which lets the value be updated in place rather than copied
+        let store = self.next_store();
+        if let Some((op, operands)) = store {
+            self.store(op, operands, None);
+        }
+        let result = assign(
+            &mut root,
+            &accessors,
+            &operator.to_string(),
+            value,
+            self.budget,
+        );
+        if result.is_err() {
+            if let Some((op, operands)) = store {
+                self.store(op, operands, Some(root.clone()));
+            }
+        }
+        self.push(result?);
+        self.push(root);
+        Ok(())
+    }
+
+    /// The instruction that follows if it sets a variable.
+    fn next_store(&self) -> Option<(Opcode, [usize; 2])> {
+        let frame = self.frame();
+        let code = frame.instructions();
+        let op = Opcode::try_from(*code.get(frame.ip)?).ok()?;
+        let operands = match op {
+            Opcode::SetGlobal => [read_u16(code, frame.ip + 1) as usize, 0],
+            Opcode::SetLocal => [code[frame.ip + 1] as usize, 0],
+            Opcode::SetFree => [code[frame.ip + 1] as usize, code[frame.ip + 2] as usize],
+            _ => return None,
+        };
+        Some((op, operands))
+    }
+}
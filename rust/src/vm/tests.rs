@@ -0,0 +1,368 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        compiler::{Bytecode, Compiler},
+        object::{ExecutionLimits, LimitExceeded, Object},
+        vm::Vm,
+        Engine, Interpreter, Lexer, Parser,
+    };
+
+    fn test_run(s: &str) -> Result<Object, String> {
+        test_run_with_limits(s, ExecutionLimits::new())
+    }
+
+    fn test_run_with_limits(s: &str, limits: ExecutionLimits) -> Result<Object, String> {
+        let l = Lexer::new(s);
+        let mut p = Parser::new(l);
+        let program = p.parse_program()?;
+        let mut compiler = Compiler::new();
+        compiler.compile(&program)?;
+        let mut vm = Vm::with_limits(limits);
+        vm.run(&compiler.bytecode()).map_err(|e| match e.span() {
+            Some(span) => format!("{} at {}", e, span),
+            None => e.to_string(),
+        })
+    }
+
+    fn run_vm_tests(tests: Vec<(&str, &str)>) {
+        for (input, expected) in tests {
+            let output = test_run(input).unwrap_or_else(|e| panic!("{}: {}", input, e));
+            assert_eq!(output.to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_expressions() {
+        let tests = vec![
+            ("1 + 2 * 3 - 4 / 2", "5"),
+            ("-(5 % 3) ** 2", "-4"),
+            ("~5 & 7 | 1 ^ 8 << 1 >> 1", "11"),
+            ("1 < 2 == (3 >= 4)", "false"),
+            ("!null && 1 || false", "true"),
+            ("false && undefined", "false"),
+            ("null ?? 2", "2"),
+            (r#""mon" + "key""#, "monkey"),
+            (r#""${1 + 1} and ${"two"}""#, "2 and two"),
+            ("[1, 2 * 2, 3][1]", "4"),
+            (r#"{"a": 1, true: [2]}[true][0]"#, "2"),
+            (r#"{"a": {"b": 3}}.a.b"#, "3"),
+            ("null?.a?[0]", "null"),
+            ("null?.a[0].b", "null"),
+            ("null?.a.len() ?? 1", "1"),
+            ("(null?.a)?.b", "null"),
+            ("let f = fn(h) { h?.g(1)(2) }; f(null)", "null"),
+            ("[1, 2, 3, 4][1..3]", "[2, 3]"),
+            ("if (1 > 2) { 10 }", "null"),
+            ("if (1 < 2) { 10 } else { 20 }", "10"),
+            ("", "null"),
+            ("let x = 1; let x = x + 1; x", "2"),
+        ];
+        run_vm_tests(tests);
+    }
+
+    #[test]
+    fn test_functions() {
+        let tests =
+            vec![
+            ("let f = fn(a, b) { a + b }; f(1, 2)", "3"),
+            ("fn() { return 1; 2 }()", "1"),
+            ("fn() { }()", "null"),
+            ("let f = fn() { let x = 1; if (x) { return x + 1 }; 0 }; f()", "2"),
+            ("fn(x) { x * 2 }", "fn(x) {(x * 2)}"),
+            (
+                "let adder = fn(a) { fn(b) { a + b } }; let add2 = adder(2); add2(3)",
+                "5",
+            ),
+            (
+                "let counter = fn() { let n = 0; fn() { n += 1 } }; let c = counter(); c(); c()",
+                "2",
+            ),
+            (
+                "let f = fn(a) { fn(b) { fn(c) { a + b + c } } }; f(1)(2)(3)",
+                "6",
+            ),
+            (
+                "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(15)",
+                "610",
+            ),
+            // tail calls don't count towards the call depth
+            (
+                "let count = fn(n) { if (n == 0) { 0 } else { count(n - 1) } }; count(100000)",
+                "0",
+            ),
+            (
+                "let even = fn(n) { if (n == 0) { true } else { odd(n - 1) } }; \
+                 let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } }; even(5001)",
+                "false",
+            ),
+            // names defined later are found once they're set
+            (
+                "let f = fn() { let g = fn() { x }; let x = 1; g() }; f()",
+                "1",
+            ),
+            ("let x = 1; let f = fn() { let y = x; let x = 2; y }; f()", "1"),
+            ("let f = fn() { len }; f()([1, 2])", "2"),
+            ("let len = fn(x) { 0 }; len([1])", "0"),
+        ];
+        run_vm_tests(tests);
+    }
+
+    #[test]
+    fn test_builtins_and_methods() {
+        let tests = vec![
+            ("len([1, 2, 3])", "3"),
+            ("collect(map(1..=3, fn(x) { x * x }))", "[1, 4, 9]"),
+            (
+                "(1..10).filter(fn(x) { x % 3 == 0 }).collect()",
+                "[3, 6, 9]",
+            ),
+            (r#""a,b".split(",").map(fn(s) { s.upper() })"#, "[A, B]"),
+            ("let m = {\"double\": fn(x) { x * 2 }}; m.double(4)", "8"),
+            ("let it = iter([1, 2]); next(it); it.next()", "2"),
+        ];
+        run_vm_tests(tests);
+    }
+
+    #[test]
+    fn test_loops_and_assignment() {
+        let tests = vec![
+            ("let i = 0; while (i < 5) { i += 1 } i", "5"),
+            (
+                "let s = 0; for (x in 1..=10) { if (x % 2 == 0) { continue } s += x } s",
+                "25",
+            ),
+            ("let i = 0; while (true) { i += 1; if (i == 3) { break } } i", "3"),
+            ("let r = []; for (c in \"ab\") { r = r.push(c) } r", "[a, b]"),
+            (
+                "let f = fn() { let n = 0; for (x in [1, 2, 3]) { n += [x, if (x == 2) { break }][0] } n }; f()",
+                "1",
+            ),
+            ("let f = fn() { for (x in [1, 2]) { return x } }; f()", "1"),
+            (
+                "let s = 0; for (x in [1, 2, 3]) { let y = if (x == 2) { continue; } else { x }; s += y } s",
+                "4",
+            ),
+            ("let f = fn() { let y = if (true) { return 5; }; 10 }; f()", "5"),
+            ("let f = fn(g) { g(if (true) { return 7; }) }; f(len)", "7"),
+            ("let a = [1, [2]]; a[1][0] *= 5; a", "[1, [10]]"),
+            ("let h = {}; h.k = 1; h[\"j\"] = 2; h", "{j: 2, k: 1}"),
+            ("let a = 1; let b = (a = 5); [a, b]", "[5, 5]"),
+            ("let f = fn() { x = 3 }; let x = 1; f(); x", "3"),
+            ("let a = [1]; let b = a; b[0] = 2; [a, b]", "[[1], [2]]"),
+        ];
+        run_vm_tests(tests);
+    }
+
+    #[test]
+    fn test_errors() {
+        let tests = vec![
+            ("1 + true", "type mismatch: INTEGER + BOOLEAN at 1:3"),
+            ("undefined", "identifier not found: undefined at 1:1"),
+            ("x = 1", "identifier not found: x at 1:3"),
+            (
+                "let f = fn(a) {\n  a / 0\n};\nf(1)",
+                "division by zero at 2:5",
+            ),
+            (
+                "fn(a) { a }()",
+                "wrong number of arguments: expected 1, got 0 at 1:12",
+            ),
+            ("1(2)", "not a function: INTEGER at 1:2"),
+            ("[1].nope()", "unknown method: ARRAY.nope at 1:9"),
+            (
+                "let a = [1]; a[5] = 1",
+                "index out of bounds: 5 (length 1) at 1:19",
+            ),
+            (
+                "let f = fn(n) { 1 + f(n + 1) }; f(0)",
+                "maximum recursion depth exceeded at 1:22",
+            ),
+            (
+                "map([1], fn(x) { x + null }).collect()",
+                "type mismatch: INTEGER + NULL at 1:20",
+            ),
+            (
+                r#"(null?["a"])["b"]"#,
+                "index operator not supported: NULL[STRING] at 1:13",
+            ),
+            ("(null?.f(1))(2)", "not a function: NULL at 1:13"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_run(input).unwrap_err(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_failed_assignment_keeps_value() {
+        let mut interpreter = Interpreter::new(Engine::Vm, ExecutionLimits::new());
+        for (input, expected) in [
+            ("let a = [1, 2]", Ok("null")),
+            ("a[9] = 0", Err("index out of bounds: 9 (length 2)")),
+            ("a", Ok("[1, 2]")),
+        ] {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            let output = interpreter.run(program).map(|obj| obj.to_string());
+            let output = output.map_err(|e| e.to_string());
+            assert_eq!(
+                output.as_deref().map_err(String::as_str),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_globals_are_kept_between_runs() {
+        let mut interpreter = Interpreter::new(Engine::Vm, ExecutionLimits::new()).incremental();
+        let inputs = [
+            ("let f = fn() { g() }", "null"),
+            ("let g = fn() { 1 }", "null"),
+            ("f() + later", "identifier not found: later"),
+            ("let later = 2", "null"),
+            ("f() + later", "3"),
+            ("match (1) { _ => 1 }", "1"),
+            ("f() + later", "3"),
+        ];
+        for (input, expected) in inputs {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            let output = match interpreter.run(program) {
+                Ok(obj) => obj.to_string(),
+                Err(e) => e.to_string(),
+            };
+            assert_eq!(output, expected, "{}", input);
+        }
+    }
+
+    fn run_interpreter(interpreter: &mut Interpreter, input: &str) -> String {
+        let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+        match interpreter.run(program) {
+            Ok(obj) => obj.to_string(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_uncompiled_programs_are_evaluated() {
+        let tests = vec![
+            ("match ([1, 2]) { [a, b] => a + b, _ => 0 }", "3"),
+            ("let [a, ..rest] = [1, 2, 3]; let {k} = {\"k\": a}; [k, rest]", "[1, [2, 3]]"),
+            ("struct P { x, y } let p = P { x: 1, y: 2 }; p.x + p.y", "3"),
+            (
+                "enum Shape { Circle(r), Empty } \
+                 [Shape.Circle(2), Shape.Empty].map(fn(s) { match (s) { Shape.Circle(r) => r, Shape.Empty => 0 } })",
+                "[2, 0]",
+            ),
+            ("try { throw 1; } catch (e) { e + 1 }", "2"),
+            ("try { 1 / 0 } catch (e) { e.kind }", "division by zero"),
+            ("throw 5;", "uncaught exception: 5"),
+            ("let g = fn*(n) { yield n; yield n * 2; }; collect(g(5))", "[5, 10]"),
+            ("let f = fn(a, b = 2, ...r) { [a, b, r] }; f(1)", "[1, 2, []]"),
+            ("let f = fn(a, b) { a - b }; [f(...[5, 1]), f(b: 1, a: 5)]", "[4, 4]"),
+        ];
+        for (input, expected) in tests {
+            let mut interpreter = Interpreter::new(Engine::Vm, ExecutionLimits::new());
+            assert_eq!(
+                run_interpreter(&mut interpreter, input),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluated_programs_share_globals() {
+        let mut interpreter = Interpreter::new(Engine::Vm, ExecutionLimits::new()).incremental();
+        let inputs = [
+            ("let f = fn(x) { x * k }", "null"),
+            ("let k = 2", "null"),
+            // evaluated programs see the VM's globals, and can call its functions
+            ("match (f(3)) { 6 => \"six\", _ => \"other\" }", "six"),
+            ("let g = fn(x) { try { f(x) } catch (e) { 0 } }; k = 3", "3"),
+            // and the VM sees what they set, and can call their functions
+            ("g(2) + f(1) + k", "12"),
+            ("k = 4; g(1)", "4"),
+            ("let [a, b] = [k, k + 1]; a + b", "9"),
+            ("a * b", "20"),
+            // globals set before an evaluated program fails are kept
+            ("let c = 1; throw c;", "uncaught exception: 1"),
+            ("c + 1", "2"),
+        ];
+        for (input, expected) in inputs {
+            assert_eq!(
+                run_interpreter(&mut interpreter, input),
+                expected,
+                "{}",
+                input
+            );
+        }
+
+        // evaluation counts towards the VM's limits, so a program that could be evaluated on
+        // its own can't be once the VM has used up most of them
+        let evaluated = "match (0) { _ => { let i = 0; while (i < 20) { i += 1 } } }";
+        let limits = ExecutionLimits::new().with_max_steps(300);
+        let mut interpreter = Interpreter::new(Engine::Vm, limits.clone());
+        assert_eq!(run_interpreter(&mut interpreter, evaluated), "null");
+        let mut interpreter = Interpreter::new(Engine::Vm, limits);
+        let compiled = "let i = 0; while (i < 20) { i += 1 }";
+        assert_eq!(run_interpreter(&mut interpreter, compiled), "null");
+        let output = run_interpreter(&mut interpreter, evaluated);
+        assert!(
+            output.starts_with(&LimitExceeded::Steps.to_string()),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_compiled_files() {
+        let input = "let f = fn(n) { if (n < 2) { n } else { f(n - 1) + f(n - 2) } }; \
+                     let s = \"\"; for (x in [f(10), f(11)]) { s += \"${x};\" } s";
+        let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+        let mut compiler = Compiler::new();
+        compiler.compile(&program).unwrap();
+        let bytes = compiler.bytecode().to_bytes().unwrap();
+        let bytecode = Bytecode::from_bytes(&bytes).unwrap();
+        let output = Vm::with_limits(ExecutionLimits::new()).run(&bytecode);
+        assert_eq!(output.unwrap().to_string(), "55;89;");
+    }
+
+    #[test]
+    fn test_execution_limits() {
+        let limits = ExecutionLimits::new().with_max_steps(1000);
+        let err = test_run_with_limits("while (true) { }", limits).unwrap_err();
+        assert!(
+            err.starts_with(&LimitExceeded::Steps.to_string()),
+            "{}",
+            err
+        );
+
+        let limits = ExecutionLimits::new().with_max_memory(1000);
+        let err = test_run_with_limits("let a = []; while (true) { a = a.push(1) }", limits);
+        let err = err.unwrap_err();
+        assert!(
+            err.starts_with(&LimitExceeded::Memory.to_string()),
+            "{}",
+            err
+        );
+
+        let limits = ExecutionLimits::new().with_max_steps(1000);
+        let input = "let a = [1]; for (i in 0..40) { a = [a, a] } \"${a}\"";
+        let err = test_run_with_limits(input, limits).unwrap_err();
+        assert!(
+            err.starts_with(&LimitExceeded::Steps.to_string()),
+            "{}",
+            err
+        );
+
+        let limits = ExecutionLimits::new();
+        limits.cancel_handle().cancel();
+        let err = test_run_with_limits("1", limits).unwrap_err();
+        assert!(
+            err.starts_with(&LimitExceeded::Cancelled.to_string()),
+            "{}",
+            err
+        );
+    }
+}